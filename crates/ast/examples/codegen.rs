@@ -0,0 +1,95 @@
+//! Prints each script given as normalized source, or with `--check` makes
+//! sure that printing keeps the program the same: `parse(print(parse(src)))`
+//! must equal `parse(src)` in both the compact and pretty styles.
+//!
+//! cargo run -p shadowjs-ast --example codegen -- [--check | --compact] <file.js>...
+
+use shadowjs_ast::codegen::Style;
+use shadowjs_ast::fold::Fold;
+use shadowjs_ast::{Program, Span};
+use shadowjs_parser::Parser;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let mut check = false;
+    let mut style = Style::default();
+    let mut paths = vec![];
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--check" => check = true,
+            "--compact" => style = Style::Compact,
+            _ => paths.push(arg),
+        }
+    }
+    if paths.is_empty() {
+        eprintln!("Usage: codegen [--check | --compact] <file.js>...");
+        return ExitCode::FAILURE;
+    }
+
+    let mut failed = false;
+    for path in &paths {
+        let src = match std::fs::read_to_string(path) {
+            Ok(src) => src,
+            Err(err) => {
+                eprintln!("{}: {}", path, err);
+                failed = true;
+                continue;
+            }
+        };
+        let program = match Parser::new(&src).parse() {
+            Ok(program) => program,
+            Err(diagnostics) => {
+                for diagnostic in diagnostics {
+                    eprintln!("{}: SyntaxError: {}", path, diagnostic.message);
+                }
+                failed = true;
+                continue;
+            }
+        };
+
+        if !check {
+            print!("{}", program.to_source(&style));
+            continue;
+        }
+        match round_trip(&program) {
+            Ok(()) => println!("ok       {}", path),
+            Err(message) => {
+                println!("MISMATCH {}: {}", path, message);
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Prints `program` in both styles and checks that each parses back to it.
+fn round_trip(program: &Program) -> Result<(), String> {
+    let expected = StripSpans.fold_program(program.clone());
+    for style in [Style::Compact, Style::default()] {
+        let src = program.to_source(&style);
+        let reparsed = Parser::new(&src).parse().map_err(|diagnostics| {
+            format!(
+                "printed source does not parse ({}):\n{}",
+                diagnostics[0].message, src
+            )
+        })?;
+        if StripSpans.fold_program(reparsed) != expected {
+            return Err(format!("printed source parses differently:\n{}", src));
+        }
+    }
+    Ok(())
+}
+
+/// Clears every span, since printing moves the nodes around.
+struct StripSpans;
+
+impl Fold for StripSpans {
+    fn fold_span(&mut self, _: Span) -> Span {
+        Span::default()
+    }
+}
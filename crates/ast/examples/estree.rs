@@ -0,0 +1,75 @@
+//! Prints the ESTree JSON of each script given, or with `--check` makes
+//! sure each one survives a round trip through ESTree JSON unchanged.
+//!
+//! cargo run -p shadowjs-ast --features estree --example estree -- [--check] <file.js>...
+
+use shadowjs_ast::Program;
+use shadowjs_parser::Parser;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let check = args.first().is_some_and(|arg| arg == "--check");
+    if check {
+        args.remove(0);
+    }
+    if args.is_empty() {
+        eprintln!("Usage: estree [--check] <file.js>...");
+        return ExitCode::FAILURE;
+    }
+
+    let mut failed = false;
+    for path in &args {
+        let src = match std::fs::read_to_string(path) {
+            Ok(src) => src,
+            Err(err) => {
+                eprintln!("{}: {}", path, err);
+                failed = true;
+                continue;
+            }
+        };
+        let program = match Parser::new(&src).parse() {
+            Ok(program) => program,
+            Err(diagnostics) => {
+                for diagnostic in diagnostics {
+                    eprintln!("{}: SyntaxError: {}", path, diagnostic.message);
+                }
+                failed = true;
+                continue;
+            }
+        };
+
+        let json = serde_json::to_string_pretty(&program.to_estree()).unwrap();
+        if !check {
+            println!("{}", json);
+            continue;
+        }
+        match round_trip(&program, &json) {
+            Ok(()) => println!("ok       {}", path),
+            Err(message) => {
+                println!("MISMATCH {}: {}", path, message);
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Reads `json` back and checks that it gives the same program, which
+/// exports to the same JSON again.
+fn round_trip(program: &Program, json: &str) -> Result<(), String> {
+    let value: serde_json::Value = serde_json::from_str(json).map_err(|err| err.to_string())?;
+    let imported = Program::from_estree(&value).map_err(|err| err.to_string())?;
+    if imported != *program {
+        return Err("the imported program differs from the parsed one".to_string());
+    }
+    if imported.to_estree() != value {
+        return Err("exporting the imported program gives different JSON".to_string());
+    }
+    Ok(())
+}
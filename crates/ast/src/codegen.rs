@@ -0,0 +1,952 @@
+//! Printing of programs back to JavaScript source.
+//!
+//! Operands are parenthesized only where the parser would otherwise group
+//! them differently, going by the binding powers in [`crate::precedence`].
+//! Comments and the spelling of literals are not kept, so `0xff` prints as
+//! `255` and every string is double-quoted. Template literals are printed
+//! from their raw text.
+
+use crate::precedence::{self, ASSIGN, CALL, CONDITIONAL, MEMBER, POSTFIX, PREFIX};
+use crate::{
+    ArrowBody, DeclarationKind, Expression, ExpressionKind, Identifier, MethodKind, Parameter,
+    Pattern, PatternKind, Program, Property, PropertyKey, PropertyKeyKind, PropertyKind, Statement,
+    StatementKind, TemplateElement,
+};
+
+// Tighter than any operator: identifiers, literals and bracketed forms
+const PRIMARY: u8 = MEMBER + 1;
+
+/// The words the lexer reads as keywords. They cannot follow a `.` or name
+/// a property in an object literal without quotes.
+const KEYWORDS: &[&str] = &[
+    "let",
+    "const",
+    "function",
+    "return",
+    "if",
+    "else",
+    "while",
+    "for",
+    "do",
+    "break",
+    "continue",
+    "throw",
+    "try",
+    "catch",
+    "finally",
+    "true",
+    "false",
+    "null",
+    "this",
+    "typeof",
+    "void",
+    "delete",
+    "in",
+    "instanceof",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Style {
+    /// Everything on one line, with only the whitespace the syntax needs
+    Compact,
+    /// One statement per line, each nested block indented by `indent`
+    Pretty { indent: String },
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Style::Pretty {
+            indent: "    ".to_string(),
+        }
+    }
+}
+
+impl Program {
+    pub fn to_source(&self, style: &Style) -> String {
+        let mut printer = Printer::new(style);
+        for (i, stmt) in self.statements.iter().enumerate() {
+            if i > 0 {
+                printer.newline();
+            }
+            printer.statement(stmt);
+        }
+        if printer.is_pretty() && !self.statements.is_empty() {
+            printer.out.push('\n');
+        }
+        printer.out
+    }
+}
+
+impl Statement {
+    pub fn to_source(&self, style: &Style) -> String {
+        let mut printer = Printer::new(style);
+        printer.statement(self);
+        printer.out
+    }
+}
+
+impl Expression {
+    pub fn to_source(&self, style: &Style) -> String {
+        let mut printer = Printer::new(style);
+        printer.expression(self, ASSIGN);
+        printer.out
+    }
+}
+
+struct Printer<'s> {
+    out: String,
+    style: &'s Style,
+    depth: usize,
+    // Where the expression statement being printed starts. A `{` or
+    // `function` there would begin a block or declaration instead.
+    statement_start: Option<usize>,
+    // Where the last regular expression literal ends, since a word after it
+    // would be read as more flags
+    regex_end: usize,
+}
+
+impl<'s> Printer<'s> {
+    fn new(style: &'s Style) -> Self {
+        Self {
+            out: String::new(),
+            style,
+            depth: 0,
+            statement_start: None,
+            regex_end: 0,
+        }
+    }
+
+    fn is_pretty(&self) -> bool {
+        matches!(self.style, Style::Pretty { .. })
+    }
+
+    /// Appends a token, separating it from the previous one where the two
+    /// would otherwise run together, as in `typeof x`, `a - -b` or `a / /b/`.
+    fn write(&mut self, token: &str) {
+        if let (Some(prev), Some(next)) = (self.out.chars().next_back(), token.chars().next()) {
+            if ((is_word_char(prev) || self.out.len() == self.regex_end) && is_word_char(next))
+                || (prev == next && matches!(prev, '+' | '-' | '/'))
+            {
+                self.out.push(' ');
+            }
+        }
+        self.out.push_str(token);
+    }
+
+    /// A space that is only there for readability.
+    fn space(&mut self) {
+        if self.is_pretty() {
+            self.out.push(' ');
+        }
+    }
+
+    fn newline(&mut self) {
+        if let Style::Pretty { indent } = self.style {
+            self.out.push('\n');
+            for _ in 0..self.depth {
+                self.out.push_str(indent);
+            }
+        }
+    }
+
+    /// Writes a binary operator, surrounded by spaces when pretty.
+    fn operator(&mut self, operator: &str) {
+        self.space();
+        self.write(operator);
+        self.space();
+    }
+
+    fn comma(&mut self) {
+        self.write(",");
+        self.space();
+    }
+
+    fn at_statement_start(&self) -> bool {
+        self.statement_start == Some(self.out.len())
+    }
+
+    // Statements
+
+    fn statement(&mut self, stmt: &Statement) {
+        match &stmt.kind {
+            StatementKind::Empty => self.write(";"),
+            StatementKind::Let { .. } | StatementKind::Const { .. } => {
+                self.declaration(stmt);
+                self.write(";");
+            }
+            StatementKind::Return(value) => {
+                self.write("return");
+                if let Some(value) = value {
+                    self.space();
+                    self.expression(value, ASSIGN);
+                }
+                self.write(";");
+            }
+            StatementKind::Expression(expr) => {
+                let outer = self.statement_start.replace(self.out.len());
+                self.expression(expr, ASSIGN);
+                self.statement_start = outer;
+                self.write(";");
+            }
+            StatementKind::Block(body) => self.block(body),
+            StatementKind::If {
+                condition,
+                consequence,
+                alternative,
+            } => {
+                self.write("if");
+                self.space();
+                self.condition(condition);
+                match alternative {
+                    // Without braces, the `else` would go to the inner `if`
+                    Some(_) if ends_with_open_if(consequence) => {
+                        self.space();
+                        self.block(std::slice::from_ref(&**consequence));
+                    }
+                    _ => self.body(consequence),
+                }
+                if let Some(alternative) = alternative {
+                    if matches!(consequence.kind, StatementKind::Block(_))
+                        || ends_with_open_if(consequence)
+                    {
+                        self.space();
+                    } else {
+                        self.newline();
+                    }
+                    self.write("else");
+                    if let StatementKind::If { .. } = alternative.kind {
+                        self.out.push(' ');
+                        self.statement(alternative);
+                    } else {
+                        self.body(alternative);
+                    }
+                }
+            }
+            StatementKind::Function { name, params, body } => {
+                self.function(Some(&name.name), params, body)
+            }
+            StatementKind::While { condition, body } => {
+                self.write("while");
+                self.space();
+                self.condition(condition);
+                self.body(body);
+            }
+            StatementKind::DoWhile { body, condition } => {
+                self.write("do");
+                self.body(body);
+                self.space();
+                self.write("while");
+                self.space();
+                self.condition(condition);
+                self.write(";");
+            }
+            StatementKind::For {
+                init,
+                condition,
+                update,
+                body,
+            } => {
+                self.write("for");
+                self.space();
+                self.write("(");
+                match init.as_deref() {
+                    Some(Statement {
+                        kind: StatementKind::Expression(expr),
+                        ..
+                    }) => self.expression(expr, ASSIGN),
+                    Some(init) => self.declaration(init),
+                    None => {}
+                }
+                self.write(";");
+                if let Some(condition) = condition {
+                    self.space();
+                    self.expression(condition, ASSIGN);
+                }
+                self.write(";");
+                if let Some(update) = update {
+                    self.space();
+                    self.expression(update, ASSIGN);
+                }
+                self.write(")");
+                self.body(body);
+            }
+            StatementKind::ForOf {
+                declaration,
+                target,
+                iterable,
+                body,
+            } => {
+                self.write("for");
+                self.space();
+                self.write("(");
+                match declaration {
+                    Some(DeclarationKind::Let) => self.write("let"),
+                    Some(DeclarationKind::Const) => self.write("const"),
+                    None => {}
+                }
+                if declaration.is_some() {
+                    self.space();
+                }
+                self.pattern(target);
+                self.space();
+                self.write("of");
+                self.space();
+                self.expression(iterable, ASSIGN);
+                self.write(")");
+                self.body(body);
+            }
+            StatementKind::Break(label) => self.jump("break", label.as_ref()),
+            StatementKind::Continue(label) => self.jump("continue", label.as_ref()),
+            StatementKind::Labeled { label, body } => {
+                self.write(&label.name);
+                self.write(":");
+                self.space();
+                self.statement(body);
+            }
+            StatementKind::Throw(value) => {
+                self.write("throw");
+                self.space();
+                self.expression(value, ASSIGN);
+                self.write(";");
+            }
+            StatementKind::Try {
+                block,
+                param,
+                handler,
+                finalizer,
+            } => {
+                self.write("try");
+                self.space();
+                self.block_statement(block);
+                if let Some(handler) = handler {
+                    self.space();
+                    self.write("catch");
+                    self.space();
+                    if let Some(param) = param {
+                        self.write("(");
+                        self.write(&param.name);
+                        self.write(")");
+                        self.space();
+                    }
+                    self.block_statement(handler);
+                }
+                if let Some(finalizer) = finalizer {
+                    self.space();
+                    self.write("finally");
+                    self.space();
+                    self.block_statement(finalizer);
+                }
+            }
+        }
+    }
+
+    /// Writes a `let` or `const` declaration without its semicolon, so it
+    /// can also start a `for` header.
+    fn declaration(&mut self, stmt: &Statement) {
+        let (keyword, pattern, value) = match &stmt.kind {
+            StatementKind::Let { pattern, value } => ("let", pattern, value.as_ref()),
+            StatementKind::Const { pattern, value } => ("const", pattern, Some(value)),
+            _ => unreachable!("declaration called on a non-declaration"),
+        };
+        self.write(keyword);
+        self.space();
+        self.pattern(pattern);
+        if let Some(value) = value {
+            self.operator("=");
+            self.expression(value, ASSIGN);
+        }
+    }
+
+    fn jump(&mut self, keyword: &str, label: Option<&Identifier>) {
+        self.write(keyword);
+        if let Some(label) = label {
+            self.write(&label.name);
+        }
+        self.write(";");
+    }
+
+    /// Writes the parenthesized condition of `if`, `while` or `do`.
+    fn condition(&mut self, condition: &Expression) {
+        self.write("(");
+        self.expression(condition, ASSIGN);
+        self.write(")");
+    }
+
+    /// Writes the statement a loop or `if` controls.
+    fn body(&mut self, body: &Statement) {
+        self.space();
+        self.statement(body);
+    }
+
+    fn block(&mut self, body: &[Statement]) {
+        self.write("{");
+        self.depth += 1;
+        for stmt in body {
+            self.newline();
+            self.statement(stmt);
+        }
+        self.depth -= 1;
+        if !body.is_empty() {
+            self.newline();
+        }
+        self.write("}");
+    }
+
+    /// Writes a statement the grammar requires to be a block, such as a
+    /// function body.
+    fn block_statement(&mut self, stmt: &Statement) {
+        match &stmt.kind {
+            StatementKind::Block(body) => self.block(body),
+            _ => self.block(std::slice::from_ref(stmt)),
+        }
+    }
+
+    fn function(&mut self, name: Option<&str>, params: &[Parameter], body: &Statement) {
+        self.write("function");
+        if let Some(name) = name {
+            self.write(name);
+        }
+        self.parameters(params);
+        self.space();
+        self.block_statement(body);
+    }
+
+    fn parameters(&mut self, params: &[Parameter]) {
+        self.write("(");
+        for (i, param) in params.iter().enumerate() {
+            if i > 0 {
+                self.comma();
+            }
+            if param.rest {
+                self.write("...");
+            }
+            self.pattern(&param.target);
+            self.default(param.default.as_ref());
+        }
+        self.write(")");
+    }
+
+    fn pattern(&mut self, pattern: &Pattern) {
+        match &pattern.kind {
+            PatternKind::Identifier(name) => self.write(name),
+            PatternKind::Member(expr) => self.expression(expr, CALL),
+            PatternKind::Object { properties, rest } => {
+                if properties.is_empty() && rest.is_none() {
+                    self.write("{}");
+                    return;
+                }
+                self.write("{");
+                self.space();
+                for (i, property) in properties.iter().enumerate() {
+                    if i > 0 {
+                        self.comma();
+                    }
+                    match (&property.key.kind, &property.value.kind) {
+                        (PropertyKeyKind::String(key), PatternKind::Identifier(name))
+                            if key == name && is_property_name(name) =>
+                        {
+                            self.write(name)
+                        }
+                        _ => {
+                            self.property_key(&property.key);
+                            self.write(":");
+                            self.space();
+                            self.pattern(&property.value);
+                        }
+                    }
+                    self.default(property.default.as_ref());
+                }
+                if let Some(rest) = rest {
+                    if !properties.is_empty() {
+                        self.comma();
+                    }
+                    self.write("...");
+                    self.pattern(&rest.target);
+                }
+                self.space();
+                self.write("}");
+            }
+            PatternKind::Array { elements, rest } => {
+                self.write("[");
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        self.comma();
+                    }
+                    if let Some(element) = element {
+                        self.pattern(&element.target);
+                        self.default(element.default.as_ref());
+                    }
+                }
+                match rest {
+                    Some(rest) => {
+                        if !elements.is_empty() {
+                            self.comma();
+                        }
+                        self.write("...");
+                        self.pattern(&rest.target);
+                    }
+                    // A trailing comma is dropped, so a hole at the end
+                    // needs another one
+                    None if matches!(elements.last(), Some(None)) => self.write(","),
+                    None => {}
+                }
+                self.write("]");
+            }
+        }
+    }
+
+    /// Writes the `= default` of a parameter or pattern element.
+    fn default(&mut self, default: Option<&Expression>) {
+        if let Some(default) = default {
+            self.operator("=");
+            self.expression(default, ASSIGN);
+        }
+    }
+
+    // Expressions
+
+    /// Writes `expr`, parenthesized if it binds looser than `min`.
+    fn expression(&mut self, expr: &Expression, min: u8) {
+        if binding_power(expr) < min {
+            self.write("(");
+            self.expression_kind(&expr.kind);
+            self.write(")");
+        } else {
+            self.expression_kind(&expr.kind);
+        }
+    }
+
+    fn expression_kind(&mut self, kind: &ExpressionKind) {
+        match kind {
+            ExpressionKind::Identifier(name) => self.write(name),
+            ExpressionKind::Number(value) => self.write(&number_literal(*value)),
+            ExpressionKind::BigInt(digits) => self.write(&format!("{}n", digits)),
+            ExpressionKind::String(value) => self.write(&string_literal(value)),
+            ExpressionKind::RegExp { pattern, flags } => {
+                // The lexer takes a `/` after `++` or `--` as division
+                let prev = self.out.trim_end();
+                let wrap = prev.ends_with("++") || prev.ends_with("--");
+                let literal = format!("/{}/{}", pattern, flags);
+                if wrap {
+                    self.write("(");
+                    self.write(&literal);
+                    self.write(")");
+                } else {
+                    self.write(&literal);
+                    self.regex_end = self.out.len();
+                }
+            }
+            ExpressionKind::Boolean(value) => self.write(if *value { "true" } else { "false" }),
+            ExpressionKind::Null => self.write("null"),
+            ExpressionKind::This => self.write("this"),
+            ExpressionKind::Prefix { operator, right } => {
+                self.write(operator);
+                // Unary operators nest without parentheses, as in `!-a`
+                let min = if is_prefix_form(right) {
+                    PREFIX
+                } else {
+                    POSTFIX
+                };
+                self.expression(right, min);
+            }
+            ExpressionKind::Infix {
+                left,
+                operator,
+                right,
+            } => self.infix(left, operator, right),
+            ExpressionKind::Conditional {
+                condition,
+                consequence,
+                alternative,
+            } => {
+                self.expression(condition, CONDITIONAL + 1);
+                self.operator("?");
+                self.expression(consequence, ASSIGN);
+                self.operator(":");
+                self.expression(alternative, ASSIGN);
+            }
+            ExpressionKind::Call {
+                function,
+                arguments,
+            } => {
+                self.expression(function, CALL);
+                self.arguments(arguments);
+            }
+            ExpressionKind::Array(elements) => {
+                self.write("[");
+                for (i, elem) in elements.iter().enumerate() {
+                    if i > 0 {
+                        self.comma();
+                    }
+                    self.expression(elem, ASSIGN);
+                }
+                self.write("]");
+            }
+            ExpressionKind::Spread(argument) => {
+                self.write("...");
+                self.expression(argument, ASSIGN);
+            }
+            ExpressionKind::Object(properties) => {
+                let wrap = self.at_statement_start();
+                if wrap {
+                    self.write("(");
+                }
+                self.object(properties);
+                if wrap {
+                    self.write(")");
+                }
+            }
+            ExpressionKind::Index { left, index } => self.index(left, index),
+            ExpressionKind::Assign {
+                target,
+                operator,
+                value,
+            } => {
+                // `{` starting a statement would begin a block
+                let wrap =
+                    matches!(target.kind, PatternKind::Object { .. }) && self.at_statement_start();
+                if wrap {
+                    self.write("(");
+                }
+                self.pattern(target);
+                self.operator(operator);
+                self.expression(value, ASSIGN);
+                if wrap {
+                    self.write(")");
+                }
+            }
+            ExpressionKind::Update {
+                operator,
+                prefix,
+                target,
+            } => {
+                if *prefix {
+                    self.write(operator);
+                    self.expression(target, POSTFIX);
+                } else {
+                    self.expression(target, CALL);
+                    self.write(operator);
+                }
+            }
+            ExpressionKind::Function { name, params, body } => {
+                let wrap = self.at_statement_start();
+                if wrap {
+                    self.write("(");
+                }
+                self.function(name.as_ref().map(|name| name.name.as_str()), params, body);
+                if wrap {
+                    self.write(")");
+                }
+            }
+            ExpressionKind::Arrow { params, body } => {
+                self.parameters(params);
+                self.operator("=>");
+                match body {
+                    ArrowBody::Block(block) => self.block_statement(block),
+                    // A `{` starting the body would begin a block instead
+                    ArrowBody::Expression(expr) => {
+                        let outer = self.statement_start.replace(self.out.len());
+                        self.expression(expr, ASSIGN);
+                        self.statement_start = outer;
+                    }
+                }
+            }
+            ExpressionKind::Template {
+                quasis,
+                expressions,
+            } => self.template(quasis, expressions),
+            ExpressionKind::TaggedTemplate { tag, quasi } => {
+                self.expression(tag, CALL);
+                self.expression(quasi, PRIMARY);
+            }
+        }
+    }
+
+    fn infix(&mut self, left: &Expression, operator: &str, right: &Expression) {
+        let power = precedence::infix(operator);
+        // Exponentiation groups to the right, everything else to the left
+        let (mut left_min, mut right_min) = if operator == "**" {
+            (power + 1, power)
+        } else {
+            (power, power + 1)
+        };
+        // `-a ** b` is rejected as ambiguous
+        if operator == "**" && is_unary(left) {
+            left_min = PRIMARY;
+        }
+        // `??` cannot be mixed with `&&` or `||` without parentheses
+        if mixes_nullish(operator, left) {
+            left_min = PRIMARY;
+        }
+        if mixes_nullish(operator, right) {
+            right_min = PRIMARY;
+        }
+
+        let start = self.out.len();
+        self.expression(left, left_min);
+        // A `/` after the `}` of a function or object literal would start a
+        // regular expression
+        if operator == "/" && self.out.ends_with('}') {
+            self.out.insert(start, '(');
+            self.out.push(')');
+        }
+        self.operator(operator);
+        self.expression(right, right_min);
+    }
+
+    fn arguments(&mut self, arguments: &[Expression]) {
+        self.write("(");
+        for (i, arg) in arguments.iter().enumerate() {
+            if i > 0 {
+                self.comma();
+            }
+            self.expression(arg, ASSIGN);
+        }
+        self.write(")");
+    }
+
+    fn object(&mut self, properties: &[Property]) {
+        if properties.is_empty() {
+            self.write("{}");
+            return;
+        }
+        // Members with a body get a line each, so the body can be
+        // indented under its member
+        let multiline = properties
+            .iter()
+            .any(|property| matches!(property.kind, PropertyKind::Method { .. }));
+        self.write("{");
+        if multiline {
+            self.depth += 1;
+        } else {
+            self.space();
+        }
+        for (i, property) in properties.iter().enumerate() {
+            if multiline {
+                if i > 0 {
+                    self.write(",");
+                }
+                self.newline();
+            } else if i > 0 {
+                self.comma();
+            }
+            match &property.kind {
+                PropertyKind::Value { key, value } => match (&key.kind, &value.kind) {
+                    (PropertyKeyKind::String(key), ExpressionKind::Identifier(name))
+                        if key == name && is_property_name(name) =>
+                    {
+                        self.write(name)
+                    }
+                    _ => {
+                        self.property_key(key);
+                        self.write(":");
+                        self.space();
+                        self.expression(value, ASSIGN);
+                    }
+                },
+                PropertyKind::Method {
+                    kind,
+                    key,
+                    params,
+                    body,
+                } => {
+                    match kind {
+                        MethodKind::Method => {}
+                        MethodKind::Get => self.write("get "),
+                        MethodKind::Set => self.write("set "),
+                    }
+                    self.property_key(key);
+                    self.parameters(params);
+                    self.space();
+                    self.block_statement(body);
+                }
+                PropertyKind::Spread(argument) => {
+                    self.write("...");
+                    self.expression(argument, ASSIGN);
+                }
+            }
+        }
+        if multiline {
+            self.depth -= 1;
+            self.newline();
+        } else {
+            self.space();
+        }
+        self.write("}");
+    }
+
+    fn property_key(&mut self, key: &PropertyKey) {
+        match &key.kind {
+            PropertyKeyKind::String(key) if is_property_name(key) => self.write(key),
+            PropertyKeyKind::String(key) => self.write(&string_literal(key)),
+            PropertyKeyKind::Number(value) => self.write(&number_literal(*value)),
+            PropertyKeyKind::Computed(expr) => {
+                self.write("[");
+                self.expression(expr, ASSIGN);
+                self.write("]");
+            }
+        }
+    }
+
+    fn index(&mut self, left: &Expression, index: &Expression) {
+        match &index.kind {
+            ExpressionKind::String(name) if is_property_name(name) => {
+                // `1.a` would read as the number `1.` followed by `a`
+                let is_integer = matches!(&left.kind, ExpressionKind::Number(value)
+                    if number_literal(*value).bytes().all(|b| b.is_ascii_digit()));
+                self.expression(left, if is_integer { PRIMARY + 1 } else { CALL });
+                self.write(".");
+                self.write(name);
+            }
+            _ => {
+                self.expression(left, CALL);
+                self.write("[");
+                self.expression(index, ASSIGN);
+                self.write("]");
+            }
+        }
+    }
+
+    fn template(&mut self, quasis: &[TemplateElement], expressions: &[Expression]) {
+        let mut text = String::from("`");
+        for (i, quasi) in quasis.iter().enumerate() {
+            text.push_str(&quasi.raw);
+            match expressions.get(i) {
+                Some(expr) => {
+                    text.push_str("${");
+                    self.write(&text);
+                    self.expression(expr, ASSIGN);
+                    text = String::from("}");
+                }
+                None => text.push('`'),
+            }
+        }
+        self.write(&text);
+    }
+}
+
+/// How tightly `expr` holds together as an operand.
+fn binding_power(expr: &Expression) -> u8 {
+    match &expr.kind {
+        ExpressionKind::Assign { .. } | ExpressionKind::Arrow { .. } => ASSIGN,
+        ExpressionKind::Conditional { .. } => CONDITIONAL,
+        ExpressionKind::Infix { operator, .. } => precedence::infix(operator),
+        ExpressionKind::Prefix { .. } | ExpressionKind::Update { prefix: true, .. } => PREFIX,
+        ExpressionKind::Update { prefix: false, .. } => POSTFIX,
+        ExpressionKind::Call { .. } | ExpressionKind::TaggedTemplate { .. } => CALL,
+        ExpressionKind::Index { .. } => MEMBER,
+        // Printed with a leading `-`
+        ExpressionKind::Number(value) if value.is_sign_negative() => PREFIX,
+        _ => PRIMARY,
+    }
+}
+
+/// Whether `expr` starts with a unary operator.
+fn is_unary(expr: &Expression) -> bool {
+    match &expr.kind {
+        ExpressionKind::Prefix { .. } => true,
+        ExpressionKind::Number(value) => value.is_sign_negative(),
+        _ => false,
+    }
+}
+
+/// Whether `expr` is parsed by a prefix operator taking it as its operand.
+fn is_prefix_form(expr: &Expression) -> bool {
+    is_unary(expr) || matches!(expr.kind, ExpressionKind::Update { prefix: true, .. })
+}
+
+fn mixes_nullish(operator: &str, operand: &Expression) -> bool {
+    let ExpressionKind::Infix {
+        operator: inner, ..
+    } = &operand.kind
+    else {
+        return false;
+    };
+    match operator {
+        "??" => matches!(inner.as_str(), "&&" | "||"),
+        "&&" | "||" => inner == "??",
+        _ => false,
+    }
+}
+
+/// Whether `stmt` ends with an `if` that has no `else`, which would take an
+/// `else` written after `stmt`.
+fn ends_with_open_if(stmt: &Statement) -> bool {
+    match &stmt.kind {
+        StatementKind::If {
+            alternative: None, ..
+        } => true,
+        StatementKind::If {
+            alternative: Some(body),
+            ..
+        }
+        | StatementKind::While { body, .. }
+        | StatementKind::For { body, .. }
+        | StatementKind::ForOf { body, .. }
+        | StatementKind::Labeled { body, .. } => ends_with_open_if(body),
+        _ => false,
+    }
+}
+
+/// Whether `c` can be part of an identifier, keyword or number, which must
+/// be kept apart from a neighboring one.
+fn is_word_char(c: char) -> bool {
+    if c.is_ascii() {
+        c.is_ascii_alphanumeric() || c == '$' || c == '_'
+    } else {
+        unicode_ident::is_xid_continue(c) || c == '\u{200c}' || c == '\u{200d}'
+    }
+}
+
+/// Whether `name` can be written as an identifier, as in `a.name`.
+pub(crate) fn is_identifier_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c == '$' || c == '_' || unicode_ident::is_xid_start(c))
+        && chars.all(|c| {
+            c == '$' || c == '\u{200c}' || c == '\u{200d}' || unicode_ident::is_xid_continue(c)
+        })
+}
+
+/// Whether `name` can be written unquoted as a property name.
+fn is_property_name(name: &str) -> bool {
+    is_identifier_name(name) && !KEYWORDS.contains(&name)
+}
+
+/// The shortest literal that reads back as `value`.
+fn number_literal(value: f64) -> String {
+    if value.is_nan() {
+        return "NaN".to_string();
+    }
+    if value.is_infinite() {
+        let sign = if value < 0.0 { "-" } else { "" };
+        return format!("{}1e999", sign);
+    }
+    // Debug formatting already switches to an exponent for large and small
+    // magnitudes, but keeps a `.0` on integers
+    let text = format!("{:?}", value);
+    match text.strip_suffix(".0") {
+        Some(integer) => integer.to_string(),
+        None => text,
+    }
+}
+
+fn string_literal(value: &str) -> String {
+    let mut literal = String::with_capacity(value.len() + 2);
+    literal.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            '\t' => literal.push_str("\\t"),
+            '\u{2028}' | '\u{2029}' => literal.push_str(&format!("\\u{:04x}", c as u32)),
+            c if c.is_ascii_control() => literal.push_str(&format!("\\x{:02x}", c as u32)),
+            c => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
@@ -0,0 +1,1222 @@
+//! Conversion between programs and ESTree JSON (<https://github.com/estree/estree>),
+//! as produced by parsers such as Acorn and Esprima.
+//!
+//! Every node has `type`, `loc` and `range`. `loc` columns start at 0 and
+//! count characters, and `range` holds byte offsets, so both match other
+//! parsers for ASCII source. The few nodes the AST keeps no position for,
+//! which are the clause of a `catch`, the function of a method and the
+//! declaration in a `for…of` head, have a null `loc` and `range`.
+//!
+//! The AST does not record how a member or key was written, so `a["b"]`
+//! and `{ "b": 1 }` are exported as `a.b` and `{ b: 1 }`.
+
+use crate::codegen::is_identifier_name;
+use crate::{
+    ArrowBody, DeclarationKind, ElementPattern, Expression, ExpressionKind, Identifier, MethodKind,
+    Parameter, Pattern, PatternKind, Position, Program, Property, PropertyKey, PropertyKeyKind,
+    PropertyKind, PropertyPattern, RestElement, Span, Statement, StatementKind, TemplateElement,
+};
+use serde_json::{json, Map, Value};
+use std::fmt;
+
+/// Why an ESTree document could not be imported: it is malformed, or uses
+/// syntax the AST cannot represent yet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EstreeError {
+    pub message: String,
+}
+
+impl EstreeError {
+    fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for EstreeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid ESTree: {}", self.message)
+    }
+}
+
+impl std::error::Error for EstreeError {}
+
+type Result<T> = std::result::Result<T, EstreeError>;
+
+impl Program {
+    /// The program as an ESTree `Program` node.
+    pub fn to_estree(&self) -> Value {
+        let span = match (self.statements.first(), self.statements.last()) {
+            (Some(first), Some(last)) => first.span.to(last.span),
+            _ => Span::default(),
+        };
+        node(
+            "Program",
+            span,
+            json!({
+                "body": statements(&self.statements),
+                "sourceType": "script",
+            }),
+        )
+    }
+
+    /// Reads a program back from an ESTree `Program` node.
+    pub fn from_estree(json: &Value) -> Result<Program> {
+        expect_type(json, "Program")?;
+        let statements = array(json, "body")?
+            .iter()
+            .map(import_statement)
+            .collect::<Result<_>>()?;
+        Ok(Program { statements })
+    }
+}
+
+// Export
+
+/// A node of type `kind` with `fields`, located at `span`.
+fn node(kind: &str, span: Span, fields: Value) -> Value {
+    let mut map = Map::new();
+    map.insert("type".to_string(), kind.into());
+    if let Value::Object(fields) = fields {
+        map.extend(fields);
+    }
+    let (loc, range) = if span == Span::default() {
+        (Value::Null, Value::Null)
+    } else {
+        let position = |p: Position| json!({ "line": p.line, "column": p.column - 1 });
+        (
+            json!({ "start": position(span.start), "end": position(span.end) }),
+            json!([span.start.offset, span.end.offset]),
+        )
+    };
+    map.insert("loc".to_string(), loc);
+    map.insert("range".to_string(), range);
+    Value::Object(map)
+}
+
+fn identifier(name: &str, span: Span) -> Value {
+    node("Identifier", span, json!({ "name": name }))
+}
+
+fn name(identifier: &Identifier) -> Value {
+    self::identifier(&identifier.name, identifier.span)
+}
+
+fn statements(statements: &[Statement]) -> Value {
+    statements.iter().map(statement).collect()
+}
+
+fn optional_statement(statement: Option<&Statement>) -> Value {
+    statement.map_or(Value::Null, self::statement)
+}
+
+fn optional_expression(expression: Option<&Expression>) -> Value {
+    expression.map_or(Value::Null, self::expression)
+}
+
+fn statement(statement: &Statement) -> Value {
+    let span = statement.span;
+    match &statement.kind {
+        StatementKind::Empty => node("EmptyStatement", span, json!({})),
+        StatementKind::Let { pattern, value } => declaration("let", pattern, value.as_ref(), span),
+        StatementKind::Const { pattern, value } => declaration("const", pattern, Some(value), span),
+        StatementKind::Return(argument) => node(
+            "ReturnStatement",
+            span,
+            json!({ "argument": optional_expression(argument.as_ref()) }),
+        ),
+        StatementKind::Expression(expr) => node(
+            "ExpressionStatement",
+            span,
+            json!({ "expression": expression(expr) }),
+        ),
+        StatementKind::Block(body) => {
+            node("BlockStatement", span, json!({ "body": statements(body) }))
+        }
+        StatementKind::If {
+            condition,
+            consequence,
+            alternative,
+        } => node(
+            "IfStatement",
+            span,
+            json!({
+                "test": expression(condition),
+                "consequent": self::statement(consequence),
+                "alternate": optional_statement(alternative.as_deref()),
+            }),
+        ),
+        StatementKind::Function { name, params, body } => {
+            function("FunctionDeclaration", Some(name), params, body, span)
+        }
+        StatementKind::While { condition, body } => node(
+            "WhileStatement",
+            span,
+            json!({ "test": expression(condition), "body": self::statement(body) }),
+        ),
+        StatementKind::DoWhile { body, condition } => node(
+            "DoWhileStatement",
+            span,
+            json!({ "body": self::statement(body), "test": expression(condition) }),
+        ),
+        StatementKind::For {
+            init,
+            condition,
+            update,
+            body,
+        } => {
+            // An expression initializer is the expression itself in ESTree
+            let init = match init.as_deref() {
+                Some(Statement {
+                    kind: StatementKind::Expression(expr),
+                    ..
+                }) => expression(expr),
+                init => optional_statement(init),
+            };
+            node(
+                "ForStatement",
+                span,
+                json!({
+                    "init": init,
+                    "test": optional_expression(condition.as_ref()),
+                    "update": optional_expression(update.as_ref()),
+                    "body": self::statement(body),
+                }),
+            )
+        }
+        StatementKind::ForOf {
+            declaration,
+            target,
+            iterable,
+            body,
+        } => {
+            let left = match declaration {
+                Some(DeclarationKind::Let) => {
+                    self::declaration("let", target, None, Span::default())
+                }
+                Some(DeclarationKind::Const) => {
+                    self::declaration("const", target, None, Span::default())
+                }
+                None => pattern(target),
+            };
+            node(
+                "ForOfStatement",
+                span,
+                json!({
+                    "left": left,
+                    "right": expression(iterable),
+                    "body": self::statement(body),
+                    "await": false,
+                }),
+            )
+        }
+        StatementKind::Break(label) => node(
+            "BreakStatement",
+            span,
+            json!({ "label": label.as_ref().map(name) }),
+        ),
+        StatementKind::Continue(label) => node(
+            "ContinueStatement",
+            span,
+            json!({ "label": label.as_ref().map(name) }),
+        ),
+        StatementKind::Labeled { label, body } => node(
+            "LabeledStatement",
+            span,
+            json!({
+                "label": name(label),
+                "body": self::statement(body),
+            }),
+        ),
+        StatementKind::Throw(argument) => node(
+            "ThrowStatement",
+            span,
+            json!({ "argument": expression(argument) }),
+        ),
+        StatementKind::Try {
+            block,
+            param,
+            handler,
+            finalizer,
+        } => {
+            let handler = handler.as_deref().map(|body| {
+                node(
+                    "CatchClause",
+                    Span::default(),
+                    json!({
+                        "param": param.as_ref().map(name),
+                        "body": self::statement(body),
+                    }),
+                )
+            });
+            node(
+                "TryStatement",
+                span,
+                json!({
+                    "block": self::statement(block),
+                    "handler": handler,
+                    "finalizer": optional_statement(finalizer.as_deref()),
+                }),
+            )
+        }
+    }
+}
+
+fn declaration(kind: &str, target: &Pattern, value: Option<&Expression>, span: Span) -> Value {
+    let declarator_span = match value {
+        Some(value) => target.span.to(value.span),
+        None => target.span,
+    };
+    let declarator = node(
+        "VariableDeclarator",
+        declarator_span,
+        json!({
+            "id": pattern(target),
+            "init": optional_expression(value),
+        }),
+    );
+    node(
+        "VariableDeclaration",
+        span,
+        json!({ "declarations": [declarator], "kind": kind }),
+    )
+}
+
+fn function(
+    kind: &str,
+    id: Option<&Identifier>,
+    params: &[Parameter],
+    body: &Statement,
+    span: Span,
+) -> Value {
+    node(
+        kind,
+        span,
+        json!({
+            "id": id.map(name),
+            "params": parameters(params),
+            "body": statement(body),
+            "generator": false,
+            "async": false,
+            "expression": false,
+        }),
+    )
+}
+
+fn parameters(params: &[Parameter]) -> Vec<Value> {
+    params
+        .iter()
+        .map(|param| {
+            if param.rest {
+                rest_element(&param.target, param.span)
+            } else {
+                with_default(&param.target, param.default.as_ref(), param.span)
+            }
+        })
+        .collect()
+}
+
+fn pattern(pattern: &Pattern) -> Value {
+    let span = pattern.span;
+    match &pattern.kind {
+        PatternKind::Identifier(name) => identifier(name, span),
+        PatternKind::Member(expr) => expression(expr),
+        PatternKind::Object { properties, rest } => {
+            let mut properties: Vec<Value> = properties
+                .iter()
+                .map(|property| {
+                    let shorthand = matches!((&property.key.kind, &property.value.kind),
+                        (PropertyKeyKind::String(key), PatternKind::Identifier(name)) if key == name);
+                    let (key, computed) = property_key(&property.key);
+                    // A shorthand's value has the key's position, even with a default
+                    let value_span = match property.default {
+                        Some(_) if shorthand => property.span,
+                        Some(_) => property.value.span.to(property.span),
+                        None => property.value.span,
+                    };
+                    node(
+                        "Property",
+                        property.span,
+                        json!({
+                            "key": key,
+                            "value": with_default(&property.value, property.default.as_ref(), value_span),
+                            "kind": "init",
+                            "method": false,
+                            "shorthand": shorthand,
+                            "computed": computed,
+                        }),
+                    )
+                })
+                .collect();
+            properties.extend(
+                rest.as_deref()
+                    .map(|rest| rest_element(&rest.target, rest.span)),
+            );
+            node("ObjectPattern", span, json!({ "properties": properties }))
+        }
+        PatternKind::Array { elements, rest } => {
+            let mut elements: Vec<Value> = elements
+                .iter()
+                .map(|element| {
+                    element.as_ref().map_or(Value::Null, |element| {
+                        with_default(&element.target, element.default.as_ref(), element.span)
+                    })
+                })
+                .collect();
+            elements.extend(
+                rest.as_deref()
+                    .map(|rest| rest_element(&rest.target, rest.span)),
+            );
+            node("ArrayPattern", span, json!({ "elements": elements }))
+        }
+    }
+}
+
+/// `target`, or an `AssignmentPattern` at `span` when it has a default.
+fn with_default(target: &Pattern, default: Option<&Expression>, span: Span) -> Value {
+    match default {
+        Some(default) => node(
+            "AssignmentPattern",
+            span,
+            json!({ "left": pattern(target), "right": expression(default) }),
+        ),
+        None => pattern(target),
+    }
+}
+
+fn rest_element(target: &Pattern, span: Span) -> Value {
+    node("RestElement", span, json!({ "argument": pattern(target) }))
+}
+
+/// The key of a property and whether it is computed.
+fn property_key(key: &PropertyKey) -> (Value, bool) {
+    let span = key.span;
+    match &key.kind {
+        PropertyKeyKind::String(key) if is_identifier_name(key) => (identifier(key, span), false),
+        PropertyKeyKind::String(key) => (node("Literal", span, json!({ "value": key })), false),
+        PropertyKeyKind::Number(value) => (number(*value, span), false),
+        PropertyKeyKind::Computed(expr) => (expression(expr), true),
+    }
+}
+
+fn property(property: &Property) -> Value {
+    let span = property.span;
+    let (key, computed, value, kind, method, shorthand) = match &property.kind {
+        PropertyKind::Value { key, value } => {
+            let shorthand = matches!((&key.kind, &value.kind),
+                (PropertyKeyKind::String(key), ExpressionKind::Identifier(name)) if key == name);
+            let (key, computed) = property_key(key);
+            (key, computed, expression(value), "init", false, shorthand)
+        }
+        PropertyKind::Method {
+            kind,
+            key,
+            params,
+            body,
+        } => {
+            let (key, computed) = property_key(key);
+            let value = function("FunctionExpression", None, params, body, Span::default());
+            let (kind, method) = match kind {
+                MethodKind::Method => ("init", true),
+                MethodKind::Get => ("get", false),
+                MethodKind::Set => ("set", false),
+            };
+            (key, computed, value, kind, method, false)
+        }
+        PropertyKind::Spread(argument) => {
+            return node(
+                "SpreadElement",
+                span,
+                json!({ "argument": expression(argument) }),
+            )
+        }
+    };
+    node(
+        "Property",
+        span,
+        json!({
+            "key": key,
+            "value": value,
+            "kind": kind,
+            "method": method,
+            "shorthand": shorthand,
+            "computed": computed,
+        }),
+    )
+}
+
+fn expression(expr: &Expression) -> Value {
+    let span = expr.span;
+    match &expr.kind {
+        ExpressionKind::Identifier(name) => identifier(name, span),
+        ExpressionKind::Number(value) => number(*value, span),
+        ExpressionKind::BigInt(digits) => {
+            node("Literal", span, json!({ "value": null, "bigint": digits }))
+        }
+        ExpressionKind::String(value) => node("Literal", span, json!({ "value": value })),
+        ExpressionKind::RegExp { pattern, flags } => node(
+            "Literal",
+            span,
+            json!({ "value": null, "regex": { "pattern": pattern, "flags": flags } }),
+        ),
+        ExpressionKind::Boolean(value) => node("Literal", span, json!({ "value": value })),
+        ExpressionKind::Null => node("Literal", span, json!({ "value": null })),
+        ExpressionKind::This => node("ThisExpression", span, json!({})),
+        ExpressionKind::Prefix { operator, right } => node(
+            "UnaryExpression",
+            span,
+            json!({ "operator": operator, "prefix": true, "argument": expression(right) }),
+        ),
+        ExpressionKind::Infix {
+            left,
+            operator,
+            right,
+        } => {
+            let kind = match operator.as_str() {
+                "&&" | "||" | "??" => "LogicalExpression",
+                _ => "BinaryExpression",
+            };
+            node(
+                kind,
+                span,
+                json!({
+                    "left": expression(left),
+                    "operator": operator,
+                    "right": expression(right),
+                }),
+            )
+        }
+        ExpressionKind::Conditional {
+            condition,
+            consequence,
+            alternative,
+        } => node(
+            "ConditionalExpression",
+            span,
+            json!({
+                "test": expression(condition),
+                "consequent": expression(consequence),
+                "alternate": expression(alternative),
+            }),
+        ),
+        ExpressionKind::Call {
+            function,
+            arguments,
+        } => node(
+            "CallExpression",
+            span,
+            json!({
+                "callee": expression(function),
+                "arguments": arguments.iter().map(expression).collect::<Vec<_>>(),
+                "optional": false,
+            }),
+        ),
+        ExpressionKind::Array(elements) => node(
+            "ArrayExpression",
+            span,
+            json!({ "elements": elements.iter().map(expression).collect::<Vec<_>>() }),
+        ),
+        ExpressionKind::Spread(argument) => node(
+            "SpreadElement",
+            span,
+            json!({ "argument": expression(argument) }),
+        ),
+        ExpressionKind::Object(properties) => node(
+            "ObjectExpression",
+            span,
+            json!({ "properties": properties.iter().map(property).collect::<Vec<_>>() }),
+        ),
+        ExpressionKind::Index { left, index } => {
+            let (property, computed) = match &index.kind {
+                ExpressionKind::String(name) if is_identifier_name(name) => {
+                    (identifier(name, index.span), false)
+                }
+                _ => (expression(index), true),
+            };
+            node(
+                "MemberExpression",
+                span,
+                json!({
+                    "object": expression(left),
+                    "property": property,
+                    "computed": computed,
+                    "optional": false,
+                }),
+            )
+        }
+        ExpressionKind::Assign {
+            target,
+            operator,
+            value,
+        } => node(
+            "AssignmentExpression",
+            span,
+            json!({
+                "operator": operator,
+                "left": pattern(target),
+                "right": expression(value),
+            }),
+        ),
+        ExpressionKind::Update {
+            operator,
+            prefix,
+            target,
+        } => node(
+            "UpdateExpression",
+            span,
+            json!({ "operator": operator, "prefix": prefix, "argument": expression(target) }),
+        ),
+        ExpressionKind::Function { name, params, body } => {
+            function("FunctionExpression", name.as_ref(), params, body, span)
+        }
+        ExpressionKind::Arrow { params, body } => {
+            let (body, is_expression) = match body {
+                ArrowBody::Expression(expr) => (expression(expr), true),
+                ArrowBody::Block(block) => (statement(block), false),
+            };
+            node(
+                "ArrowFunctionExpression",
+                span,
+                json!({
+                    "id": null,
+                    "params": parameters(params),
+                    "body": body,
+                    "generator": false,
+                    "async": false,
+                    "expression": is_expression,
+                }),
+            )
+        }
+        ExpressionKind::Template {
+            quasis,
+            expressions,
+        } => {
+            let last = quasis.len().saturating_sub(1);
+            let quasis: Vec<Value> = quasis
+                .iter()
+                .enumerate()
+                .map(|(i, quasi)| template_element(quasi, i == last))
+                .collect();
+            node(
+                "TemplateLiteral",
+                span,
+                json!({
+                    "quasis": quasis,
+                    "expressions": expressions.iter().map(expression).collect::<Vec<_>>(),
+                }),
+            )
+        }
+        ExpressionKind::TaggedTemplate { tag, quasi } => node(
+            "TaggedTemplateExpression",
+            span,
+            json!({ "tag": expression(tag), "quasi": expression(quasi) }),
+        ),
+    }
+}
+
+/// A number literal. JSON has no infinity, so a literal too large for an
+/// f64 keeps its value as null and gets a `raw` that reads back the same.
+fn number(value: f64, span: Span) -> Value {
+    let fields = if value.is_infinite() {
+        json!({ "value": null, "raw": "1e999" })
+    } else if value.fract() == 0.0 && value.abs() < 9007199254740992.0 {
+        json!({ "value": value as i64 })
+    } else {
+        json!({ "value": value })
+    };
+    node("Literal", span, fields)
+}
+
+fn template_element(element: &TemplateElement, tail: bool) -> Value {
+    node(
+        "TemplateElement",
+        element.span,
+        json!({
+            "value": { "raw": element.raw, "cooked": element.cooked },
+            "tail": tail,
+        }),
+    )
+}
+
+// Import
+
+fn field<'v>(node: &'v Value, key: &str) -> Result<&'v Value> {
+    node.get(key)
+        .ok_or_else(|| EstreeError::new(format!("{} is missing '{}'", describe(node), key)))
+}
+
+fn string(node: &Value, key: &str) -> Result<String> {
+    field(node, key)?
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| {
+            EstreeError::new(format!("'{}' of {} must be a string", key, describe(node)))
+        })
+}
+
+fn boolean(node: &Value, key: &str) -> Result<bool> {
+    match node.get(key) {
+        None | Some(Value::Null) => Ok(false),
+        Some(value) => value.as_bool().ok_or_else(|| {
+            EstreeError::new(format!("'{}' of {} must be a boolean", key, describe(node)))
+        }),
+    }
+}
+
+fn array<'v>(node: &'v Value, key: &str) -> Result<&'v Vec<Value>> {
+    field(node, key)?.as_array().ok_or_else(|| {
+        EstreeError::new(format!("'{}' of {} must be an array", key, describe(node)))
+    })
+}
+
+/// The child node at `key`, or None when it is null or missing.
+fn optional<'v>(node: &'v Value, key: &str) -> Option<&'v Value> {
+    node.get(key).filter(|value| !value.is_null())
+}
+
+fn node_type(node: &Value) -> Result<&str> {
+    node.get("type")
+        .and_then(Value::as_str)
+        .ok_or_else(|| EstreeError::new("a node has no 'type'"))
+}
+
+fn describe(node: &Value) -> String {
+    match node.get("type").and_then(Value::as_str) {
+        Some(kind) => format!("a {} node", kind),
+        None => "a node".to_string(),
+    }
+}
+
+fn expect_type(node: &Value, kind: &str) -> Result<()> {
+    let found = node_type(node)?;
+    if found == kind {
+        Ok(())
+    } else {
+        Err(EstreeError::new(format!(
+            "expected {}, found {}",
+            kind, found
+        )))
+    }
+}
+
+fn unsupported(node: &Value) -> EstreeError {
+    EstreeError::new(format!("{} is not supported", describe(node)))
+}
+
+/// The span from `loc` and `range`. Either may be missing or null, as
+/// nodes without a known location are.
+fn import_span(node: &Value) -> Result<Span> {
+    let Some(loc) = optional(node, "loc") else {
+        return Ok(Span::default());
+    };
+    let offsets = match optional(node, "range") {
+        Some(range) => {
+            let offset = |i: usize| range.get(i).and_then(Value::as_u64).map(|n| n as usize);
+            match (offset(0), offset(1)) {
+                (Some(start), Some(end)) => (start, end),
+                _ => return Err(EstreeError::new("'range' must hold two offsets")),
+            }
+        }
+        None => (0, 0),
+    };
+    let position = |key: &str, offset: usize| -> Result<Position> {
+        let point = field(loc, key)?;
+        let number = |key: &str| {
+            point
+                .get(key)
+                .and_then(Value::as_u64)
+                .map(|n| n as usize)
+                .ok_or_else(|| EstreeError::new(format!("'loc' is missing '{}'", key)))
+        };
+        Ok(Position::new(
+            number("line")?,
+            number("column")? + 1,
+            offset,
+        ))
+    };
+    Ok(Span::new(
+        position("start", offsets.0)?,
+        position("end", offsets.1)?,
+    ))
+}
+
+fn import_identifier(node: &Value) -> Result<Identifier> {
+    expect_type(node, "Identifier")?;
+    Ok(Identifier::new(string(node, "name")?, import_span(node)?))
+}
+
+fn import_statements(node: &Value, key: &str) -> Result<Vec<Statement>> {
+    array(node, key)?.iter().map(import_statement).collect()
+}
+
+fn import_optional_statement(node: &Value, key: &str) -> Result<Option<Box<Statement>>> {
+    optional(node, key)
+        .map(|child| import_statement(child).map(Box::new))
+        .transpose()
+}
+
+fn import_optional_expression(node: &Value, key: &str) -> Result<Option<Expression>> {
+    optional(node, key).map(import_expression).transpose()
+}
+
+fn import_child_statement(node: &Value, key: &str) -> Result<Box<Statement>> {
+    import_statement(field(node, key)?).map(Box::new)
+}
+
+fn import_child(node: &Value, key: &str) -> Result<Box<Expression>> {
+    import_expression(field(node, key)?).map(Box::new)
+}
+
+fn import_statement(node: &Value) -> Result<Statement> {
+    let span = import_span(node)?;
+    let kind = match node_type(node)? {
+        "EmptyStatement" => StatementKind::Empty,
+        "VariableDeclaration" => {
+            let [declarator] = array(node, "declarations")?.as_slice() else {
+                return Err(EstreeError::new(
+                    "only declarations of a single variable are supported",
+                ));
+            };
+            expect_type(declarator, "VariableDeclarator")?;
+            let pattern = import_pattern(field(declarator, "id")?)?;
+            let value = import_optional_expression(declarator, "init")?;
+            match (string(node, "kind")?.as_str(), value) {
+                ("let", None) if !matches!(pattern.kind, PatternKind::Identifier(_)) => {
+                    return Err(EstreeError::new(
+                        "a destructuring declaration needs an initializer",
+                    ))
+                }
+                ("let", value) => StatementKind::Let { pattern, value },
+                ("const", Some(value)) => StatementKind::Const { pattern, value },
+                ("const", None) => {
+                    return Err(EstreeError::new("a const declaration needs an initializer"))
+                }
+                (kind, _) => {
+                    return Err(EstreeError::new(format!(
+                        "'{}' declarations are not supported",
+                        kind
+                    )))
+                }
+            }
+        }
+        "ReturnStatement" => StatementKind::Return(import_optional_expression(node, "argument")?),
+        "ExpressionStatement" => {
+            StatementKind::Expression(import_expression(field(node, "expression")?)?)
+        }
+        "BlockStatement" => StatementKind::Block(import_statements(node, "body")?),
+        "IfStatement" => StatementKind::If {
+            condition: import_expression(field(node, "test")?)?,
+            consequence: import_child_statement(node, "consequent")?,
+            alternative: import_optional_statement(node, "alternate")?,
+        },
+        "FunctionDeclaration" => {
+            let (name, params, body) = import_function(node)?;
+            let name =
+                name.ok_or_else(|| EstreeError::new("a function declaration needs a name"))?;
+            StatementKind::Function { name, params, body }
+        }
+        "WhileStatement" => StatementKind::While {
+            condition: import_expression(field(node, "test")?)?,
+            body: import_child_statement(node, "body")?,
+        },
+        "DoWhileStatement" => StatementKind::DoWhile {
+            body: import_child_statement(node, "body")?,
+            condition: import_expression(field(node, "test")?)?,
+        },
+        "ForStatement" => {
+            let init = match optional(node, "init") {
+                Some(init) if node_type(init)? == "VariableDeclaration" => {
+                    Some(Box::new(import_statement(init)?))
+                }
+                Some(init) => {
+                    let expr = import_expression(init)?;
+                    let span = expr.span;
+                    Some(Box::new(Statement::new(
+                        StatementKind::Expression(expr),
+                        span,
+                    )))
+                }
+                None => None,
+            };
+            StatementKind::For {
+                init,
+                condition: import_optional_expression(node, "test")?,
+                update: import_optional_expression(node, "update")?,
+                body: import_child_statement(node, "body")?,
+            }
+        }
+        "ForOfStatement" if !boolean(node, "await")? => {
+            let left = field(node, "left")?;
+            let (declaration, target) = if node_type(left)? == "VariableDeclaration" {
+                let declaration = match string(left, "kind")?.as_str() {
+                    "let" => DeclarationKind::Let,
+                    "const" => DeclarationKind::Const,
+                    _ => return Err(unsupported(left)),
+                };
+                let [declarator] = array(left, "declarations")?.as_slice() else {
+                    return Err(EstreeError::new("a for-of loop declares a single variable"));
+                };
+                expect_type(declarator, "VariableDeclarator")?;
+                if optional(declarator, "init").is_some() {
+                    return Err(EstreeError::new(
+                        "a for-of loop variable cannot have an initializer",
+                    ));
+                }
+                (Some(declaration), import_pattern(field(declarator, "id")?)?)
+            } else {
+                (None, import_pattern(left)?)
+            };
+            StatementKind::ForOf {
+                declaration,
+                target,
+                iterable: import_expression(field(node, "right")?)?,
+                body: import_child_statement(node, "body")?,
+            }
+        }
+        "BreakStatement" => {
+            StatementKind::Break(optional(node, "label").map(import_identifier).transpose()?)
+        }
+        "ContinueStatement" => {
+            StatementKind::Continue(optional(node, "label").map(import_identifier).transpose()?)
+        }
+        "LabeledStatement" => StatementKind::Labeled {
+            label: import_identifier(field(node, "label")?)?,
+            body: import_child_statement(node, "body")?,
+        },
+        "ThrowStatement" => StatementKind::Throw(import_expression(field(node, "argument")?)?),
+        "TryStatement" => {
+            let (param, handler) = match optional(node, "handler") {
+                Some(clause) => {
+                    expect_type(clause, "CatchClause")?;
+                    let param = optional(clause, "param")
+                        .map(import_identifier)
+                        .transpose()?;
+                    (param, Some(import_child_statement(clause, "body")?))
+                }
+                None => (None, None),
+            };
+            StatementKind::Try {
+                block: import_child_statement(node, "block")?,
+                param,
+                handler,
+                finalizer: import_optional_statement(node, "finalizer")?,
+            }
+        }
+        _ => return Err(unsupported(node)),
+    };
+    Ok(Statement::new(kind, span))
+}
+
+/// The name, parameters and body of a function node.
+fn import_function(node: &Value) -> Result<(Option<Identifier>, Vec<Parameter>, Box<Statement>)> {
+    if boolean(node, "generator")? || boolean(node, "async")? {
+        return Err(unsupported(node));
+    }
+    let name = optional(node, "id").map(import_identifier).transpose()?;
+    let params = import_parameters(node)?;
+    let body = field(node, "body")?;
+    expect_type(body, "BlockStatement")?;
+    Ok((name, params, Box::new(import_statement(body)?)))
+}
+
+fn import_parameters(node: &Value) -> Result<Vec<Parameter>> {
+    let params = array(node, "params")?;
+    params
+        .iter()
+        .enumerate()
+        .map(|(i, param)| match node_type(param)? {
+            "RestElement" if i == params.len() - 1 => Ok(Parameter {
+                target: import_pattern(field(param, "argument")?)?,
+                default: None,
+                rest: true,
+                span: import_span(param)?,
+            }),
+            "RestElement" => Err(EstreeError::new("a rest parameter must be last")),
+            _ => {
+                let (target, default) = import_with_default(param)?;
+                Ok(Parameter {
+                    target,
+                    default,
+                    rest: false,
+                    span: import_span(param)?,
+                })
+            }
+        })
+        .collect()
+}
+
+fn import_pattern(node: &Value) -> Result<Pattern> {
+    let kind = match node_type(node)? {
+        "Identifier" => PatternKind::Identifier(import_identifier(node)?.name),
+        "MemberExpression" => PatternKind::Member(Box::new(import_expression(node)?)),
+        "ObjectPattern" => {
+            let (rest, properties) = split_rest(array(node, "properties")?)?;
+            let properties = properties
+                .iter()
+                .map(|property| {
+                    expect_type(property, "Property")?;
+                    if string(property, "kind")? != "init" || boolean(property, "method")? {
+                        return Err(unsupported(property));
+                    }
+                    let (value, default) = import_with_default(field(property, "value")?)?;
+                    Ok(PropertyPattern {
+                        key: import_property_key(property)?,
+                        value,
+                        default,
+                        span: import_span(property)?,
+                    })
+                })
+                .collect::<Result<_>>()?;
+            PatternKind::Object { properties, rest }
+        }
+        "ArrayPattern" => {
+            let (rest, elements) = split_rest(array(node, "elements")?)?;
+            let elements = elements
+                .iter()
+                .map(|element| match element {
+                    Value::Null => Ok(None),
+                    element => {
+                        let (target, default) = import_with_default(element)?;
+                        let span = import_span(element)?;
+                        Ok(Some(ElementPattern {
+                            target,
+                            default,
+                            span,
+                        }))
+                    }
+                })
+                .collect::<Result<_>>()?;
+            PatternKind::Array { elements, rest }
+        }
+        _ => return Err(unsupported(node)),
+    };
+    Ok(Pattern::new(kind, import_span(node)?))
+}
+
+/// A pattern, taking apart the `AssignmentPattern` that gives it a default.
+fn import_with_default(node: &Value) -> Result<(Pattern, Option<Expression>)> {
+    if node_type(node)? == "AssignmentPattern" {
+        let target = import_pattern(field(node, "left")?)?;
+        Ok((target, Some(import_expression(field(node, "right")?)?)))
+    } else {
+        Ok((import_pattern(node)?, None))
+    }
+}
+
+/// Splits the `RestElement` off the end of a pattern's items.
+fn split_rest(items: &[Value]) -> Result<(Option<Box<RestElement>>, &[Value])> {
+    if let Some((last, others)) = items.split_last() {
+        if !last.is_null() && node_type(last)? == "RestElement" {
+            let rest = RestElement {
+                target: import_pattern(field(last, "argument")?)?,
+                span: import_span(last)?,
+            };
+            return Ok((Some(Box::new(rest)), others));
+        }
+    }
+    if items
+        .iter()
+        .any(|item| !item.is_null() && node_type(item).ok() == Some("RestElement"))
+    {
+        return Err(EstreeError::new("a rest element must be last"));
+    }
+    Ok((None, items))
+}
+
+fn import_expressions(node: &Value, key: &str) -> Result<Vec<Expression>> {
+    array(node, key)?.iter().map(import_expression).collect()
+}
+
+fn import_expression(node: &Value) -> Result<Expression> {
+    let span = import_span(node)?;
+    let kind = match node_type(node)? {
+        "Identifier" => ExpressionKind::Identifier(string(node, "name")?),
+        "Literal" => import_literal(node)?,
+        "ThisExpression" => ExpressionKind::This,
+        "UnaryExpression" => ExpressionKind::Prefix {
+            operator: string(node, "operator")?,
+            right: import_child(node, "argument")?,
+        },
+        "BinaryExpression" | "LogicalExpression" => ExpressionKind::Infix {
+            left: import_child(node, "left")?,
+            operator: string(node, "operator")?,
+            right: import_child(node, "right")?,
+        },
+        "ConditionalExpression" => ExpressionKind::Conditional {
+            condition: import_child(node, "test")?,
+            consequence: import_child(node, "consequent")?,
+            alternative: import_child(node, "alternate")?,
+        },
+        "CallExpression" if !boolean(node, "optional")? => ExpressionKind::Call {
+            function: import_child(node, "callee")?,
+            arguments: import_expressions(node, "arguments")?,
+        },
+        "ArrayExpression" => ExpressionKind::Array(import_expressions(node, "elements")?),
+        "SpreadElement" => ExpressionKind::Spread(import_child(node, "argument")?),
+        "ObjectExpression" => {
+            let properties = array(node, "properties")?
+                .iter()
+                .map(import_property)
+                .collect::<Result<_>>()?;
+            ExpressionKind::Object(properties)
+        }
+        "MemberExpression" if !boolean(node, "optional")? => {
+            let property = field(node, "property")?;
+            let index = if boolean(node, "computed")? {
+                import_expression(property)?
+            } else {
+                let property = import_identifier(property)?;
+                Expression::new(ExpressionKind::String(property.name), property.span)
+            };
+            ExpressionKind::Index {
+                left: import_child(node, "object")?,
+                index: Box::new(index),
+            }
+        }
+        "AssignmentExpression" => ExpressionKind::Assign {
+            target: import_pattern(field(node, "left")?)?,
+            operator: string(node, "operator")?,
+            value: import_child(node, "right")?,
+        },
+        "UpdateExpression" => ExpressionKind::Update {
+            operator: string(node, "operator")?,
+            prefix: boolean(node, "prefix")?,
+            target: import_child(node, "argument")?,
+        },
+        "FunctionExpression" => {
+            let (name, params, body) = import_function(node)?;
+            ExpressionKind::Function { name, params, body }
+        }
+        "ArrowFunctionExpression" => {
+            if boolean(node, "generator")? || boolean(node, "async")? {
+                return Err(unsupported(node));
+            }
+            let body = field(node, "body")?;
+            let body = if node_type(body)? == "BlockStatement" {
+                ArrowBody::Block(Box::new(import_statement(body)?))
+            } else {
+                ArrowBody::Expression(Box::new(import_expression(body)?))
+            };
+            ExpressionKind::Arrow {
+                params: import_parameters(node)?,
+                body,
+            }
+        }
+        "TemplateLiteral" => {
+            let quasis = array(node, "quasis")?
+                .iter()
+                .map(import_template_element)
+                .collect::<Result<_>>()?;
+            ExpressionKind::Template {
+                quasis,
+                expressions: import_expressions(node, "expressions")?,
+            }
+        }
+        "TaggedTemplateExpression" => {
+            let quasi = field(node, "quasi")?;
+            expect_type(quasi, "TemplateLiteral")?;
+            ExpressionKind::TaggedTemplate {
+                tag: import_child(node, "tag")?,
+                quasi: Box::new(import_expression(quasi)?),
+            }
+        }
+        _ => return Err(unsupported(node)),
+    };
+    Ok(Expression::new(kind, span))
+}
+
+fn import_literal(node: &Value) -> Result<ExpressionKind> {
+    if let Some(regex) = optional(node, "regex") {
+        return Ok(ExpressionKind::RegExp {
+            pattern: string(regex, "pattern")?,
+            flags: string(regex, "flags")?,
+        });
+    }
+    if optional(node, "bigint").is_some() {
+        return Ok(ExpressionKind::BigInt(string(node, "bigint")?));
+    }
+    Ok(match field(node, "value")? {
+        Value::Number(n) => ExpressionKind::Number(n.as_f64().unwrap_or(f64::NAN)),
+        Value::String(s) => ExpressionKind::String(s.clone()),
+        Value::Bool(b) => ExpressionKind::Boolean(*b),
+        // A number JSON cannot hold, such as `1e999`
+        Value::Null => match optional(node, "raw")
+            .and_then(Value::as_str)
+            .and_then(|raw| raw.parse::<f64>().ok())
+        {
+            Some(value) => ExpressionKind::Number(value),
+            None => ExpressionKind::Null,
+        },
+        _ => return Err(EstreeError::new("a Literal has an invalid 'value'")),
+    })
+}
+
+fn import_property(node: &Value) -> Result<Property> {
+    let span = import_span(node)?;
+    if node_type(node)? == "SpreadElement" {
+        let argument = import_expression(field(node, "argument")?)?;
+        return Ok(Property::new(PropertyKind::Spread(argument), span));
+    }
+    expect_type(node, "Property")?;
+    let key = import_property_key(node)?;
+    let value = field(node, "value")?;
+    let kind = match (string(node, "kind")?.as_str(), boolean(node, "method")?) {
+        ("init", false) => {
+            let value = import_expression(value)?;
+            return Ok(Property::new(PropertyKind::Value { key, value }, span));
+        }
+        ("init", true) => MethodKind::Method,
+        ("get", false) => MethodKind::Get,
+        ("set", false) => MethodKind::Set,
+        _ => return Err(unsupported(node)),
+    };
+    expect_type(value, "FunctionExpression")?;
+    let (name, params, body) = import_function(value)?;
+    if name.is_some() {
+        return Err(EstreeError::new("a method cannot have an 'id'"));
+    }
+    let kind = PropertyKind::Method {
+        kind,
+        key,
+        params,
+        body,
+    };
+    Ok(Property::new(kind, span))
+}
+
+/// The key of a `Property` node in an object literal or pattern.
+fn import_property_key(property: &Value) -> Result<PropertyKey> {
+    let key = field(property, "key")?;
+    let span = import_span(key)?;
+    let kind = if boolean(property, "computed")? {
+        PropertyKeyKind::Computed(Box::new(import_expression(key)?))
+    } else if node_type(key)? == "Identifier" {
+        PropertyKeyKind::String(import_identifier(key)?.name)
+    } else {
+        expect_type(key, "Literal")?;
+        match import_literal(key)? {
+            ExpressionKind::String(key) => PropertyKeyKind::String(key),
+            ExpressionKind::Number(value) => PropertyKeyKind::Number(value),
+            _ => {
+                return Err(EstreeError::new(
+                    "property keys must be names, strings or numbers",
+                ))
+            }
+        }
+    };
+    Ok(PropertyKey::new(kind, span))
+}
+
+fn import_template_element(node: &Value) -> Result<TemplateElement> {
+    expect_type(node, "TemplateElement")?;
+    let value = field(node, "value")?;
+    let cooked = optional(value, "cooked")
+        .map(|_| string(value, "cooked"))
+        .transpose()?;
+    Ok(TemplateElement {
+        cooked,
+        raw: string(value, "raw")?,
+        span: import_span(node)?,
+    })
+}
@@ -0,0 +1,376 @@
+//! Rebuilding traversal of the AST.
+//!
+//! [`Fold`] takes each node by value and returns the node to put in its
+//! place. Like [`VisitMut`](crate::VisitMut) it works a node at a time, and
+//! each method defaults to folding the node's children in source order
+//! through the matching `walk_` function. Every span goes through
+//! [`Fold::fold_span`], so a folder can move or clear positions without
+//! rebuilding each kind of node.
+
+use crate::{
+    ArrowBody, ElementPattern, Expression, ExpressionKind, Identifier, Parameter, Pattern,
+    PatternKind, Program, Property, PropertyKey, PropertyKeyKind, PropertyKind, PropertyPattern,
+    RestElement, Span, Statement, StatementKind, TemplateElement,
+};
+
+pub trait Fold {
+    fn fold_program(&mut self, program: Program) -> Program {
+        walk_program(self, program)
+    }
+
+    fn fold_statement(&mut self, stmt: Statement) -> Statement {
+        walk_statement(self, stmt)
+    }
+
+    fn fold_expression(&mut self, expr: Expression) -> Expression {
+        walk_expression(self, expr)
+    }
+
+    fn fold_template_element(&mut self, element: TemplateElement) -> TemplateElement {
+        TemplateElement {
+            span: self.fold_span(element.span),
+            ..element
+        }
+    }
+
+    /// Called with the span of every node once its children are folded.
+    fn fold_span(&mut self, span: Span) -> Span {
+        span
+    }
+}
+
+pub fn walk_program<F: Fold + ?Sized>(folder: &mut F, program: Program) -> Program {
+    Program {
+        statements: fold_statements(folder, program.statements),
+    }
+}
+
+fn fold_statements<F: Fold + ?Sized>(folder: &mut F, stmts: Vec<Statement>) -> Vec<Statement> {
+    stmts
+        .into_iter()
+        .map(|stmt| folder.fold_statement(stmt))
+        .collect()
+}
+
+fn fold_expressions<F: Fold + ?Sized>(folder: &mut F, exprs: Vec<Expression>) -> Vec<Expression> {
+    exprs
+        .into_iter()
+        .map(|expr| folder.fold_expression(expr))
+        .collect()
+}
+
+fn fold_identifier<F: Fold + ?Sized>(folder: &mut F, identifier: Identifier) -> Identifier {
+    Identifier::new(identifier.name, folder.fold_span(identifier.span))
+}
+
+fn fold_parameters<F: Fold + ?Sized>(folder: &mut F, params: Vec<Parameter>) -> Vec<Parameter> {
+    params
+        .into_iter()
+        .map(|param| Parameter {
+            target: fold_pattern(folder, param.target),
+            default: param.default.map(|default| folder.fold_expression(default)),
+            rest: param.rest,
+            span: folder.fold_span(param.span),
+        })
+        .collect()
+}
+
+fn fold_pattern<F: Fold + ?Sized>(folder: &mut F, pattern: Pattern) -> Pattern {
+    let kind = match pattern.kind {
+        kind @ PatternKind::Identifier(_) => kind,
+        PatternKind::Member(expr) => PatternKind::Member(fold_boxed_expression(folder, expr)),
+        PatternKind::Object { properties, rest } => PatternKind::Object {
+            properties: properties
+                .into_iter()
+                .map(|property| PropertyPattern {
+                    key: fold_property_key(folder, property.key),
+                    value: fold_pattern(folder, property.value),
+                    default: property
+                        .default
+                        .map(|default| folder.fold_expression(default)),
+                    span: folder.fold_span(property.span),
+                })
+                .collect(),
+            rest: rest.map(|rest| fold_rest_element(folder, rest)),
+        },
+        PatternKind::Array { elements, rest } => PatternKind::Array {
+            elements: elements
+                .into_iter()
+                .map(|element| {
+                    element.map(|element| ElementPattern {
+                        target: fold_pattern(folder, element.target),
+                        default: element
+                            .default
+                            .map(|default| folder.fold_expression(default)),
+                        span: folder.fold_span(element.span),
+                    })
+                })
+                .collect(),
+            rest: rest.map(|rest| fold_rest_element(folder, rest)),
+        },
+    };
+    Pattern::new(kind, folder.fold_span(pattern.span))
+}
+
+fn fold_rest_element<F: Fold + ?Sized>(
+    folder: &mut F,
+    mut rest: Box<RestElement>,
+) -> Box<RestElement> {
+    let RestElement { target, span } = *rest;
+    *rest = RestElement {
+        target: fold_pattern(folder, target),
+        span: folder.fold_span(span),
+    };
+    rest
+}
+
+fn fold_property_key<F: Fold + ?Sized>(folder: &mut F, key: PropertyKey) -> PropertyKey {
+    let kind = match key.kind {
+        kind @ (PropertyKeyKind::String(_) | PropertyKeyKind::Number(_)) => kind,
+        PropertyKeyKind::Computed(expr) => {
+            PropertyKeyKind::Computed(fold_boxed_expression(folder, expr))
+        }
+    };
+    PropertyKey::new(kind, folder.fold_span(key.span))
+}
+
+fn fold_property<F: Fold + ?Sized>(folder: &mut F, property: Property) -> Property {
+    let kind = match property.kind {
+        PropertyKind::Value { key, value } => PropertyKind::Value {
+            key: fold_property_key(folder, key),
+            value: folder.fold_expression(value),
+        },
+        PropertyKind::Method {
+            kind,
+            key,
+            params,
+            body,
+        } => PropertyKind::Method {
+            kind,
+            key: fold_property_key(folder, key),
+            params: fold_parameters(folder, params),
+            body: fold_boxed_statement(folder, body),
+        },
+        PropertyKind::Spread(argument) => PropertyKind::Spread(folder.fold_expression(argument)),
+    };
+    Property::new(kind, folder.fold_span(property.span))
+}
+
+// Folds a boxed node in place, reusing its allocation
+fn fold_boxed_statement<F: Fold + ?Sized>(
+    folder: &mut F,
+    mut stmt: Box<Statement>,
+) -> Box<Statement> {
+    *stmt = folder.fold_statement(*stmt);
+    stmt
+}
+
+fn fold_boxed_expression<F: Fold + ?Sized>(
+    folder: &mut F,
+    mut expr: Box<Expression>,
+) -> Box<Expression> {
+    *expr = folder.fold_expression(*expr);
+    expr
+}
+
+pub fn walk_statement<F: Fold + ?Sized>(folder: &mut F, stmt: Statement) -> Statement {
+    let kind = match stmt.kind {
+        StatementKind::Empty => StatementKind::Empty,
+        StatementKind::Break(label) => {
+            StatementKind::Break(label.map(|label| fold_identifier(folder, label)))
+        }
+        StatementKind::Continue(label) => {
+            StatementKind::Continue(label.map(|label| fold_identifier(folder, label)))
+        }
+        StatementKind::Let { pattern, value } => StatementKind::Let {
+            pattern: fold_pattern(folder, pattern),
+            value: value.map(|value| folder.fold_expression(value)),
+        },
+        StatementKind::Const { pattern, value } => StatementKind::Const {
+            pattern: fold_pattern(folder, pattern),
+            value: folder.fold_expression(value),
+        },
+        StatementKind::Return(value) => {
+            StatementKind::Return(value.map(|value| folder.fold_expression(value)))
+        }
+        StatementKind::Expression(expr) => StatementKind::Expression(folder.fold_expression(expr)),
+        StatementKind::Block(body) => StatementKind::Block(fold_statements(folder, body)),
+        StatementKind::If {
+            condition,
+            consequence,
+            alternative,
+        } => StatementKind::If {
+            condition: folder.fold_expression(condition),
+            consequence: fold_boxed_statement(folder, consequence),
+            alternative: alternative.map(|alternative| fold_boxed_statement(folder, alternative)),
+        },
+        StatementKind::Function { name, params, body } => StatementKind::Function {
+            name: fold_identifier(folder, name),
+            params: fold_parameters(folder, params),
+            body: fold_boxed_statement(folder, body),
+        },
+        StatementKind::While { condition, body } => StatementKind::While {
+            condition: folder.fold_expression(condition),
+            body: fold_boxed_statement(folder, body),
+        },
+        StatementKind::DoWhile { body, condition } => {
+            let body = fold_boxed_statement(folder, body);
+            StatementKind::DoWhile {
+                body,
+                condition: folder.fold_expression(condition),
+            }
+        }
+        StatementKind::For {
+            init,
+            condition,
+            update,
+            body,
+        } => StatementKind::For {
+            init: init.map(|init| fold_boxed_statement(folder, init)),
+            condition: condition.map(|condition| folder.fold_expression(condition)),
+            update: update.map(|update| folder.fold_expression(update)),
+            body: fold_boxed_statement(folder, body),
+        },
+        StatementKind::ForOf {
+            declaration,
+            target,
+            iterable,
+            body,
+        } => StatementKind::ForOf {
+            declaration,
+            target: fold_pattern(folder, target),
+            iterable: folder.fold_expression(iterable),
+            body: fold_boxed_statement(folder, body),
+        },
+        StatementKind::Labeled { label, body } => StatementKind::Labeled {
+            label: fold_identifier(folder, label),
+            body: fold_boxed_statement(folder, body),
+        },
+        StatementKind::Throw(value) => StatementKind::Throw(folder.fold_expression(value)),
+        StatementKind::Try {
+            block,
+            param,
+            handler,
+            finalizer,
+        } => StatementKind::Try {
+            block: fold_boxed_statement(folder, block),
+            param: param.map(|param| fold_identifier(folder, param)),
+            handler: handler.map(|handler| fold_boxed_statement(folder, handler)),
+            finalizer: finalizer.map(|finalizer| fold_boxed_statement(folder, finalizer)),
+        },
+    };
+    Statement::new(kind, folder.fold_span(stmt.span))
+}
+
+pub fn walk_expression<F: Fold + ?Sized>(folder: &mut F, expr: Expression) -> Expression {
+    let kind = match expr.kind {
+        kind @ (ExpressionKind::Identifier(_)
+        | ExpressionKind::Number(_)
+        | ExpressionKind::BigInt(_)
+        | ExpressionKind::String(_)
+        | ExpressionKind::RegExp { .. }
+        | ExpressionKind::Boolean(_)
+        | ExpressionKind::Null
+        | ExpressionKind::This) => kind,
+        ExpressionKind::Prefix { operator, right } => ExpressionKind::Prefix {
+            operator,
+            right: fold_boxed_expression(folder, right),
+        },
+        ExpressionKind::Infix {
+            left,
+            operator,
+            right,
+        } => ExpressionKind::Infix {
+            left: fold_boxed_expression(folder, left),
+            operator,
+            right: fold_boxed_expression(folder, right),
+        },
+        ExpressionKind::Conditional {
+            condition,
+            consequence,
+            alternative,
+        } => ExpressionKind::Conditional {
+            condition: fold_boxed_expression(folder, condition),
+            consequence: fold_boxed_expression(folder, consequence),
+            alternative: fold_boxed_expression(folder, alternative),
+        },
+        ExpressionKind::Call {
+            function,
+            arguments,
+        } => ExpressionKind::Call {
+            function: fold_boxed_expression(folder, function),
+            arguments: fold_expressions(folder, arguments),
+        },
+        ExpressionKind::Array(elements) => {
+            ExpressionKind::Array(fold_expressions(folder, elements))
+        }
+        ExpressionKind::Spread(argument) => {
+            ExpressionKind::Spread(fold_boxed_expression(folder, argument))
+        }
+        ExpressionKind::Object(properties) => ExpressionKind::Object(
+            properties
+                .into_iter()
+                .map(|property| fold_property(folder, property))
+                .collect(),
+        ),
+        ExpressionKind::Index { left, index } => ExpressionKind::Index {
+            left: fold_boxed_expression(folder, left),
+            index: fold_boxed_expression(folder, index),
+        },
+        ExpressionKind::Assign {
+            target,
+            operator,
+            value,
+        } => ExpressionKind::Assign {
+            target: fold_pattern(folder, target),
+            operator,
+            value: fold_boxed_expression(folder, value),
+        },
+        ExpressionKind::Update {
+            operator,
+            prefix,
+            target,
+        } => ExpressionKind::Update {
+            operator,
+            prefix,
+            target: fold_boxed_expression(folder, target),
+        },
+        ExpressionKind::Function { name, params, body } => ExpressionKind::Function {
+            name: name.map(|name| fold_identifier(folder, name)),
+            params: fold_parameters(folder, params),
+            body: fold_boxed_statement(folder, body),
+        },
+        ExpressionKind::Arrow { params, body } => ExpressionKind::Arrow {
+            params: fold_parameters(folder, params),
+            body: match body {
+                ArrowBody::Expression(expr) => {
+                    ArrowBody::Expression(fold_boxed_expression(folder, expr))
+                }
+                ArrowBody::Block(block) => ArrowBody::Block(fold_boxed_statement(folder, block)),
+            },
+        },
+        ExpressionKind::Template {
+            quasis,
+            expressions,
+        } => {
+            let mut folded_quasis = Vec::with_capacity(quasis.len());
+            let mut folded_expressions = Vec::with_capacity(expressions.len());
+            let mut expressions = expressions.into_iter();
+            for quasi in quasis {
+                folded_quasis.push(folder.fold_template_element(quasi));
+                if let Some(expr) = expressions.next() {
+                    folded_expressions.push(folder.fold_expression(expr));
+                }
+            }
+            ExpressionKind::Template {
+                quasis: folded_quasis,
+                expressions: folded_expressions,
+            }
+        }
+        ExpressionKind::TaggedTemplate { tag, quasi } => ExpressionKind::TaggedTemplate {
+            tag: fold_boxed_expression(folder, tag),
+            quasi: fold_boxed_expression(folder, quasi),
+        },
+    };
+    Expression::new(kind, folder.fold_span(expr.span))
+}
@@ -1,3 +1,15 @@
+pub mod codegen;
+pub mod diagnostic;
+#[cfg(feature = "estree")]
+pub mod estree;
+pub mod fold;
 pub mod nodes;
+pub mod precedence;
+pub mod visit;
+pub mod visit_mut;
 
+pub use diagnostic::*;
+pub use fold::Fold;
 pub use nodes::*;
+pub use visit::Visit;
+pub use visit_mut::VisitMut;
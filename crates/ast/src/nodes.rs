@@ -1,11 +1,62 @@
+/// A point in the source text. Lines and columns start at 1, and columns
+/// count characters. `offset` is the byte offset into the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+    pub offset: usize,
+}
+
+impl Position {
+    pub fn new(line: usize, column: usize, offset: usize) -> Self {
+        Self {
+            line,
+            column,
+            offset,
+        }
+    }
+}
+
+/// The source text a token or node was parsed from. `end` is the position
+/// just past its last character.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Span {
+    pub fn new(start: Position, end: Position) -> Self {
+        Self { start, end }
+    }
+
+    /// The span covering both `self` and the later `other`.
+    pub fn to(self, other: Span) -> Span {
+        Span::new(self.start, other.end)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statement {
+    pub kind: StatementKind,
+    pub span: Span,
+}
+
+impl Statement {
+    pub fn new(kind: StatementKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
-pub enum Statement {
+pub enum StatementKind {
+    Empty,
     Let {
-        name: String,
-        value: Expression,
+        pattern: Pattern,
+        value: Option<Expression>, // None for `let x;`, only with an Identifier pattern
     },
     Const {
-        name: String,
+        pattern: Pattern,
         value: Expression,
     },
     Return(Option<Expression>),
@@ -17,18 +68,70 @@ pub enum Statement {
         alternative: Option<Box<Statement>>,
     },
     Function {
-        name: String,
-        params: Vec<String>,
+        name: Identifier,
+        params: Vec<Parameter>,
         body: Box<Statement>, // Block
     },
+    While {
+        condition: Expression,
+        body: Box<Statement>,
+    },
+    DoWhile {
+        body: Box<Statement>,
+        condition: Expression,
+    },
+    For {
+        init: Option<Box<Statement>>, // Let, Const or Expression
+        condition: Option<Expression>,
+        update: Option<Expression>,
+        body: Box<Statement>,
+    },
+    ForOf {
+        declaration: Option<DeclarationKind>, // None when `target` is assigned to
+        target: Pattern,
+        iterable: Expression,
+        body: Box<Statement>,
+    },
+    Break(Option<Identifier>),    // Label
+    Continue(Option<Identifier>), // Label
+    Labeled {
+        label: Identifier,
+        body: Box<Statement>,
+    },
+    Throw(Expression),
+    Try {
+        block: Box<Statement>,             // Block
+        param: Option<Identifier>,         // None for `catch {` and when there is no catch
+        handler: Option<Box<Statement>>,   // Block
+        finalizer: Option<Box<Statement>>, // Block
+    },
 }
 
 #[derive(Debug, Clone, PartialEq)]
-pub enum Expression {
+pub struct Expression {
+    pub kind: ExpressionKind,
+    pub span: Span,
+}
+
+impl Expression {
+    pub fn new(kind: ExpressionKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExpressionKind {
     Identifier(String),
     Number(f64),
+    BigInt(String), // The digits as written, e.g. "0xff", without separators
     String(String),
+    RegExp {
+        pattern: String,
+        flags: String,
+    },
     Boolean(bool),
+    Null,
+    This,
     Prefix {
         operator: String,
         right: Box<Expression>,
@@ -38,16 +141,267 @@ pub enum Expression {
         operator: String,
         right: Box<Expression>,
     },
+    Conditional {
+        condition: Box<Expression>,
+        consequence: Box<Expression>,
+        alternative: Box<Expression>,
+    },
     Call {
         function: Box<Expression>,
-        arguments: Vec<Expression>,
+        arguments: Vec<Expression>, // May contain Spread
     },
-    Array(Vec<Expression>),
-    Object(Vec<(String, Expression)>),
+    Array(Vec<Expression>), // May contain Spread
+    // `...argument`, only found in call arguments and array literals
+    Spread(Box<Expression>),
+    Object(Vec<Property>),
     Index {
         left: Box<Expression>,
         index: Box<Expression>,
     },
+    Assign {
+        target: Pattern,  // Only an Identifier or Member with a compound operator
+        operator: String, // "=" or a compound operator such as "+="
+        value: Box<Expression>,
+    },
+    Update {
+        operator: String, // "++" or "--"
+        prefix: bool,
+        target: Box<Expression>, // Identifier or Index
+    },
+    Function {
+        name: Option<Identifier>,
+        params: Vec<Parameter>,
+        body: Box<Statement>, // Block
+    },
+    Arrow {
+        params: Vec<Parameter>,
+        body: ArrowBody,
+    },
+    Template {
+        quasis: Vec<TemplateElement>, // One more than there are expressions
+        expressions: Vec<Expression>,
+    },
+    TaggedTemplate {
+        tag: Box<Expression>,
+        quasi: Box<Expression>, // Template
+    },
+}
+
+/// A name that is not an expression: that of a function, a label or a
+/// `catch` binding.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Identifier {
+    pub name: String,
+    pub span: Span,
+}
+
+impl Identifier {
+    pub fn new(name: impl Into<String>, span: Span) -> Self {
+        Self {
+            name: name.into(),
+            span,
+        }
+    }
+}
+
+/// Whether a `for…of` loop declares its variable with `let` or `const`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeclarationKind {
+    Let,
+    Const,
+}
+
+/// A function parameter: `target`, `target = default` or `...target`. Only
+/// the last parameter can be a rest parameter, and it has no default. The
+/// span covers the `...` and the default.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Parameter {
+    pub target: Pattern,
+    pub default: Option<Expression>,
+    pub rest: bool,
+    pub span: Span,
+}
+
+impl Parameter {
+    /// A plain parameter without a default.
+    pub fn new(name: impl Into<String>, span: Span) -> Self {
+        Self {
+            target: Pattern::new(PatternKind::Identifier(name.into()), span),
+            default: None,
+            rest: false,
+            span,
+        }
+    }
+}
+
+/// Where a declaration, parameter, `for…of` loop or assignment puts its
+/// value, either whole or taken apart by `{…}` and `[…]` destructuring.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pattern {
+    pub kind: PatternKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatternKind {
+    Identifier(String),
+    // `a.b` or `a[b]`, only found in assignments and `for…of` loops
+    // without a declaration
+    Member(Box<Expression>), // Index
+    Object {
+        properties: Vec<PropertyPattern>,
+        rest: Option<Box<RestElement>>, // Identifier, or Member when assigning
+    },
+    Array {
+        elements: Vec<Option<ElementPattern>>, // None for a hole, as in `[a, , b]`
+        rest: Option<Box<RestElement>>,
+    },
+}
+
+impl Pattern {
+    pub fn new(kind: PatternKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+
+    /// The names this pattern declares, in source order.
+    pub fn bound_names(&self) -> Vec<&str> {
+        self.bindings().into_iter().map(|(name, _)| name).collect()
+    }
+
+    /// The names this pattern declares and where each is written, in
+    /// source order.
+    pub fn bindings(&self) -> Vec<(&str, Span)> {
+        let mut bindings = vec![];
+        self.collect_bindings(&mut bindings);
+        bindings
+    }
+
+    fn collect_bindings<'a>(&'a self, bindings: &mut Vec<(&'a str, Span)>) {
+        match &self.kind {
+            PatternKind::Identifier(name) => bindings.push((name, self.span)),
+            PatternKind::Member(_) => {}
+            PatternKind::Object { properties, rest } => {
+                for property in properties {
+                    property.value.collect_bindings(bindings);
+                }
+                if let Some(rest) = rest {
+                    rest.target.collect_bindings(bindings);
+                }
+            }
+            PatternKind::Array { elements, rest } => {
+                for element in elements.iter().flatten() {
+                    element.target.collect_bindings(bindings);
+                }
+                if let Some(rest) = rest {
+                    rest.target.collect_bindings(bindings);
+                }
+            }
+        }
+    }
+}
+
+/// A member of an object literal.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Property {
+    pub kind: PropertyKind,
+    pub span: Span,
+}
+
+impl Property {
+    pub fn new(kind: PropertyKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PropertyKind {
+    // `key: value`. `{a}` is short for `{a: a}`
+    Value {
+        key: PropertyKey,
+        value: Expression,
+    },
+    // `key() {…}`, `get key() {…}` or `set key(value) {…}`
+    Method {
+        kind: MethodKind,
+        key: PropertyKey,
+        params: Vec<Parameter>,
+        body: Box<Statement>, // Block
+    },
+    // `...argument`, copying the own properties of its value
+    Spread(Expression),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MethodKind {
+    Method,
+    Get,
+    Set,
+}
+
+/// The key of a property in an object literal or pattern. Numbers and
+/// computed keys are converted to strings when the code runs, so `{1e3: a}`
+/// has the key "1000". A computed key has the span of its expression, without
+/// the brackets.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PropertyKey {
+    pub kind: PropertyKeyKind,
+    pub span: Span,
+}
+
+impl PropertyKey {
+    pub fn new(kind: PropertyKeyKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PropertyKeyKind {
+    String(String), // A name or string literal
+    Number(f64),
+    Computed(Box<Expression>), // `[expression]`
+}
+
+/// `key: value = default` in an object pattern. `{a}` is short for
+/// `{a: a}`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PropertyPattern {
+    pub key: PropertyKey,
+    pub value: Pattern,
+    pub default: Option<Expression>,
+    pub span: Span,
+}
+
+/// `target = default` in an array pattern.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ElementPattern {
+    pub target: Pattern,
+    pub default: Option<Expression>,
+    pub span: Span,
+}
+
+/// `...target` at the end of an object or array pattern. The span covers
+/// the `...`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RestElement {
+    pub target: Pattern,
+    pub span: Span,
+}
+
+/// The body of an arrow function: a single expression whose value is
+/// returned, as in `x => x + 1`, or a block.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArrowBody {
+    Expression(Box<Expression>),
+    Block(Box<Statement>),
+}
+
+/// The text between a template literal's substitutions. `cooked` is None
+/// for an invalid escape sequence, which only tagged templates allow.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateElement {
+    pub cooked: Option<String>,
+    pub raw: String,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone, PartialEq)]
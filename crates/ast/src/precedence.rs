@@ -0,0 +1,42 @@
+//! Operator binding power, from loosest to tightest. The parser climbs these
+//! levels, and code generation uses them to decide where an operand needs
+//! parentheses.
+
+pub const ASSIGN: u8 = 1;
+pub const CONDITIONAL: u8 = 2;
+pub const NULLISH: u8 = 3;
+pub const LOGICAL_OR: u8 = 4;
+pub const LOGICAL_AND: u8 = 5;
+pub const BITWISE_OR: u8 = 6;
+pub const BITWISE_XOR: u8 = 7;
+pub const BITWISE_AND: u8 = 8;
+pub const EQUALS: u8 = 9;
+pub const LESS_GREATER: u8 = 10;
+pub const SHIFT: u8 = 11;
+pub const SUM: u8 = 12;
+pub const PRODUCT: u8 = 13;
+pub const EXPONENT: u8 = 14;
+pub const PREFIX: u8 = 15;
+pub const POSTFIX: u8 = 16;
+pub const CALL: u8 = 17;
+pub const MEMBER: u8 = 18;
+
+/// The binding power of a binary operator as written, such as `"+"`, or 0
+/// for anything else.
+pub fn infix(operator: &str) -> u8 {
+    match operator {
+        "**" => EXPONENT,
+        "*" | "/" | "%" => PRODUCT,
+        "+" | "-" => SUM,
+        "<<" | ">>" | ">>>" => SHIFT,
+        "<" | ">" | "<=" | ">=" | "in" | "instanceof" => LESS_GREATER,
+        "==" | "!=" | "===" | "!==" => EQUALS,
+        "&" => BITWISE_AND,
+        "^" => BITWISE_XOR,
+        "|" => BITWISE_OR,
+        "&&" => LOGICAL_AND,
+        "||" => LOGICAL_OR,
+        "??" => NULLISH,
+        _ => 0,
+    }
+}
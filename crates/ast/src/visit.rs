@@ -0,0 +1,552 @@
+//! Read-only traversal of the AST.
+//!
+//! [`Visit`] has a method for each node and for each kind of statement and
+//! expression. Every method defaults to visiting the node's children in
+//! source order through the matching `walk_` function, so an implementation
+//! only overrides the methods for the nodes it cares about, and can call the
+//! `walk_` function to carry on into the children.
+
+use crate::{
+    ArrowBody, DeclarationKind, Expression, ExpressionKind, Identifier, Parameter, Pattern,
+    PatternKind, Program, Property, PropertyKey, PropertyKeyKind, PropertyKind, Statement,
+    StatementKind, TemplateElement,
+};
+
+pub trait Visit {
+    /// Returned by a visit method to stop the traversal. Visitors that
+    /// cannot fail use `std::convert::Infallible`.
+    type Error;
+
+    fn visit_program(&mut self, program: &Program) -> Result<(), Self::Error> {
+        walk_program(self, program)
+    }
+
+    /// Visits a statement by dispatching on its kind.
+    fn visit_statement(&mut self, stmt: &Statement) -> Result<(), Self::Error> {
+        walk_statement(self, stmt)
+    }
+
+    /// Visits an expression by dispatching on its kind.
+    fn visit_expression(&mut self, expr: &Expression) -> Result<(), Self::Error> {
+        walk_expression(self, expr)
+    }
+
+    fn visit_template_element(&mut self, _element: &TemplateElement) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    // Statements
+
+    fn visit_empty(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn visit_let(
+        &mut self,
+        pattern: &Pattern,
+        value: Option<&Expression>,
+    ) -> Result<(), Self::Error> {
+        walk_pattern(self, pattern)?;
+        walk_optional_expression(self, value)
+    }
+
+    fn visit_const(&mut self, pattern: &Pattern, value: &Expression) -> Result<(), Self::Error> {
+        walk_pattern(self, pattern)?;
+        self.visit_expression(value)
+    }
+
+    fn visit_return(&mut self, value: Option<&Expression>) -> Result<(), Self::Error> {
+        walk_optional_expression(self, value)
+    }
+
+    fn visit_expression_statement(&mut self, expr: &Expression) -> Result<(), Self::Error> {
+        self.visit_expression(expr)
+    }
+
+    fn visit_block(&mut self, body: &[Statement]) -> Result<(), Self::Error> {
+        walk_statements(self, body)
+    }
+
+    fn visit_if(
+        &mut self,
+        condition: &Expression,
+        consequence: &Statement,
+        alternative: Option<&Statement>,
+    ) -> Result<(), Self::Error> {
+        walk_if(self, condition, consequence, alternative)
+    }
+
+    fn visit_function_declaration(
+        &mut self,
+        _name: &Identifier,
+        params: &[Parameter],
+        body: &Statement,
+    ) -> Result<(), Self::Error> {
+        walk_parameters(self, params)?;
+        self.visit_statement(body)
+    }
+
+    fn visit_while(&mut self, condition: &Expression, body: &Statement) -> Result<(), Self::Error> {
+        self.visit_expression(condition)?;
+        self.visit_statement(body)
+    }
+
+    fn visit_do_while(
+        &mut self,
+        body: &Statement,
+        condition: &Expression,
+    ) -> Result<(), Self::Error> {
+        self.visit_statement(body)?;
+        self.visit_expression(condition)
+    }
+
+    fn visit_for(
+        &mut self,
+        init: Option<&Statement>,
+        condition: Option<&Expression>,
+        update: Option<&Expression>,
+        body: &Statement,
+    ) -> Result<(), Self::Error> {
+        walk_for(self, init, condition, update, body)
+    }
+
+    fn visit_for_of(
+        &mut self,
+        _declaration: Option<DeclarationKind>,
+        target: &Pattern,
+        iterable: &Expression,
+        body: &Statement,
+    ) -> Result<(), Self::Error> {
+        walk_pattern(self, target)?;
+        self.visit_expression(iterable)?;
+        self.visit_statement(body)
+    }
+
+    fn visit_break(&mut self, _label: Option<&Identifier>) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn visit_continue(&mut self, _label: Option<&Identifier>) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn visit_labeled(&mut self, _label: &Identifier, body: &Statement) -> Result<(), Self::Error> {
+        self.visit_statement(body)
+    }
+
+    fn visit_throw(&mut self, value: &Expression) -> Result<(), Self::Error> {
+        self.visit_expression(value)
+    }
+
+    fn visit_try(
+        &mut self,
+        block: &Statement,
+        _param: Option<&Identifier>,
+        handler: Option<&Statement>,
+        finalizer: Option<&Statement>,
+    ) -> Result<(), Self::Error> {
+        walk_try(self, block, handler, finalizer)
+    }
+
+    // Expressions
+
+    fn visit_identifier(&mut self, _name: &str) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn visit_number(&mut self, _value: f64) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn visit_bigint(&mut self, _digits: &str) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn visit_string(&mut self, _value: &str) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn visit_regexp(&mut self, _pattern: &str, _flags: &str) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn visit_boolean(&mut self, _value: bool) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn visit_null(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn visit_this(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn visit_prefix(&mut self, _operator: &str, right: &Expression) -> Result<(), Self::Error> {
+        self.visit_expression(right)
+    }
+
+    fn visit_infix(
+        &mut self,
+        left: &Expression,
+        _operator: &str,
+        right: &Expression,
+    ) -> Result<(), Self::Error> {
+        self.visit_expression(left)?;
+        self.visit_expression(right)
+    }
+
+    fn visit_conditional(
+        &mut self,
+        condition: &Expression,
+        consequence: &Expression,
+        alternative: &Expression,
+    ) -> Result<(), Self::Error> {
+        self.visit_expression(condition)?;
+        self.visit_expression(consequence)?;
+        self.visit_expression(alternative)
+    }
+
+    fn visit_call(
+        &mut self,
+        function: &Expression,
+        arguments: &[Expression],
+    ) -> Result<(), Self::Error> {
+        self.visit_expression(function)?;
+        walk_expressions(self, arguments)
+    }
+
+    fn visit_array(&mut self, elements: &[Expression]) -> Result<(), Self::Error> {
+        walk_expressions(self, elements)
+    }
+
+    fn visit_spread(&mut self, argument: &Expression) -> Result<(), Self::Error> {
+        self.visit_expression(argument)
+    }
+
+    fn visit_object(&mut self, properties: &[Property]) -> Result<(), Self::Error> {
+        walk_object(self, properties)
+    }
+
+    fn visit_index(&mut self, left: &Expression, index: &Expression) -> Result<(), Self::Error> {
+        self.visit_expression(left)?;
+        self.visit_expression(index)
+    }
+
+    fn visit_assign(
+        &mut self,
+        target: &Pattern,
+        _operator: &str,
+        value: &Expression,
+    ) -> Result<(), Self::Error> {
+        walk_pattern(self, target)?;
+        self.visit_expression(value)
+    }
+
+    fn visit_update(
+        &mut self,
+        _operator: &str,
+        _prefix: bool,
+        target: &Expression,
+    ) -> Result<(), Self::Error> {
+        self.visit_expression(target)
+    }
+
+    fn visit_function_expression(
+        &mut self,
+        _name: Option<&Identifier>,
+        params: &[Parameter],
+        body: &Statement,
+    ) -> Result<(), Self::Error> {
+        walk_parameters(self, params)?;
+        self.visit_statement(body)
+    }
+
+    fn visit_arrow_function(
+        &mut self,
+        params: &[Parameter],
+        body: &ArrowBody,
+    ) -> Result<(), Self::Error> {
+        walk_parameters(self, params)?;
+        match body {
+            ArrowBody::Expression(expr) => self.visit_expression(expr),
+            ArrowBody::Block(block) => self.visit_statement(block),
+        }
+    }
+
+    fn visit_template(
+        &mut self,
+        quasis: &[TemplateElement],
+        expressions: &[Expression],
+    ) -> Result<(), Self::Error> {
+        walk_template(self, quasis, expressions)
+    }
+
+    fn visit_tagged_template(
+        &mut self,
+        tag: &Expression,
+        quasi: &Expression,
+    ) -> Result<(), Self::Error> {
+        self.visit_expression(tag)?;
+        self.visit_expression(quasi)
+    }
+}
+
+pub fn walk_program<V: Visit + ?Sized>(visitor: &mut V, program: &Program) -> Result<(), V::Error> {
+    walk_statements(visitor, &program.statements)
+}
+
+pub fn walk_statements<V: Visit + ?Sized>(
+    visitor: &mut V,
+    stmts: &[Statement],
+) -> Result<(), V::Error> {
+    stmts
+        .iter()
+        .try_for_each(|stmt| visitor.visit_statement(stmt))
+}
+
+pub fn walk_expressions<V: Visit + ?Sized>(
+    visitor: &mut V,
+    exprs: &[Expression],
+) -> Result<(), V::Error> {
+    exprs
+        .iter()
+        .try_for_each(|expr| visitor.visit_expression(expr))
+}
+
+pub fn walk_optional_expression<V: Visit + ?Sized>(
+    visitor: &mut V,
+    expr: Option<&Expression>,
+) -> Result<(), V::Error> {
+    expr.map_or(Ok(()), |expr| visitor.visit_expression(expr))
+}
+
+pub fn walk_statement<V: Visit + ?Sized>(
+    visitor: &mut V,
+    stmt: &Statement,
+) -> Result<(), V::Error> {
+    match &stmt.kind {
+        StatementKind::Empty => visitor.visit_empty(),
+        StatementKind::Let { pattern, value } => visitor.visit_let(pattern, value.as_ref()),
+        StatementKind::Const { pattern, value } => visitor.visit_const(pattern, value),
+        StatementKind::Return(value) => visitor.visit_return(value.as_ref()),
+        StatementKind::Expression(expr) => visitor.visit_expression_statement(expr),
+        StatementKind::Block(body) => visitor.visit_block(body),
+        StatementKind::If {
+            condition,
+            consequence,
+            alternative,
+        } => visitor.visit_if(condition, consequence, alternative.as_deref()),
+        StatementKind::Function { name, params, body } => {
+            visitor.visit_function_declaration(name, params, body)
+        }
+        StatementKind::While { condition, body } => visitor.visit_while(condition, body),
+        StatementKind::DoWhile { body, condition } => visitor.visit_do_while(body, condition),
+        StatementKind::For {
+            init,
+            condition,
+            update,
+            body,
+        } => visitor.visit_for(init.as_deref(), condition.as_ref(), update.as_ref(), body),
+        StatementKind::ForOf {
+            declaration,
+            target,
+            iterable,
+            body,
+        } => visitor.visit_for_of(*declaration, target, iterable, body),
+        StatementKind::Break(label) => visitor.visit_break(label.as_ref()),
+        StatementKind::Continue(label) => visitor.visit_continue(label.as_ref()),
+        StatementKind::Labeled { label, body } => visitor.visit_labeled(label, body),
+        StatementKind::Throw(value) => visitor.visit_throw(value),
+        StatementKind::Try {
+            block,
+            param,
+            handler,
+            finalizer,
+        } => visitor.visit_try(
+            block,
+            param.as_ref(),
+            handler.as_deref(),
+            finalizer.as_deref(),
+        ),
+    }
+}
+
+pub fn walk_expression<V: Visit + ?Sized>(
+    visitor: &mut V,
+    expr: &Expression,
+) -> Result<(), V::Error> {
+    match &expr.kind {
+        ExpressionKind::Identifier(name) => visitor.visit_identifier(name),
+        ExpressionKind::Number(value) => visitor.visit_number(*value),
+        ExpressionKind::BigInt(digits) => visitor.visit_bigint(digits),
+        ExpressionKind::String(value) => visitor.visit_string(value),
+        ExpressionKind::RegExp { pattern, flags } => visitor.visit_regexp(pattern, flags),
+        ExpressionKind::Boolean(value) => visitor.visit_boolean(*value),
+        ExpressionKind::Null => visitor.visit_null(),
+        ExpressionKind::This => visitor.visit_this(),
+        ExpressionKind::Prefix { operator, right } => visitor.visit_prefix(operator, right),
+        ExpressionKind::Infix {
+            left,
+            operator,
+            right,
+        } => visitor.visit_infix(left, operator, right),
+        ExpressionKind::Conditional {
+            condition,
+            consequence,
+            alternative,
+        } => visitor.visit_conditional(condition, consequence, alternative),
+        ExpressionKind::Call {
+            function,
+            arguments,
+        } => visitor.visit_call(function, arguments),
+        ExpressionKind::Array(elements) => visitor.visit_array(elements),
+        ExpressionKind::Spread(argument) => visitor.visit_spread(argument),
+        ExpressionKind::Object(properties) => visitor.visit_object(properties),
+        ExpressionKind::Index { left, index } => visitor.visit_index(left, index),
+        ExpressionKind::Assign {
+            target,
+            operator,
+            value,
+        } => visitor.visit_assign(target, operator, value),
+        ExpressionKind::Update {
+            operator,
+            prefix,
+            target,
+        } => visitor.visit_update(operator, *prefix, target),
+        ExpressionKind::Function { name, params, body } => {
+            visitor.visit_function_expression(name.as_ref(), params, body)
+        }
+        ExpressionKind::Arrow { params, body } => visitor.visit_arrow_function(params, body),
+        ExpressionKind::Template {
+            quasis,
+            expressions,
+        } => visitor.visit_template(quasis, expressions),
+        ExpressionKind::TaggedTemplate { tag, quasi } => visitor.visit_tagged_template(tag, quasi),
+    }
+}
+
+/// Visits the patterns and default values of a function's parameters.
+pub fn walk_parameters<V: Visit + ?Sized>(
+    visitor: &mut V,
+    params: &[Parameter],
+) -> Result<(), V::Error> {
+    params.iter().try_for_each(|param| {
+        walk_pattern(visitor, &param.target)?;
+        walk_optional_expression(visitor, param.default.as_ref())
+    })
+}
+
+/// Visits the expressions inside a pattern: the objects of its members and
+/// its default values. The names it binds are not visited.
+pub fn walk_pattern<V: Visit + ?Sized>(visitor: &mut V, pattern: &Pattern) -> Result<(), V::Error> {
+    match &pattern.kind {
+        PatternKind::Identifier(_) => Ok(()),
+        PatternKind::Member(expr) => visitor.visit_expression(expr),
+        PatternKind::Object { properties, rest } => {
+            for property in properties {
+                walk_property_key(visitor, &property.key)?;
+                walk_pattern(visitor, &property.value)?;
+                walk_optional_expression(visitor, property.default.as_ref())?;
+            }
+            rest.as_deref()
+                .map_or(Ok(()), |rest| walk_pattern(visitor, &rest.target))
+        }
+        PatternKind::Array { elements, rest } => {
+            for element in elements.iter().flatten() {
+                walk_pattern(visitor, &element.target)?;
+                walk_optional_expression(visitor, element.default.as_ref())?;
+            }
+            rest.as_deref()
+                .map_or(Ok(()), |rest| walk_pattern(visitor, &rest.target))
+        }
+    }
+}
+
+pub fn walk_if<V: Visit + ?Sized>(
+    visitor: &mut V,
+    condition: &Expression,
+    consequence: &Statement,
+    alternative: Option<&Statement>,
+) -> Result<(), V::Error> {
+    visitor.visit_expression(condition)?;
+    visitor.visit_statement(consequence)?;
+    alternative.map_or(Ok(()), |alternative| visitor.visit_statement(alternative))
+}
+
+pub fn walk_for<V: Visit + ?Sized>(
+    visitor: &mut V,
+    init: Option<&Statement>,
+    condition: Option<&Expression>,
+    update: Option<&Expression>,
+    body: &Statement,
+) -> Result<(), V::Error> {
+    if let Some(init) = init {
+        visitor.visit_statement(init)?;
+    }
+    walk_optional_expression(visitor, condition)?;
+    walk_optional_expression(visitor, update)?;
+    visitor.visit_statement(body)
+}
+
+pub fn walk_try<V: Visit + ?Sized>(
+    visitor: &mut V,
+    block: &Statement,
+    handler: Option<&Statement>,
+    finalizer: Option<&Statement>,
+) -> Result<(), V::Error> {
+    visitor.visit_statement(block)?;
+    for stmt in handler.into_iter().chain(finalizer) {
+        visitor.visit_statement(stmt)?;
+    }
+    Ok(())
+}
+
+/// Visits the computed keys, values, methods and spread arguments of an
+/// object literal in source order.
+pub fn walk_object<V: Visit + ?Sized>(
+    visitor: &mut V,
+    properties: &[Property],
+) -> Result<(), V::Error> {
+    properties
+        .iter()
+        .try_for_each(|property| match &property.kind {
+            PropertyKind::Value { key, value } => {
+                walk_property_key(visitor, key)?;
+                visitor.visit_expression(value)
+            }
+            PropertyKind::Method {
+                key, params, body, ..
+            } => {
+                walk_property_key(visitor, key)?;
+                walk_parameters(visitor, params)?;
+                visitor.visit_statement(body)
+            }
+            PropertyKind::Spread(argument) => visitor.visit_expression(argument),
+        })
+}
+
+pub fn walk_property_key<V: Visit + ?Sized>(
+    visitor: &mut V,
+    key: &PropertyKey,
+) -> Result<(), V::Error> {
+    match &key.kind {
+        PropertyKeyKind::String(_) | PropertyKeyKind::Number(_) => Ok(()),
+        PropertyKeyKind::Computed(expr) => visitor.visit_expression(expr),
+    }
+}
+
+/// Visits the quasis and substitutions of a template in source order.
+pub fn walk_template<V: Visit + ?Sized>(
+    visitor: &mut V,
+    quasis: &[TemplateElement],
+    expressions: &[Expression],
+) -> Result<(), V::Error> {
+    for (i, quasi) in quasis.iter().enumerate() {
+        visitor.visit_template_element(quasi)?;
+        if let Some(expr) = expressions.get(i) {
+            visitor.visit_expression(expr)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,272 @@
+//! In-place traversal of the AST.
+//!
+//! [`VisitMut`] works a node at a time rather than a kind at a time like
+//! [`Visit`](crate::Visit), since rewriting a statement or expression often
+//! means replacing it with one of another kind. Each method defaults to
+//! visiting the node's children in source order through the matching
+//! `walk_` function.
+
+use crate::{
+    ArrowBody, Expression, ExpressionKind, Parameter, Pattern, PatternKind, Program, PropertyKey,
+    PropertyKeyKind, PropertyKind, Statement, StatementKind, TemplateElement,
+};
+
+pub trait VisitMut {
+    fn visit_program_mut(&mut self, program: &mut Program) {
+        walk_program_mut(self, program)
+    }
+
+    fn visit_statement_mut(&mut self, stmt: &mut Statement) {
+        walk_statement_mut(self, stmt)
+    }
+
+    fn visit_expression_mut(&mut self, expr: &mut Expression) {
+        walk_expression_mut(self, expr)
+    }
+
+    fn visit_template_element_mut(&mut self, _element: &mut TemplateElement) {}
+}
+
+pub fn walk_program_mut<V: VisitMut + ?Sized>(visitor: &mut V, program: &mut Program) {
+    for stmt in &mut program.statements {
+        visitor.visit_statement_mut(stmt);
+    }
+}
+
+pub fn walk_statement_mut<V: VisitMut + ?Sized>(visitor: &mut V, stmt: &mut Statement) {
+    match &mut stmt.kind {
+        StatementKind::Empty | StatementKind::Break(_) | StatementKind::Continue(_) => {}
+        StatementKind::Let { pattern, value } => {
+            walk_pattern_mut(visitor, pattern);
+            if let Some(value) = value {
+                visitor.visit_expression_mut(value);
+            }
+        }
+        StatementKind::Return(value) => {
+            if let Some(value) = value {
+                visitor.visit_expression_mut(value);
+            }
+        }
+        StatementKind::Const { pattern, value } => {
+            walk_pattern_mut(visitor, pattern);
+            visitor.visit_expression_mut(value);
+        }
+        StatementKind::Expression(value) | StatementKind::Throw(value) => {
+            visitor.visit_expression_mut(value)
+        }
+        StatementKind::Block(body) => {
+            for stmt in body {
+                visitor.visit_statement_mut(stmt);
+            }
+        }
+        StatementKind::If {
+            condition,
+            consequence,
+            alternative,
+        } => {
+            visitor.visit_expression_mut(condition);
+            visitor.visit_statement_mut(consequence);
+            if let Some(alternative) = alternative {
+                visitor.visit_statement_mut(alternative);
+            }
+        }
+        StatementKind::Function { params, body, .. } => {
+            walk_parameters_mut(visitor, params);
+            visitor.visit_statement_mut(body);
+        }
+        StatementKind::Labeled { body, .. } => visitor.visit_statement_mut(body),
+        StatementKind::While { condition, body } => {
+            visitor.visit_expression_mut(condition);
+            visitor.visit_statement_mut(body);
+        }
+        StatementKind::DoWhile { body, condition } => {
+            visitor.visit_statement_mut(body);
+            visitor.visit_expression_mut(condition);
+        }
+        StatementKind::For {
+            init,
+            condition,
+            update,
+            body,
+        } => {
+            if let Some(init) = init {
+                visitor.visit_statement_mut(init);
+            }
+            if let Some(condition) = condition {
+                visitor.visit_expression_mut(condition);
+            }
+            if let Some(update) = update {
+                visitor.visit_expression_mut(update);
+            }
+            visitor.visit_statement_mut(body);
+        }
+        StatementKind::ForOf {
+            target,
+            iterable,
+            body,
+            ..
+        } => {
+            walk_pattern_mut(visitor, target);
+            visitor.visit_expression_mut(iterable);
+            visitor.visit_statement_mut(body);
+        }
+        StatementKind::Try {
+            block,
+            handler,
+            finalizer,
+            ..
+        } => {
+            visitor.visit_statement_mut(block);
+            if let Some(handler) = handler {
+                visitor.visit_statement_mut(handler);
+            }
+            if let Some(finalizer) = finalizer {
+                visitor.visit_statement_mut(finalizer);
+            }
+        }
+    }
+}
+
+pub fn walk_expression_mut<V: VisitMut + ?Sized>(visitor: &mut V, expr: &mut Expression) {
+    match &mut expr.kind {
+        ExpressionKind::Identifier(_)
+        | ExpressionKind::Number(_)
+        | ExpressionKind::BigInt(_)
+        | ExpressionKind::String(_)
+        | ExpressionKind::RegExp { .. }
+        | ExpressionKind::Boolean(_)
+        | ExpressionKind::Null
+        | ExpressionKind::This => {}
+        ExpressionKind::Prefix { right, .. } | ExpressionKind::Spread(right) => {
+            visitor.visit_expression_mut(right)
+        }
+        ExpressionKind::Infix { left, right, .. }
+        | ExpressionKind::Index {
+            left, index: right, ..
+        }
+        | ExpressionKind::TaggedTemplate {
+            tag: left,
+            quasi: right,
+        } => {
+            visitor.visit_expression_mut(left);
+            visitor.visit_expression_mut(right);
+        }
+        ExpressionKind::Assign { target, value, .. } => {
+            walk_pattern_mut(visitor, target);
+            visitor.visit_expression_mut(value);
+        }
+        ExpressionKind::Conditional {
+            condition,
+            consequence,
+            alternative,
+        } => {
+            visitor.visit_expression_mut(condition);
+            visitor.visit_expression_mut(consequence);
+            visitor.visit_expression_mut(alternative);
+        }
+        ExpressionKind::Call {
+            function,
+            arguments,
+        } => {
+            visitor.visit_expression_mut(function);
+            for arg in arguments {
+                visitor.visit_expression_mut(arg);
+            }
+        }
+        ExpressionKind::Array(elements) => {
+            for elem in elements {
+                visitor.visit_expression_mut(elem);
+            }
+        }
+        ExpressionKind::Object(properties) => {
+            for property in properties {
+                match &mut property.kind {
+                    PropertyKind::Value { key, value } => {
+                        walk_property_key_mut(visitor, key);
+                        visitor.visit_expression_mut(value);
+                    }
+                    PropertyKind::Method {
+                        key, params, body, ..
+                    } => {
+                        walk_property_key_mut(visitor, key);
+                        walk_parameters_mut(visitor, params);
+                        visitor.visit_statement_mut(body);
+                    }
+                    PropertyKind::Spread(argument) => visitor.visit_expression_mut(argument),
+                }
+            }
+        }
+        ExpressionKind::Update { target, .. } => visitor.visit_expression_mut(target),
+        ExpressionKind::Function { params, body, .. } => {
+            walk_parameters_mut(visitor, params);
+            visitor.visit_statement_mut(body);
+        }
+        ExpressionKind::Arrow { params, body } => {
+            walk_parameters_mut(visitor, params);
+            match body {
+                ArrowBody::Expression(expr) => visitor.visit_expression_mut(expr),
+                ArrowBody::Block(block) => visitor.visit_statement_mut(block),
+            }
+        }
+        ExpressionKind::Template {
+            quasis,
+            expressions,
+        } => {
+            let mut expressions = expressions.iter_mut();
+            for quasi in quasis {
+                visitor.visit_template_element_mut(quasi);
+                if let Some(expr) = expressions.next() {
+                    visitor.visit_expression_mut(expr);
+                }
+            }
+        }
+    }
+}
+
+/// Visits the patterns and default values of a function's parameters.
+pub fn walk_parameters_mut<V: VisitMut + ?Sized>(visitor: &mut V, params: &mut [Parameter]) {
+    for param in params {
+        walk_pattern_mut(visitor, &mut param.target);
+        if let Some(default) = &mut param.default {
+            visitor.visit_expression_mut(default);
+        }
+    }
+}
+
+pub fn walk_property_key_mut<V: VisitMut + ?Sized>(visitor: &mut V, key: &mut PropertyKey) {
+    if let PropertyKeyKind::Computed(expr) = &mut key.kind {
+        visitor.visit_expression_mut(expr);
+    }
+}
+
+/// Visits the expressions inside a pattern: the objects of its members and
+/// its default values.
+pub fn walk_pattern_mut<V: VisitMut + ?Sized>(visitor: &mut V, pattern: &mut Pattern) {
+    match &mut pattern.kind {
+        PatternKind::Identifier(_) => {}
+        PatternKind::Member(expr) => visitor.visit_expression_mut(expr),
+        PatternKind::Object { properties, rest } => {
+            for property in properties {
+                walk_property_key_mut(visitor, &mut property.key);
+                walk_pattern_mut(visitor, &mut property.value);
+                if let Some(default) = &mut property.default {
+                    visitor.visit_expression_mut(default);
+                }
+            }
+            if let Some(rest) = rest {
+                walk_pattern_mut(visitor, &mut rest.target);
+            }
+        }
+        PatternKind::Array { elements, rest } => {
+            for element in elements.iter_mut().flatten() {
+                walk_pattern_mut(visitor, &mut element.target);
+                if let Some(default) = &mut element.default {
+                    visitor.visit_expression_mut(default);
+                }
+            }
+            if let Some(rest) = rest {
+                walk_pattern_mut(visitor, &mut rest.target);
+            }
+        }
+    }
+}
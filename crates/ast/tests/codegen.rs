@@ -0,0 +1,640 @@
+//! Printing must keep the program the same: for randomly generated
+//! programs, `parse(to_source(ast))` must equal `ast` in both the compact
+//! and the pretty style.
+
+use shadowjs_ast::codegen::Style;
+use shadowjs_ast::fold::Fold;
+use shadowjs_ast::{
+    ArrowBody, DeclarationKind, ElementPattern, Expression, ExpressionKind, Identifier, MethodKind,
+    Parameter, Pattern, PatternKind, Program, Property, PropertyKey, PropertyKeyKind, PropertyKind,
+    PropertyPattern, RestElement, Span, Statement, StatementKind, TemplateElement,
+};
+use shadowjs_parser::Parser;
+
+const PROGRAMS: u64 = 2000;
+
+#[test]
+fn random_programs_print_back_to_themselves() {
+    for seed in 1..=PROGRAMS {
+        let program = Generator::new(seed).program();
+        for style in [Style::Compact, Style::default()] {
+            if let Err(message) = round_trip(&program, &style) {
+                panic!("random program {} in {:?} style: {}", seed, style, message);
+            }
+        }
+    }
+}
+
+/// Prints `program` in `style` and checks that it parses back to it.
+fn round_trip(program: &Program, style: &Style) -> Result<(), String> {
+    let src = program.to_source(style);
+    let reparsed = Parser::new(&src).parse().map_err(|diagnostics| {
+        format!(
+            "printed source does not parse ({}):\n{}",
+            diagnostics[0].message, src
+        )
+    })?;
+    if StripSpans.fold_program(reparsed) != *program {
+        return Err(format!("printed source parses differently:\n{}", src));
+    }
+    Ok(())
+}
+
+/// Clears every span, since the generated programs have none.
+struct StripSpans;
+
+impl Fold for StripSpans {
+    fn fold_span(&mut self, _: Span) -> Span {
+        Span::default()
+    }
+}
+
+/// `source` printed back in the pretty style.
+fn pretty(source: &str) -> String {
+    Parser::new(source)
+        .parse()
+        .unwrap()
+        .to_source(&Style::default())
+}
+
+#[test]
+fn patterns_in_declarations_and_loop_heads_are_spaced() {
+    assert_eq!(
+        pretty("for (const [p, q] of r) {}"),
+        "for (const [p, q] of r) {}\n"
+    );
+    assert_eq!(
+        pretty("for (let {a, b: [c]} of list) f(a, c);"),
+        "for (let { a, b: [c] } of list) f(a, c);\n"
+    );
+    assert_eq!(
+        pretty("for ([x, y] of pairs) ;"),
+        "for ([x, y] of pairs) ;\n"
+    );
+    assert_eq!(pretty("for (x of xs) {}"), "for (x of xs) {}\n");
+    assert_eq!(
+        pretty("const {a, ...rest} = o; let [b] = c;"),
+        "const { a, ...rest } = o;\nlet [b] = c;\n"
+    );
+    assert_eq!(
+        Parser::new("for (const [p, q] of r) {}")
+            .parse()
+            .unwrap()
+            .to_source(&Style::Compact),
+        "for(const[p,q]of r){}"
+    );
+}
+
+// Random programs, limited to trees the parser can produce
+
+const NAMES: &[&str] = &["a", "b", "x", "$", "_tmp", "ünï", "undefined"];
+const STRINGS: &[&str] = &[
+    "",
+    "a",
+    "if",
+    "a b",
+    "quote \" and \\",
+    "line\nbreak",
+    "\u{2028}",
+];
+const NUMBERS: &[f64] = &[0.0, 1.0, 2.5, 255.0, 1e21, 1e-7, 0.1, 123456789.0];
+// Raw template text with the cooked string the lexer makes of it
+const TEMPLATE_TEXT: &[(&str, &str)] = &[
+    ("", ""),
+    ("a b", "a b"),
+    ("\\n", "\n"),
+    ("\\`", "`"),
+    ("$", "$"),
+];
+const PREFIX_OPERATORS: &[&str] = &["-", "+", "!", "~", "typeof", "void", "delete"];
+const INFIX_OPERATORS: &[&str] = &[
+    "+",
+    "-",
+    "*",
+    "/",
+    "%",
+    "**",
+    "==",
+    "!=",
+    "===",
+    "!==",
+    "<",
+    ">",
+    "<=",
+    ">=",
+    "&",
+    "|",
+    "^",
+    "<<",
+    ">>",
+    ">>>",
+    "&&",
+    "||",
+    "??",
+    "in",
+    "instanceof",
+];
+const ASSIGN_OPERATORS: &[&str] = &["=", "+=", "-=", "**=", "/=", ">>>=", "&&=", "||=", "??="];
+
+struct Generator {
+    state: u64,
+}
+
+impl Generator {
+    fn new(seed: u64) -> Self {
+        Self {
+            state: seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1,
+        }
+    }
+
+    // xorshift64*
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn chance(&mut self, percent: usize) -> bool {
+        self.below(100) < percent
+    }
+
+    fn pick<'t, T>(&mut self, items: &'t [T]) -> &'t T {
+        &items[self.below(items.len())]
+    }
+
+    fn name(&mut self) -> String {
+        self.pick(NAMES).to_string()
+    }
+
+    fn identifier(&mut self) -> Identifier {
+        Identifier::new(self.name(), Span::default())
+    }
+
+    fn program(&mut self) -> Program {
+        let count = 1 + self.below(4);
+        Program {
+            statements: (0..count).map(|_| self.statement(3)).collect(),
+        }
+    }
+
+    fn block(&mut self, depth: usize) -> Statement {
+        let count = self.below(3);
+        stmt(StatementKind::Block(
+            (0..count).map(|_| self.statement(depth)).collect(),
+        ))
+    }
+
+    fn statement(&mut self, depth: usize) -> Statement {
+        if depth == 0 {
+            return stmt(StatementKind::Expression(self.expression(2)));
+        }
+        let depth = depth - 1;
+        let kind = match self.below(18) {
+            0 => StatementKind::Empty,
+            1 => {
+                let pattern = self.binding(2);
+                let value = match pattern.kind {
+                    PatternKind::Identifier(_) => self.chance(70).then(|| self.expression(3)),
+                    _ => Some(self.expression(3)),
+                };
+                StatementKind::Let { pattern, value }
+            }
+            2 => StatementKind::Const {
+                pattern: self.binding(2),
+                value: self.expression(3),
+            },
+            3 => StatementKind::Return(self.chance(70).then(|| self.expression(3))),
+            4 => return self.block(depth),
+            5 => {
+                let consequence = self.statement(depth);
+                let alternative = self.chance(50).then(|| Box::new(self.statement(depth)));
+                // Only braces can keep an `else` off an inner `if`
+                let consequence = match (&alternative, ends_with_open_if(&consequence)) {
+                    (Some(_), true) => stmt(StatementKind::Block(vec![consequence])),
+                    _ => consequence,
+                };
+                StatementKind::If {
+                    condition: self.expression(3),
+                    consequence: Box::new(consequence),
+                    alternative,
+                }
+            }
+            6 => StatementKind::Function {
+                name: self.identifier(),
+                params: self.params(depth, false),
+                body: Box::new(self.block(depth)),
+            },
+            7 => StatementKind::While {
+                condition: self.expression(3),
+                body: Box::new(self.statement(depth)),
+            },
+            8 => StatementKind::DoWhile {
+                body: Box::new(self.statement(depth)),
+                condition: self.expression(3),
+            },
+            9 => {
+                let init = match self.below(4) {
+                    0 => None,
+                    1 => Some(StatementKind::Let {
+                        pattern: self.binding(1),
+                        value: Some(self.expression(3)),
+                    }),
+                    2 => Some(StatementKind::Const {
+                        pattern: self.binding(1),
+                        value: self.expression(3),
+                    }),
+                    _ => Some(StatementKind::Expression(self.expression(3))),
+                };
+                StatementKind::For {
+                    init: init.map(|init| Box::new(stmt(init))),
+                    condition: self.chance(70).then(|| self.expression(3)),
+                    update: self.chance(70).then(|| self.expression(3)),
+                    body: Box::new(self.statement(depth)),
+                }
+            }
+            10 => StatementKind::Break(self.chance(50).then(|| self.identifier())),
+            11 => StatementKind::Continue(self.chance(50).then(|| self.identifier())),
+            12 => StatementKind::Labeled {
+                label: self.identifier(),
+                body: Box::new(self.statement(depth)),
+            },
+            13 => StatementKind::Throw(self.expression(3)),
+            16 => {
+                let declaration = match self.below(3) {
+                    0 => None,
+                    1 => Some(DeclarationKind::Let),
+                    _ => Some(DeclarationKind::Const),
+                };
+                StatementKind::ForOf {
+                    target: match declaration {
+                        Some(_) => self.binding(1),
+                        None => self.assignment_pattern(1),
+                    },
+                    declaration,
+                    iterable: self.expression(3),
+                    body: Box::new(self.statement(depth)),
+                }
+            }
+            14 => {
+                let handler = self.chance(70);
+                StatementKind::Try {
+                    block: Box::new(self.block(depth)),
+                    param: (handler && self.chance(70)).then(|| self.identifier()),
+                    handler: handler.then(|| Box::new(self.block(depth))),
+                    finalizer: (!handler || self.chance(50)).then(|| Box::new(self.block(depth))),
+                }
+            }
+            _ => StatementKind::Expression(self.expression(4)),
+        };
+        stmt(kind)
+    }
+
+    /// Arrow parameters are parsed as an expression first, so `converted`
+    /// ones have the limits of an assignment pattern.
+    fn params(&mut self, depth: usize, converted: bool) -> Vec<Parameter> {
+        let count = self.below(3);
+        let mut params: Vec<Parameter> = (0..count)
+            .map(|_| Parameter {
+                target: self.pattern(1, true, converted),
+                default: self.chance(30).then(|| self.expression(depth)),
+                rest: false,
+                span: Span::default(),
+            })
+            .collect();
+        if self.chance(20) {
+            params.push(Parameter {
+                target: self.pattern(1, true, converted),
+                default: None,
+                rest: true,
+                span: Span::default(),
+            });
+        }
+        params
+    }
+
+    /// A pattern for a declaration or parameter, which only binds names.
+    fn binding(&mut self, depth: usize) -> Pattern {
+        self.pattern(depth, true, false)
+    }
+
+    /// A pattern for an assignment. These are converted from array and
+    /// object literals, so they have no holes.
+    fn assignment_pattern(&mut self, depth: usize) -> Pattern {
+        self.pattern(depth, false, true)
+    }
+
+    fn pattern(&mut self, depth: usize, binding: bool, converted: bool) -> Pattern {
+        if depth == 0 || self.chance(50) {
+            return self.simple_pattern(binding);
+        }
+        let depth = depth - 1;
+        let count = self.below(3);
+        if self.chance(50) {
+            let properties = (0..count)
+                .map(|_| {
+                    // Often shorthand, as in `{a}`
+                    let (key, value) = if self.chance(40) {
+                        let name = self.name();
+                        (
+                            key(PropertyKeyKind::String(name.clone())),
+                            pattern(PatternKind::Identifier(name)),
+                        )
+                    } else {
+                        let key = self.property_key(depth);
+                        (key, self.pattern(depth, binding, converted))
+                    };
+                    PropertyPattern {
+                        key,
+                        value,
+                        default: self.chance(30).then(|| self.expression(1)),
+                        span: Span::default(),
+                    }
+                })
+                .collect();
+            let rest = self.chance(20).then(|| rest(self.simple_pattern(binding)));
+            pattern(PatternKind::Object { properties, rest })
+        } else {
+            let elements = (0..count)
+                .map(|_| {
+                    (converted || self.chance(80)).then(|| ElementPattern {
+                        target: self.pattern(depth, binding, converted),
+                        default: self.chance(30).then(|| self.expression(1)),
+                        span: Span::default(),
+                    })
+                })
+                .collect();
+            pattern(PatternKind::Array {
+                elements,
+                rest: self
+                    .chance(20)
+                    .then(|| rest(self.pattern(depth, binding, converted))),
+            })
+        }
+    }
+
+    /// A name, or a member when assigning.
+    fn simple_pattern(&mut self, binding: bool) -> Pattern {
+        if binding || self.chance(60) {
+            pattern(PatternKind::Identifier(self.name()))
+        } else {
+            pattern(PatternKind::Member(Box::new(self.index(1))))
+        }
+    }
+
+    fn property_key(&mut self, depth: usize) -> PropertyKey {
+        key(match self.below(5) {
+            0 => PropertyKeyKind::Number(*self.pick(NUMBERS)),
+            1 => PropertyKeyKind::Computed(Box::new(self.expression(depth))),
+            _ => PropertyKeyKind::String(self.pick(STRINGS).to_string()),
+        })
+    }
+
+    /// The members of an object literal: values, often shorthand, methods,
+    /// accessors and spreads.
+    fn properties(&mut self, depth: usize) -> Vec<Property> {
+        let count = self.below(3);
+        (0..count)
+            .map(|_| match self.below(10) {
+                0 => property(PropertyKind::Spread(self.expression(depth))),
+                1 | 2 => {
+                    let name = self.name();
+                    property(PropertyKind::Value {
+                        key: key(PropertyKeyKind::String(name.clone())),
+                        value: expr(ExpressionKind::Identifier(name)),
+                    })
+                }
+                3..=5 => {
+                    let kind = *self.pick(&[MethodKind::Method, MethodKind::Get, MethodKind::Set]);
+                    let params = match kind {
+                        MethodKind::Method => self.params(depth, false),
+                        MethodKind::Get => vec![],
+                        MethodKind::Set => vec![Parameter {
+                            target: self.binding(1),
+                            default: self.chance(30).then(|| self.expression(depth)),
+                            rest: false,
+                            span: Span::default(),
+                        }],
+                    };
+                    property(PropertyKind::Method {
+                        kind,
+                        key: self.property_key(depth),
+                        params,
+                        body: Box::new(self.block(depth.min(1))),
+                    })
+                }
+                _ => property(PropertyKind::Value {
+                    key: self.property_key(depth),
+                    value: self.expression(depth),
+                }),
+            })
+            .collect()
+    }
+
+    /// Array elements or call arguments, some of them spread.
+    fn elements(&mut self, depth: usize) -> Vec<Expression> {
+        let count = self.below(3);
+        (0..count)
+            .map(|_| {
+                let element = self.expression(depth);
+                if self.chance(20) {
+                    expr(ExpressionKind::Spread(Box::new(element)))
+                } else {
+                    element
+                }
+            })
+            .collect()
+    }
+
+    fn target(&mut self, depth: usize) -> Expression {
+        if self.chance(50) {
+            expr(ExpressionKind::Identifier(self.name()))
+        } else {
+            self.index(depth)
+        }
+    }
+
+    fn index(&mut self, depth: usize) -> Expression {
+        let index = if self.chance(60) {
+            expr(ExpressionKind::String(self.pick(STRINGS).to_string()))
+        } else {
+            self.expression(depth)
+        };
+        expr(ExpressionKind::Index {
+            left: Box::new(self.expression(depth)),
+            index: Box::new(index),
+        })
+    }
+
+    fn template(&mut self, depth: usize) -> Expression {
+        let count = self.below(3);
+        let expressions: Vec<Expression> = (0..count).map(|_| self.expression(depth)).collect();
+        let quasis = (0..=count)
+            .map(|_| {
+                let (raw, cooked) = *self.pick(TEMPLATE_TEXT);
+                TemplateElement {
+                    cooked: Some(cooked.to_string()),
+                    raw: raw.to_string(),
+                    span: Span::default(),
+                }
+            })
+            .collect();
+        expr(ExpressionKind::Template {
+            quasis,
+            expressions,
+        })
+    }
+
+    fn expression(&mut self, depth: usize) -> Expression {
+        if depth == 0 {
+            return self.leaf();
+        }
+        let depth = depth - 1;
+        let kind = match self.below(19) {
+            0 => ExpressionKind::Prefix {
+                operator: self.pick(PREFIX_OPERATORS).to_string(),
+                right: Box::new(self.expression(depth)),
+            },
+            1..=3 => ExpressionKind::Infix {
+                left: Box::new(self.expression(depth)),
+                operator: self.pick(INFIX_OPERATORS).to_string(),
+                right: Box::new(self.expression(depth)),
+            },
+            4 => ExpressionKind::Conditional {
+                condition: Box::new(self.expression(depth)),
+                consequence: Box::new(self.expression(depth)),
+                alternative: Box::new(self.expression(depth)),
+            },
+            5 => ExpressionKind::Call {
+                function: Box::new(self.expression(depth)),
+                arguments: self.elements(depth),
+            },
+            6 => ExpressionKind::Array(self.elements(depth)),
+            7 => ExpressionKind::Object(self.properties(depth)),
+            8 | 9 => return self.index(depth),
+            10 => {
+                let operator = self.pick(ASSIGN_OPERATORS).to_string();
+                let target = match self.target(depth).kind {
+                    _ if operator == "=" && self.chance(30) => self.assignment_pattern(2),
+                    ExpressionKind::Identifier(name) => pattern(PatternKind::Identifier(name)),
+                    kind => pattern(PatternKind::Member(Box::new(expr(kind)))),
+                };
+                ExpressionKind::Assign {
+                    target,
+                    operator,
+                    value: Box::new(self.expression(depth)),
+                }
+            }
+            11 => ExpressionKind::Update {
+                operator: self.pick(&["++", "--"]).to_string(),
+                prefix: self.chance(50),
+                target: Box::new(self.target(depth)),
+            },
+            12 => ExpressionKind::Function {
+                name: self.chance(50).then(|| self.identifier()),
+                params: self.params(depth, false),
+                body: Box::new(self.block(depth.min(1))),
+            },
+            15 => ExpressionKind::Arrow {
+                params: self.params(depth, true),
+                body: if self.chance(50) {
+                    ArrowBody::Expression(Box::new(self.expression(depth)))
+                } else {
+                    ArrowBody::Block(Box::new(self.block(depth.min(1))))
+                },
+            },
+            13 => return self.template(depth),
+            14 => ExpressionKind::TaggedTemplate {
+                tag: Box::new(self.expression(depth)),
+                quasi: Box::new(self.template(depth)),
+            },
+            _ => return self.leaf(),
+        };
+        expr(kind)
+    }
+
+    fn leaf(&mut self) -> Expression {
+        let kind = match self.below(9) {
+            0 | 1 => ExpressionKind::Identifier(self.name()),
+            2 => ExpressionKind::Number(*self.pick(NUMBERS)),
+            3 => ExpressionKind::String(self.pick(STRINGS).to_string()),
+            4 => ExpressionKind::BigInt(self.pick(&["0", "10", "0xff"]).to_string()),
+            5 => ExpressionKind::RegExp {
+                pattern: self.pick(&["a+", "[/]", "\\d*"]).to_string(),
+                flags: self.pick(&["", "g", "gi"]).to_string(),
+            },
+            6 => ExpressionKind::Boolean(self.chance(50)),
+            7 => ExpressionKind::This,
+            _ => ExpressionKind::Null,
+        };
+        expr(kind)
+    }
+}
+
+fn stmt(kind: StatementKind) -> Statement {
+    Statement::new(kind, Span::default())
+}
+
+fn expr(kind: ExpressionKind) -> Expression {
+    Expression::new(kind, Span::default())
+}
+
+fn pattern(kind: PatternKind) -> Pattern {
+    Pattern::new(kind, Span::default())
+}
+
+fn rest(target: Pattern) -> Box<RestElement> {
+    Box::new(RestElement {
+        target,
+        span: Span::default(),
+    })
+}
+
+fn key(kind: PropertyKeyKind) -> PropertyKey {
+    PropertyKey::new(kind, Span::default())
+}
+
+fn property(kind: PropertyKind) -> Property {
+    Property::new(kind, Span::default())
+}
+
+fn ends_with_open_if(stmt: &Statement) -> bool {
+    match &stmt.kind {
+        StatementKind::If {
+            alternative: None, ..
+        } => true,
+        StatementKind::If {
+            alternative: Some(body),
+            ..
+        }
+        | StatementKind::While { body, .. }
+        | StatementKind::For { body, .. }
+        | StatementKind::ForOf { body, .. }
+        | StatementKind::Labeled { body, .. } => ends_with_open_if(body),
+        _ => false,
+    }
+}
+
+#[test]
+fn objects_with_methods_put_each_member_on_its_own_line() {
+    assert_eq!(pretty("x = {a: 1, b};"), "x = { a: 1, b };\n");
+    assert_eq!(
+        pretty("x = {a: 1, get b() { return 2; }, set b(v) {}, c() { f(); g(); }};"),
+        "x = {\n    a: 1,\n    get b() {\n        return 2;\n    },\n    set b(v) {},\n    c() {\n        f();\n        g();\n    }\n};\n"
+    );
+    assert_eq!(
+        pretty("function f() { return {m() { return 1; }}; }"),
+        "function f() {\n    return {\n        m() {\n            return 1;\n        }\n    };\n}\n"
+    );
+    assert_eq!(
+        Parser::new("x = {a: 1, m() { return 1; }};")
+            .parse()
+            .unwrap()
+            .to_source(&Style::Compact),
+        "x={a:1,m(){return 1;}};"
+    );
+}
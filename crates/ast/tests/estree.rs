@@ -0,0 +1,108 @@
+//! Programs must survive a round trip through ESTree JSON with every
+//! position intact.
+
+use serde_json::Value;
+use shadowjs_ast::Program;
+use shadowjs_parser::Parser;
+
+const SOURCES: &[&str] = &[
+    "let x = 1 + 2 * y;",
+    "function add(a, b = 1, ...rest) { return a + b; }",
+    "let f = function fact(n) { return n <= 1 ? 1 : n * fact(n - 1); };",
+    "let g = (a, [b, c] = [], {d}) => a + b + c + d;",
+    "let {a, b: [c, d = 2, ...e], [k]: f, ...g} = x;",
+    "[a.b, {c = 1}] = x;",
+    "({a, b: 1, [c]: 2, 3: 4, get d() { return 1; }, set d(v) {}, m() {}, ...e});",
+    "outer: for (let i = 0; i < 3; i++) { for (;;) { continue outer; } break; }",
+    "for (const [k, v] of entries) { try { k(); } catch (err) { throw err; } finally {} }",
+    "for ({x} of xs) while (x) do x--; while (x);",
+    "try {} catch {}",
+    "let s = `a${b}c${`d${e}`}`; tag`x${y}`;",
+    "if (a) b(); else if (c) { d(); }",
+    "let ünï = '\u{2028}'; // comment\nlet r = /a+[/]/gi; let n = 10n;",
+];
+
+fn parse(source: &str) -> Program {
+    match Parser::new(source).parse() {
+        Ok(program) => program,
+        Err(diagnostics) => panic!("{:?} failed to parse: {:?}", source, diagnostics),
+    }
+}
+
+#[test]
+fn round_trip_keeps_positions() {
+    for source in SOURCES {
+        let program = parse(source);
+        let json = program.to_estree();
+        let imported = Program::from_estree(&json)
+            .unwrap_or_else(|err| panic!("{:?} did not import: {}", source, err));
+        assert_eq!(imported, program, "{:?}", source);
+        assert_eq!(imported.to_estree(), json, "{:?}", source);
+    }
+}
+
+#[test]
+fn nodes_are_located() {
+    for source in SOURCES {
+        let json = parse(source).to_estree();
+        let mut unlocated = vec![];
+        collect_unlocated(&json, None, &mut unlocated);
+        assert!(unlocated.is_empty(), "{:?}: {:?}", source, unlocated);
+    }
+}
+
+#[test]
+fn patterns_and_names_point_at_their_source() {
+    let source = "function f([a, ...b], {c: d}) { try {} catch (err) {} }";
+    let json = parse(source).to_estree();
+    let function = &json["body"][0];
+    assert_eq!(text(source, &function["id"]), "f");
+    let params = &function["params"];
+    assert_eq!(text(source, &params[0]), "[a, ...b]");
+    assert_eq!(text(source, &params[0]["elements"][1]), "...b");
+    assert_eq!(text(source, &params[1]), "{c: d}");
+    assert_eq!(text(source, &params[1]["properties"][0]), "c: d");
+    assert_eq!(text(source, &params[1]["properties"][0]["key"]), "c");
+    assert_eq!(text(source, &params[1]["properties"][0]["value"]), "d");
+    let handler = &function["body"]["body"][0]["handler"];
+    assert_eq!(text(source, &handler["param"]), "err");
+    assert_eq!(handler["param"]["loc"]["start"]["column"], 46);
+}
+
+/// The source text at the `range` of `node`.
+fn text<'s>(source: &'s str, node: &Value) -> &'s str {
+    let offset = |i: usize| node["range"][i].as_u64().unwrap() as usize;
+    &source[offset(0)..offset(1)]
+}
+
+/// The types of the nodes in `value` without a `loc`, leaving out the ones
+/// the AST keeps no position for.
+fn collect_unlocated(value: &Value, parent: Option<&str>, unlocated: &mut Vec<String>) {
+    match value {
+        Value::Array(items) => {
+            for item in items {
+                collect_unlocated(item, parent, unlocated);
+            }
+        }
+        Value::Object(map) => {
+            let kind = map.get("type").and_then(Value::as_str);
+            if let Some(kind) = kind {
+                let exempt = matches!(
+                    (parent, kind),
+                    (_, "CatchClause")
+                        | (Some("Property"), "FunctionExpression")
+                        | (Some("ForOfStatement"), "VariableDeclaration")
+                );
+                if map["loc"].is_null() && !exempt {
+                    unlocated.push(kind.to_string());
+                }
+            }
+            for (key, child) in map {
+                if key != "loc" {
+                    collect_unlocated(child, kind.or(parent), unlocated);
+                }
+            }
+        }
+        _ => {}
+    }
+}
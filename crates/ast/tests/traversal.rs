@@ -0,0 +1,242 @@
+//! The traversals on parsed programs, checked through the source the
+//! result prints as.
+
+use shadowjs_ast::codegen::Style;
+use shadowjs_ast::fold::{walk_expression, Fold};
+use shadowjs_ast::visit::walk_expression as walk_expression_ref;
+use shadowjs_ast::visit_mut::{walk_expression_mut, walk_statement_mut};
+use shadowjs_ast::{
+    Expression, ExpressionKind, Parameter, Pattern, PatternKind, Program, Statement, StatementKind,
+    Visit, VisitMut,
+};
+use shadowjs_parser::Parser;
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+
+fn parse(source: &str) -> Program {
+    Parser::new(source).parse().unwrap()
+}
+
+fn pretty(program: &Program) -> String {
+    program.to_source(&Style::default())
+}
+
+/// Counts the uses of each name as an expression.
+#[derive(Default)]
+struct CountIdentifiers {
+    counts: BTreeMap<String, usize>,
+}
+
+impl Visit for CountIdentifiers {
+    type Error = Infallible;
+
+    fn visit_identifier(&mut self, name: &str) -> Result<(), Infallible> {
+        *self.counts.entry(name.to_string()).or_default() += 1;
+        Ok(())
+    }
+}
+
+#[test]
+fn visit_counts_identifiers() {
+    let program = parse(
+        "let total = 0;
+         function add(n, { by = step } = {}) { return n + by + step; }
+         for (const x of items) total = add(total, x);
+         result = `${total} of ${items.length}`;",
+    );
+    let mut counter = CountIdentifiers::default();
+    counter.visit_program(&program).unwrap();
+    // Names being bound or assigned, like `total` in its declaration and
+    // `result`, are patterns rather than uses; nor is `length`, a property
+    // name
+    let counts: Vec<_> = counter
+        .counts
+        .iter()
+        .map(|(name, count)| format!("{}={}", name, count))
+        .collect();
+    assert_eq!(
+        counts,
+        ["add=1", "by=1", "items=2", "n=1", "step=2", "total=2", "x=1"]
+    );
+    // Visiting leaves the program as it was
+    assert_eq!(
+        program.to_source(&Style::Compact),
+        "let total=0;function add(n,{by=step}={}){return n+by+step;}\
+         for(const x of items)total=add(total,x);result=`${total} of ${items.length}`;"
+    );
+}
+
+/// Stops at the first call, returning what it calls.
+struct FirstCall;
+
+impl Visit for FirstCall {
+    type Error = String;
+
+    fn visit_expression(&mut self, expr: &Expression) -> Result<(), String> {
+        if let ExpressionKind::Call { function, .. } = &expr.kind {
+            return Err(function.to_source(&Style::Compact));
+        }
+        walk_expression_ref(self, expr)
+    }
+}
+
+#[test]
+fn visit_stops_at_an_error() {
+    let program = parse("x = 1 + g(h(2)); f();");
+    assert_eq!(FirstCall.visit_program(&program), Err("g".to_string()));
+}
+
+/// Renames every binding of `from`, and every use of it, to `to`.
+struct Rename {
+    from: &'static str,
+    to: &'static str,
+}
+
+impl Rename {
+    fn pattern(&self, pattern: &mut Pattern) {
+        match &mut pattern.kind {
+            PatternKind::Identifier(name) => {
+                if name == self.from {
+                    *name = self.to.to_string();
+                }
+            }
+            PatternKind::Member(_) => {}
+            PatternKind::Object { properties, rest } => {
+                for property in properties {
+                    self.pattern(&mut property.value);
+                }
+                if let Some(rest) = rest {
+                    self.pattern(&mut rest.target);
+                }
+            }
+            PatternKind::Array { elements, rest } => {
+                for element in elements.iter_mut().flatten() {
+                    self.pattern(&mut element.target);
+                }
+                if let Some(rest) = rest {
+                    self.pattern(&mut rest.target);
+                }
+            }
+        }
+    }
+
+    fn parameters(&self, params: &mut [Parameter]) {
+        for param in params {
+            self.pattern(&mut param.target);
+        }
+    }
+}
+
+impl VisitMut for Rename {
+    fn visit_statement_mut(&mut self, stmt: &mut Statement) {
+        match &mut stmt.kind {
+            StatementKind::Let { pattern, .. } | StatementKind::Const { pattern, .. } => {
+                self.pattern(pattern)
+            }
+            StatementKind::Function { params, .. } => self.parameters(params),
+            _ => {}
+        }
+        walk_statement_mut(self, stmt);
+    }
+
+    fn visit_expression_mut(&mut self, expr: &mut Expression) {
+        match &mut expr.kind {
+            ExpressionKind::Identifier(name) if name == self.from => *name = self.to.to_string(),
+            ExpressionKind::Assign { target, .. } => self.pattern(target),
+            ExpressionKind::Function { params, .. } | ExpressionKind::Arrow { params, .. } => {
+                self.parameters(params)
+            }
+            _ => {}
+        }
+        walk_expression_mut(self, expr);
+    }
+}
+
+#[test]
+fn visit_mut_renames_bindings() {
+    let mut program = parse(
+        "let count = 0;
+         const { count: alias, ...rest } = o;
+         function bump([count] = [1]) { return count + 1; }
+         count = bump(count);
+         const twice = (count) => count * 2;",
+    );
+    Rename {
+        from: "count",
+        to: "n",
+    }
+    .visit_program_mut(&mut program);
+    // The property name `count` in the object pattern stays as it is
+    assert_eq!(
+        pretty(&program),
+        "let n = 0;\n\
+         const { count: alias, ...rest } = o;\n\
+         function bump([n] = [1]) {\n    return n + 1;\n}\n\
+         n = bump(n);\n\
+         const twice = (n) => n * 2;\n"
+    );
+}
+
+/// Adds up numbers, and picks the branch of a conditional on a constant.
+struct Simplify;
+
+impl Fold for Simplify {
+    fn fold_expression(&mut self, expr: Expression) -> Expression {
+        let expr = walk_expression(self, expr);
+        match expr.kind {
+            ExpressionKind::Infix {
+                left,
+                operator,
+                right,
+            } => match (&left.kind, operator.as_str(), &right.kind) {
+                (ExpressionKind::Number(a), "+", ExpressionKind::Number(b)) => Expression {
+                    kind: ExpressionKind::Number(a + b),
+                    span: expr.span,
+                },
+                _ => Expression {
+                    kind: ExpressionKind::Infix {
+                        left,
+                        operator,
+                        right,
+                    },
+                    span: expr.span,
+                },
+            },
+            ExpressionKind::Conditional {
+                condition,
+                consequence,
+                alternative,
+            } => match condition.kind {
+                ExpressionKind::Boolean(true) => *consequence,
+                ExpressionKind::Boolean(false) => *alternative,
+                _ => Expression {
+                    kind: ExpressionKind::Conditional {
+                        condition,
+                        consequence,
+                        alternative,
+                    },
+                    span: expr.span,
+                },
+            },
+            _ => expr,
+        }
+    }
+}
+
+#[test]
+fn fold_rewrites_expressions() {
+    let program = parse(
+        "x = 1 + 2 + 3;
+         y = f(true ? a : b, (1 + 1) * b);
+         if (c + (0 + 0)) { z = false ? 'a' : 'b' + 1; }",
+    );
+    let program = Simplify.fold_program(program);
+    assert_eq!(
+        program.to_source(&Style::Compact),
+        "x=6;y=f(a,2*b);if(c+0){z=\"b\"+1;}"
+    );
+    assert_eq!(
+        pretty(&program),
+        "x = 6;\ny = f(a, 2 * b);\nif (c + 0) {\n    z = \"b\" + 1;\n}\n"
+    );
+}
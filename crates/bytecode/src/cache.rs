@@ -0,0 +1,42 @@
+//! Inline caches, which remember what a `GetNamed`, `SetNamed`, `GetGlobal`
+//! or `SetGlobal` instruction found the last times it ran so the VM can skip
+//! the lookup when it sees the same thing again.
+
+/// The most object shapes one property access remembers. Past this it is
+/// megamorphic: it keeps the shapes it has and stops learning new ones.
+pub const POLYMORPHIC_LIMIT: usize = 4;
+
+/// The slot a named property was found in, for each object shape a
+/// property access has seen. Shapes are known by their id.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PropertyCache {
+    entries: [(u64, usize); POLYMORPHIC_LIMIT],
+    len: usize,
+}
+
+impl PropertyCache {
+    /// The slot of the property in objects with shape `shape_id`.
+    pub fn lookup(&self, shape_id: u64) -> Option<usize> {
+        self.entries[..self.len]
+            .iter()
+            .find(|(id, _)| *id == shape_id)
+            .map(|(_, slot)| *slot)
+    }
+
+    /// Remembers the slot for another shape, unless the cache is full.
+    pub fn insert(&mut self, shape_id: u64, slot: usize) {
+        if self.len < POLYMORPHIC_LIMIT {
+            self.entries[self.len] = (shape_id, slot);
+            self.len += 1;
+        }
+    }
+
+    /// How many shapes are cached: 1 for a monomorphic access.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
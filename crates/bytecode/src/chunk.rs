@@ -1,17 +1,33 @@
+use crate::cache::PropertyCache;
 use crate::opcode::OpCode;
-use shadowjs_gc::trace::Trace;
+use shadowjs_gc::trace::{Trace, Tracer};
+use std::cell::Cell;
 use std::rc::Rc;
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct FunctionTemplate {
     pub name: Rc<String>,
+    // Parameters before the rest parameter, if there is one
     pub arity: usize,
-    pub chunk: Chunk,
+    // Whether arguments past `arity` are collected into an array
+    pub has_rest: bool,
+    // Arrow functions take `this` from where they are created
+    pub is_arrow: bool,
+    pub chunk: Rc<Chunk>,
+    pub upvalues: Vec<UpvalueDescriptor>,
+}
+
+/// Where a closure finds a captured variable when it is created: a local slot of
+/// the enclosing frame, or one of the enclosing closure's own upvalues.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UpvalueDescriptor {
+    pub is_local: bool,
+    pub index: usize,
 }
 
 impl Trace for FunctionTemplate {
-    fn trace(&self, visited: &mut std::collections::HashSet<usize>) {
-        self.chunk.trace(visited);
+    fn trace(&self, tracer: &mut Tracer) {
+        self.chunk.trace(tracer);
     }
 }
 
@@ -23,23 +39,54 @@ pub enum Constant {
 }
 
 impl Trace for Constant {
-    fn trace(&self, visited: &mut std::collections::HashSet<usize>) {
-        match self {
-            Constant::Function(f) => f.trace(visited),
-            _ => {}
+    fn trace(&self, tracer: &mut Tracer) {
+        if let Constant::Function(f) = self {
+            f.trace(tracer);
         }
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+/// An entry in a chunk's exception table: an exception raised by an
+/// instruction in `start..end` resumes at `target`, with the stack cut back to
+/// `stack_depth` slots above the frame base and the exception pushed on top.
+/// Entries for inner `try` statements come before the ones enclosing them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExceptionHandler {
+    pub start: usize,
+    pub end: usize,
+    pub target: usize,
+    pub stack_depth: usize,
+}
+
+/// An entry in a chunk's line table: the instructions from `offset` up to the
+/// next entry were compiled from the source at `line` and `column`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineEntry {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
 pub struct Chunk {
     pub code: Vec<OpCode>,
     pub constants: Vec<Constant>,
+    pub handlers: Vec<ExceptionHandler>,
+    pub lines: Vec<LineEntry>,
+    // The script the chunk was compiled from, for error locations
+    pub source_name: Rc<String>,
+    // One for each `GetNamed` and `SetNamed`, filled in as the code runs
+    pub property_caches: Vec<Cell<PropertyCache>>,
+    // One for each `GetGlobal` and `SetGlobal`: the VM's cell for the global
+    pub global_caches: Vec<Cell<Option<usize>>>,
+    // One for each `TemplateObject`: where the VM keeps the strings object
+    // it made for that tagged template
+    pub template_caches: Vec<Cell<Option<usize>>>,
 }
 
 impl Trace for Chunk {
-    fn trace(&self, visited: &mut std::collections::HashSet<usize>) {
-        self.constants.trace(visited);
+    fn trace(&self, tracer: &mut Tracer) {
+        self.constants.trace(tracer);
     }
 }
 
@@ -48,15 +95,67 @@ impl Chunk {
         Self {
             code: vec![],
             constants: vec![],
+            handlers: vec![],
+            lines: vec![],
+            source_name: Rc::default(),
+            property_caches: vec![],
+            global_caches: vec![],
+            template_caches: vec![],
         }
     }
 
-    pub fn write(&mut self, op: OpCode) {
+    /// Appends an instruction compiled from the source at `line` and `column`.
+    /// Runs of instructions from the same position share one line table entry.
+    pub fn write(&mut self, op: OpCode, line: usize, column: usize) {
+        let same = self
+            .lines
+            .last()
+            .is_some_and(|entry| entry.line == line && entry.column == column);
+        if !same {
+            self.lines.push(LineEntry {
+                offset: self.code.len(),
+                line,
+                column,
+            });
+        }
         self.code.push(op);
     }
 
+    /// The line and column the instruction at `offset` was compiled from.
+    pub fn location(&self, offset: usize) -> Option<(usize, usize)> {
+        let idx = self.lines.partition_point(|entry| entry.offset <= offset);
+        let entry = self.lines.get(idx.checked_sub(1)?)?;
+        Some((entry.line, entry.column))
+    }
+
+    /// Finds the innermost handler covering the instruction at `ip`.
+    pub fn find_handler(&self, ip: usize) -> Option<&ExceptionHandler> {
+        self.handlers
+            .iter()
+            .find(|handler| handler.start <= ip && ip < handler.end)
+    }
+
     pub fn add_constant(&mut self, value: Constant) -> usize {
         self.constants.push(value);
         self.constants.len() - 1
     }
+
+    /// Adds an empty cache for a property access, returning its index.
+    pub fn add_property_cache(&mut self) -> usize {
+        self.property_caches.push(Cell::default());
+        self.property_caches.len() - 1
+    }
+
+    /// Adds an empty cache for a global variable access, returning its index.
+    pub fn add_global_cache(&mut self) -> usize {
+        self.global_caches.push(Cell::default());
+        self.global_caches.len() - 1
+    }
+
+    /// Adds an empty cache for a tagged template's strings object, returning
+    /// its index.
+    pub fn add_template_cache(&mut self) -> usize {
+        self.template_caches.push(Cell::default());
+        self.template_caches.len() - 1
+    }
 }
@@ -1,195 +1,1812 @@
-use crate::chunk::{Chunk, Constant, FunctionTemplate};
+use crate::chunk::{Chunk, Constant, ExceptionHandler, FunctionTemplate, UpvalueDescriptor};
 use crate::opcode::OpCode;
-use shadowjs_ast::{Expression, Program, Statement};
+use shadowjs_ast::visit::{walk_expression, walk_statement};
+use shadowjs_ast::{
+    ArrowBody, DeclarationKind, Diagnostic, Expression, ExpressionKind, Identifier, MethodKind,
+    Parameter, Pattern, PatternKind, Program, Property, PropertyKey, PropertyKeyKind, PropertyKind,
+    Span, Statement, StatementKind, TemplateElement, Visit,
+};
+use std::collections::HashSet;
 use std::rc::Rc;
 
+struct Local {
+    name: String,
+    depth: usize,
+    is_captured: bool,
+    is_const: bool,
+    // False for a `let` or `const` binding until its declaration is
+    // compiled. Reads compiled before then, or from a function that may be
+    // called before then, check the binding at runtime.
+    is_initialized: bool,
+}
+
+/// A statement that `break` (and for loops, `continue`) can jump out of.
+struct Breakable {
+    labels: Vec<String>,
+    is_loop: bool,
+    // Locals that outlive the statement; a break discards everything above them
+    break_locals: usize,
+    // Locals that outlive one iteration; a continue discards everything above them
+    continue_locals: usize,
+    // Known up front when the continue target precedes the body
+    continue_target: Option<usize>,
+    break_jumps: Vec<usize>,
+    continue_jumps: Vec<usize>,
+    // `try` statements enclosing the breakable; jumps out run the finally
+    // blocks of any opened since
+    try_depth: usize,
+}
+
+/// A `try` block, or the catch block of a `try` with a finally, that is being
+/// compiled.
+struct TryContext {
+    finalizer: Option<Statement>,
+    // Locals declared outside the statement
+    locals: usize,
+    // Code protected by the statement. Copies of finally blocks inlined on the
+    // way out of it are cut out, since they run after the statement is left.
+    ranges: Vec<(usize, usize)>,
+    range_start: usize,
+}
+
+impl TryContext {
+    fn close_range(&mut self, end: usize) {
+        if self.range_start < end {
+            self.ranges.push((self.range_start, end));
+        }
+    }
+}
+
+/// What a function's code is compiled from. Only an arrow function can
+/// have an expression for its body, whose value it returns.
+#[derive(Clone, Copy)]
+enum FunctionBody<'a> {
+    Block(&'a Statement),
+    Expression(&'a Expression),
+}
+
+/// How the names in a pattern get their values.
+#[derive(Clone, Copy)]
+enum Binding {
+    // Declared by `let`, `const` or a parameter list
+    Declaration { is_const: bool },
+    // Assigned to, along with any members in the pattern
+    Assignment,
+}
+
+/// The property a member expression reads or writes. Constant names are
+/// accessed with the cached `GetNamed` and `SetNamed`, other keys are
+/// evaluated onto the stack for `GetIndex` and `SetIndex`.
+#[derive(Clone, Copy)]
+enum MemberKey<'a> {
+    Named(&'a str),
+    Computed(&'a Expression),
+}
+
+impl<'a> MemberKey<'a> {
+    fn of(index: &'a Expression) -> Self {
+        match &index.kind {
+            ExpressionKind::String(name) => MemberKey::Named(name),
+            _ => MemberKey::Computed(index),
+        }
+    }
+
+    /// How many values the reference takes on the stack: the object, and
+    /// the key unless it is named.
+    fn depth(&self) -> usize {
+        match self {
+            MemberKey::Named(_) => 1,
+            MemberKey::Computed(_) => 2,
+        }
+    }
+}
+
 pub struct BytecodeCompiler {
     chunk: Chunk,
+    // Slot 0 of every frame holds the callee, so locals start at 1
+    locals: Vec<Local>,
+    upvalues: Vec<UpvalueDescriptor>,
+    scope_depth: usize,
+    // `let` and `const` declarations of the script, only tracked by the
+    // outermost compiler
+    lexical_globals: HashSet<String>,
+    const_globals: HashSet<String>,
+    breakables: Vec<Breakable>,
+    tries: Vec<TryContext>,
+    // Labels waiting for the statement they are attached to
+    pending_labels: Vec<String>,
+    // The node being compiled; its start is recorded in the line table
+    span: Span,
+    // Compiler of the function this one is nested in
+    enclosing: Option<Box<BytecodeCompiler>>,
+}
+
+impl Default for BytecodeCompiler {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl BytecodeCompiler {
     pub fn new() -> Self {
         Self {
             chunk: Chunk::new(),
+            locals: vec![Local {
+                name: String::new(),
+                depth: 0,
+                is_captured: false,
+                is_const: false,
+                is_initialized: true,
+            }],
+            upvalues: vec![],
+            scope_depth: 0,
+            lexical_globals: HashSet::new(),
+            const_globals: HashSet::new(),
+            breakables: vec![],
+            tries: vec![],
+            pending_labels: vec![],
+            span: Span::default(),
+            enclosing: None,
         }
     }
 
-    pub fn compile(ast: &Program) -> Result<Chunk, String> {
+    pub fn compile(ast: &Program) -> Result<Chunk, Diagnostic> {
+        Self::compile_named(ast, "<anonymous>")
+    }
+
+    /// Compiles a script, naming it `source_name` in error locations.
+    pub fn compile_named(ast: &Program, source_name: &str) -> Result<Chunk, Diagnostic> {
         let mut compiler = Self::new();
-        for stmt in &ast.statements {
-            compiler.compile_statement(stmt)?;
-        }
+        compiler.chunk.source_name = Rc::new(source_name.to_string());
+        compiler.visit_program(ast)?;
         Ok(compiler.chunk)
     }
 
-    fn compile_statement(&mut self, stmt: &Statement) -> Result<(), String> {
-        match stmt {
-            Statement::Expression(expr) => {
-                self.compile_expression(expr)?;
+    fn compile_statements(&mut self, stmts: &[Statement]) -> Result<(), Diagnostic> {
+        // Reserve a slot for every declaration up front, so functions hoisted
+        // above a `let` can still capture it
+        let outer = self.span;
+        if self.scope_depth == 0 {
+            // Scripts keep their declarations in globals, which are only
+            // checked for names declared twice
+            let mut functions = HashSet::new();
+            for stmt in stmts {
+                self.span = stmt.span;
+                match &stmt.kind {
+                    StatementKind::Let { pattern, .. } | StatementKind::Const { pattern, .. } => {
+                        let is_const = matches!(stmt.kind, StatementKind::Const { .. });
+                        for (name, span) in pattern.bindings() {
+                            self.span = span;
+                            if functions.contains(name)
+                                || !self.lexical_globals.insert(name.to_string())
+                            {
+                                return Err(self.redeclaration_error(name));
+                            }
+                            if is_const {
+                                self.const_globals.insert(name.to_string());
+                            }
+                        }
+                    }
+                    StatementKind::Function { name, .. } => {
+                        self.span = name.span;
+                        if self.lexical_globals.contains(name.name.as_str()) {
+                            return Err(self.redeclaration_error(&name.name));
+                        }
+                        functions.insert(name.name.as_str());
+                    }
+                    _ => {}
+                }
+            }
+        } else {
+            for stmt in stmts {
+                // Redeclarations are reported at the declaration
+                self.span = stmt.span;
+                match &stmt.kind {
+                    StatementKind::Let { pattern, .. } => self.reserve_locals(pattern, false)?,
+                    StatementKind::Const { pattern, .. } => self.reserve_locals(pattern, true)?,
+                    StatementKind::Function { name, .. } => {
+                        self.emit(OpCode::Undefined);
+                        self.span = name.span;
+                        self.declare_local(&name.name)?;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        self.span = outer;
+
+        // Function declarations are hoisted to the top of their scope
+        for stmt in stmts {
+            if let StatementKind::Function { .. } = &stmt.kind {
+                self.visit_statement(stmt)?;
+            }
+        }
+        for stmt in stmts {
+            if !matches!(stmt.kind, StatementKind::Function { .. }) {
+                self.visit_statement(stmt)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn begin_try(&mut self, finalizer: Option<&Statement>) {
+        self.tries.push(TryContext {
+            finalizer: finalizer.cloned(),
+            locals: self.locals.len(),
+            ranges: vec![],
+            range_start: self.chunk.code.len(),
+        });
+    }
+
+    /// Returns the ranges protected by the innermost `try` context.
+    fn end_try(&mut self) -> Vec<(usize, usize)> {
+        let mut context = self.tries.pop().unwrap();
+        context.close_range(self.chunk.code.len());
+        context.ranges
+    }
+
+    /// Points the given ranges at a handler starting at the next instruction.
+    fn add_handlers(&mut self, ranges: &[(usize, usize)], stack_depth: usize) {
+        let target = self.chunk.code.len();
+        for &(start, end) in ranges {
+            self.chunk.handlers.push(ExceptionHandler {
+                start,
+                end,
+                target,
+                stack_depth,
+            });
+        }
+    }
+
+    /// Inlines the finally blocks of the `try` statements a jump leaves, from
+    /// the innermost out. Each one is compiled as if its statement had
+    /// already been exited, but still inside the statements around it.
+    fn emit_finalizers(&mut self, try_depth: usize) -> Result<(), Diagnostic> {
+        let mut exited = vec![];
+        while self.tries.len() > try_depth {
+            let mut context = self.tries.pop().unwrap();
+            context.close_range(self.chunk.code.len());
+            if let Some(finalizer) = &context.finalizer {
+                self.compile_outside(context.locals, finalizer)?;
+            }
+            exited.push(context);
+        }
+
+        let resume = self.chunk.code.len();
+        for mut context in exited.into_iter().rev() {
+            context.range_start = resume;
+            self.tries.push(context);
+        }
+        Ok(())
+    }
+
+    /// Compiles `stmt` where the locals from `locals` up, and the breakables
+    /// inside the exited `try` statements, are out of scope. The locals keep
+    /// their slots, since they are still on the stack.
+    fn compile_outside(&mut self, locals: usize, stmt: &Statement) -> Result<(), Diagnostic> {
+        let names: Vec<String> = self.locals[locals..]
+            .iter_mut()
+            .map(|local| std::mem::take(&mut local.name))
+            .collect();
+        let inner = self
+            .breakables
+            .iter()
+            .position(|breakable| breakable.try_depth > self.tries.len())
+            .unwrap_or(self.breakables.len());
+        let breakables = self.breakables.split_off(inner);
+
+        let result = self.visit_statement(stmt);
+
+        self.breakables.extend(breakables);
+        for (local, name) in self.locals[locals..].iter_mut().zip(names) {
+            local.name = name;
+        }
+        result
+    }
+
+    /// Tracks a value the VM leaves on top of the stack as a local that user
+    /// code cannot name, so slots assigned after it line up.
+    fn add_hidden_local(&mut self, name: &str) {
+        self.locals.push(Local {
+            name: name.to_string(),
+            depth: self.scope_depth,
+            is_captured: false,
+            is_const: false,
+            is_initialized: true,
+        });
+    }
+
+    fn emit_binary_operator(&mut self, operator: &str) -> Result<(), Diagnostic> {
+        let op = match operator {
+            "+" => OpCode::Add,
+            "-" => OpCode::Sub,
+            "*" => OpCode::Mul,
+            "/" => OpCode::Div,
+            "%" => OpCode::Mod,
+            "**" => OpCode::Exp,
+            "<<" => OpCode::ShiftLeft,
+            ">>" => OpCode::ShiftRight,
+            ">>>" => OpCode::UnsignedShiftRight,
+            "&" => OpCode::BitAnd,
+            "|" => OpCode::BitOr,
+            "^" => OpCode::BitXor,
+            "==" => OpCode::Equal,
+            "!=" => OpCode::NotEqual,
+            "===" => OpCode::StrictEqual,
+            "!==" => OpCode::StrictNotEqual,
+            "<" => OpCode::LessThan,
+            ">" => OpCode::GreaterThan,
+            "<=" => OpCode::LessEqual,
+            ">=" => OpCode::GreaterEqual,
+            "in" => OpCode::In,
+            "instanceof" => OpCode::InstanceOf,
+            _ => return Err(self.error(format!("Unknown operator: {}", operator))),
+        };
+        self.emit(op);
+        Ok(())
+    }
+
+    /// current value (left on the stack) should be kept.
+    fn emit_short_circuit(&mut self, operator: &str) -> usize {
+        self.emit(OpCode::Dup);
+        match operator {
+            "&&" | "&&=" => self.emit_jump(OpCode::JumpIfFalse(0)),
+            "||" | "||=" => self.emit_jump(OpCode::JumpIfTrue(0)),
+            _ => self.emit_jump(OpCode::JumpIfNotNullish(0)),
+        }
+    }
+
+    fn emit_get_variable(&mut self, name: &str) {
+        if let Some(slot) = self.resolve_local(name) {
+            self.emit(OpCode::GetLocal(slot));
+            if !self.locals[slot].is_initialized {
+                self.emit_check_initialized(name);
+            }
+        } else if let Some(idx) = self.resolve_upvalue(name) {
+            self.emit(OpCode::GetUpvalue(idx));
+            if self.may_be_uninitialized(name) {
+                self.emit_check_initialized(name);
+            }
+        } else {
+            let idx = self.name_constant(name);
+            let cache = self.chunk.add_global_cache();
+            self.emit(OpCode::GetGlobal(idx, cache));
+        }
+    }
+
+    /// Stores the value on top in the variable `name`. A `const` binding
+    /// is given code that throws instead, as the assignment is only an
+    /// error if it runs.
+    fn emit_set_variable(&mut self, name: &str) {
+        let is_const = self.is_const_binding(name);
+        if let Some(slot) = self.resolve_local(name) {
+            if !self.locals[slot].is_initialized {
+                self.emit(OpCode::GetLocal(slot));
+                self.emit_check_initialized(name);
                 self.emit(OpCode::Pop);
             }
-            Statement::Let { name, value } | Statement::Const { name, value } => {
-                self.compile_expression(value)?;
-                let idx = self
-                    .chunk
-                    .add_constant(Constant::String(Rc::new(name.clone())));
-                self.emit(OpCode::SetGlobal(idx));
+            self.emit(if is_const {
+                OpCode::AssignConst
+            } else {
+                OpCode::SetLocal(slot)
+            });
+        } else if let Some(idx) = self.resolve_upvalue(name) {
+            if self.may_be_uninitialized(name) {
+                self.emit(OpCode::GetUpvalue(idx));
+                self.emit_check_initialized(name);
                 self.emit(OpCode::Pop);
             }
-            Statement::Block(stmts) => {
-                for stmt in stmts {
-                    self.compile_statement(stmt)?;
+            self.emit(if is_const {
+                OpCode::AssignConst
+            } else {
+                OpCode::SetUpvalue(idx)
+            });
+        } else if is_const {
+            self.emit(OpCode::AssignConst);
+        } else {
+            let idx = self.name_constant(name);
+            let cache = self.chunk.add_global_cache();
+            self.emit(OpCode::SetGlobal(idx, cache));
+        }
+    }
+
+    fn emit_check_initialized(&mut self, name: &str) {
+        let idx = self.name_constant(name);
+        self.emit(OpCode::CheckInitialized(idx));
+    }
+
+    /// Whether `name`, a variable of an enclosing function, is a `let` or
+    /// `const` binding that the function being compiled could be called
+    /// before the declaration of.
+    fn may_be_uninitialized(&self, name: &str) -> bool {
+        let Some(enclosing) = &self.enclosing else {
+            return false;
+        };
+        match enclosing.resolve_local(name) {
+            Some(slot) => !enclosing.locals[slot].is_initialized,
+            None => enclosing.may_be_uninitialized(name),
+        }
+    }
+
+    /// Pushes the key of a member expression, if it is not a constant name.
+    fn emit_member_key(&mut self, key: MemberKey) -> Result<(), Diagnostic> {
+        match key {
+            MemberKey::Named(_) => Ok(()),
+            MemberKey::Computed(index) => self.visit_expression(index),
+        }
+    }
+
+    /// Replaces the object, and key if it was pushed, with the property.
+    fn emit_get_member(&mut self, key: MemberKey) {
+        match key {
+            MemberKey::Named(name) => {
+                let idx = self.name_constant(name);
+                let cache = self.chunk.add_property_cache();
+                self.emit(OpCode::GetNamed(idx, cache));
+            }
+            MemberKey::Computed(_) => self.emit(OpCode::GetIndex),
+        }
+    }
+
+    /// Sets the property to the value on top, leaving only the value.
+    fn emit_set_member(&mut self, key: MemberKey) {
+        match key {
+            MemberKey::Named(name) => {
+                let idx = self.name_constant(name);
+                let cache = self.chunk.add_property_cache();
+                self.emit(OpCode::SetNamed(idx, cache));
+            }
+            MemberKey::Computed(_) => self.emit(OpCode::SetIndex),
+        }
+    }
+
+    /// Duplicates the object and key of a member reference.
+    fn emit_dup_member(&mut self, key: MemberKey) {
+        match key {
+            MemberKey::Named(_) => self.emit(OpCode::Dup),
+            MemberKey::Computed(_) => self.emit(OpCode::Dup2),
+        }
+    }
+
+    /// Whether `name` resolves to a local or a variable in an enclosing function.
+    fn is_declared(&self, name: &str) -> bool {
+        self.resolve_local(name).is_some()
+            || self
+                .enclosing
+                .as_ref()
+                .is_some_and(|enclosing| enclosing.is_declared(name))
+    }
+
+    fn is_const_binding(&self, name: &str) -> bool {
+        if let Some(slot) = self.resolve_local(name) {
+            return self.locals[slot].is_const;
+        }
+        match &self.enclosing {
+            Some(enclosing) => enclosing.is_const_binding(name),
+            None => self.const_globals.contains(name),
+        }
+    }
+
+    fn compile_function(
+        &mut self,
+        name: &str,
+        self_binding: Option<&str>,
+        params: &[Parameter],
+        body: FunctionBody,
+        is_arrow: bool,
+    ) -> Result<FunctionTemplate, Diagnostic> {
+        let mut compiler = BytecodeCompiler::new();
+        compiler.chunk.source_name = self.chunk.source_name.clone();
+        compiler.span = self.span;
+        compiler.enclosing = Some(Box::new(std::mem::take(self)));
+        let result = compiler.compile_function_body(self_binding, params, body);
+        *self = *compiler.enclosing.take().unwrap();
+        result?;
+
+        let has_rest = params.last().is_some_and(|param| param.rest);
+        Ok(FunctionTemplate {
+            name: Rc::new(name.to_string()),
+            arity: params.len() - usize::from(has_rest),
+            has_rest,
+            is_arrow,
+            chunk: Rc::new(compiler.chunk),
+            upvalues: compiler.upvalues,
+        })
+    }
+
+    fn compile_function_body(
+        &mut self,
+        self_binding: Option<&str>,
+        params: &[Parameter],
+        body: FunctionBody,
+    ) -> Result<(), Diagnostic> {
+        // A named function expression can refer to itself through the callee slot
+        if let Some(self_binding) = self_binding {
+            self.locals[0].name = self_binding.to_string();
+        }
+
+        // Parameters and the body's top-level declarations share one scope.
+        // The VM leaves the arguments, then the rest array, in the slots
+        // after the callee.
+        self.begin_scope();
+        for param in params {
+            match &param.target.kind {
+                PatternKind::Identifier(name) => {
+                    self.span = param.span;
+                    self.declare_local(name)?;
+                }
+                _ => self.add_hidden_local(""),
+            }
+        }
+        // Each parameter is defaulted and then taken apart in turn, so later
+        // defaults see the names bound before them
+        for (i, param) in params.iter().enumerate() {
+            self.span = param.span;
+            if let Some(default) = &param.default {
+                self.compile_default(i + 1, default)?;
+            }
+            if !matches!(param.target.kind, PatternKind::Identifier(_)) {
+                self.reserve_locals(&param.target, false)?;
+                self.emit(OpCode::GetLocal(i + 1));
+                self.compile_pattern(&param.target, Binding::Declaration { is_const: false })?;
+            }
+        }
+        match body {
+            FunctionBody::Block(Statement {
+                kind: StatementKind::Block(stmts),
+                ..
+            }) => self.compile_statements(stmts)?,
+            FunctionBody::Block(stmt) => self.visit_statement(stmt)?,
+            FunctionBody::Expression(expr) => {
+                self.visit_expression(expr)?;
+                self.emit(OpCode::Return);
+                return Ok(());
+            }
+        }
+        // Ensure return
+        self.emit(OpCode::Undefined);
+        self.emit(OpCode::Return);
+        Ok(())
+    }
+
+    /// Assigns `default` to the parameter in `slot` if its argument is
+    /// undefined.
+    fn compile_default(&mut self, slot: usize, default: &Expression) -> Result<(), Diagnostic> {
+        self.emit(OpCode::GetLocal(slot));
+        self.emit(OpCode::Undefined);
+        self.emit(OpCode::StrictEqual);
+        let skip = self.emit_jump(OpCode::JumpIfFalse(0));
+        self.visit_expression(default)?;
+        self.emit(OpCode::SetLocal(slot));
+        self.emit(OpCode::Pop);
+        self.patch_jump(skip);
+        Ok(())
+    }
+
+    /// Binds the value on top of the stack to the names in `pattern`, which
+    /// are declared in the current scope unless they were reserved already.
+    fn declare_pattern(&mut self, pattern: &Pattern, is_const: bool) -> Result<(), Diagnostic> {
+        if let PatternKind::Identifier(name) = &pattern.kind {
+            return self.define_variable(name, is_const);
+        }
+        let names = pattern.bound_names();
+        if self.scope_depth > 0 && !names.iter().all(|name| self.reserved_slot(name).is_some()) {
+            // The value keeps a slot of its own below the names it is taken
+            // apart into
+            self.add_hidden_local("");
+            let slot = self.locals.len() - 1;
+            self.reserve_locals(pattern, is_const)?;
+            self.emit(OpCode::GetLocal(slot));
+        }
+        self.compile_pattern(pattern, Binding::Declaration { is_const })
+    }
+
+    /// Gives each name in `pattern` an uninitialized local in the current
+    /// scope.
+    fn reserve_locals(&mut self, pattern: &Pattern, is_const: bool) -> Result<(), Diagnostic> {
+        let outer = self.span;
+        for (name, span) in pattern.bindings() {
+            self.span = span;
+            self.emit(OpCode::Uninitialized);
+            self.declare_local(name)?;
+            let local = self.locals.last_mut().unwrap();
+            local.is_const = is_const;
+            local.is_initialized = false;
+        }
+        self.span = outer;
+        Ok(())
+    }
+
+    /// Takes apart the value on top of the stack into `pattern`, popping it.
+    /// Object patterns read each property in turn, and array patterns step
+    /// through the value with an iterator.
+    fn compile_pattern(&mut self, pattern: &Pattern, binding: Binding) -> Result<(), Diagnostic> {
+        let outer = std::mem::replace(&mut self.span, pattern.span);
+        let result = self.compile_pattern_kind(&pattern.kind, binding);
+        self.span = outer;
+        result
+    }
+
+    fn compile_pattern_kind(
+        &mut self,
+        pattern: &PatternKind,
+        binding: Binding,
+    ) -> Result<(), Diagnostic> {
+        match pattern {
+            PatternKind::Identifier(name) => match binding {
+                Binding::Declaration { is_const } => return self.define_variable(name, is_const),
+                Binding::Assignment => {
+                    self.emit_set_variable(name);
+                    self.emit(OpCode::Pop);
                 }
+            },
+            PatternKind::Member(expr) => {
+                let ExpressionKind::Index { left, index } = &expr.kind else {
+                    return Err(self.error("Invalid destructuring assignment target"));
+                };
+                // Move the value above the object and key
+                let key = MemberKey::of(index);
+                self.visit_expression(left)?;
+                self.emit(OpCode::Bury(1));
+                if let MemberKey::Computed(index) = key {
+                    self.visit_expression(index)?;
+                    self.emit(OpCode::Bury(1));
+                }
+                self.emit_set_member(key);
+                self.emit(OpCode::Pop);
+            }
+            PatternKind::Object {
+                properties,
+                rest: None,
+            } => {
+                for property in properties {
+                    self.emit(OpCode::Dup);
+                    match &property.key.kind {
+                        PropertyKeyKind::String(name) => {
+                            self.emit_get_member(MemberKey::Named(name))
+                        }
+                        _ => {
+                            self.compile_property_key(&property.key)?;
+                            self.emit(OpCode::GetIndex);
+                        }
+                    }
+                    self.compile_pattern_default(property.default.as_ref())?;
+                    self.compile_pattern(&property.value, binding)?;
+                }
+                self.emit(OpCode::Pop);
             }
-            Statement::If {
-                condition,
-                consequence,
-                alternative,
+            PatternKind::Object {
+                properties,
+                rest: Some(rest),
             } => {
-                self.compile_expression(condition)?;
+                // Each key is left below the value for the rest to leave
+                // out, so computed keys are only evaluated once
+                for property in properties {
+                    self.compile_property_key(&property.key)?;
+                    self.emit(OpCode::Dup2);
+                    self.emit(OpCode::GetIndex);
+                    self.compile_pattern_default(property.default.as_ref())?;
+                    self.compile_pattern(&property.value, binding)?;
+                    self.emit(OpCode::Bury(1));
+                }
+                self.emit(OpCode::ObjectRest(properties.len()));
+                self.emit(OpCode::CopyProperties);
+                self.compile_pattern(&rest.target, binding)?;
+            }
+            PatternKind::Array { elements, rest } => {
+                self.emit(OpCode::GetIterator);
+                for element in elements {
+                    self.emit(OpCode::IteratorValue);
+                    match element {
+                        Some(element) => {
+                            self.compile_pattern_default(element.default.as_ref())?;
+                            self.compile_pattern(&element.target, binding)?;
+                        }
+                        None => self.emit(OpCode::Pop),
+                    }
+                }
+                if let Some(rest) = rest {
+                    self.emit(OpCode::IteratorRest);
+                    self.compile_pattern(&rest.target, binding)?;
+                }
+                self.emit(OpCode::Pop);
+            }
+        }
+        Ok(())
+    }
+
+    /// Pushes a property key: a string or number, which the VM converts to
+    /// a string when it uses the key, or the value of a computed key.
+    fn compile_property_key(&mut self, key: &PropertyKey) -> Result<(), Diagnostic> {
+        match &key.kind {
+            PropertyKeyKind::String(name) => {
+                let idx = self.name_constant(name);
+                self.emit(OpCode::Constant(idx));
+                Ok(())
+            }
+            PropertyKeyKind::Number(value) => self.visit_number(*value),
+            PropertyKeyKind::Computed(expr) => self.visit_expression(expr),
+        }
+    }
+
+    /// Replaces the value on top of the stack with `default` if it is
+    /// undefined.
+    fn compile_pattern_default(&mut self, default: Option<&Expression>) -> Result<(), Diagnostic> {
+        let Some(default) = default else {
+            return Ok(());
+        };
+        self.emit(OpCode::Dup);
+        self.emit(OpCode::Undefined);
+        self.emit(OpCode::StrictEqual);
+        let skip = self.emit_jump(OpCode::JumpIfFalse(0));
+        self.emit(OpCode::Pop);
+        self.visit_expression(default)?;
+        self.patch_jump(skip);
+        Ok(())
+    }
+
+    /// Pushes an array of `elements`, expanding any spread among them. The
+    /// elements before the first spread are collected in one go.
+    fn compile_elements(&mut self, elements: &[Expression]) -> Result<(), Diagnostic> {
+        let leading = elements
+            .iter()
+            .position(|elem| matches!(elem.kind, ExpressionKind::Spread(_)))
+            .unwrap_or(elements.len());
+        for elem in &elements[..leading] {
+            self.visit_expression(elem)?;
+        }
+        self.emit(OpCode::Array(leading));
+
+        for elem in &elements[leading..] {
+            match &elem.kind {
+                ExpressionKind::Spread(argument) => {
+                    self.visit_expression(argument)?;
+                    self.emit(OpCode::ArraySpread);
+                }
+                _ => {
+                    self.visit_expression(elem)?;
+                    self.emit(OpCode::ArrayPush);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn begin_scope(&mut self) {
+        self.scope_depth += 1;
+    }
+
+    fn end_scope(&mut self) {
+        self.scope_depth -= 1;
+        while let Some(local) = self.locals.last() {
+            if local.depth <= self.scope_depth {
+                break;
+            }
+            let local = self.locals.pop().unwrap();
+            if local.is_captured {
+                self.emit(OpCode::CloseUpvalue);
+            } else {
+                self.emit(OpCode::Pop);
+            }
+        }
+    }
+
+    fn begin_breakable(
+        &mut self,
+        labels: Vec<String>,
+        is_loop: bool,
+        continue_target: Option<usize>,
+    ) {
+        self.breakables.push(Breakable {
+            labels,
+            is_loop,
+            break_locals: self.locals.len(),
+            continue_locals: self.locals.len(),
+            continue_target,
+            break_jumps: vec![],
+            continue_jumps: vec![],
+            try_depth: self.tries.len(),
+        });
+    }
+
+    fn end_breakable(&mut self) {
+        let breakable = self.breakables.pop().unwrap();
+        for jump in breakable.break_jumps {
+            self.patch_jump(jump);
+        }
+    }
+
+    fn patch_continue_jumps(&mut self) {
+        let jumps = std::mem::take(&mut self.breakables.last_mut().unwrap().continue_jumps);
+        for jump in jumps {
+            self.patch_jump(jump);
+        }
+    }
+
+    fn resolve_breakable(
+        &self,
+        label: Option<&str>,
+        is_continue: bool,
+    ) -> Result<usize, Diagnostic> {
+        let found = self.breakables.iter().rposition(|breakable| match label {
+            Some(label) => breakable.labels.iter().any(|l| l == label),
+            None => breakable.is_loop,
+        });
+
+        match (found, label) {
+            (Some(idx), Some(label)) if is_continue && !self.breakables[idx].is_loop => Err(self
+                .error(format!(
+                    "Label '{}' does not denote an iteration statement",
+                    label
+                ))),
+            (Some(idx), _) => Ok(idx),
+            (None, Some(label)) => Err(self.error(format!("Undefined label '{}'", label))),
+            (None, None) if is_continue => {
+                Err(self.error("Illegal continue statement: no surrounding iteration statement"))
+            }
+            (None, None) => Err(self.error("Illegal break statement")),
+        }
+    }
+
+    /// Emits the pops for a jump out of nested scopes, leaving `count` locals.
+    /// The compiler keeps tracking the locals since code after the jump still
+    /// belongs to their scopes.
+    fn discard_locals(&mut self, count: usize) {
+        for _ in count..self.locals.len() {
+            // A closure later in the scope may still capture the slot
+            self.emit(OpCode::CloseUpvalue);
+        }
+    }
+
+    /// Binds the value on top of the stack to `name` in the current scope.
+    fn define_variable(&mut self, name: &str, is_const: bool) -> Result<(), Diagnostic> {
+        if self.scope_depth == 0 {
+            if is_const {
+                self.const_globals.insert(name.to_string());
+            }
+            let idx = self.name_constant(name);
+            let cache = self.chunk.add_global_cache();
+            self.emit(OpCode::SetGlobal(idx, cache));
+            self.emit(OpCode::Pop);
+            return Ok(());
+        }
+
+        match self.reserved_slot(name) {
+            Some(slot) => {
+                self.emit(OpCode::SetLocal(slot));
+                self.emit(OpCode::Pop);
+                self.locals[slot].is_initialized = true;
+                Ok(())
+            }
+            // The value already sits in the new local's stack slot
+            None => {
+                self.declare_local(name)?;
+                self.locals.last_mut().unwrap().is_const = is_const;
+                Ok(())
+            }
+        }
+    }
+
+    /// The slot of the local named `name` in the current scope, if any.
+    fn reserved_slot(&self, name: &str) -> Option<usize> {
+        let offset = self
+            .locals
+            .iter()
+            .rev()
+            .take_while(|local| local.depth == self.scope_depth)
+            .position(|local| local.name == name)?;
+        Some(self.locals.len() - 1 - offset)
+    }
+
+    fn declare_local(&mut self, name: &str) -> Result<(), Diagnostic> {
+        let redeclared = self
+            .locals
+            .iter()
+            .rev()
+            .take_while(|local| local.depth == self.scope_depth)
+            .any(|local| local.name == name);
+        if redeclared {
+            return Err(self.redeclaration_error(name));
+        }
+
+        self.locals.push(Local {
+            name: name.to_string(),
+            depth: self.scope_depth,
+            is_captured: false,
+            is_const: false,
+            is_initialized: true,
+        });
+        Ok(())
+    }
+
+    fn redeclaration_error(&self, name: &str) -> Diagnostic {
+        self.error(format!("Identifier '{}' has already been declared", name))
+    }
+
+    fn resolve_local(&self, name: &str) -> Option<usize> {
+        self.locals.iter().rposition(|local| local.name == name)
+    }
+
+    fn resolve_upvalue(&mut self, name: &str) -> Option<usize> {
+        let enclosing = self.enclosing.as_mut()?;
+        if let Some(slot) = enclosing.resolve_local(name) {
+            enclosing.locals[slot].is_captured = true;
+            return Some(self.add_upvalue(true, slot));
+        }
+        let idx = enclosing.resolve_upvalue(name)?;
+        Some(self.add_upvalue(false, idx))
+    }
+
+    fn add_upvalue(&mut self, is_local: bool, index: usize) -> usize {
+        let upvalue = UpvalueDescriptor { is_local, index };
+        if let Some(idx) = self.upvalues.iter().position(|u| *u == upvalue) {
+            return idx;
+        }
+        self.upvalues.push(upvalue);
+        self.upvalues.len() - 1
+    }
+
+    fn name_constant(&mut self, name: &str) -> usize {
+        self.chunk
+            .add_constant(Constant::String(Rc::new(name.to_string())))
+    }
+
+    /// An early error at the node being compiled.
+    fn error(&self, message: impl Into<String>) -> Diagnostic {
+        Diagnostic::error(self.span, message)
+    }
+
+    fn emit(&mut self, op: OpCode) {
+        self.chunk
+            .write(op, self.span.start.line, self.span.start.column);
+    }
+
+    fn emit_jump(&mut self, op: OpCode) -> usize {
+        self.emit(op);
+        self.chunk.code.len() - 1
+    }
 
-                let jump_if_false_idx = self.emit_jump(OpCode::JumpIfFalse(0));
+    fn patch_jump(&mut self, idx: usize) {
+        let target = self.chunk.code.len();
+        match &mut self.chunk.code[idx] {
+            OpCode::Jump(t)
+            | OpCode::JumpIfFalse(t)
+            | OpCode::JumpIfTrue(t)
+            | OpCode::JumpIfNotNullish(t)
+            | OpCode::IteratorNext(t) => *t = target,
+            _ => panic!("Cannot patch non-jump instruction"),
+        }
+    }
+}
+
+/// Code generation. Each node is compiled by the visit method for its kind,
+/// with `visit_statement` and `visit_expression` tracking the span of the
+/// node being compiled.
+impl Visit for BytecodeCompiler {
+    type Error = Diagnostic;
 
-                self.compile_statement(consequence)?;
+    fn visit_program(&mut self, program: &Program) -> Result<(), Diagnostic> {
+        self.compile_statements(&program.statements)
+    }
+
+    fn visit_statement(&mut self, stmt: &Statement) -> Result<(), Diagnostic> {
+        let outer = std::mem::replace(&mut self.span, stmt.span);
+        let result = walk_statement(self, stmt);
+        self.span = outer;
+        result
+    }
 
-                let jump_idx = self.emit_jump(OpCode::Jump(0));
+    fn visit_expression(&mut self, expr: &Expression) -> Result<(), Diagnostic> {
+        let outer = std::mem::replace(&mut self.span, expr.span);
+        let result = walk_expression(self, expr);
+        self.span = outer;
+        result
+    }
+
+    fn visit_expression_statement(&mut self, expr: &Expression) -> Result<(), Diagnostic> {
+        self.visit_expression(expr)?;
+        self.emit(OpCode::Pop);
+        Ok(())
+    }
 
-                self.patch_jump(jump_if_false_idx);
+    fn visit_let(
+        &mut self,
+        pattern: &Pattern,
+        value: Option<&Expression>,
+    ) -> Result<(), Diagnostic> {
+        match value {
+            Some(value) => self.visit_expression(value)?,
+            None => self.emit(OpCode::Undefined),
+        }
+        self.declare_pattern(pattern, false)
+    }
+
+    fn visit_const(&mut self, pattern: &Pattern, value: &Expression) -> Result<(), Diagnostic> {
+        self.visit_expression(value)?;
+        self.declare_pattern(pattern, true)
+    }
+
+    fn visit_block(&mut self, body: &[Statement]) -> Result<(), Diagnostic> {
+        self.begin_scope();
+        self.compile_statements(body)?;
+        self.end_scope();
+        Ok(())
+    }
+
+    fn visit_if(
+        &mut self,
+        condition: &Expression,
+        consequence: &Statement,
+        alternative: Option<&Statement>,
+    ) -> Result<(), Diagnostic> {
+        self.visit_expression(condition)?;
+
+        let jump_if_false_idx = self.emit_jump(OpCode::JumpIfFalse(0));
+
+        self.visit_statement(consequence)?;
+
+        let jump_idx = self.emit_jump(OpCode::Jump(0));
+
+        self.patch_jump(jump_if_false_idx);
+
+        if let Some(alt) = alternative {
+            self.visit_statement(alt)?;
+        }
+
+        self.patch_jump(jump_idx);
+        Ok(())
+    }
+
+    fn visit_while(&mut self, condition: &Expression, body: &Statement) -> Result<(), Diagnostic> {
+        let labels = std::mem::take(&mut self.pending_labels);
+        let start = self.chunk.code.len();
+        self.visit_expression(condition)?;
+        let exit_jump = self.emit_jump(OpCode::JumpIfFalse(0));
+
+        self.begin_breakable(labels, true, Some(start));
+        self.visit_statement(body)?;
+        self.emit(OpCode::Loop(start));
+
+        self.patch_jump(exit_jump);
+        self.end_breakable();
+        Ok(())
+    }
+
+    fn visit_do_while(
+        &mut self,
+        body: &Statement,
+        condition: &Expression,
+    ) -> Result<(), Diagnostic> {
+        let labels = std::mem::take(&mut self.pending_labels);
+        let start = self.chunk.code.len();
+
+        self.begin_breakable(labels, true, None);
+        self.visit_statement(body)?;
+        self.patch_continue_jumps();
+        self.visit_expression(condition)?;
+        let exit_jump = self.emit_jump(OpCode::JumpIfFalse(0));
+        self.emit(OpCode::Loop(start));
+
+        self.patch_jump(exit_jump);
+        self.end_breakable();
+        Ok(())
+    }
 
-                if let Some(alt) = alternative {
-                    self.compile_statement(alt)?;
+    fn visit_for(
+        &mut self,
+        init: Option<&Statement>,
+        condition: Option<&Expression>,
+        update: Option<&Expression>,
+        body: &Statement,
+    ) -> Result<(), Diagnostic> {
+        let labels = std::mem::take(&mut self.pending_labels);
+        self.begin_scope();
+
+        // Each iteration gets a fresh copy of the `let` bindings, so
+        // closures created in the body see that iteration's values
+        let mut loop_vars = vec![];
+        if let Some(init) = init {
+            self.visit_statement(init)?;
+            if let StatementKind::Let { pattern, .. } = &init.kind {
+                for name in pattern.bound_names() {
+                    loop_vars.push((name.to_string(), self.resolve_local(name).unwrap()));
                 }
+            }
+        }
+
+        let start = self.chunk.code.len();
+        let exit_jump = match condition {
+            Some(condition) => {
+                self.visit_expression(condition)?;
+                Some(self.emit_jump(OpCode::JumpIfFalse(0)))
+            }
+            None => None,
+        };
+
+        let break_locals = self.locals.len();
+        if !loop_vars.is_empty() {
+            self.begin_scope();
+            for (name, outer) in &loop_vars {
+                self.emit(OpCode::GetLocal(*outer));
+                self.declare_local(name)?;
+            }
+        }
+
+        self.begin_breakable(labels, true, None);
+        self.breakables.last_mut().unwrap().break_locals = break_locals;
+        self.visit_statement(body)?;
+        self.patch_continue_jumps();
+
+        if !loop_vars.is_empty() {
+            for (name, outer) in &loop_vars {
+                let inner = self.resolve_local(name).unwrap();
+                self.emit(OpCode::GetLocal(inner));
+                self.emit(OpCode::SetLocal(*outer));
+                self.emit(OpCode::Pop);
+            }
+            self.end_scope();
+        }
+
+        if let Some(update) = update {
+            self.visit_expression(update)?;
+            self.emit(OpCode::Pop);
+        }
+        self.emit(OpCode::Loop(start));
+
+        if let Some(exit_jump) = exit_jump {
+            self.patch_jump(exit_jump);
+        }
+        self.end_breakable();
+        self.end_scope();
+        Ok(())
+    }
+
+    fn visit_for_of(
+        &mut self,
+        declaration: Option<DeclarationKind>,
+        target: &Pattern,
+        iterable: &Expression,
+        body: &Statement,
+    ) -> Result<(), Diagnostic> {
+        let labels = std::mem::take(&mut self.pending_labels);
+        self.begin_scope();
+        self.visit_expression(iterable)?;
+        self.emit(OpCode::GetIterator);
+        self.add_hidden_local("");
+        let loop_locals = self.locals.len();
+
+        // Each iteration binds the next value in a scope of its own, so
+        // closures created in the body see that iteration's values
+        let start = self.chunk.code.len();
+        let exit_jump = self.emit_jump(OpCode::IteratorNext(0));
+        self.begin_scope();
+        match declaration {
+            Some(declaration) => {
+                self.declare_pattern(target, declaration == DeclarationKind::Const)?
+            }
+            None => self.compile_pattern(target, Binding::Assignment)?,
+        }
+
+        self.begin_breakable(labels, true, Some(start));
+        let breakable = self.breakables.last_mut().unwrap();
+        breakable.break_locals = loop_locals;
+        breakable.continue_locals = loop_locals;
+        self.visit_statement(body)?;
+        self.end_scope();
+        self.emit(OpCode::Loop(start));
+
+        // The iterator is left when it is done, or on a break
+        self.patch_jump(exit_jump);
+        self.end_breakable();
+        self.end_scope();
+        Ok(())
+    }
 
-                self.patch_jump(jump_idx);
+    fn visit_break(&mut self, label: Option<&Identifier>) -> Result<(), Diagnostic> {
+        let label = label.map(|label| label.name.as_str());
+        let idx = self.resolve_breakable(label, false)?;
+        self.emit_finalizers(self.breakables[idx].try_depth)?;
+        self.discard_locals(self.breakables[idx].break_locals);
+        let jump = self.emit_jump(OpCode::Jump(0));
+        self.breakables[idx].break_jumps.push(jump);
+        Ok(())
+    }
+
+    fn visit_continue(&mut self, label: Option<&Identifier>) -> Result<(), Diagnostic> {
+        let label = label.map(|label| label.name.as_str());
+        let idx = self.resolve_breakable(label, true)?;
+        self.emit_finalizers(self.breakables[idx].try_depth)?;
+        self.discard_locals(self.breakables[idx].continue_locals);
+        match self.breakables[idx].continue_target {
+            Some(target) => self.emit(OpCode::Loop(target)),
+            None => {
+                let jump = self.emit_jump(OpCode::Jump(0));
+                self.breakables[idx].continue_jumps.push(jump);
+            }
+        }
+        Ok(())
+    }
+
+    fn visit_labeled(&mut self, label: &Identifier, body: &Statement) -> Result<(), Diagnostic> {
+        self.pending_labels.push(label.name.clone());
+        match body.kind {
+            StatementKind::While { .. }
+            | StatementKind::DoWhile { .. }
+            | StatementKind::For { .. }
+            | StatementKind::ForOf { .. }
+            | StatementKind::Labeled { .. } => self.visit_statement(body)?,
+            _ => {
+                let labels = std::mem::take(&mut self.pending_labels);
+                self.begin_breakable(labels, false, None);
+                self.visit_statement(body)?;
+                self.end_breakable();
             }
-            Statement::Return(expr) => {
-                if let Some(e) = expr {
-                    self.compile_expression(e)?;
-                } else {
-                    self.emit(OpCode::Undefined);
+        }
+        Ok(())
+    }
+
+    fn visit_return(&mut self, value: Option<&Expression>) -> Result<(), Diagnostic> {
+        if let Some(e) = value {
+            self.visit_expression(e)?;
+        } else {
+            self.emit(OpCode::Undefined);
+        }
+        if self.tries.is_empty() {
+            self.emit(OpCode::Return);
+        } else {
+            // Park the result in a hidden local while finally blocks run
+            self.add_hidden_local(".result");
+            self.emit_finalizers(0)?;
+            self.locals.pop();
+            self.emit(OpCode::Return);
+        }
+        Ok(())
+    }
+
+    fn visit_throw(&mut self, value: &Expression) -> Result<(), Diagnostic> {
+        self.visit_expression(value)?;
+        self.emit(OpCode::Throw);
+        Ok(())
+    }
+
+    fn visit_try(
+        &mut self,
+        block: &Statement,
+        param: Option<&Identifier>,
+        handler: Option<&Statement>,
+        finalizer: Option<&Statement>,
+    ) -> Result<(), Diagnostic> {
+        // Handlers resume with only the locals declared outside the statement
+        let stack_depth = self.locals.len();
+
+        self.begin_try(finalizer);
+        self.visit_statement(block)?;
+        let mut unhandled = self.end_try();
+        if let Some(finalizer) = finalizer {
+            self.visit_statement(finalizer)?;
+        }
+        let mut end_jumps = vec![self.emit_jump(OpCode::Jump(0))];
+
+        if let Some(handler) = handler {
+            self.add_handlers(&unhandled, stack_depth);
+            // Exceptions in the catch block still run the finally block
+            self.begin_try(finalizer);
+            self.begin_scope();
+            match param {
+                Some(param) => {
+                    let outer = std::mem::replace(&mut self.span, param.span);
+                    self.declare_local(&param.name)?;
+                    self.span = outer;
                 }
-                self.emit(OpCode::Return);
+                None => self.emit(OpCode::Pop),
             }
-            Statement::Function { name, params, body } => {
-                let mut compiler = BytecodeCompiler::new();
-                compiler.compile_statement(body)?;
-                // Ensure return
-                compiler.emit(OpCode::Undefined);
-                compiler.emit(OpCode::Return);
-
-                let template = FunctionTemplate {
-                    name: Rc::new(name.clone()),
-                    arity: params.len(),
-                    chunk: compiler.chunk,
-                };
+            self.visit_statement(handler)?;
+            self.end_scope();
+            unhandled = self.end_try();
+            if let Some(finalizer) = finalizer {
+                self.visit_statement(finalizer)?;
+            }
+            end_jumps.push(self.emit_jump(OpCode::Jump(0)));
+        }
 
-                let idx = self
-                    .chunk
-                    .add_constant(Constant::Function(Rc::new(template)));
-                self.emit(OpCode::Constant(idx));
+        if let Some(finalizer) = finalizer {
+            // Run the finally block with the exception in a hidden local, then
+            // rethrow it
+            self.add_handlers(&unhandled, stack_depth);
+            self.add_hidden_local(".exception");
+            self.visit_statement(finalizer)?;
+            self.locals.pop();
+            self.emit(OpCode::Throw);
+        }
 
-                let name_idx = self
-                    .chunk
-                    .add_constant(Constant::String(Rc::new(name.clone())));
-                self.emit(OpCode::SetGlobal(name_idx));
+        for jump in end_jumps {
+            self.patch_jump(jump);
+        }
+        Ok(())
+    }
+
+    fn visit_function_declaration(
+        &mut self,
+        name: &Identifier,
+        params: &[Parameter],
+        body: &Statement,
+    ) -> Result<(), Diagnostic> {
+        let name = &name.name;
+        let template =
+            self.compile_function(name, None, params, FunctionBody::Block(body), false)?;
+        let idx = self
+            .chunk
+            .add_constant(Constant::Function(Rc::new(template)));
+        self.emit(OpCode::Closure(idx));
+        self.define_variable(name, false)
+    }
+
+    fn visit_number(&mut self, value: f64) -> Result<(), Diagnostic> {
+        let idx = self.chunk.add_constant(Constant::Number(value));
+        self.emit(OpCode::Constant(idx));
+        Ok(())
+    }
+
+    fn visit_string(&mut self, value: &str) -> Result<(), Diagnostic> {
+        let idx = self
+            .chunk
+            .add_constant(Constant::String(Rc::new(value.to_string())));
+        self.emit(OpCode::Constant(idx));
+        Ok(())
+    }
+
+    fn visit_regexp(&mut self, pattern: &str, flags: &str) -> Result<(), Diagnostic> {
+        let pattern = self
+            .chunk
+            .add_constant(Constant::String(Rc::new(pattern.to_string())));
+        let flags = self
+            .chunk
+            .add_constant(Constant::String(Rc::new(flags.to_string())));
+        self.emit(OpCode::RegExp(pattern, flags));
+        Ok(())
+    }
+
+    fn visit_bigint(&mut self, _digits: &str) -> Result<(), Diagnostic> {
+        Err(self.error("BigInt literals are not supported"))
+    }
+
+    fn visit_boolean(&mut self, value: bool) -> Result<(), Diagnostic> {
+        self.emit(if value { OpCode::True } else { OpCode::False });
+        Ok(())
+    }
+
+    fn visit_null(&mut self) -> Result<(), Diagnostic> {
+        self.emit(OpCode::Null);
+        Ok(())
+    }
+
+    fn visit_this(&mut self) -> Result<(), Diagnostic> {
+        self.emit(OpCode::This);
+        Ok(())
+    }
+
+    fn visit_identifier(&mut self, name: &str) -> Result<(), Diagnostic> {
+        // `undefined` is an ordinary global binding unless something shadows it
+        if name == "undefined" && !self.is_declared(name) {
+            self.emit(OpCode::Undefined);
+        } else {
+            self.emit_get_variable(name);
+        }
+        Ok(())
+    }
+
+    fn visit_prefix(&mut self, operator: &str, right: &Expression) -> Result<(), Diagnostic> {
+        match operator {
+            // An undeclared global is not a ReferenceError under typeof
+            "typeof" => match &right.kind {
+                ExpressionKind::Identifier(name) if !self.is_declared(name) => {
+                    let idx = self.name_constant(name);
+                    self.emit(OpCode::TypeOfGlobal(idx));
+                    return Ok(());
+                }
+                _ => {
+                    self.visit_expression(right)?;
+                    self.emit(OpCode::TypeOf);
+                }
+            },
+            "void" => {
+                self.visit_expression(right)?;
                 self.emit(OpCode::Pop);
+                self.emit(OpCode::Undefined);
+            }
+            "delete" => match &right.kind {
+                ExpressionKind::Index { left, index } => {
+                    self.visit_expression(left)?;
+                    self.visit_expression(index)?;
+                    self.emit(OpCode::Delete);
+                }
+                // Variable bindings cannot be deleted, but deleting a name
+                // nothing declares succeeds
+                ExpressionKind::Identifier(name) if !self.is_declared(name) => {
+                    let idx = self.name_constant(name);
+                    self.emit(OpCode::DeleteGlobal(idx));
+                }
+                ExpressionKind::Identifier(_) => self.emit(OpCode::False),
+                _ => {
+                    self.visit_expression(right)?;
+                    self.emit(OpCode::Pop);
+                    self.emit(OpCode::True);
+                }
+            },
+            _ => {
+                self.visit_expression(right)?;
+                let op = match operator {
+                    "-" => OpCode::Negate,
+                    "+" => OpCode::ToNumber,
+                    "!" => OpCode::Not,
+                    "~" => OpCode::BitNot,
+                    _ => return Err(self.error(format!("Unknown operator: {}", operator))),
+                };
+                self.emit(op);
             }
         }
         Ok(())
     }
 
-    fn compile_expression(&mut self, expr: &Expression) -> Result<(), String> {
-        match expr {
-            Expression::Number(val) => {
-                let idx = self.chunk.add_constant(Constant::Number(*val));
-                self.emit(OpCode::Constant(idx));
+    fn visit_infix(
+        &mut self,
+        left: &Expression,
+        operator: &str,
+        right: &Expression,
+    ) -> Result<(), Diagnostic> {
+        self.visit_expression(left)?;
+        if matches!(operator, "&&" | "||" | "??") {
+            let end_jump = self.emit_short_circuit(operator);
+            self.emit(OpCode::Pop);
+            self.visit_expression(right)?;
+            self.patch_jump(end_jump);
+            return Ok(());
+        }
+        self.visit_expression(right)?;
+        self.emit_binary_operator(operator)
+    }
+
+    fn visit_conditional(
+        &mut self,
+        condition: &Expression,
+        consequence: &Expression,
+        alternative: &Expression,
+    ) -> Result<(), Diagnostic> {
+        self.visit_expression(condition)?;
+        let else_jump = self.emit_jump(OpCode::JumpIfFalse(0));
+        self.visit_expression(consequence)?;
+        let end_jump = self.emit_jump(OpCode::Jump(0));
+        self.patch_jump(else_jump);
+        self.visit_expression(alternative)?;
+        self.patch_jump(end_jump);
+        Ok(())
+    }
+
+    fn visit_call(
+        &mut self,
+        function: &Expression,
+        arguments: &[Expression],
+    ) -> Result<(), Diagnostic> {
+        // A method call passes the object it was read from as `this`
+        let is_method = if let ExpressionKind::Index { left, index } = &function.kind {
+            let key = MemberKey::of(index);
+            self.visit_expression(left)?;
+            self.emit(OpCode::Dup);
+            self.emit_member_key(key)?;
+            self.emit_get_member(key);
+            true
+        } else {
+            self.visit_expression(function)?;
+            false
+        };
+        // Spread arguments are gathered into an array of all the arguments
+        let has_spread = arguments
+            .iter()
+            .any(|arg| matches!(arg.kind, ExpressionKind::Spread(_)));
+        if has_spread {
+            self.compile_elements(arguments)?;
+        } else {
+            for arg in arguments {
+                self.visit_expression(arg)?;
             }
-            Expression::String(val) => {
+        }
+        self.emit(match (is_method, has_spread) {
+            (false, false) => OpCode::Call(arguments.len()),
+            (true, false) => OpCode::CallMethod(arguments.len()),
+            (false, true) => OpCode::CallSpread,
+            (true, true) => OpCode::CallMethodSpread,
+        });
+        Ok(())
+    }
+
+    fn visit_array(&mut self, elements: &[Expression]) -> Result<(), Diagnostic> {
+        self.compile_elements(elements)
+    }
+
+    fn visit_spread(&mut self, _argument: &Expression) -> Result<(), Diagnostic> {
+        Err(self.error("Spread syntax is only allowed in arrays and call arguments"))
+    }
+
+    fn visit_template(
+        &mut self,
+        quasis: &[TemplateElement],
+        expressions: &[Expression],
+    ) -> Result<(), Diagnostic> {
+        if expressions.is_empty() {
+            let cooked = quasis[0].cooked.clone().unwrap_or_default();
+            let idx = self.chunk.add_constant(Constant::String(Rc::new(cooked)));
+            self.emit(OpCode::Constant(idx));
+            return Ok(());
+        }
+        // `a${b}c` joins "a", b and "c" as strings, leaving out empty parts
+        let mut count = 0;
+        for (i, quasi) in quasis.iter().enumerate() {
+            if let Some(cooked) = quasi.cooked.as_ref().filter(|c| !c.is_empty()) {
                 let idx = self
                     .chunk
-                    .add_constant(Constant::String(Rc::new(val.clone())));
+                    .add_constant(Constant::String(Rc::new(cooked.clone())));
                 self.emit(OpCode::Constant(idx));
+                count += 1;
             }
-            Expression::Identifier(name) => {
-                let idx = self
-                    .chunk
-                    .add_constant(Constant::String(Rc::new(name.clone())));
-                self.emit(OpCode::GetGlobal(idx));
+            if let Some(expr) = expressions.get(i) {
+                self.visit_expression(expr)?;
+                count += 1;
             }
-            Expression::Call {
-                function,
-                arguments,
-            } => {
-                self.compile_expression(function)?;
-                for arg in arguments {
-                    self.compile_expression(arg)?;
+        }
+        self.emit(OpCode::Concat(count));
+        Ok(())
+    }
+
+    fn visit_tagged_template(
+        &mut self,
+        tag: &Expression,
+        quasi: &Expression,
+    ) -> Result<(), Diagnostic> {
+        let ExpressionKind::Template {
+            quasis,
+            expressions,
+        } = &quasi.kind
+        else {
+            return Err(self.error("Tagged template without a template literal"));
+        };
+        // The tag is called with the strings object, then each
+        // substitution value. The strings object is made the first time the
+        // template runs, and the same one is passed every time after.
+        self.visit_expression(tag)?;
+        for quasi in quasis {
+            match &quasi.cooked {
+                Some(cooked) => {
+                    let idx = self
+                        .chunk
+                        .add_constant(Constant::String(Rc::new(cooked.clone())));
+                    self.emit(OpCode::Constant(idx));
                 }
-                self.emit(OpCode::Call(arguments.len()));
+                None => self.emit(OpCode::Undefined),
             }
-            Expression::Array(elements) => {
-                for elem in elements {
-                    self.compile_expression(elem)?;
+        }
+        for quasi in quasis {
+            let idx = self
+                .chunk
+                .add_constant(Constant::String(Rc::new(quasi.raw.clone())));
+            self.emit(OpCode::Constant(idx));
+        }
+        let cache = self.chunk.add_template_cache();
+        self.emit(OpCode::TemplateObject(quasis.len(), cache));
+        for expr in expressions {
+            self.visit_expression(expr)?;
+        }
+        self.emit(OpCode::Call(expressions.len() + 1));
+        Ok(())
+    }
+
+    /// Creates an empty object and defines each property on it in turn, so
+    /// keys are computed and values evaluated in source order, and a later
+    /// property replaces an earlier one with the same key.
+    fn visit_object(&mut self, properties: &[Property]) -> Result<(), Diagnostic> {
+        self.emit(OpCode::Object);
+        for property in properties {
+            match &property.kind {
+                PropertyKind::Value { key, value } => {
+                    self.compile_property_key(key)?;
+                    self.visit_expression(value)?;
+                    self.emit(OpCode::DefineProperty);
                 }
-                self.emit(OpCode::Array(elements.len()));
-            }
-            Expression::Object(pairs) => {
-                for (key, value) in pairs {
+                PropertyKind::Method {
+                    kind,
+                    key,
+                    params,
+                    body,
+                } => {
+                    self.compile_property_key(key)?;
+                    let name = match &key.kind {
+                        PropertyKeyKind::String(name) => name.as_str(),
+                        _ => "",
+                    };
+                    let body = FunctionBody::Block(body);
+                    let template = self.compile_function(name, None, params, body, false)?;
                     let idx = self
                         .chunk
-                        .add_constant(Constant::String(Rc::new(key.clone())));
-                    self.emit(OpCode::Constant(idx));
-                    self.compile_expression(value)?;
+                        .add_constant(Constant::Function(Rc::new(template)));
+                    self.emit(OpCode::Closure(idx));
+                    self.emit(match kind {
+                        MethodKind::Method => OpCode::DefineProperty,
+                        MethodKind::Get => OpCode::DefineGetter,
+                        MethodKind::Set => OpCode::DefineSetter,
+                    });
                 }
-                self.emit(OpCode::Object(pairs.len()));
-            }
-            Expression::Index { left, index } => {
-                self.compile_expression(left)?;
-                self.compile_expression(index)?;
-                self.emit(OpCode::GetIndex);
-            }
-            Expression::Infix {
-                left,
-                operator,
-                right,
-            } => {
-                self.compile_expression(left)?;
-                self.compile_expression(right)?;
-                match operator.as_str() {
-                    "+" => self.emit(OpCode::Add),
-                    "-" => self.emit(OpCode::Sub),
-                    "*" => self.emit(OpCode::Mul),
-                    "/" => self.emit(OpCode::Div),
-                    "==" => self.emit(OpCode::Equal),
-                    "!=" => self.emit(OpCode::NotEqual),
-                    "<" => self.emit(OpCode::LessThan),
-                    ">" => self.emit(OpCode::GreaterThan),
-                    _ => return Err(format!("Unknown operator: {}", operator)),
+                PropertyKind::Spread(argument) => {
+                    self.visit_expression(argument)?;
+                    self.emit(OpCode::ObjectSpread);
+                    self.emit(OpCode::CopyProperties);
                 }
             }
-            _ => return Err("Expression not supported yet".to_string()),
         }
         Ok(())
     }
 
-    fn emit(&mut self, op: OpCode) {
-        self.chunk.write(op);
+    fn visit_index(&mut self, left: &Expression, index: &Expression) -> Result<(), Diagnostic> {
+        let key = MemberKey::of(index);
+        self.visit_expression(left)?;
+        self.emit_member_key(key)?;
+        self.emit_get_member(key);
+        Ok(())
     }
 
-    fn emit_jump(&mut self, op: OpCode) -> usize {
-        self.emit(op);
-        self.chunk.code.len() - 1
+    fn visit_assign(
+        &mut self,
+        target: &Pattern,
+        operator: &str,
+        value: &Expression,
+    ) -> Result<(), Diagnostic> {
+        match &target.kind {
+            PatternKind::Identifier(name) => {
+                match operator {
+                    "=" => self.visit_expression(value)?,
+                    "&&=" | "||=" | "??=" => {
+                        self.emit_get_variable(name);
+                        let skip_jump = self.emit_short_circuit(operator);
+                        self.emit(OpCode::Pop);
+                        self.visit_expression(value)?;
+                        self.emit_set_variable(name);
+                        self.patch_jump(skip_jump);
+                        return Ok(());
+                    }
+                    _ => {
+                        self.emit_get_variable(name);
+                        self.visit_expression(value)?;
+                        self.emit_binary_operator(operator.trim_end_matches('='))?;
+                    }
+                }
+                self.emit_set_variable(name);
+            }
+            PatternKind::Member(expr) => {
+                let ExpressionKind::Index { left, index } = &expr.kind else {
+                    return Err(self.error("Invalid left-hand side in assignment"));
+                };
+                let key = MemberKey::of(index);
+                self.visit_expression(left)?;
+                self.emit_member_key(key)?;
+                match operator {
+                    "=" => self.visit_expression(value)?,
+                    "&&=" | "||=" | "??=" => {
+                        self.emit_dup_member(key);
+                        self.emit_get_member(key);
+                        let skip_jump = self.emit_short_circuit(operator);
+                        self.emit(OpCode::Pop);
+                        self.visit_expression(value)?;
+                        self.emit_set_member(key);
+                        let end_jump = self.emit_jump(OpCode::Jump(0));
+
+                        // Keep the current value, drop the object and key below it
+                        self.patch_jump(skip_jump);
+                        self.emit(OpCode::Bury(key.depth()));
+                        for _ in 0..key.depth() {
+                            self.emit(OpCode::Pop);
+                        }
+                        self.patch_jump(end_jump);
+                        return Ok(());
+                    }
+                    _ => {
+                        self.emit_dup_member(key);
+                        self.emit_get_member(key);
+                        self.visit_expression(value)?;
+                        self.emit_binary_operator(operator.trim_end_matches('='))?;
+                    }
+                }
+                self.emit_set_member(key);
+            }
+            // Destructuring leaves the whole value as the result
+            PatternKind::Object { .. } | PatternKind::Array { .. } if operator == "=" => {
+                self.visit_expression(value)?;
+                self.emit(OpCode::Dup);
+                self.compile_pattern(target, Binding::Assignment)?;
+            }
+            _ => return Err(self.error("Invalid left-hand side in assignment")),
+        }
+        Ok(())
     }
 
-    fn patch_jump(&mut self, idx: usize) {
-        let target = self.chunk.code.len();
-        match self.chunk.code[idx] {
-            OpCode::Jump(_) => {
-                self.chunk.code[idx] = OpCode::Jump(target);
+    fn visit_update(
+        &mut self,
+        operator: &str,
+        prefix: bool,
+        target: &Expression,
+    ) -> Result<(), Diagnostic> {
+        let op = if operator == "++" {
+            OpCode::Add
+        } else {
+            OpCode::Sub
+        };
+        let one = self.chunk.add_constant(Constant::Number(1.0));
+
+        match &target.kind {
+            ExpressionKind::Identifier(name) => {
+                self.emit_get_variable(name);
+                self.emit(OpCode::ToNumber);
+                if !prefix {
+                    self.emit(OpCode::Dup);
+                }
+                self.emit(OpCode::Constant(one));
+                self.emit(op);
+                self.emit_set_variable(name);
             }
-            OpCode::JumpIfFalse(_) => {
-                self.chunk.code[idx] = OpCode::JumpIfFalse(target);
+            ExpressionKind::Index { left, index } => {
+                let key = MemberKey::of(index);
+                self.visit_expression(left)?;
+                self.emit_member_key(key)?;
+                self.emit_dup_member(key);
+                self.emit_get_member(key);
+                self.emit(OpCode::ToNumber);
+                if !prefix {
+                    // Stash the old value below the object and key
+                    self.emit(OpCode::Dup);
+                    self.emit(OpCode::Bury(key.depth() + 1));
+                }
+                self.emit(OpCode::Constant(one));
+                self.emit(op);
+                self.emit_set_member(key);
             }
-            _ => panic!("Cannot patch non-jump instruction"),
+            _ => return Err(self.error(format!("Invalid operand for {}", operator))),
         }
+
+        // A postfix update evaluates to the old value
+        if !prefix {
+            self.emit(OpCode::Pop);
+        }
+        Ok(())
+    }
+
+    fn visit_function_expression(
+        &mut self,
+        name: Option<&Identifier>,
+        params: &[Parameter],
+        body: &Statement,
+    ) -> Result<(), Diagnostic> {
+        let name = name.map(|name| name.name.as_str());
+        let body = FunctionBody::Block(body);
+        let template =
+            self.compile_function(name.unwrap_or_default(), name, params, body, false)?;
+        let idx = self
+            .chunk
+            .add_constant(Constant::Function(Rc::new(template)));
+        self.emit(OpCode::Closure(idx));
+        Ok(())
+    }
+
+    fn visit_arrow_function(
+        &mut self,
+        params: &[Parameter],
+        body: &ArrowBody,
+    ) -> Result<(), Diagnostic> {
+        let body = match body {
+            ArrowBody::Expression(expr) => FunctionBody::Expression(expr),
+            ArrowBody::Block(block) => FunctionBody::Block(block),
+        };
+        let template = self.compile_function("", None, params, body, true)?;
+        let idx = self
+            .chunk
+            .add_constant(Constant::Function(Rc::new(template)));
+        self.emit(OpCode::Closure(idx));
+        Ok(())
     }
 }
@@ -1,8 +1,14 @@
+pub mod cache;
 pub mod chunk;
 pub mod compiler;
 pub mod opcode;
 
+pub use cache::PropertyCache;
 pub use chunk::Chunk;
 pub use chunk::Constant;
+pub use chunk::ExceptionHandler;
+pub use chunk::FunctionTemplate;
+pub use chunk::LineEntry;
+pub use chunk::UpvalueDescriptor;
 pub use compiler::BytecodeCompiler;
 pub use opcode::OpCode;
@@ -5,21 +5,94 @@ pub enum OpCode {
     Sub,
     Mul,
     Div,
+    Mod,
+    Exp,
+    ShiftLeft,
+    ShiftRight,
+    UnsignedShiftRight,
+    BitAnd,
+    BitOr,
+    BitXor,
+    BitNot,
+    Negate,
+    Not,
+    ToNumber,
+    TypeOf,
+    TypeOfGlobal(usize), // Index of name in constants; undeclared names are "undefined"
+    Delete,
+    DeleteGlobal(usize), // Index of name in constants; true only for an undeclared name
     Equal,
     NotEqual,
+    StrictEqual,
+    StrictNotEqual,
     LessThan,
     GreaterThan,
+    LessEqual,
+    GreaterEqual,
+    In,
+    InstanceOf,
     Pop,
-    GetGlobal(usize), // Index of name in constants
-    SetGlobal(usize), // Index of name in constants
-    Call(usize),      // Number of arguments
-    Array(usize),     // Number of elements
-    Object(usize),    // Number of pairs
+    Dup,
+    Dup2,        // Duplicates the top two values
+    Bury(usize), // Moves the top value below the next n values
+    // Indices of the name in constants and of the chunk's global cache
+    GetGlobal(usize, usize),
+    SetGlobal(usize, usize),
+    GetLocal(usize),   // Stack slot relative to the frame base
+    SetLocal(usize),   // Stack slot relative to the frame base
+    GetUpvalue(usize), // Index into the closure's upvalues
+    SetUpvalue(usize), // Index into the closure's upvalues
+    // Index of the binding's name in constants; throws if the top value is
+    // a `let` or `const` binding that has not been initialized yet
+    CheckInitialized(usize),
+    AssignConst, // Throws the TypeError for assigning to a `const` binding
+    CloseUpvalue,
+    Closure(usize),    // Index of function template in constants
+    Call(usize),       // Number of arguments
+    CallMethod(usize), // Number of arguments; the receiver is below the callee
+    CallSpread,        // Like Call, with the arguments in an array on top
+    CallMethodSpread,  // Like CallMethod, with the arguments in an array on top
+    This,
+    Array(usize),        // Number of elements
+    ArrayPush,           // Appends the top value to the array below it
+    ArraySpread,         // Appends the elements of the top value to the array below it
+    GetIterator,         // Replaces the top value with an iterator over it
+    IteratorNext(usize), // Pushes the next value of the iterator on top; jumps once it is done
+    IteratorValue,       // Pushes the next value of the iterator on top, or undefined
+    IteratorRest,        // Pushes an array of the values left in the iterator on top
+    // Number of keys below the object; replaces them and the object with an
+    // empty copy, the object and the keys to copy for CopyProperties
+    ObjectRest(usize),
+    Object,         // Pushes a new empty object
+    DefineProperty, // Pops a value and key, and defines them on the object below
+    DefineGetter,   // Like DefineProperty, with the getter function as the value
+    DefineSetter,   // Like DefineProperty, with the setter function as the value
+    ObjectSpread,   // Pushes the keys of the top value to copy to the object below it
+    // Copies the keys on top from the value below them to the object below
+    // that, then pops the keys and the value. Runs again after each getter
+    // it calls.
+    CopyProperties,
+    Concat(usize),        // Number of values to join as strings
+    RegExp(usize, usize), // Indices of pattern and flags in constants
+    // Number of template parts and index of the chunk's template cache; pops
+    // the cooked strings, then the raw strings
+    TemplateObject(usize, usize),
     GetIndex,
     SetIndex,
-    Jump(usize),        // Relative jump
-    JumpIfFalse(usize), // Relative jump if false
+    // Like GetIndex and SetIndex with a constant key: indices of the name in
+    // constants and of the chunk's property cache
+    GetNamed(usize, usize),
+    SetNamed(usize, usize),
+    Jump(usize),             // Absolute forward jump
+    JumpIfFalse(usize),      // Absolute forward jump if false
+    JumpIfTrue(usize),       // Absolute forward jump if true
+    JumpIfNotNullish(usize), // Absolute forward jump unless null or undefined
+    Loop(usize),             // Absolute backward jump
     Return,
+    Throw,
     Undefined,
+    Uninitialized, // The value of a `let` or `const` binding before its declaration
     Null,
+    True,
+    False,
 }
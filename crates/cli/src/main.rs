@@ -1,4 +1,4 @@
-use shadowjs_engine::ShadowEngine;
+use shadowjs_engine::{EngineError, ShadowEngine};
 use std::env;
 use std::fs;
 use std::time::Instant;
@@ -6,18 +6,20 @@ use std::time::Instant;
 fn main() {
     let args: Vec<String> = env::args().collect();
     if args.len() < 2 {
-        eprintln!("Usage: shadowjs <file.js> [--bench] [--debug]");
+        eprintln!("Usage: shadowjs <file.js> [--bench] [--debug] [--cache-stats]");
         return;
     }
 
     let mut filename = String::new();
     let mut bench = false;
     let mut debug = false;
+    let mut cache_stats = false;
 
     for arg in &args[1..] {
         match arg.as_str() {
             "--bench" => bench = true,
             "--debug" => debug = true,
+            "--cache-stats" => cache_stats = true,
             _ => {
                 if filename.is_empty() {
                     filename = arg.clone();
@@ -27,7 +29,7 @@ fn main() {
     }
 
     if filename.is_empty() {
-        eprintln!("Usage: shadowjs <file.js> [--bench] [--debug]");
+        eprintln!("Usage: shadowjs <file.js> [--bench] [--debug] [--cache-stats]");
         return;
     }
 
@@ -36,8 +38,8 @@ fn main() {
     engine.set_debug(debug);
 
     let start = Instant::now();
-    if let Err(e) = engine.eval(&src) {
-        eprintln!("Error: {}", e);
+    if let Err(e) = engine.eval_named(&src, &filename) {
+        report_error(&e, &filename, &src);
     }
     let duration = start.elapsed();
 
@@ -53,4 +55,79 @@ fn main() {
         };
         println!("Execution time: {}", time_str);
     }
+
+    if cache_stats {
+        let stats = engine.cache_stats();
+        eprintln!(
+            "Inline caches: properties {} hits, {} misses; globals {} hits, {} misses",
+            stats.property_hits, stats.property_misses, stats.global_hits, stats.global_misses
+        );
+    }
+}
+
+/// Prints an error, followed by the offending source line with a caret under
+/// the column when the location is known.
+fn report_error(error: &EngineError, filename: &str, src: &str) {
+    match error {
+        EngineError::Runtime(error) => {
+            eprintln!("Uncaught {}", error.kind);
+            if let Some(location) = &error.location {
+                eprintln!("  --> {}", location);
+                print_snippet(src, location.line, location.column, 1);
+            }
+        }
+        EngineError::Syntax(diagnostics) => {
+            for diagnostic in diagnostics {
+                let start = diagnostic.span.start;
+                let end = diagnostic.span.end;
+                eprintln!("SyntaxError: {}", diagnostic.message);
+                eprintln!("  --> {}:{}:{}", filename, start.line, start.column);
+                // Underline the whole span when it fits on one line
+                let width = if end.line == start.line {
+                    end.column.saturating_sub(start.column).max(1)
+                } else {
+                    1
+                };
+                print_snippet(src, start.line, start.column, width);
+                if let Some(suggestion) = &diagnostic.suggestion {
+                    eprintln!("  = help: {}", suggestion);
+                }
+            }
+        }
+    }
+}
+
+/// Splits `src` at each JavaScript line terminator, which besides `\n`
+/// and `\r\n` include a lone `\r`, U+2028 and U+2029.
+fn source_lines(src: &str) -> impl Iterator<Item = &str> {
+    // Like `str::lines`, a final line terminator does not start a new line
+    let src = src.strip_suffix('\n').unwrap_or(src);
+    src.split('\n').flat_map(|line| {
+        line.strip_suffix('\r')
+            .unwrap_or(line)
+            .split(['\r', '\u{2028}', '\u{2029}'])
+    })
+}
+
+fn print_snippet(src: &str, line_number: usize, column: usize, width: usize) {
+    let Some(line) = line_number
+        .checked_sub(1)
+        .and_then(|idx| source_lines(src).nth(idx))
+    else {
+        return;
+    };
+    let gutter = line_number.to_string();
+    let indent: String = line
+        .chars()
+        .take(column.saturating_sub(1))
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    eprintln!("{} |", " ".repeat(gutter.len()));
+    eprintln!("{} | {}", gutter, line);
+    eprintln!(
+        "{} | {}{}",
+        " ".repeat(gutter.len()),
+        indent,
+        "^".repeat(width)
+    );
 }
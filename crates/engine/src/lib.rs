@@ -1,23 +1,94 @@
+use shadowjs_ast::Diagnostic;
 use shadowjs_bytecode::BytecodeCompiler;
+use shadowjs_jsruntime::init_js_runtime;
 use shadowjs_parser::Parser;
-use shadowjs_vm::VM;
+use shadowjs_vm::{CacheStats, HeapStats, RuntimeError, VM};
+use std::fmt;
+
+pub use shadowjs_vm::Value;
+
+/// Why a script failed: it was rejected before running, with every problem
+/// found, or it threw an exception nothing caught.
+#[derive(Debug)]
+pub enum EngineError {
+    Syntax(Vec<Diagnostic>),
+    Runtime(RuntimeError),
+}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EngineError::Syntax(diagnostics) => {
+                for (idx, diagnostic) in diagnostics.iter().enumerate() {
+                    if idx > 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "SyntaxError: {}", diagnostic)?;
+                }
+                Ok(())
+            }
+            EngineError::Runtime(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for EngineError {}
+
+impl From<RuntimeError> for EngineError {
+    fn from(error: RuntimeError) -> Self {
+        EngineError::Runtime(error)
+    }
+}
 
 pub struct ShadowEngine {
     vm: VM,
 }
 
+impl Default for ShadowEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ShadowEngine {
     pub fn new() -> Self {
-        Self { vm: VM::new() }
+        let mut vm = VM::new();
+        let heap = vm.enter_heap();
+        init_js_runtime(&mut vm);
+        drop(heap);
+        Self { vm }
     }
 
     pub fn set_debug(&mut self, debug: bool) {
         self.vm.set_debug(debug);
     }
 
-    pub fn eval(&mut self, src: &str) -> Result<(), String> {
-        let ast = Parser::new(src).parse()?;
-        let bytecode = BytecodeCompiler::compile(&ast)?;
+    /// How much the engine's heap holds and how often it has been collected.
+    pub fn heap_stats(&self) -> HeapStats {
+        self.vm.heap_stats()
+    }
+
+    /// The value of the global `name`, if a script or the runtime has
+    /// defined it. It must not be used once the engine is dropped.
+    pub fn global(&self, name: &str) -> Option<Value> {
+        self.vm.global(name)
+    }
+
+    /// Hit and miss counts of the VM's inline caches, over every script
+    /// evaluated so far.
+    pub fn cache_stats(&self) -> CacheStats {
+        self.vm.cache_stats()
+    }
+
+    pub fn eval(&mut self, src: &str) -> Result<(), EngineError> {
+        self.eval_named(src, "<anonymous>")
+    }
+
+    /// Evaluates a script, naming it `source_name` in error locations.
+    pub fn eval_named(&mut self, src: &str, source_name: &str) -> Result<(), EngineError> {
+        let ast = Parser::new(src).parse().map_err(EngineError::Syntax)?;
+        let bytecode = BytecodeCompiler::compile_named(&ast, source_name)
+            .map_err(|diagnostic| EngineError::Syntax(vec![diagnostic]))?;
         self.vm.execute(bytecode)?;
         Ok(())
     }
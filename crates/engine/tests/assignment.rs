@@ -0,0 +1,100 @@
+mod common;
+
+use common::eval;
+
+#[test]
+fn only_array_indices_address_elements() {
+    assert_eq!(eval("let a = [1, 2]; a[-1] = 9; result = a;"), "[1, 2]");
+    assert_eq!(eval("let a = [1, 2]; a[0.5] = 9; result = a;"), "[1, 2]");
+    assert_eq!(
+        eval("let a = [1, 2]; a[4294967295] = 9; result = a;"),
+        "[1, 2]"
+    );
+    assert_eq!(
+        eval("let a = [1, 2]; result = [a[-1], a[1.7], a[NaN]];"),
+        "[undefined, undefined, undefined]"
+    );
+    assert_eq!(
+        eval("let a = [1, 2]; result = [a[1], a['1'], a[1.0]];"),
+        "[2, 2, 2]"
+    );
+    assert_eq!(
+        eval("let a = [1, 2]; a['1'] = 5; a[2] = 6; result = a;"),
+        "[1, 5, 6]"
+    );
+    assert_eq!(eval("let a = [1, 2]; result = a['01'];"), "undefined");
+}
+
+#[test]
+fn only_array_indices_are_deleted() {
+    assert_eq!(eval("let a = [1, 2]; delete a[-1]; result = a;"), "[1, 2]");
+    assert_eq!(eval("let a = [1, 2]; delete a[0.5]; result = a;"), "[1, 2]");
+    assert_eq!(
+        eval("let a = [1, 2]; delete a['0']; result = a;"),
+        "[undefined, 2]"
+    );
+}
+
+#[test]
+fn in_checks_array_indices() {
+    assert_eq!(
+        eval("let a = [1, 2]; result = [0 in a, '1' in a, 2 in a, -1 in a, 'length' in a];"),
+        "[true, true, false, false, true]"
+    );
+}
+
+#[test]
+fn non_integer_keys_are_object_properties() {
+    assert_eq!(
+        eval("let o = {}; o[-1] = 'a'; o[1.5] = 'b'; result = [o['-1'], o['1.5']];"),
+        r#"["a", "b"]"#
+    );
+}
+
+#[test]
+fn assigning_to_a_constant_throws_when_it_runs() {
+    let caught = "catch (e) { result = e.name + ': ' + e.message; }";
+    let message = r#""TypeError: Assignment to constant variable.""#;
+    assert_eq!(
+        eval(&format!("const c = 1; try {{ c = 2; }} {}", caught)),
+        message
+    );
+    assert_eq!(
+        eval(&format!("const c = 1; try {{ c += 2; }} {}", caught)),
+        message
+    );
+    assert_eq!(
+        eval(&format!("const c = 1; try {{ c++; }} {}", caught)),
+        message
+    );
+    assert_eq!(
+        eval(&format!("const c = 1; try {{ [c] = [2]; }} {}", caught)),
+        message
+    );
+    assert_eq!(
+        eval(&format!(
+            "{{ const c = 1; let f = () => {{ c = 2; }}; try {{ f(); }} {} }}",
+            caught
+        )),
+        message
+    );
+    assert_eq!(
+        eval(&format!(
+            "try {{ for (const i = 0; i < 2; i++) {{}} }} {}",
+            caught
+        )),
+        message
+    );
+    // The value is still computed first
+    assert_eq!(
+        eval("const c = 1; let n = 0; try { c = n = 5; } catch (e) {} result = [c, n];"),
+        "[1, 5]"
+    );
+}
+
+#[test]
+fn assignments_to_constants_that_never_run_are_allowed() {
+    assert_eq!(eval("const c = 1; if (false) { c = 2; } result = c;"), "1");
+    assert_eq!(eval("const c = 1; function f() { c++; } result = c;"), "1");
+    assert_eq!(eval("const c = 1; result = c;"), "1");
+}
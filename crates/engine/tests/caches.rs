@@ -0,0 +1,102 @@
+use shadowjs_engine::ShadowEngine;
+
+/// Reads `p.x` from 1000 objects of one shape.
+const MONOMORPHIC: &str = "let sum = 0;
+     for (let i = 0; i < 1000; i++) { const p = { x: i }; sum += p.x; }
+     result = sum;";
+
+/// Makes 70 objects, each starting with a key no other object has.
+const DISTINCT_FIRST_KEYS: &str = "for (let i = 0; i < 70; i++) { const o = {}; o['k' + i] = i; }";
+
+/// Runs `source` in `engine` and returns the property cache hits and misses
+/// it added.
+fn property_accesses(engine: &mut ShadowEngine, source: &str) -> (u64, u64) {
+    let before = engine.cache_stats();
+    engine.eval(source).unwrap();
+    let after = engine.cache_stats();
+    (
+        after.property_hits - before.property_hits,
+        after.property_misses - before.property_misses,
+    )
+}
+
+#[test]
+fn unused_layouts_make_room_for_new_ones() {
+    let mut first = ShadowEngine::new();
+    first.eval(DISTINCT_FIRST_KEYS).unwrap();
+    drop(first);
+    let mut second = ShadowEngine::new();
+    let (hits, misses) = property_accesses(&mut second, MONOMORPHIC);
+    assert!(
+        hits >= 999 && misses <= 1,
+        "{} hits, {} misses",
+        hits,
+        misses
+    );
+
+    // The same in one engine, once a collection has freed the objects
+    let mut engine = ShadowEngine::new();
+    engine
+        .eval(&format!(
+            "{DISTINCT_FIRST_KEYS}
+             for (let n = 0; n < 10; n++) {{ let a = []; for (let i = 0; i < 20000; i++) a[i] = i; }}"
+        ))
+        .unwrap();
+    assert!(engine.heap_stats().collections > 0);
+    let (hits, misses) = property_accesses(&mut engine, MONOMORPHIC);
+    assert!(
+        hits >= 999 && misses <= 1,
+        "{} hits, {} misses",
+        hits,
+        misses
+    );
+}
+
+/// Reads `o.x` 100 times from each of `shapes` objects with different keys
+/// before it.
+fn read_from_shapes(shapes: usize) -> String {
+    format!(
+        "const kinds = [];
+         for (let k = 0; k < {shapes}; k++) {{ const o = {{}}; o['before' + k] = 0; o.x = k; kinds[k] = o; }}
+         function readX(o) {{ return o.x; }}
+         let sum = 0;
+         for (let round = 0; round < 100; round++) {{
+             for (let k = 0; k < {shapes}; k++) sum += readX(kinds[k]);
+         }}
+         result = sum;"
+    )
+}
+
+#[test]
+fn monomorphic_accesses_hit_after_warm_up() {
+    let mut engine = ShadowEngine::new();
+    assert_eq!(property_accesses(&mut engine, MONOMORPHIC), (999, 1));
+    let before = engine.cache_stats();
+    engine
+        .eval("let total = 0; for (let i = 0; i < 100; i++) total += i; result = total;")
+        .unwrap();
+    let after = engine.cache_stats();
+    // A miss the first time each instruction naming a global runs
+    assert_eq!(after.global_misses - before.global_misses, 5);
+    assert!(after.global_hits - before.global_hits >= 190, "{:?}", after);
+}
+
+#[test]
+fn polymorphic_accesses_hit_for_each_shape() {
+    // A miss the first time `o.x` is read or written in each shape
+    assert_eq!(
+        property_accesses(&mut ShadowEngine::new(), &read_from_shapes(4)),
+        (396, 8)
+    );
+}
+
+#[test]
+fn megamorphic_accesses_miss_past_the_limit() {
+    // The four shapes seen first stay cached and the other four miss every
+    // time they are read, besides the misses writing `x` while building the
+    // objects and reading it the first time
+    assert_eq!(
+        property_accesses(&mut ShadowEngine::new(), &read_from_shapes(8)),
+        (396, 8 + 4 + 400)
+    );
+}
@@ -0,0 +1,52 @@
+mod common;
+
+use common::eval;
+
+#[test]
+fn counter_factories_keep_separate_counts() {
+    assert_eq!(
+        eval(
+            "function makeCounter(start) { \
+               let count = start; \
+               return { increment: () => ++count, read: function() { return count; } }; \
+             } \
+             const a = makeCounter(0); const b = makeCounter(10); \
+             a.increment(); a.increment(); b.increment(); \
+             result = [a.read(), b.read(), a.increment()];"
+        ),
+        "[2, 11, 3]"
+    );
+}
+
+#[test]
+fn closures_share_the_variables_they_capture() {
+    assert_eq!(
+        eval(
+            "function pair() { \
+               let value = 'initial'; \
+               const get = () => value; \
+               const set = v => { value = v; }; \
+               return [get, set]; \
+             } \
+             const [get, set] = pair(); const before = get(); set('changed'); \
+             result = [before, get()];"
+        ),
+        "[\"initial\", \"changed\"]"
+    );
+    // Captured after the function returns, and through several levels
+    assert_eq!(
+        eval(
+            "function outer(a) { return function middle(b) { return c => a + b + c; }; } \
+             result = outer('x')('y')('z');"
+        ),
+        "\"xyz\""
+    );
+    // A captured parameter stays writable
+    assert_eq!(
+        eval(
+            "function acc(total) { return n => total += n; } \
+             const add = acc(1); add(2); result = add(3);"
+        ),
+        "6"
+    );
+}
@@ -0,0 +1,41 @@
+//! Helpers shared by the engine tests. A script reports what it computed by
+//! assigning it to the global `result`.
+
+#![allow(dead_code)]
+
+use shadowjs_engine::{EngineError, ShadowEngine, Value};
+
+/// Runs `source` and shows the value it left in `result`.
+pub fn eval(source: &str) -> String {
+    let mut engine = ShadowEngine::new();
+    if let Err(error) = engine.eval(source) {
+        panic!("{:?} failed: {}", source, error);
+    }
+    match engine.global("result") {
+        Some(value) => show(&value),
+        None => panic!("{:?} did not set result", source),
+    }
+}
+
+/// Runs `source`, which must fail, and returns the error as it is reported.
+pub fn error(source: &str) -> String {
+    match ShadowEngine::new().eval(source) {
+        Ok(()) => panic!("{:?} did not fail", source),
+        Err(EngineError::Syntax(diagnostics)) => diagnostics[0].message.clone(),
+        Err(error) => error.to_string(),
+    }
+}
+
+/// The value written as a JS literal where it has one, so strings can be
+/// told apart from the numbers and arrays in them.
+pub fn show(value: &Value) -> String {
+    match value {
+        Value::String(s) => format!("{:?}", s),
+        Value::Array(elements) => {
+            let elements = elements.borrow();
+            let shown: Vec<_> = elements.iter().map(show).collect();
+            format!("[{}]", shown.join(", "))
+        }
+        _ => value.to_string(),
+    }
+}
@@ -0,0 +1,95 @@
+mod common;
+
+use common::eval;
+use shadowjs_engine::ShadowEngine;
+
+#[test]
+fn arrays_convert_by_joining_their_elements() {
+    assert_eq!(eval("result = '' + [1, 'a', [2, 3]];"), r#""1,a,2,3""#);
+    assert_eq!(eval("result = '' + [null, undefined, 1];"), r#"",,1""#);
+    assert_eq!(eval("let a = [1]; result = '' + [a, a];"), r#""1,1""#);
+}
+
+#[test]
+fn an_array_inside_itself_converts_to_an_empty_string() {
+    assert_eq!(eval("let a = [1]; a[1] = a; result = 'x' + a;"), r#""x1,""#);
+    assert_eq!(
+        eval("let a = [1, [2]]; a[1][1] = a; result = '' + a;"),
+        r#""1,2,""#
+    );
+    assert_eq!(eval("let a = [1]; a[1] = a; result = a == '1,';"), "true");
+}
+
+#[test]
+fn displaying_an_array_inside_itself_terminates() {
+    let mut engine = ShadowEngine::new();
+    engine.eval("let a = [1, [2]]; a[1][1] = a;").unwrap();
+    assert_eq!(engine.global("a").unwrap().to_string(), "[1, [2, ]]");
+}
+
+#[test]
+fn loose_equality_converts_its_operands() {
+    assert_eq!(
+        eval("result = [1 == '1', 0 == '', '0' == false, 1 == true, 2 == true];"),
+        "[true, true, true, true, false]"
+    );
+    assert_eq!(
+        eval("result = [null == undefined, null == 0, undefined == false, null === undefined];"),
+        "[true, false, false, false]"
+    );
+    assert_eq!(
+        eval("result = [1 === '1', '1' != 1, '1' !== 1];"),
+        "[false, false, true]"
+    );
+    assert_eq!(
+        eval("result = ['1,2' == [1, 2], [] == false, [0] == 0];"),
+        "[true, true, true]"
+    );
+}
+
+#[test]
+fn nan_equals_nothing() {
+    assert_eq!(
+        eval("const n = 0 / 0; result = [n == n, n === n, n !== n, n != n, n < n || n >= n];"),
+        "[false, false, true, true, false]"
+    );
+}
+
+#[test]
+fn addition_joins_strings() {
+    assert_eq!(
+        eval("result = ['a' + 1, 1 + 'a', 1 + 2 + 'a', 'a' + 1 + 2];"),
+        "[\"a1\", \"1a\", \"3a\", \"a12\"]"
+    );
+    assert_eq!(
+        eval("result = ['' + null + undefined + true, 1 + null, 1 + undefined, true + 1];"),
+        "[\"nullundefinedtrue\", 1, NaN, 2]"
+    );
+}
+
+#[test]
+fn relational_comparison_of_strings_is_by_code_unit() {
+    assert_eq!(
+        eval("result = ['10' < '9', 10 < 9, '10' < 9, 'a' < 'b', 'B' < 'a'];"),
+        "[true, false, false, true, true]"
+    );
+    assert_eq!(
+        eval("result = ['' < 'a', 'ab' > 'a', null < 1, undefined < 1];"),
+        "[true, true, true, false]"
+    );
+    // By UTF-16 code unit, so a surrogate pair sorts before U+FF61
+    assert_eq!(eval("result = '😀' < '\\uFF61';"), "true");
+}
+
+#[test]
+fn objects_convert_to_primitives() {
+    assert_eq!(eval("result = [] + {};"), "\"[object Object]\"");
+    assert_eq!(
+        eval("result = [[] + [], {} + [], [1] + [2], [1] * [2]];"),
+        "[\"\", \"[object Object]\", \"12\", 2]"
+    );
+    assert_eq!(
+        eval("result = [+[], +{}, +[5], +['7'], +[1, 2]];"),
+        "[0, NaN, 5, 7, NaN]"
+    );
+}
@@ -0,0 +1,299 @@
+mod common;
+
+use common::{error, eval};
+
+#[test]
+fn object_patterns() {
+    let point = "const point = { x: 1, y: 2, z: 3 }; ";
+    assert_eq!(
+        eval(&format!(
+            "{}const {{ x, y }} = point; result = x + y;",
+            point
+        )),
+        "3"
+    );
+    assert_eq!(
+        eval(&format!(
+            "{}const {{ x: px, missing }} = point; result = [px, missing];",
+            point
+        )),
+        "[1, undefined]"
+    );
+    assert_eq!(
+        eval(&format!(
+            "{}const {{ w = 10, z = 20 }} = point; result = [w, z];",
+            point
+        )),
+        "[10, 3]"
+    );
+    assert_eq!(eval("const { length } = 'four'; result = length;"), "4");
+}
+
+#[test]
+fn nested_patterns_and_object_rest() {
+    assert_eq!(
+        eval(
+            "const { a, b: { c } = {}, ...rest } = { a: 1, b: { c: 2 }, d: 3, e: 4 }; \
+             result = [a, c, rest.d, rest.e, rest.a];"
+        ),
+        "[1, 2, 3, 4, undefined]"
+    );
+    assert_eq!(
+        eval("const { b: { c: fallback } = { c: 'default' } } = {}; result = fallback;"),
+        "\"default\""
+    );
+    assert_eq!(
+        eval("const [[n1, n2], { key }] = [[1, 2], { key: 'k' }]; result = [n1, n2, key];"),
+        "[1, 2, \"k\"]"
+    );
+}
+
+#[test]
+fn array_patterns() {
+    assert_eq!(
+        eval(
+            "let [first, , third = 3, ...others] = [1, 2, undefined, 4, 5]; \
+             result = [first, third, others];"
+        ),
+        "[1, 3, [4, 5]]"
+    );
+    assert_eq!(
+        eval("const [p, q, r] = [1]; result = [q, r];"),
+        "[undefined, undefined]"
+    );
+    assert_eq!(
+        eval("const [ch, ...chars] = 'héllo'; result = [ch, chars];"),
+        "[\"h\", [\"é\", \"l\", \"l\", \"o\"]]"
+    );
+}
+
+#[test]
+fn assignment_patterns() {
+    assert_eq!(
+        eval("let s = 1; let t = 2; [s, t] = [t, s]; result = [s, t];"),
+        "[2, 1]"
+    );
+    assert_eq!(
+        eval(
+            "const target = {}; \
+             ({ a: target.first, c: target['second'] = 'two' } = { a: 'one' }); \
+             result = [target.first, target.second];"
+        ),
+        "[\"one\", \"two\"]"
+    );
+    assert_eq!(
+        eval("let head; let tail; let all = ([head, ...tail] = [1, 2, 3]); result = [all, head, tail];"),
+        "[[1, 2, 3], 1, [2, 3]]"
+    );
+    assert_eq!(
+        eval("let u; let v; ({ u, v = 5 } = { u: 4 }); result = [u, v];"),
+        "[4, 5]"
+    );
+}
+
+#[test]
+fn parameter_patterns() {
+    assert_eq!(
+        eval(
+            "function area({ width, height = width }) { return width * height; } \
+             result = area({ width: 3 });"
+        ),
+        "9"
+    );
+    assert_eq!(
+        eval("const sum = ([m, n], ...more) => m + n + more.length; result = sum([1, 2], 0, 0);"),
+        "5"
+    );
+    assert_eq!(
+        eval(
+            "const greet = ({ name } = { name: 'world' }) => 'hello ' + name; \
+             result = [greet(), greet({ name: 'you' })];"
+        ),
+        "[\"hello world\", \"hello you\"]"
+    );
+    assert_eq!(
+        eval(
+            "function later(first, { limit } = { limit: first * 2 }) { return limit; } \
+             result = later(4);"
+        ),
+        "8"
+    );
+}
+
+#[test]
+fn for_of_loops() {
+    assert_eq!(
+        eval("let total = 0; for (const value of [1, 2, 3]) { total += value; } result = total;"),
+        "6"
+    );
+    assert_eq!(
+        eval("let s = ''; for (let letter of 'abc') { s = letter + s; } result = s;"),
+        "\"cba\""
+    );
+    assert_eq!(
+        eval(
+            "const pairs = []; \
+             for (const [k, v] of [['a', 1], ['b', 2]]) { pairs[pairs.length] = k + '=' + v; } \
+             result = pairs;"
+        ),
+        "[\"a=1\", \"b=2\"]"
+    );
+    assert_eq!(
+        eval("let last; for (last of [1, 2, 3]) {} result = last;"),
+        "3"
+    );
+    assert_eq!(
+        eval("const names = {}; for ({ id: names.last } of [{ id: 1 }, { id: 2 }]) {} result = names.last;"),
+        "2"
+    );
+}
+
+#[test]
+fn for_of_binds_each_iteration_separately() {
+    assert_eq!(
+        eval(
+            "const readers = []; \
+             for (const i of [1, 2, 3]) { readers[readers.length] = () => i; } \
+             result = [readers[0](), readers[2]()];"
+        ),
+        "[1, 3]"
+    );
+}
+
+#[test]
+fn for_of_loop_control() {
+    assert_eq!(
+        eval(
+            "let visited = ''; \
+             outer: for (const i of [1, 2, 3]) { \
+               for (const j of [1, 2, 3]) { \
+                 if (j == 2) continue; \
+                 if (i == 3) break outer; \
+                 visited += i + '' + j + ' '; \
+               } \
+             } \
+             result = visited;"
+        ),
+        "\"11 13 21 23 \""
+    );
+    assert_eq!(
+        eval(
+            "let visited = ''; \
+             outer: for (const i of [1, 2, 3]) { \
+               for (const j of [1, 2, 3]) { \
+                 if (j == 2) continue outer; \
+                 visited += i + '' + j + ' '; \
+               } \
+             } \
+             result = visited;"
+        ),
+        "\"11 21 31 \""
+    );
+    assert_eq!(
+        eval(
+            "function find(list, wanted) { \
+               for (const item of list) { if (item == wanted) return 'found'; } \
+               return 'missing'; \
+             } \
+             result = [find([1, 2], 2), find([], 1)];"
+        ),
+        "[\"found\", \"missing\"]"
+    );
+    assert_eq!(
+        eval(
+            "const growing = [1]; \
+             for (const item of growing) { if (item < 3) growing[growing.length] = item + 1; } \
+             result = growing;"
+        ),
+        "[1, 2, 3]"
+    );
+}
+
+#[test]
+fn destructuring_errors_at_run_time() {
+    assert_eq!(
+        eval(
+            "try { const [bad] = 5; } catch (e) { result = [e instanceof TypeError, e.message]; }"
+        ),
+        "[true, \"5 is not iterable\"]"
+    );
+    assert_eq!(
+        eval("try { const { prop } = null; } catch (e) { result = e instanceof TypeError; }"),
+        "true"
+    );
+    assert_eq!(
+        eval("try { for (const item of {}) {} } catch (e) { result = e instanceof TypeError; }"),
+        "true"
+    );
+    // The object rest's getters run in frames of their own, so recursing
+    // through them throws instead of overflowing the native stack
+    assert_eq!(
+        eval(
+            "const o = { get a() { const { ...r } = o; return r; } }; \
+             try { o.a; } catch (e) { result = e instanceof RangeError; }"
+        ),
+        "true"
+    );
+    assert_eq!(
+        eval(
+            "const { a, ...rest } = { a: 1, get b() { return 'got'; }, c: 3 }; \
+             result = [Object.keys(rest), rest.b];"
+        ),
+        "[[\"b\", \"c\"], \"got\"]"
+    );
+}
+
+#[test]
+fn malformed_patterns() {
+    assert_eq!(
+        error("let {a};"),
+        "Missing initializer in destructuring declaration"
+    );
+    assert_eq!(
+        error("let [...a, b] = c;"),
+        "Rest element must be last element"
+    );
+    assert_eq!(
+        error("let [...a = 1] = c;"),
+        "Rest element may not have a default initializer"
+    );
+    assert_eq!(
+        error("({a = 1});"),
+        "Invalid shorthand property initializer"
+    );
+    assert_eq!(
+        error("[a + 1] = c;"),
+        "Invalid destructuring assignment target"
+    );
+    assert_eq!(error("([a.b]) => a;"), "Invalid arrow function parameter");
+    assert_eq!(
+        error("for (a + 1 of c) {}"),
+        "Invalid left-hand side in for-of loop"
+    );
+}
+
+#[test]
+fn parenthesized_literals_are_not_patterns() {
+    assert_eq!(
+        error("let a; ({a}) = {a: 3};"),
+        "Invalid left-hand side in assignment"
+    );
+    assert_eq!(
+        error("let a; ([a]) = [4];"),
+        "Invalid left-hand side in assignment"
+    );
+    assert_eq!(
+        error("let a; [([a])] = [[4]];"),
+        "Invalid destructuring assignment target"
+    );
+    assert_eq!(
+        error("let a; for (([a]) of [[1]]) {}"),
+        "Invalid left-hand side in for-of loop"
+    );
+    // Parentheses around a plain target or the whole assignment are fine
+    assert_eq!(
+        eval("let a = {}; let b; (b) = 1; (a.c) = 2; ({b} = {b: 3}); result = [b, a.c];"),
+        "[3, 2]"
+    );
+    assert_eq!(eval("let a; [(a)] = [5]; result = a;"), "5");
+}
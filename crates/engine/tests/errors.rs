@@ -0,0 +1,106 @@
+mod common;
+
+use common::eval;
+use shadowjs_engine::ShadowEngine;
+
+#[test]
+fn caught_vm_errors_are_instances_of_their_constructor() {
+    assert_eq!(
+        eval(
+            "try { null.x; } catch (e) { \
+             result = [e instanceof TypeError, e instanceof Error, e instanceof RangeError]; }"
+        ),
+        "[true, true, false]"
+    );
+    assert_eq!(
+        eval("try { missing; } catch (e) { result = [e instanceof ReferenceError, e.name]; }"),
+        "[true, \"ReferenceError\"]"
+    );
+}
+
+#[test]
+fn deep_recursion_throws_a_catchable_range_error() {
+    assert_eq!(
+        eval(
+            "let depth = 0; function down() { depth++; down(); } \
+             try { down(); } catch (e) { result = [e instanceof RangeError, e.message, depth > 1000]; }"
+        ),
+        "[true, \"Maximum call stack size exceeded\", true]"
+    );
+    // The stack has unwound once caught, so calls work again
+    assert_eq!(
+        eval(
+            "const f = n => n == 0 ? 0 : 1 + f(n - 1); \
+             let caught = false; try { f(1e6); } catch (e) { caught = e.name; } \
+             result = [caught, f(100)];"
+        ),
+        "[\"RangeError\", 100]"
+    );
+    assert_eq!(
+        eval(
+            "function a() { try { return a(); } finally { } } \
+             try { a(); } catch (e) { result = e instanceof RangeError; }"
+        ),
+        "true"
+    );
+}
+
+#[test]
+fn constructed_errors_are_instances_of_their_constructor() {
+    assert_eq!(
+        eval("let e = TypeError('m'); result = [e instanceof TypeError, e instanceof Error];"),
+        "[true, true]"
+    );
+    assert_eq!(
+        eval(
+            "let e = RangeError('m'); result = [e instanceof RangeError, e instanceof TypeError];"
+        ),
+        "[true, false]"
+    );
+    assert_eq!(
+        eval("let e = Error('m'); result = [e instanceof Error, e instanceof SyntaxError];"),
+        "[true, false]"
+    );
+    assert_eq!(
+        eval("try { throw SyntaxError('m'); } catch (e) { result = e instanceof SyntaxError; }"),
+        "true"
+    );
+}
+
+#[test]
+fn other_values_are_not_error_instances() {
+    assert_eq!(
+        eval("result = [{ name: 'TypeError' } instanceof TypeError, 1 instanceof Error];"),
+        "[false, false]"
+    );
+    assert_eq!(
+        eval("function F() {} result = TypeError('m') instanceof F;"),
+        "false"
+    );
+}
+
+#[test]
+fn declaration_errors_point_at_the_name() {
+    let report = |source: &str| ShadowEngine::new().eval(source).unwrap_err().to_string();
+    assert_eq!(
+        report("let x = 1;\nlet {a, b: [x]} = o;"),
+        "SyntaxError: Identifier 'x' has already been declared at 2:13"
+    );
+    assert_eq!(
+        report("let f = 1;\n  function f() {}"),
+        "SyntaxError: Identifier 'f' has already been declared at 2:12"
+    );
+    assert_eq!(
+        report("function g() { let [y, ...y] = o; }"),
+        "SyntaxError: Identifier 'y' has already been declared at 1:27"
+    );
+}
+
+#[test]
+fn bigint_literals_are_rejected_before_running() {
+    let report = |source: &str| ShadowEngine::new().eval(source).unwrap_err().to_string();
+    assert_eq!(
+        report("result = 1;\nx = 10n;"),
+        "SyntaxError: BigInt literals are not supported at 2:5"
+    );
+}
@@ -0,0 +1,126 @@
+mod common;
+
+use common::{error, eval};
+
+#[test]
+fn finally_runs_on_every_way_out() {
+    assert_eq!(
+        eval(
+            "let log = ''; \
+             function f(how) { \
+               try { if (how == 'throw') throw 'e'; if (how == 'return') return 'r'; log += 'b'; } \
+               catch (e) { log += 'c'; } \
+               finally { log += 'f'; } \
+               return 'end'; \
+             } \
+             result = [f('normal'), f('throw'), f('return'), log];"
+        ),
+        "[\"end\", \"end\", \"r\", \"bfcff\"]"
+    );
+}
+
+#[test]
+fn finally_overrides_the_completion() {
+    assert_eq!(
+        eval("function f() { try { return 'try'; } finally { return 'finally'; } } result = f();"),
+        "\"finally\""
+    );
+    assert_eq!(
+        eval("function f() { try { throw 'lost'; } finally { return 'finally'; } } result = f();"),
+        "\"finally\""
+    );
+    assert_eq!(
+        eval(
+            "function f() { try { return 'try'; } finally { throw 'finally'; } } \
+             try { f(); } catch (e) { result = e; }"
+        ),
+        "\"finally\""
+    );
+    // The value returned is the one computed before finally ran
+    assert_eq!(
+        eval("function f() { let x = 1; try { return x; } finally { x = 2; } } result = f();"),
+        "1"
+    );
+}
+
+#[test]
+fn break_and_continue_run_finally() {
+    assert_eq!(
+        eval(
+            "let log = ''; \
+             for (let i = 0; i < 3; i++) { \
+               try { if (i == 0) continue; if (i == 2) break; log += 'body' + i; } \
+               finally { log += ' finally' + i + ' '; } \
+             } \
+             result = log;"
+        ),
+        "\" finally0 body1 finally1  finally2 \""
+    );
+    assert_eq!(
+        eval(
+            "let log = ''; \
+             outer: for (const i of [1, 2]) { \
+               try { \
+                 for (const j of [1, 2]) { try { if (j == 2) continue outer; } finally { log += 'inner' + i + j + ' '; } } \
+               } finally { log += 'outer' + i + ' '; } \
+             } \
+             result = log;"
+        ),
+        "\"inner11 inner12 outer1 inner21 inner22 outer2 \""
+    );
+    assert_eq!(
+        eval(
+            "let n = 0; \
+             while (true) { try { try { break; } finally { n++; } } finally { n += 10; } } \
+             result = n;"
+        ),
+        "11"
+    );
+    // A break in finally discards the exception being thrown
+    assert_eq!(
+        eval("for (;;) { try { throw 'lost'; } finally { break; } } result = 'kept going';"),
+        "\"kept going\""
+    );
+}
+
+#[test]
+fn exceptions_rethrown_from_nested_handlers() {
+    assert_eq!(
+        eval(
+            "let log = ''; \
+             try { \
+               try { throw 'first'; } \
+               catch (e) { log += 'inner:' + e + ' '; throw e + '!'; } \
+               finally { log += 'inner finally '; } \
+             } catch (e) { log += 'outer:' + e; } \
+             result = log;"
+        ),
+        "\"inner:first inner finally outer:first!\""
+    );
+    assert_eq!(
+        eval(
+            "function thrower() { try { throw TypeError('deep'); } finally { } } \
+             function middle() { try { thrower(); } catch (e) { throw e; } } \
+             try { middle(); } catch (e) { result = [e instanceof TypeError, e.message]; }"
+        ),
+        "[true, \"deep\"]"
+    );
+}
+
+#[test]
+fn a_throw_inside_catch_replaces_the_exception() {
+    assert_eq!(
+        eval("try { try { throw 'a'; } catch (e) { throw 'b'; } } catch (e) { result = e; }"),
+        "\"b\""
+    );
+    assert_eq!(
+        eval(
+            "try { try { throw 'a'; } catch (e) { null.x; } } catch (e) { result = e instanceof TypeError; }"
+        ),
+        "true"
+    );
+    assert_eq!(
+        error("try { throw 'a'; } catch (e) { throw 'from catch'; }"),
+        "Uncaught from catch at <anonymous>:1:32"
+    );
+}
@@ -0,0 +1,136 @@
+mod common;
+
+use common::{error, eval};
+
+#[test]
+fn arrow_bodies() {
+    assert_eq!(
+        eval("const add = (a, b) => a + b; result = add(2, 3);"),
+        "5"
+    );
+    assert_eq!(eval("const square = x => x * x; result = square(7);"), "49");
+    assert_eq!(
+        eval(
+            "const total = (n) => { let sum = 0; for (let i = 0; i < n; i++) sum += i; return sum; }; \
+             result = total(5);"
+        ),
+        "10"
+    );
+    assert_eq!(
+        eval("const empty = () => {}; result = empty();"),
+        "undefined"
+    );
+    assert_eq!(
+        eval("const make = () => ({ a: 1 }); result = make().a;"),
+        "1"
+    );
+    assert_eq!(
+        eval("const curry = a => b => c => a + b + c; result = curry(1)(2)(3);"),
+        "6"
+    );
+    assert_eq!(eval("result = (x => x * 2)(21);"), "42");
+}
+
+#[test]
+fn arrows_take_this_from_where_they_are_written() {
+    assert_eq!(
+        eval(
+            "const counter = { \
+               count: 3, \
+               read: function () { return this.count; }, \
+               readLater: function () { const read = () => this.count; return read(); }, \
+               nested: function () { return (() => (() => this.count)())(); } \
+             }; \
+             result = [counter.read(), counter.readLater(), counter.nested()];"
+        ),
+        "[3, 3, 3]"
+    );
+    assert_eq!(eval("const f = () => this; result = f();"), "undefined");
+}
+
+#[test]
+fn default_parameters() {
+    let greet = "function greet(name = 'world', punctuation = '!') { \
+                   return 'hello ' + name + punctuation; \
+                 } ";
+    assert_eq!(
+        eval(&format!(
+            "{}result = [greet(), greet('you'), greet(undefined, '?'), greet(null)];",
+            greet
+        )),
+        "[\"hello world!\", \"hello you!\", \"hello world?\", \"hello null!\"]"
+    );
+    assert_eq!(
+        eval("const scale = (x, factor = x) => x * factor; result = scale(4);"),
+        "16"
+    );
+}
+
+#[test]
+fn rest_parameters() {
+    assert_eq!(
+        eval(
+            "function rest(first, ...others) { return others; } \
+             result = [rest(1, 2, 3), rest(1).length];"
+        ),
+        "[[2, 3], 0]"
+    );
+    assert_eq!(
+        eval(
+            "const sum = (...values) => { \
+               let total = 0; for (let i = 0; i < values.length; i++) total += values[i]; \
+               return total; \
+             }; \
+             result = sum(1, 2, 3, 4);"
+        ),
+        "10"
+    );
+}
+
+#[test]
+fn spread_arguments_and_elements() {
+    let sum = "const sum = (...values) => { \
+                 let total = 0; for (let i = 0; i < values.length; i++) total += values[i]; \
+                 return total; \
+               }; \
+               const numbers = [1, 2, 3]; ";
+    assert_eq!(eval(&format!("{}result = sum(...numbers);", sum)), "6");
+    assert_eq!(
+        eval(&format!(
+            "{}result = sum(0, ...numbers, 10, ...[100]);",
+            sum
+        )),
+        "116"
+    );
+    assert_eq!(
+        eval(&format!("{}result = [...numbers, 4, ...numbers];", sum)),
+        "[1, 2, 3, 4, 1, 2, 3]"
+    );
+    assert_eq!(eval("result = [...'abc'];"), "[\"a\", \"b\", \"c\"]");
+    assert_eq!(
+        eval("const add = (a, b) => a + b; result = add(...[20, 22]);"),
+        "42"
+    );
+    assert_eq!(
+        eval("try { [...5]; } catch (e) { result = e instanceof TypeError; }"),
+        "true"
+    );
+}
+
+#[test]
+fn malformed_arrows_and_parameters() {
+    assert_eq!(error("let f = x\n=> x;"), "Unexpected token '=>'");
+    assert_eq!(
+        error("a + x => x;"),
+        "Malformed arrow function parameter list"
+    );
+    assert_eq!(
+        error("function g(...a, b) {}"),
+        "Rest parameter must be last formal parameter"
+    );
+    assert_eq!(
+        error("function g(...a = 1) {}"),
+        "Rest parameter may not have a default initializer"
+    );
+    assert_eq!(error("(a + 1) => a;"), "Invalid arrow function parameter");
+}
@@ -0,0 +1,69 @@
+mod common;
+
+use shadowjs_engine::ShadowEngine;
+
+/// Builds a throwaway array of `length` numbers `count` times.
+fn allocating_loop(count: usize, length: usize) -> String {
+    format!(
+        "let total = 0;
+         for (let n = 0; n < {count}; n++) {{
+             let a = [];
+             for (let i = 0; i < {length}; i++) a[i] = i;
+             total = total + a.length;
+         }}
+         result = total;"
+    )
+}
+
+#[test]
+fn collections_keep_an_allocating_loop_bounded() {
+    let mut engine = ShadowEngine::new();
+    engine.eval(&allocating_loop(50, 20_000)).unwrap();
+    let stats = engine.heap_stats();
+    // Each array takes about 512KB, so the loop allocates about 25MB
+    assert!(stats.collections >= 10, "{:?}", stats);
+    assert!(stats.peak_bytes_allocated < 4 << 20, "{:?}", stats);
+    assert_eq!(common::show(&engine.global("result").unwrap()), "1000000");
+}
+
+#[test]
+fn engines_on_one_thread_collect_separately() {
+    let mut first = ShadowEngine::new();
+    first
+        .eval("let kept = { value: 42 }; let list = [kept, 'text'];")
+        .unwrap();
+
+    let mut second = ShadowEngine::new();
+    second.eval(&allocating_loop(10, 20_000)).unwrap();
+    assert!(second.heap_stats().collections > 0);
+    assert_eq!(first.heap_stats().collections, 0);
+
+    first
+        .eval("result = [kept.value, list[0] === kept, list[1]];")
+        .unwrap();
+    assert_eq!(
+        common::show(&first.global("result").unwrap()),
+        r#"[42, true, "text"]"#
+    );
+}
+
+#[test]
+fn collections_mark_long_linked_lists() {
+    let mut engine = ShadowEngine::new();
+    engine
+        .eval(&format!(
+            "let head = null;
+             for (let i = 0; i < 100000; i++) head = {{ value: i, next: head }};
+             {}
+             let length = 0;
+             for (let node = head; node !== null; node = node.next) length++;
+             result = [length, head.value];",
+            allocating_loop(10, 20_000)
+        ))
+        .unwrap();
+    assert!(engine.heap_stats().collections > 0);
+    assert_eq!(
+        common::show(&engine.global("result").unwrap()),
+        "[100000, 99999]"
+    );
+}
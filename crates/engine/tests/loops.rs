@@ -0,0 +1,116 @@
+mod common;
+
+use common::{error, eval};
+
+#[test]
+fn labeled_break_and_continue() {
+    assert_eq!(
+        eval(
+            "let found = null; \
+             search: for (let i = 0; i < 5; i++) { \
+               for (let j = 0; j < 5; j++) { if (i * j == 6) { found = [i, j]; break search; } } \
+             } \
+             result = found;"
+        ),
+        "[2, 3]"
+    );
+    assert_eq!(
+        eval(
+            "let pairs = ''; \
+             rows: for (let i = 0; i < 3; i++) { \
+               let j = 0; \
+               while (true) { if (j > i) continue rows; pairs += i + '' + j + ' '; j++; } \
+             } \
+             result = pairs;"
+        ),
+        "\"00 10 11 20 21 22 \""
+    );
+    assert_eq!(
+        eval(
+            "let n = 0; \
+             outer: do { inner: do { n++; if (n < 3) continue outer; break outer; } while (false); } while (true); \
+             result = n;"
+        ),
+        "3"
+    );
+    // A label on a block can only be broken out of
+    assert_eq!(
+        eval(
+            "let log = 'a'; block: { log += 'b'; if (log) break block; log += 'c'; } result = log;"
+        ),
+        "\"ab\""
+    );
+}
+
+#[test]
+fn misplaced_labels_and_jumps() {
+    assert_eq!(
+        error("block: { continue block; }"),
+        "Label 'block' does not denote an iteration statement"
+    );
+    assert_eq!(
+        error("while (true) { break nowhere; }"),
+        "Undefined label 'nowhere'"
+    );
+    assert_eq!(
+        error("continue;"),
+        "Illegal continue statement: no surrounding iteration statement"
+    );
+    assert_eq!(error("break;"), "Illegal break statement");
+    assert_eq!(
+        error("outer: while (true) { function f() { break outer; } }"),
+        "Undefined label 'outer'"
+    );
+}
+
+#[test]
+fn loops_capture_a_binding_per_iteration() {
+    assert_eq!(
+        eval(
+            "const fs = []; \
+             for (let i = 0; i < 3; i++) fs[i] = () => i; \
+             result = [fs[0](), fs[1](), fs[2]()];"
+        ),
+        "[0, 1, 2]"
+    );
+    assert_eq!(
+        eval(
+            "const fs = []; let i = 0; \
+             while (i < 3) { const j = i; fs[i] = () => j * 10; i++; } \
+             result = [fs[0](), fs[2]()];"
+        ),
+        "[0, 20]"
+    );
+}
+
+#[test]
+fn continue_keeps_each_iterations_binding() {
+    assert_eq!(
+        eval(
+            "const fs = []; \
+             for (let i = 0; i < 4; i++) { if (i % 2) continue; fs[fs.length] = () => i; } \
+             result = [fs[0](), fs[1]()];"
+        ),
+        "[0, 2]"
+    );
+    assert_eq!(
+        eval(
+            "const fs = []; \
+             outer: for (let i = 0; i < 3; i++) { \
+               for (let j = 0; j < 3; j++) { fs[fs.length] = () => i + '' + j; if (j == 1) continue outer; } \
+             } \
+             result = fs.length + ':' + fs[0]() + fs[1]() + fs[5]();"
+        ),
+        "\"6:000121\""
+    );
+    // Each iteration's binding starts as the last one ended, and the update
+    // changes only the new copy
+    assert_eq!(
+        eval(
+            "const fs = []; \
+             for (let i = 0; i < 6; i++) { fs[fs.length] = () => i; i++; } \
+             result = [fs[0](), fs[1](), fs[2]()];"
+        ),
+        "[1, 3, 5]"
+    );
+}
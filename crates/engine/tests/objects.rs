@@ -0,0 +1,350 @@
+mod common;
+
+use common::{error, eval};
+
+const TEMPERATURE: &str = "const temperature = { \
+                             celsius: 20, \
+                             get fahrenheit() { return this.celsius * 9 / 5 + 32; }, \
+                             set fahrenheit(value) { this.celsius = (value - 32) * 5 / 9; } \
+                           }; ";
+
+#[test]
+fn shorthand_and_keys() {
+    assert_eq!(
+        eval("const a = 1; const b = 2; const o = { a, b, }; result = o.a + o.b;"),
+        "3"
+    );
+    assert_eq!(
+        eval(
+            "const name = 'dyn'; const o = { [name + 'amic']: 1, [1 + 1]: 'two' }; \
+             result = [o.dynamic, o['2']];"
+        ),
+        "[1, \"two\"]"
+    );
+    assert_eq!(
+        eval(
+            "const o = { 1: 'one', 1.5: 'half', 1e3: 'thousand', 0x10: 'sixteen' }; \
+             result = [o[1], o['1.5'], o['1000'], o[16]];"
+        ),
+        "[\"one\", \"half\", \"thousand\", \"sixteen\"]"
+    );
+    assert_eq!(
+        eval("const o = { 'two words': 2, '': 'empty' }; result = [o['two words'], o['']];"),
+        "[2, \"empty\"]"
+    );
+    assert_eq!(
+        eval(
+            "const get = 'g'; const set = 's'; const o = { get, set }; \
+             result = [o.get + o.set, { get: 1, set: 2 }.get];"
+        ),
+        "[\"gs\", 1]"
+    );
+}
+
+#[test]
+fn methods() {
+    assert_eq!(
+        eval(
+            "const name = 'dyn'; \
+             const counter = { \
+               count: 0, \
+               increment(by = 1) { this.count += by; return this; }, \
+               'quoted method'() { return 'quoted'; }, \
+               [name]() { return 'computed'; }, \
+             }; \
+             result = [counter.increment().increment(2).count, counter['quoted method'](), counter.dyn()];"
+        ),
+        "[3, \"quoted\", \"computed\"]"
+    );
+}
+
+#[test]
+fn getters_and_setters() {
+    assert_eq!(
+        eval(&format!("{}result = temperature.fahrenheit;", TEMPERATURE)),
+        "68"
+    );
+    assert_eq!(
+        eval(&format!(
+            "{}temperature.fahrenheit = 212; result = temperature.celsius;",
+            TEMPERATURE
+        )),
+        "100"
+    );
+    assert_eq!(
+        eval(&format!(
+            "{}result = (temperature.fahrenheit = 32);",
+            TEMPERATURE
+        )),
+        "32"
+    );
+    assert_eq!(
+        eval(&format!(
+            "{}temperature.fahrenheit = 32; temperature['fahrenheit'] += 18; \
+             result = temperature.celsius;",
+            TEMPERATURE
+        )),
+        "10"
+    );
+}
+
+#[test]
+fn accessors_without_a_pair() {
+    assert_eq!(
+        eval(
+            "const o = { get value() { return 'fixed'; } }; o.value = 'changed'; \
+             result = o.value;"
+        ),
+        "\"fixed\""
+    );
+    assert_eq!(
+        eval(
+            "const o = { set value(v) { this.written = v; } }; o.value = 5; \
+             result = [o.value, o.written];"
+        ),
+        "[undefined, 5]"
+    );
+    assert_eq!(
+        eval(
+            "const o = { get both() { return 'get'; }, other: 1, set both(v) { this.other = v; } }; \
+             o.both = 2; result = o.both + o.other;"
+        ),
+        "\"get2\""
+    );
+    assert_eq!(
+        eval("const o = { get x() { return 'getter'; }, x: 'data' }; result = o.x;"),
+        "\"data\""
+    );
+    assert_eq!(
+        eval(
+            "let log = ''; const o = { get a() { log += 'a'; return 1; } }; \
+             const { a: fromGetter } = o; result = fromGetter + log;"
+        ),
+        "\"1a\""
+    );
+}
+
+#[test]
+fn key_order() {
+    assert_eq!(
+        eval("result = Object.keys({ z: 1, a: 2, m: 3 });"),
+        "[\"z\", \"a\", \"m\"]"
+    );
+    assert_eq!(
+        eval("result = Object.keys({ b: 1, 2: 2, a: 3, 1: 4 });"),
+        "[\"1\", \"2\", \"b\", \"a\"]"
+    );
+    assert_eq!(
+        eval("const o = { a: 1, b: 2, a: 3 }; result = [Object.keys(o), o.a];"),
+        "[[\"a\", \"b\"], 3]"
+    );
+    assert_eq!(
+        eval(
+            "const o = { first: 1, second: 2 }; delete o.first; o.first = 3; \
+             result = Object.keys(o);"
+        ),
+        "[\"second\", \"first\"]"
+    );
+    assert_eq!(
+        eval("const o = { a: 1, b: 2, c: 3 }; delete o.b; o.d = 4; o.b = 5; result = Object.keys(o);"),
+        "[\"a\", \"c\", \"d\", \"b\"]"
+    );
+    assert_eq!(eval("result = Object.keys(['x', 'y']);"), "[\"0\", \"1\"]");
+}
+
+#[test]
+fn objects_with_many_properties() {
+    let many = "const many = {}; for (let i = 0; i < 100; i++) { many['k' + i] = i; } ";
+    assert_eq!(
+        eval(&format!(
+            "{}const keys = Object.keys(many); result = [keys.length, keys[0], keys[99]];",
+            many
+        )),
+        "[100, \"k0\", \"k99\"]"
+    );
+    assert_eq!(
+        eval(&format!("{}result = many.k70 + many['k99'];", many)),
+        "169"
+    );
+    assert_eq!(
+        eval(&format!(
+            "{}delete many.k0; many.k0 = 'back'; result = [Object.keys(many)[99], many.k0];",
+            many
+        )),
+        "[\"k0\", \"back\"]"
+    );
+}
+
+#[test]
+fn one_access_site_sees_many_shapes() {
+    let kinds = "const dictionary = { x: 1, a: 'dictionary' }; delete dictionary.x; \
+                 const kinds = [{ a: 1 }, { b: 0, a: 2 }, { c: 0, a: 3 }, dictionary, \
+                                { get a() { return 'getter'; } }, {}]; ";
+    assert_eq!(
+        eval(&format!(
+            "{}function readA(o) {{ return o.a; }} \
+             let seen = []; \
+             for (let round = 0; round < 3; round++) {{ \
+               seen[round] = []; \
+               for (const o of kinds) {{ seen[round][seen[round].length] = readA(o); }} \
+             }} \
+             result = seen;",
+            kinds
+        )),
+        "[[1, 2, 3, \"dictionary\", \"getter\", undefined], \
+          [1, 2, 3, \"dictionary\", \"getter\", undefined], \
+          [1, 2, 3, \"dictionary\", \"getter\", undefined]]"
+    );
+    assert_eq!(
+        eval(&format!(
+            "{}function writeA(o, value) {{ o.a = value; return o.a; }} \
+             let written = []; \
+             for (const o of kinds) {{ written[written.length] = writeA(o, 'w'); }} \
+             result = [written, Object.keys(kinds[5])];",
+            kinds
+        )),
+        "[[\"w\", \"w\", \"w\", \"w\", \"getter\", \"w\"], [\"a\"]]"
+    );
+}
+
+#[test]
+fn spread() {
+    assert_eq!(
+        eval(
+            "const base = { x: 1, y: 2 }; const o = { ...base, y: 3, z: 4 }; \
+             result = [o.x, o.y, o.z];"
+        ),
+        "[1, 3, 4]"
+    );
+    assert_eq!(
+        eval("const base = { x: 1, y: 2 }; result = Object.keys({ w: 0, ...base, x: 5 });"),
+        "[\"w\", \"x\", \"y\"]"
+    );
+    assert_eq!(
+        eval("const base = { x: 1, y: 2 }; result = { y: 9, ...base }.y;"),
+        "2"
+    );
+    assert_eq!(
+        eval("const base = { x: 1 }; const copy = { ...base }; copy.x = 10; result = base.x;"),
+        "1"
+    );
+    assert_eq!(
+        eval("result = [Object.keys({ ...['a', 'b'] }), { ...'hi' }[1]];"),
+        "[[\"0\", \"1\"], \"i\"]"
+    );
+    assert_eq!(
+        eval("result = Object.keys({ ...null, ...undefined, ...5 });"),
+        "[]"
+    );
+    assert_eq!(
+        eval(&format!(
+            "{}const copy = {{ ...temperature }}; copy.celsius = 0; result = copy.fahrenheit;",
+            TEMPERATURE
+        )),
+        "68"
+    );
+}
+
+#[test]
+fn spread_calls_getters() {
+    // A getter's result keeps its key's place, and a property a getter
+    // deletes is left out
+    assert_eq!(
+        eval(
+            "const source = { a: 1, get b() { delete this.c; return 2; }, c: 3, d: 4 }; \
+             const copy = { ...source }; result = [Object.keys(copy), copy.b];"
+        ),
+        "[[\"a\", \"b\", \"d\"], 2]"
+    );
+    // Getters run in frames of their own, so recursing through spread
+    // throws instead of overflowing the native stack
+    assert_eq!(
+        eval(
+            "const o = { get a() { return { ...o }; } }; \
+             try { const copy = { ...o }; } catch (e) { result = e.name + ': ' + e.message; }"
+        ),
+        "\"RangeError: Maximum call stack size exceeded\""
+    );
+}
+
+#[test]
+fn object_rest() {
+    assert_eq!(
+        eval(
+            "const key = 'y'; const { [key]: picked, ...others } = { x: 1, y: 2, z: 3 }; \
+             result = [picked, Object.keys(others)];"
+        ),
+        "[2, [\"x\", \"z\"]]"
+    );
+    assert_eq!(
+        eval(
+            "const { 0: zeroth, ...rest } = ['a', 'b', 'c']; \
+             result = [zeroth, Object.keys(rest)];"
+        ),
+        "[\"a\", [\"1\", \"2\"]]"
+    );
+    assert_eq!(
+        eval(
+            "let evaluated = 0; const nextKey = () => { evaluated++; return 'x'; }; \
+             const { [nextKey()]: once, ...remaining } = { x: 1, y: 2 }; \
+             result = [evaluated, once, Object.keys(remaining)];"
+        ),
+        "[1, 1, [\"y\"]]"
+    );
+    assert_eq!(
+        eval(
+            "const copy = {}; let assigned; ({ x: assigned, ...copy.rest } = { x: 1, y: 2 }); \
+             result = Object.keys(copy.rest);"
+        ),
+        "[\"y\"]"
+    );
+}
+
+#[test]
+fn errors_at_run_time() {
+    assert_eq!(
+        eval("try { const { ...all } = null; } catch (e) { result = e.message; }"),
+        "\"Cannot destructure 'null' as it is null\""
+    );
+    let throwing = "const throwing = { get bad() { throw 'thrown by getter'; } }; ";
+    assert_eq!(
+        eval(&format!(
+            "{}try {{ const spread = {{ ...throwing }}; }} catch (e) {{ result = e; }}",
+            throwing
+        )),
+        "\"thrown by getter\""
+    );
+    assert_eq!(
+        eval(&format!(
+            "{}try {{ throwing.bad; }} catch (e) {{ result = e; }}",
+            throwing
+        )),
+        "\"thrown by getter\""
+    );
+}
+
+#[test]
+fn malformed_members() {
+    assert_eq!(
+        error("({ get a(x) {} });"),
+        "Getter must not have any formal parameters"
+    );
+    assert_eq!(
+        error("({ set a() {} });"),
+        "Setter must have exactly one formal parameter"
+    );
+    assert_eq!(
+        error("({ set a(...x) {} });"),
+        "Setter function argument must not be a rest parameter"
+    );
+    assert_eq!(error("({ a b });"), "Expected ':', got 'b'");
+    assert_eq!(error("({ 'a' });"), "Expected ':', got '}'");
+    assert_eq!(
+        error("({ ...a, b } = c);"),
+        "Invalid destructuring assignment target"
+    );
+    assert_eq!(
+        error("({ f() {} } = c);"),
+        "Invalid left-hand side in assignment"
+    );
+}
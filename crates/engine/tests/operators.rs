@@ -0,0 +1,101 @@
+mod common;
+
+use common::{error, eval};
+
+#[test]
+fn logical_operators_short_circuit() {
+    assert_eq!(
+        eval(
+            "let log = ''; const t = v => { log += v; return v; }; \
+             const values = [t(0) && t(1), t(2) || t(3), t(null) ?? t(4), t(5) ?? t(6), t('') || t(0) || t(7)]; \
+             result = [values, log];"
+        ),
+        "[[0, 2, 4, 5, 7], \"02null4507\"]"
+    );
+    // The operand not evaluated may throw, or refer to nothing
+    assert_eq!(
+        eval("result = [false && missing.x, true || missing(), 'set' ?? missing];"),
+        "[false, true, \"set\"]"
+    );
+    assert_eq!(
+        eval(
+            "let a = null; let b = 0; let c = 'kept'; let calls = 0; \
+             a ??= ++calls; b ||= ++calls; c &&= c + '!'; c ||= ++calls; \
+             result = [a, b, c, calls];"
+        ),
+        "[1, 2, \"kept!\", 2]"
+    );
+    assert_eq!(
+        eval("let n = 0; const r = n++ ? n++ : (n += 10); result = [r, n];"),
+        "[11, 11]"
+    );
+}
+
+#[test]
+fn typeof_an_undeclared_name() {
+    assert_eq!(
+        eval("result = [typeof undeclaredThing, typeof undefined, typeof null, typeof {}, typeof [], typeof (() => 1), typeof 'a', typeof 1, typeof true, typeof typeof 1];"),
+        "[\"undefined\", \"undefined\", \"object\", \"object\", \"object\", \"function\", \"string\", \"number\", \"boolean\", \"string\"]"
+    );
+    assert_eq!(
+        eval("try { typeof undeclaredThing.x; } catch (e) { result = e.name; }"),
+        "\"ReferenceError\""
+    );
+}
+
+#[test]
+fn void_evaluates_its_operand() {
+    assert_eq!(
+        eval("let n = 0; result = [void n++, n, void 0 === undefined, typeof void 'x'];"),
+        "[undefined, 1, true, \"undefined\"]"
+    );
+}
+
+#[test]
+fn deleting_properties() {
+    assert_eq!(
+        eval(
+            "const o = { a: 1, b: 2 }; \
+             result = [delete o.a, delete o['b'], delete o.missing, Object.keys(o), 'a' in o];"
+        ),
+        "[true, true, true, [], false]"
+    );
+    assert_eq!(
+        eval("const a = [1, 2, 3]; result = [delete a[1], a[1], a.length];"),
+        "[true, undefined, 3]"
+    );
+    // Anything other than a reference is deleted trivially, after being
+    // evaluated
+    assert_eq!(
+        eval("let n = 0; result = [delete 1, delete (n++), n];"),
+        "[true, true, 1]"
+    );
+}
+
+#[test]
+fn deleting_a_variable() {
+    // Only a name nothing declares can be deleted
+    assert_eq!(
+        eval(
+            "let declared = 1; function f() {} \
+             result = [delete undeclaredThing, delete declared, delete f, declared];"
+        ),
+        "[true, false, false, 1]"
+    );
+    assert_eq!(
+        eval("function g(param) { let local; return [delete param, delete local, delete nothing]; } result = g();"),
+        "[false, false, true]"
+    );
+    assert_eq!(
+        error("'use strict'; delete undeclaredThing;"),
+        "Delete of an unqualified identifier in strict mode"
+    );
+    assert_eq!(
+        error("function h(x) { 'use strict'; return delete (x); }"),
+        "Delete of an unqualified identifier in strict mode"
+    );
+    assert_eq!(
+        eval("'use strict'; const o = { p: 1 }; result = [delete o.p, o.p];"),
+        "[true, undefined]"
+    );
+}
@@ -0,0 +1,226 @@
+mod common;
+
+use common::{error, eval};
+
+/// A script that sets `s` to `ab` repeated to 50,000 characters.
+const LONG: &str = "let s = ''; for (let i = 0; i < 25000; i++) s += 'ab'; ";
+
+#[test]
+fn regexp_after_a_statement_head() {
+    assert_eq!(
+        eval("result = 'division'; if (true) /a/.test('a') && (result = 'regexp');"),
+        "\"regexp\""
+    );
+    assert_eq!(
+        eval("let n = 0; result = []; while (n++ < 2) /b/g.test('b') && (result[result.length] = n);"),
+        "[1, 2]"
+    );
+    assert_eq!(eval("let a = 8; let b = 2; result = (a) / b / 2;"), "2");
+}
+
+#[test]
+fn division_after_an_expression_ending_in_a_brace() {
+    assert_eq!(
+        eval("const x = function() {} / 2; let foo = 1; let g = 1; let q = {} /foo/g; result = [x, q];"),
+        "[NaN, NaN]"
+    );
+    assert_eq!(
+        eval("result = 'division'; { } /a/.test('a') && (result = 'regexp');"),
+        "\"regexp\""
+    );
+}
+
+#[test]
+fn repeated_alternation_on_a_long_input() {
+    assert_eq!(
+        eval(&format!(
+            "{}let m = /(a|b)*/.exec(s); result = [m[0].length, m[1]];",
+            LONG
+        )),
+        "[50000, \"b\"]"
+    );
+    assert_eq!(
+        eval(&format!(
+            "{}let m = /^(?:a|b)*?c/.exec(s + 'c'); result = m[0].length;",
+            LONG
+        )),
+        "50001"
+    );
+    assert_eq!(
+        eval(&format!("{}result = /^[ab]*$/.test(s);", LONG)),
+        "true"
+    );
+}
+
+/// The match of `regexp` in `input` and its groups.
+fn exec(regexp: &str, input: &str) -> String {
+    eval(&format!(
+        "let m = {}.exec('{}'); result = []; \
+         for (let i = 0; i < m.length; i++) result[i] = m[i];",
+        regexp, input
+    ))
+}
+
+#[test]
+fn repeated_alternation_backtracks_over_its_count() {
+    assert_eq!(exec("/(a|b)*b/", "abab"), "[\"abab\", \"a\"]");
+    assert_eq!(exec("/(a|b){2,3}a/", "abbba"), "[\"bbba\", \"b\"]");
+    assert_eq!(exec("/(a|b)*?b/", "aab"), "[\"aab\", \"a\"]");
+    assert_eq!(
+        exec("/((a)|(b))*/", "ab"),
+        "[\"ab\", \"b\", undefined, \"b\"]"
+    );
+    // A backreference can need another alternative, so this one backtracks
+    // the usual way
+    assert_eq!(exec("/(a|b)*\\1/", "abb"), "[\"abb\", \"b\"]");
+}
+
+#[test]
+fn flags() {
+    assert_eq!(
+        eval(
+            "const r = /a/gimsuyd; \
+             result = [r.flags, r.global, r.ignoreCase, r.multiline, r.dotAll, r.unicode, r.sticky, r.hasIndices];"
+        ),
+        "[\"dgimsuy\", true, true, true, true, true, true, true]"
+    );
+    assert_eq!(
+        eval("result = [/A/i.test('a'), /A/.test('a')];"),
+        "[true, false]"
+    );
+    assert_eq!(
+        eval("result = [/^b/m.test('a\\nb'), /^b/.test('a\\nb')];"),
+        "[true, false]"
+    );
+    assert_eq!(
+        eval("result = [/a.b/s.test('a\\nb'), /a.b/.test('a\\nb')];"),
+        "[true, false]"
+    );
+    assert_eq!(
+        eval("const r = /a/g; result = [r.test('aa'), r.lastIndex, r.test('aa'), r.lastIndex, r.test('aa'), r.lastIndex];"),
+        "[true, 1, true, 2, false, 0]"
+    );
+    assert_eq!(
+        eval(
+            "const r = /a/y; r.lastIndex = 1; result = [r.test('ba'), r.test('ba'), r.lastIndex];"
+        ),
+        "[true, false, 0]"
+    );
+    assert_eq!(
+        eval("result = /^.$/u.test('😀') + ' ' + /^.$/.test('😀');"),
+        "\"true false\""
+    );
+    assert_eq!(
+        eval(
+            "const m = /b(c)(?<last>d)?/d.exec('abc'); \
+             result = [m.indices[0][0], m.indices[0][1], m.indices[1][0], m.indices[1][1], \
+                       m.indices[2], m.indices.groups.last];"
+        ),
+        "[1, 3, 2, 3, undefined, undefined]"
+    );
+}
+
+#[test]
+fn named_groups_and_backreferences() {
+    assert_eq!(
+        eval(
+            "const m = /(?<year>\\d{4})-(?<month>\\d{2})/.exec('on 2024-05'); \
+             result = [m.groups.year, m.groups.month, m[1], m.index];"
+        ),
+        "[\"2024\", \"05\", \"2024\", 3]"
+    );
+    assert_eq!(
+        eval("result = [/(?<q>['\"]).*?\\k<q>/.exec(`say \"hi\" 'x'`)[0], /(a)\\1/.test('aa')];"),
+        "[\"\\\"hi\\\"\", true]"
+    );
+    assert_eq!(
+        eval("result = /(?<a>x)|y/.exec('y').groups.a;"),
+        "undefined"
+    );
+}
+
+#[test]
+fn lookaround() {
+    assert_eq!(
+        eval("result = [/(?<=\\$)\\d+/.exec('cost: $42')[0], /(?<!\\$)\\b\\d+/.exec('$1 and 2')[0]];"),
+        "[\"42\", \"2\"]"
+    );
+    assert_eq!(
+        eval("result = [/a(?=b)/.exec('acab').index, /a(?!b)/.exec('abac').index];"),
+        "[2, 2]"
+    );
+    assert_eq!(eval("result = /(?<=(\\d)(\\d))x/.exec('12x').length;"), "3");
+}
+
+#[test]
+fn unicode_property_escapes() {
+    assert_eq!(
+        eval("result = [/^\\p{L}+$/u.test('héllo'), /^\\p{L}+$/u.test('h3llo'), /\\P{L}/u.exec('ab1')[0]];"),
+        "[true, false, \"1\"]"
+    );
+    assert_eq!(
+        eval("result = [/\\p{Script=Greek}/u.test('α'), /^\\p{Lu}/u.test('Éa'), /\\p{Nd}+/u.exec('x٣4')[0]];"),
+        "[true, true, \"٣4\"]"
+    );
+}
+
+#[test]
+fn split_and_replace() {
+    assert_eq!(
+        eval("result = 'a1b22c'.split(/\\d+/);"),
+        "[\"a\", \"b\", \"c\"]"
+    );
+    assert_eq!(
+        eval("result = 'a1b2c'.split(/(\\d)/);"),
+        "[\"a\", \"1\", \"b\", \"2\", \"c\"]"
+    );
+    assert_eq!(eval("result = 'a,b,c'.split(/,/, 2);"), "[\"a\", \"b\"]");
+    assert_eq!(
+        eval("result = '2024-05'.replace(/(?<y>\\d+)-(?<m>\\d+)/, '$<m>/$<y>');"),
+        "\"05/2024\""
+    );
+    assert_eq!(
+        eval("result = 'john smith'.replace(/(\\w+) (\\w+)/, '$2, $1 ($&) $$');"),
+        "\"smith, john (john smith) $\""
+    );
+    assert_eq!(
+        eval("result = 'aaa'.replace(/a/g, 'b') + 'aaa'.replace(/a/, 'b');"),
+        "\"bbbbaa\""
+    );
+    assert_eq!(
+        eval("result = 'x'.replace(/x/, '$3$<none>');"),
+        "\"$3$<none>\""
+    );
+}
+
+#[test]
+fn invalid_patterns() {
+    for pattern in [
+        "(",
+        "a**",
+        "[b-a]",
+        "(?<n>a)(?<n>b)",
+        "\\\\k<missing>",
+        "a{2,1}",
+        "(?<=a",
+    ] {
+        assert_eq!(
+            eval(&format!(
+                "try {{ RegExp('{}', 'u'); result = 'compiled'; }} catch (e) {{ result = e.name; }}",
+                pattern
+            )),
+            "\"SyntaxError\"",
+            "{}",
+            pattern
+        );
+    }
+    assert_eq!(
+        eval("try { RegExp('a', 'gg'); } catch (e) { result = e.name; }"),
+        "\"SyntaxError\""
+    );
+    assert_eq!(
+        eval("try { RegExp('a', 'x'); } catch (e) { result = e.name; }"),
+        "\"SyntaxError\""
+    );
+    assert_eq!(error("const r = /a{2,1}/;"), "Uncaught SyntaxError: Invalid regular expression: /a{2,1}/: numbers out of order in {} quantifier at <anonymous>:1:11");
+}
@@ -0,0 +1,75 @@
+mod common;
+
+use common::{error, eval};
+
+#[test]
+fn redeclaring_script_bindings_is_an_early_error() {
+    let message = "Identifier 'a' has already been declared";
+    assert_eq!(error("let a = 1; let a = 2;"), message);
+    assert_eq!(error("let a; const a = 1;"), message);
+    assert_eq!(error("let a; function a() {}"), message);
+    assert_eq!(error("function a() {} let a;"), message);
+    assert_eq!(error("const [a, { b: a }] = [];"), message);
+    // Nothing runs when the script is rejected
+    assert_eq!(error("print(1); let a; let a;"), message);
+    assert_eq!(
+        eval("function f() { return 1; } function f() { return 2; } result = f();"),
+        "2"
+    );
+}
+
+#[test]
+fn redeclaring_block_bindings_is_an_early_error() {
+    let message = "Identifier 'a' has already been declared";
+    assert_eq!(error("{ let a; let a; }"), message);
+    assert_eq!(error("function f() { let a; function a() {} }"), message);
+    assert_eq!(error("function f(a) { let a; }"), message);
+    assert_eq!(eval("let a = 1; { let a = 2; } result = a;"), "1");
+}
+
+#[test]
+fn reading_a_binding_before_its_declaration_throws() {
+    let caught = "try { read(); } catch (e) { result = e.name + ': ' + e.message; }";
+    assert_eq!(
+        eval(&format!(
+            "{{ function read() {{ return x; }} {} let x = 1; }}",
+            caught
+        )),
+        r#""ReferenceError: Cannot access 'x' before initialization""#
+    );
+    assert_eq!(
+        eval(&format!(
+            "{{ let read = () => y; {} const y = 1; }}",
+            caught
+        )),
+        r#""ReferenceError: Cannot access 'y' before initialization""#
+    );
+    assert_eq!(
+        eval("{ try { z = 1; } catch (e) { result = e.message; } let z; }"),
+        r#""Cannot access 'z' before initialization""#
+    );
+    assert_eq!(
+        eval("{ try { typeof w; } catch (e) { result = e.name; } let w; }"),
+        r#""ReferenceError""#
+    );
+    assert_eq!(
+        eval("try { v; } catch (e) { result = e.name; } let v;"),
+        r#""ReferenceError""#
+    );
+}
+
+#[test]
+fn reading_a_binding_after_its_declaration_works() {
+    assert_eq!(
+        eval("{ function read() { return x; } let x = 1; result = read(); }"),
+        "1"
+    );
+    assert_eq!(eval("{ let x; result = x; }"), "undefined");
+    assert_eq!(
+        eval(
+            "let fs = []; for (let i = 0; i < 3; i++) { let j = i; fs[i] = () => j; } \
+             result = [fs[0](), fs[2]()];"
+        ),
+        "[0, 2]"
+    );
+}
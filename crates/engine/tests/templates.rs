@@ -0,0 +1,76 @@
+mod common;
+
+use common::eval;
+
+const TAG: &str = "function tag(strings) { return strings; } ";
+
+#[test]
+fn substitutions() {
+    assert_eq!(
+        eval("const n = 2; result = `${n} + ${n} = ${n + n}`;"),
+        "\"2 + 2 = 4\""
+    );
+    assert_eq!(eval("result = `a${`b${'c'}`}`;"), "\"abc\"");
+}
+
+#[test]
+fn strings_are_cooked_and_raw() {
+    assert_eq!(
+        eval(&format!(
+            "{TAG}const s = tag`a\\n${{1}}b`; \
+             result = [s.length, s[0], s.raw[0], s[1], s.raw.length];"
+        )),
+        "[2, \"a\\n\", \"a\\\\n\", \"b\", 2]"
+    );
+    // An escape a string literal would reject leaves only the cooked string
+    // undefined
+    assert_eq!(
+        eval(&format!(
+            "{TAG}const s = tag`\\unicode`; result = [s[0], s.raw[0]];"
+        )),
+        "[undefined, \"\\\\unicode\"]"
+    );
+}
+
+#[test]
+fn each_call_site_has_one_strings_object() {
+    assert_eq!(
+        eval(&format!(
+            "{TAG}function f() {{ return tag`a${{1}}b`; }} result = f() === f();"
+        )),
+        "true"
+    );
+    assert_eq!(
+        eval(&format!(
+            "{TAG}const seen = []; \
+             for (let i = 0; i < 3; i++) {{ seen[i] = tag`x`; }} \
+             result = [seen[0] === seen[1], seen[1] === seen[2]];"
+        )),
+        "[true, true]"
+    );
+    assert_eq!(eval(&format!("{TAG}result = tag`a` === tag`a`;")), "false");
+}
+
+#[test]
+fn strings_are_frozen() {
+    assert_eq!(
+        eval(&format!(
+            "{TAG}const s = tag`a${{1}}b`; \
+             s[0] = 'changed'; s.extra = 1; s.raw[1] = 'changed'; \
+             result = [s[0], s.extra, s.raw[1], delete s[0], delete s.missing, s[0]];"
+        )),
+        "[\"a\", undefined, \"b\", false, true, \"a\"]"
+    );
+}
+
+#[test]
+fn strings_are_iterable() {
+    assert_eq!(
+        eval(&format!(
+            "{TAG}const s = tag`a${{1}}b${{2}}c`; \
+             const joined = []; for (const part of s) {{ joined[joined.length] = part; }} \
+             result = [[...s], joined, [...s.raw]];"
+        )),
+        "[[\"a\", \"b\", \"c\"], [\"a\", \"b\", \"c\"], [\"a\", \"b\", \"c\"]]"
+    );
+}
@@ -0,0 +1,77 @@
+mod common;
+
+use common::eval;
+
+#[test]
+fn identifiers_in_non_latin_scripts() {
+    assert_eq!(
+        eval("let π = 3.14159; let число = 42; let 名前 = '山田'; result = [π, число, 名前];"),
+        "[3.14159, 42, \"山田\"]"
+    );
+    assert_eq!(
+        eval("let συνάρτηση = function (χ) { return χ * 2; }; result = συνάρτηση(21);"),
+        "42"
+    );
+    assert_eq!(
+        eval(
+            "let مرحبا = 'salaam'; let שלום = 'shalom'; let नमस्ते = 'namaste'; \
+             result = [مرحبا, שלום, नमस्ते];"
+        ),
+        "[\"salaam\", \"shalom\", \"namaste\"]"
+    );
+}
+
+#[test]
+fn dollar_and_underscore_names() {
+    assert_eq!(
+        eval(
+            "let $ = function (s) { return '$(' + s + ')'; }; let _ = 1; let $_$ = 2; \
+             result = [$('#app'), $_$ + _];"
+        ),
+        "[\"$(#app)\", 3]"
+    );
+}
+
+#[test]
+fn joiners_make_distinct_names() {
+    assert_eq!(
+        eval(
+            "let a\u{200C}b = 'zwnj'; let a\u{200D}b = 'zwj'; let ab = 'plain'; \
+             result = [a\u{200C}b, a\u{200D}b, ab];"
+        ),
+        "[\"zwnj\", \"zwj\", \"plain\"]"
+    );
+}
+
+#[test]
+fn escapes_name_the_same_binding() {
+    assert_eq!(
+        eval(
+            r"let \u0061bc = 1; let x\u{79}z = 2; let \u03C0\u{3c1} = 3; let caf\u00e9 = 4;
+             result = [abc, xyz, πρ, café];"
+        ),
+        "[1, 2, 3, 4]"
+    );
+}
+
+#[test]
+fn unicode_whitespace_and_line_terminators() {
+    assert_eq!(
+        eval("result = 1\u{A0}+ 2\u{3000}+ 3\u{FEFF}+ 4\u{2003}+ 5\u{202F}+ 6;"),
+        "21"
+    );
+    // The separators end lines, so a semicolon is inserted after them
+    assert_eq!(
+        eval("let ls = 1\u{2028}let ps = 2\u{2029}result = [ls, ps];"),
+        "[1, 2]"
+    );
+    assert_eq!(
+        eval("function f() { return\u{2028}42; } result = f();"),
+        "undefined"
+    );
+    assert_eq!(
+        eval("let a = 1 // comment\u{2028}let cr = 2\rresult = [a, cr];"),
+        "[1, 2]"
+    );
+    assert_eq!(eval("result = '\u{2028}\u{2029}'.length;"), "2");
+}
@@ -1,35 +1,37 @@
 pub mod trace;
 
-use crate::trace::Trace;
+use crate::trace::{Trace, Tracer};
 use std::cell::{Cell, RefCell};
-use std::collections::HashSet;
+use std::ops::{Deref, DerefMut};
 use std::ptr::NonNull;
+use std::rc::Rc;
 
-// Thread-local heap to avoid passing context everywhere
+// Each VM has a heap of its own, so one VM's collections never free what
+// another still uses. New values go to the heap of the VM running on this
+// thread, or to the detached heap when none is, which is never swept.
 thread_local! {
-    static HEAP: RefCell<Heap> = RefCell::new(Heap::new());
+    static CURRENT: RefCell<Option<Rc<RefCell<Heap>>>> = const { RefCell::new(None) };
+    static DETACHED: RefCell<Heap> = RefCell::new(Heap::new());
 }
 
+const INITIAL_THRESHOLD: usize = 1024 * 1024; // 1MB start
+
 trait Traceable {
-    fn trace(&self, visited: &mut HashSet<usize>);
     fn set_marked(&self, marked: bool);
     fn is_marked(&self) -> bool;
-    fn as_ptr(&self) -> usize;
+    fn size(&self) -> usize;
+    // Traces the references the value holds
+    fn trace_data(&self, tracer: &mut Tracer);
 }
 
 struct GcBox<T: ?Sized> {
     marked: Cell<bool>,
+    // The box and the memory its value owns, as last measured
+    size: Cell<usize>,
     data: RefCell<T>,
 }
 
 impl<T: Trace + ?Sized> Traceable for GcBox<T> {
-    fn trace(&self, visited: &mut HashSet<usize>) {
-        if !self.marked.get() {
-            self.marked.set(true);
-            self.data.borrow().trace(visited);
-        }
-    }
-
     fn set_marked(&self, marked: bool) {
         self.marked.set(marked);
     }
@@ -38,8 +40,18 @@ impl<T: Trace + ?Sized> Traceable for GcBox<T> {
         self.marked.get()
     }
 
-    fn as_ptr(&self) -> usize {
-        self as *const Self as *const () as usize
+    fn size(&self) -> usize {
+        self.size.get()
+    }
+
+    fn trace_data(&self, tracer: &mut Tracer) {
+        self.data.borrow().trace(tracer);
+    }
+}
+
+impl<T: Trace> GcBox<T> {
+    fn measure(&self, data: &T) -> usize {
+        std::mem::size_of::<Self>() + data.heap_size()
     }
 }
 
@@ -47,6 +59,7 @@ pub struct Heap {
     objects: Vec<Box<dyn Traceable>>,
     bytes_allocated: usize,
     threshold: usize,
+    stats: HeapStats,
 }
 
 impl Heap {
@@ -54,26 +67,36 @@ impl Heap {
         Self {
             objects: Vec::new(),
             bytes_allocated: 0,
-            threshold: 1024 * 1024, // 1MB start
+            threshold: INITIAL_THRESHOLD,
+            stats: HeapStats::default(),
         }
     }
 
     fn alloc<T: Trace + 'static>(&mut self, value: T) -> Gc<T> {
         let gc_box = Box::new(GcBox {
             marked: Cell::new(false),
+            size: Cell::new(0),
             data: RefCell::new(value),
         });
+        let size = gc_box.measure(&gc_box.data.borrow());
+        gc_box.size.set(size);
 
         let ptr_raw = &*gc_box as *const GcBox<T>;
         let ptr = NonNull::new(ptr_raw as *mut GcBox<T>).unwrap();
 
         self.objects.push(gc_box);
+        self.grow(size);
 
         Gc { ptr }
     }
 
+    fn grow(&mut self, bytes: usize) {
+        self.bytes_allocated += bytes;
+        self.stats.peak_bytes_allocated = self.stats.peak_bytes_allocated.max(self.bytes_allocated);
+    }
+
     fn collect(&mut self, roots: &[&dyn Trace]) {
-        let mut visited = HashSet::new();
+        let mut tracer = Tracer::new();
 
         // 1. Unmark all
         for obj in &self.objects {
@@ -82,55 +105,113 @@ impl Heap {
 
         // 2. Mark roots
         for root in roots {
-            root.trace(&mut visited);
+            root.trace(&mut tracer);
+        }
+        // Then everything they reach, from a worklist rather than by
+        // recursion, which a long linked list would overflow the stack with
+        while let Some(gray) = tracer.next_gray() {
+            unsafe { gray.as_ref() }.trace_data(&mut tracer);
         }
 
         // 3. Sweep
         self.objects.retain(|obj| obj.is_marked());
-        let after = self.objects.len();
+        self.bytes_allocated = self.objects.iter().map(|obj| obj.size()).sum();
+        self.stats.collections += 1;
 
         // Adjust threshold
-        if after > self.threshold {
-            self.threshold = after * 2;
-        }
+        self.threshold = (self.bytes_allocated * 2).max(INITIAL_THRESHOLD);
     }
 }
 
+/// How much a heap holds and how often it has been collected.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct HeapStats {
+    pub collections: usize,
+    pub bytes_allocated: usize,
+    // The most `bytes_allocated` has been, which collections keep bounded
+    pub peak_bytes_allocated: usize,
+}
+
 #[derive(Debug)]
 pub struct Gc<T: ?Sized> {
     ptr: NonNull<GcBox<T>>,
 }
 
 impl<T: Trace + 'static> Trace for Gc<T> {
-    fn trace(&self, visited: &mut HashSet<usize>) {
-        let ptr_val = self.ptr.as_ptr() as *const () as usize;
-        if visited.insert(ptr_val) {
-            unsafe {
-                let gc_box = self.ptr.as_ref();
-                gc_box.set_marked(true);
-                gc_box.data.borrow().trace(visited);
-            }
-        }
+    fn trace(&self, tracer: &mut Tracer) {
+        tracer.mark(self.ptr);
     }
 }
 
 impl<T: Trace + 'static> Gc<T> {
     pub fn new(value: T) -> Self {
-        HEAP.with(|heap| heap.borrow_mut().alloc(value))
+        CURRENT.with(|current| match &*current.borrow() {
+            Some(heap) => heap.borrow_mut().alloc(value),
+            None => DETACHED.with(|heap| heap.borrow_mut().alloc(value)),
+        })
     }
 
     pub fn borrow(&self) -> std::cell::Ref<'_, T> {
         unsafe { self.ptr.as_ref().data.borrow() }
     }
 
-    pub fn borrow_mut(&self) -> std::cell::RefMut<'_, T> {
-        unsafe { self.ptr.as_ref().data.borrow_mut() }
+    /// Borrows the value mutably. Memory the value gains or gives up while
+    /// borrowed is counted towards the next collection once it is released.
+    pub fn borrow_mut(&self) -> GcRefMut<'_, T> {
+        let gc_box = unsafe { self.ptr.as_ref() };
+        GcRefMut {
+            value: gc_box.data.borrow_mut(),
+            gc_box,
+        }
+    }
+}
+
+/// A mutable borrow of the value in a `Gc`.
+pub struct GcRefMut<'a, T: Trace> {
+    value: std::cell::RefMut<'a, T>,
+    gc_box: &'a GcBox<T>,
+}
+
+impl<T: Trace> Deref for GcRefMut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T: Trace> DerefMut for GcRefMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<T: Trace> Drop for GcRefMut<'_, T> {
+    fn drop(&mut self) {
+        let size = self.gc_box.measure(&self.value);
+        let old = self.gc_box.size.replace(size);
+        if size == old {
+            return;
+        }
+        // The running VM's heap, which is the one the value was allocated in
+        // unless it came from the detached heap. Any drift from that is
+        // corrected when the heap is next collected.
+        CURRENT.with(|current| {
+            if let Some(heap) = &*current.borrow() {
+                let mut heap = heap.borrow_mut();
+                if size > old {
+                    heap.grow(size - old);
+                } else {
+                    heap.bytes_allocated = heap.bytes_allocated.saturating_sub(old - size);
+                }
+            }
+        });
     }
 }
 
 impl<T: ?Sized> Clone for Gc<T> {
     fn clone(&self) -> Self {
-        Self { ptr: self.ptr }
+        *self
     }
 }
 
@@ -142,15 +223,164 @@ impl<T: ?Sized> PartialEq for Gc<T> {
     }
 }
 
-pub struct GC;
+/// The heap of one VM. Values are freed when a collection finds them
+/// unreachable from the roots given, or when the heap is dropped, so no
+/// `Gc` may outlive the VM that allocated it.
+pub struct GC {
+    heap: Rc<RefCell<Heap>>,
+}
+
+impl Default for GC {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl GC {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            heap: Rc::new(RefCell::new(Heap::new())),
+        }
     }
+
+    /// Makes this heap the one new values are allocated in, until the
+    /// returned scope is dropped.
+    pub fn enter(&self) -> HeapScope {
+        let previous = CURRENT.with(|current| current.replace(Some(Rc::clone(&self.heap))));
+        HeapScope { previous }
+    }
+
+    /// Whether enough has been allocated since the last collection to run another.
+    pub fn should_collect(&self) -> bool {
+        let heap = self.heap.borrow();
+        heap.bytes_allocated > heap.threshold
+    }
+
     pub fn collect(&mut self, roots: &[&dyn Trace]) {
-        HEAP.with(|heap| {
-            heap.borrow_mut().collect(roots);
-        })
+        self.heap.borrow_mut().collect(roots);
+    }
+
+    pub fn stats(&self) -> HeapStats {
+        let heap = self.heap.borrow();
+        HeapStats {
+            bytes_allocated: heap.bytes_allocated,
+            ..heap.stats
+        }
+    }
+}
+
+/// Restores the heap allocated in before `GC::enter` when dropped.
+pub struct HeapScope {
+    previous: Option<Rc<RefCell<Heap>>>,
+}
+
+impl Drop for HeapScope {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        CURRENT.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Node {
+        next: Option<Gc<Node>>,
+        data: Vec<u64>,
+    }
+
+    impl Trace for Node {
+        fn trace(&self, tracer: &mut Tracer) {
+            if let Some(next) = &self.next {
+                next.trace(tracer);
+            }
+        }
+
+        fn heap_size(&self) -> usize {
+            self.data.capacity() * std::mem::size_of::<u64>()
+        }
+    }
+
+    fn node(len: usize) -> Node {
+        Node {
+            next: None,
+            data: vec![0; len],
+        }
+    }
+
+    #[test]
+    fn collections_keep_an_allocating_loop_bounded() {
+        let mut gc = GC::new();
+        let _scope = gc.enter();
+        let root = Gc::new(node(0));
+        for _ in 0..300 {
+            // 800KB each, so the loop allocates far more than it keeps
+            let garbage = Gc::new(node(100_000));
+            root.borrow_mut().next = Some(garbage);
+            if gc.should_collect() {
+                gc.collect(&[&root]);
+            }
+        }
+        let stats = gc.stats();
+        assert!(stats.collections > 50, "{:?}", stats);
+        assert!(
+            stats.peak_bytes_allocated < 4 * INITIAL_THRESHOLD,
+            "{:?}",
+            stats
+        );
+        assert!(root.borrow().next.is_some());
+    }
+
+    #[test]
+    fn growing_a_value_counts_towards_the_next_collection() {
+        let gc = GC::new();
+        let _scope = gc.enter();
+        let value = Gc::new(node(0));
+        let before = gc.stats().bytes_allocated;
+        value.borrow_mut().data.resize(1000, 0);
+        assert!(gc.stats().bytes_allocated >= before + 8000);
+        value.borrow_mut().data = vec![];
+        assert_eq!(gc.stats().bytes_allocated, before);
+    }
+
+    #[test]
+    fn deep_chains_are_marked_without_recursing() {
+        let mut gc = GC::new();
+        let _scope = gc.enter();
+        let mut head = Gc::new(node(0));
+        for _ in 0..1_000_000 {
+            let mut next = node(0);
+            next.next = Some(head);
+            head = Gc::new(next);
+        }
+        let _garbage = Gc::new(node(10));
+        let before = gc.stats().bytes_allocated;
+        gc.collect(&[&head]);
+        assert!(gc.stats().bytes_allocated < before);
+        let mut length = 0;
+        let mut current = Some(head);
+        while let Some(node) = current {
+            length += 1;
+            current = node.borrow().next;
+        }
+        assert_eq!(length, 1_000_001);
+    }
+
+    #[test]
+    fn heaps_are_collected_separately() {
+        let mut first = GC::new();
+        let second = GC::new();
+        let kept = {
+            let _scope = second.enter();
+            Gc::new(node(10))
+        };
+        let _scope = first.enter();
+        let _garbage = Gc::new(node(10));
+        first.collect(&[]);
+        assert_eq!(first.stats().bytes_allocated, 0);
+        // Still allocated in the other heap
+        assert_eq!(kept.borrow().data.len(), 10);
+        assert!(second.stats().bytes_allocated > 0);
     }
 }
@@ -1,21 +1,64 @@
+use crate::Traceable;
 use std::collections::HashSet;
+use std::ptr::NonNull;
+
+/// The marking state of a collection. Tracing a `Gc` only marks its value
+/// and queues it; the collector traces the queued values afterwards, so a
+/// long chain of values is marked without recursing down it.
+pub struct Tracer {
+    visited: HashSet<usize>,
+    // Values marked whose own references are still to be traced
+    gray: Vec<NonNull<dyn Traceable>>,
+}
+
+impl Tracer {
+    pub(crate) fn new() -> Self {
+        Self {
+            visited: HashSet::new(),
+            gray: Vec::new(),
+        }
+    }
+
+    pub(crate) fn mark(&mut self, ptr: NonNull<dyn Traceable>) {
+        if self.visited.insert(ptr.as_ptr() as *const () as usize) {
+            unsafe { ptr.as_ref() }.set_marked(true);
+            self.gray.push(ptr);
+        }
+    }
+
+    pub(crate) fn next_gray(&mut self) -> Option<NonNull<dyn Traceable>> {
+        self.gray.pop()
+    }
+}
 
 pub trait Trace {
-    fn trace(&self, visited: &mut HashSet<usize>);
+    fn trace(&self, tracer: &mut Tracer);
+
+    /// Bytes the value owns outside itself, such as the buffer of a Vec,
+    /// which count towards the next collection like the value does. It is
+    /// measured each time the value is borrowed mutably, so it should be
+    /// cheap and need not count what its elements own.
+    fn heap_size(&self) -> usize {
+        0
+    }
 }
 
 impl<T: Trace> Trace for Vec<T> {
-    fn trace(&self, visited: &mut HashSet<usize>) {
+    fn trace(&self, tracer: &mut Tracer) {
         for item in self {
-            item.trace(visited);
+            item.trace(tracer);
         }
     }
+
+    fn heap_size(&self) -> usize {
+        self.capacity() * std::mem::size_of::<T>()
+    }
 }
 
 impl<K, V: Trace, S> Trace for std::collections::HashMap<K, V, S> {
-    fn trace(&self, visited: &mut HashSet<usize>) {
+    fn trace(&self, tracer: &mut Tracer) {
         for val in self.values() {
-            val.trace(visited);
+            val.trace(tracer);
         }
     }
 }
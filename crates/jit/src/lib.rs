@@ -9,6 +9,7 @@ use windows_sys::Win32::System::Memory::{
 #[cfg(unix)]
 use libc::{mmap, munmap, MAP_ANONYMOUS, MAP_PRIVATE, PROT_EXEC, PROT_READ, PROT_WRITE};
 
+#[derive(Default)]
 pub struct JitCompiler {
     // We keep track of allocated memory to free it later
     allocated_pages: Vec<(*mut u8, usize)>,
@@ -104,7 +105,7 @@ impl JitCompiler {
         let ptr = self.allocate_executable_memory(&code)?;
 
         // Cast to function pointer
-        let func = unsafe { std::mem::transmute::<_, fn() -> f64>(ptr) };
+        let func = unsafe { std::mem::transmute::<*const u8, fn() -> f64>(ptr) };
 
         Ok(func)
     }
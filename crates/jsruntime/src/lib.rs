@@ -1,3 +1,48 @@
-pub fn init_js_runtime() {
-    // Register built-in JS types
+mod object;
+mod regexp;
+mod string;
+
+use shadowjs_vm::{create_error, Value, VM};
+
+/// Registers the built-in JS globals on `vm`.
+pub fn init_js_runtime(vm: &mut VM) {
+    vm.define_error_constructor("Error", error);
+    vm.define_error_constructor("TypeError", type_error);
+    vm.define_error_constructor("ReferenceError", reference_error);
+    vm.define_error_constructor("RangeError", range_error);
+    vm.define_error_constructor("SyntaxError", syntax_error);
+
+    object::init_object(vm);
+    vm.define_global("RegExp", Value::NativeFunction(regexp::regexp_constructor));
+    vm.set_regexp_constructor(regexp::regexp_constructor);
+    string::init_string_methods(vm);
+}
+
+// Calling an Error constructor without `new` creates an error just the same
+fn error(_this: Value, args: Vec<Value>) -> Result<Value, Value> {
+    construct_error("Error", &args)
+}
+
+fn type_error(_this: Value, args: Vec<Value>) -> Result<Value, Value> {
+    construct_error("TypeError", &args)
+}
+
+fn reference_error(_this: Value, args: Vec<Value>) -> Result<Value, Value> {
+    construct_error("ReferenceError", &args)
+}
+
+fn range_error(_this: Value, args: Vec<Value>) -> Result<Value, Value> {
+    construct_error("RangeError", &args)
+}
+
+fn syntax_error(_this: Value, args: Vec<Value>) -> Result<Value, Value> {
+    construct_error("SyntaxError", &args)
+}
+
+fn construct_error(name: &str, args: &[Value]) -> Result<Value, Value> {
+    let message = match args.first() {
+        None | Some(Value::Undefined) => String::new(),
+        Some(message) => shadowjs_value::to_string(message),
+    };
+    Ok(create_error(name, &message))
 }
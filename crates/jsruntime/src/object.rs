@@ -0,0 +1,37 @@
+//! The `Object` global and its static methods.
+
+use shadowjs_gc::Gc;
+use shadowjs_value::{Object, Value};
+use shadowjs_vm::{create_error, VM};
+use std::rc::Rc;
+
+pub fn init_object(vm: &mut VM) {
+    let object: Object = [("keys".to_string(), Value::NativeFunction(object_keys))]
+        .into_iter()
+        .collect();
+    vm.define_global("Object", Value::Object(Gc::new(object)));
+}
+
+/// `Object.keys(value)`: the names of its own properties, integer keys first
+/// in ascending order and then the rest in the order they were added.
+fn object_keys(_this: Value, args: Vec<Value>) -> Result<Value, Value> {
+    let keys: Vec<String> = match args.first().unwrap_or(&Value::Undefined) {
+        Value::Object(obj) => obj.borrow().keys().into_iter().map(String::from).collect(),
+        Value::Array(arr) => (0..arr.borrow().len()).map(|i| i.to_string()).collect(),
+        Value::String(s) => (0..s.encode_utf16().count())
+            .map(|i| i.to_string())
+            .collect(),
+        Value::Null | Value::Undefined => {
+            return Err(create_error(
+                "TypeError",
+                "Cannot convert undefined or null to object",
+            ))
+        }
+        _ => vec![],
+    };
+    let keys = keys
+        .into_iter()
+        .map(|key| Value::String(Rc::new(key)))
+        .collect();
+    Ok(Value::Array(Gc::new(keys)))
+}
@@ -0,0 +1,493 @@
+use super::parser::{is_line_terminator, is_word_char, CharClass, Node, Regex};
+
+/// The capture groups of a match as UTF-16 index ranges. Group 0 is the
+/// whole match; groups that did not participate are None.
+pub type Captures = Vec<Option<(usize, usize)>>;
+
+// How deeply nodes may nest while matching. Every repetition of a group
+// nests further, so the stack is grown on the heap as needed, up to this.
+const MAX_DEPTH: usize = 100_000;
+// Stack space to keep free before matching a node, and how much to add
+// when there is less
+const RED_ZONE: usize = 64 * 1024;
+const STACK_GROWTH: usize = 1024 * 1024;
+
+/// Matching needed more backtracking state than the stack can hold.
+#[derive(Debug)]
+pub struct StackOverflow;
+
+/// Matches `regex` against `input`, trying each start position from
+/// `start` onwards, or only `start` itself if `sticky`.
+pub fn find_at(
+    regex: &Regex,
+    input: &[u16],
+    start: usize,
+    sticky: bool,
+) -> Result<Option<Captures>, StackOverflow> {
+    let mut matcher = Matcher {
+        regex,
+        input,
+        captures: vec![None; regex.group_count + 1],
+        depth: 0,
+        overflowed: false,
+    };
+
+    let mut pos = start;
+    while pos <= input.len() {
+        matcher.captures.fill(None);
+        let mut end = None;
+        if matcher.match_node(&regex.node, pos, true, &mut |_, p| {
+            end = Some(p);
+            true
+        }) {
+            matcher.captures[0] = Some((pos, end.unwrap()));
+            return Ok(Some(matcher.captures));
+        }
+        if matcher.overflowed {
+            return Err(StackOverflow);
+        }
+        if sticky {
+            break;
+        }
+        pos = advance_index(input, pos, regex.flags.unicode);
+    }
+    Ok(None)
+}
+
+/// The index after `pos`, skipping a whole surrogate pair in unicode mode.
+pub fn advance_index(input: &[u16], pos: usize, unicode: bool) -> usize {
+    if unicode && is_high_surrogate(input.get(pos)) && is_low_surrogate(input.get(pos + 1)) {
+        pos + 2
+    } else {
+        pos + 1
+    }
+}
+
+fn is_high_surrogate(unit: Option<&u16>) -> bool {
+    unit.is_some_and(|u| (0xD800..0xDC00).contains(u))
+}
+
+fn is_low_surrogate(unit: Option<&u16>) -> bool {
+    unit.is_some_and(|u| (0xDC00..0xE000).contains(u))
+}
+
+/// What to do after a node matches: continue with the rest of the pattern
+/// from the given position, returning whether the whole pattern matched.
+type Continuation<'k, 'a> = dyn FnMut(&mut Matcher<'a>, usize) -> bool + 'k;
+
+/// A backtracking matcher. Each node matches by calling its continuation
+/// with every position it can end at, in order of preference, until one
+/// succeeds. Lookbehinds match their contents backwards from the current
+/// position, so `forward` is false inside them.
+struct Matcher<'a> {
+    regex: &'a Regex,
+    input: &'a [u16],
+    captures: Captures,
+    depth: usize,
+    // Set once MAX_DEPTH is reached, failing the rest of the match
+    overflowed: bool,
+}
+
+impl<'a> Matcher<'a> {
+    fn match_node(
+        &mut self,
+        node: &'a Node,
+        pos: usize,
+        forward: bool,
+        k: &mut Continuation<'_, 'a>,
+    ) -> bool {
+        if self.depth >= MAX_DEPTH {
+            self.overflowed = true;
+        }
+        if self.overflowed {
+            return false;
+        }
+        self.depth += 1;
+        let matched = stacker::maybe_grow(RED_ZONE, STACK_GROWTH, || {
+            self.match_node_kind(node, pos, forward, k)
+        });
+        self.depth -= 1;
+        matched
+    }
+
+    fn match_node_kind(
+        &mut self,
+        node: &'a Node,
+        pos: usize,
+        forward: bool,
+        k: &mut Continuation<'_, 'a>,
+    ) -> bool {
+        match node {
+            Node::Empty => k(self, pos),
+            Node::Char(_) | Node::Any | Node::Class(_) => match self.step(node, pos, forward) {
+                Some(next) => k(self, next),
+                None => false,
+            },
+            Node::LineStart => {
+                let at_start = pos == 0
+                    || (self.regex.flags.multiline
+                        && is_line_terminator(u32::from(self.input[pos - 1])));
+                at_start && k(self, pos)
+            }
+            Node::LineEnd => {
+                let at_end = pos == self.input.len()
+                    || (self.regex.flags.multiline
+                        && is_line_terminator(u32::from(self.input[pos])));
+                at_end && k(self, pos)
+            }
+            Node::WordBoundary { negate } => {
+                let before = pos > 0 && is_word_char(u32::from(self.input[pos - 1]));
+                let after = pos < self.input.len() && is_word_char(u32::from(self.input[pos]));
+                ((before != after) != *negate) && k(self, pos)
+            }
+            Node::Sequence(nodes) => self.match_sequence(nodes, pos, forward, k),
+            Node::Alternation(alternatives) => alternatives
+                .iter()
+                .any(|alternative| self.match_node(alternative, pos, forward, k)),
+            Node::Group { node, index: None } => self.match_node(node, pos, forward, k),
+            Node::Group {
+                node,
+                index: Some(index),
+            } => {
+                let index = *index;
+                self.match_node(node, pos, forward, &mut |m, end| {
+                    let saved = m.captures[index];
+                    m.captures[index] = Some(if forward { (pos, end) } else { (end, pos) });
+                    if k(m, end) {
+                        return true;
+                    }
+                    m.captures[index] = saved;
+                    false
+                })
+            }
+            Node::Look {
+                node,
+                behind,
+                negate,
+            } => {
+                // Lookarounds are atomic: once they match, they are not
+                // retried with other choices
+                let saved = self.captures.clone();
+                let matched = self.match_node(node, pos, !behind, &mut |_, _| true);
+                if matched == *negate {
+                    self.captures = saved;
+                    return false;
+                }
+                if *negate {
+                    self.captures = saved.clone();
+                }
+                if k(self, pos) {
+                    return true;
+                }
+                self.captures = saved;
+                false
+            }
+            Node::Repeat {
+                node,
+                min,
+                max,
+                greedy,
+                groups,
+            } => {
+                let repeat = Repeat {
+                    node,
+                    min: *min,
+                    max: *max,
+                    greedy: *greedy,
+                    groups: groups.clone(),
+                };
+                // Which alternative an iteration took only matters to the
+                // rest of the pattern through a backreference
+                if is_single_char(node) && (groups.is_empty() || !self.regex.has_backreferences) {
+                    self.match_simple_repeat(&repeat, pos, forward, k)
+                } else {
+                    self.match_repeat(&repeat, 0, pos, forward, k)
+                }
+            }
+            Node::BackReference(index) => {
+                let Some((start, end)) = self.captures[*index] else {
+                    return k(self, pos);
+                };
+                let len = end - start;
+                let range = if forward {
+                    pos..pos + len
+                } else {
+                    pos.wrapping_sub(len)..pos
+                };
+                if range.end > self.input.len() || range.start > range.end {
+                    return false;
+                }
+                let ignore_case = self.regex.flags.ignore_case;
+                let unicode = self.regex.flags.unicode;
+                let equal = self.input[start..end]
+                    .iter()
+                    .zip(&self.input[range.clone()])
+                    .all(|(&a, &b)| chars_equal(u32::from(a), u32::from(b), ignore_case, unicode));
+                let next = if forward { range.end } else { range.start };
+                equal && k(self, next)
+            }
+        }
+    }
+
+    fn match_sequence(
+        &mut self,
+        nodes: &'a [Node],
+        pos: usize,
+        forward: bool,
+        k: &mut Continuation<'_, 'a>,
+    ) -> bool {
+        let (first, rest) = if forward {
+            match nodes.split_first() {
+                Some(split) => split,
+                None => return k(self, pos),
+            }
+        } else {
+            match nodes.split_last() {
+                Some(split) => split,
+                None => return k(self, pos),
+            }
+        };
+        self.match_node(first, pos, forward, &mut |m, next| {
+            m.match_sequence(rest, next, forward, k)
+        })
+    }
+
+    /// Matches a repeated node after `count` iterations.
+    fn match_repeat(
+        &mut self,
+        repeat: &Repeat<'a>,
+        count: usize,
+        pos: usize,
+        forward: bool,
+        k: &mut Continuation<'_, 'a>,
+    ) -> bool {
+        if repeat.max == Some(count) {
+            return k(self, pos);
+        }
+
+        let iterate = |m: &mut Self, k: &mut Continuation<'_, 'a>| {
+            let saved = m.captures[repeat.groups.clone()].to_vec();
+            m.captures[repeat.groups.clone()].fill(None);
+            let matched = m.match_node(repeat.node, pos, forward, &mut |m, next| {
+                // An iteration that matches nothing cannot make progress
+                if next == pos && count >= repeat.min {
+                    return false;
+                }
+                m.match_repeat(repeat, count + 1, next, forward, k)
+            });
+            if !matched {
+                m.captures[repeat.groups.clone()].clone_from_slice(&saved);
+            }
+            matched
+        };
+
+        if count < repeat.min {
+            iterate(self, k)
+        } else if repeat.greedy {
+            iterate(self, k) || k(self, pos)
+        } else {
+            k(self, pos) || iterate(self, k)
+        }
+    }
+
+    /// Matches a repeated node that `is_single_char` without recursing for
+    /// each iteration, which keeps `.*` and `(a|b)*` on long inputs off the
+    /// call stack. Each iteration can only end one character on, so the
+    /// iterations never need to be retried with other choices; only their
+    /// count is backtracked over.
+    fn match_simple_repeat(
+        &mut self,
+        repeat: &Repeat<'a>,
+        pos: usize,
+        forward: bool,
+        k: &mut Continuation<'_, 'a>,
+    ) -> bool {
+        let groups = repeat.groups.clone();
+        let saved = self.captures[groups.clone()].to_vec();
+        let max = repeat.max.unwrap_or(usize::MAX);
+        if repeat.greedy {
+            let mut ends = vec![pos];
+            // The captures of the groups inside after each iteration
+            let mut iterations: Vec<Captures> = vec![];
+            while ends.len() - 1 < max {
+                self.captures[groups.clone()].fill(None);
+                match self.match_char(repeat.node, *ends.last().unwrap(), forward) {
+                    Some(next) => ends.push(next),
+                    None => break,
+                }
+                if !groups.is_empty() {
+                    iterations.push(self.captures[groups.clone()].to_vec());
+                }
+            }
+            for count in (repeat.min..ends.len()).rev() {
+                if !groups.is_empty() {
+                    let captures = match count {
+                        0 => &saved,
+                        _ => &iterations[count - 1],
+                    };
+                    self.captures[groups.clone()].clone_from_slice(captures);
+                }
+                if k(self, ends[count]) {
+                    return true;
+                }
+            }
+        } else {
+            let mut end = pos;
+            for count in 0..=max {
+                if count >= repeat.min && k(self, end) {
+                    return true;
+                }
+                if count == max {
+                    break;
+                }
+                self.captures[groups.clone()].fill(None);
+                match self.match_char(repeat.node, end, forward) {
+                    Some(next) => end = next,
+                    None => break,
+                }
+            }
+        }
+        self.captures[groups].clone_from_slice(&saved);
+        false
+    }
+
+    /// Matches a node that `is_single_char` at `pos`, taking the first
+    /// alternative that matches and recording the groups along it, and
+    /// returns the position after it.
+    fn match_char(&mut self, node: &Node, pos: usize, forward: bool) -> Option<usize> {
+        match node {
+            Node::Group { node, index } => {
+                let end = self.match_char(node, pos, forward)?;
+                if let Some(index) = *index {
+                    self.captures[index] = Some(if forward { (pos, end) } else { (end, pos) });
+                }
+                Some(end)
+            }
+            Node::Alternation(alternatives) => alternatives
+                .iter()
+                .find_map(|alternative| self.match_char(alternative, pos, forward)),
+            Node::Sequence(nodes) => self.match_char(&nodes[0], pos, forward),
+            _ => self.step(node, pos, forward),
+        }
+    }
+
+    /// Matches a single character node at `pos`, returning the position
+    /// after it.
+    fn step(&self, node: &Node, pos: usize, forward: bool) -> Option<usize> {
+        let (c, next) = self.read_char(pos, forward)?;
+        let flags = self.regex.flags;
+        let matched = match node {
+            Node::Char(expected) => chars_equal(*expected, c, flags.ignore_case, flags.unicode),
+            Node::Any => flags.dot_all || !is_line_terminator(c),
+            Node::Class(class) => class_matches(class, c, flags.ignore_case, flags.unicode),
+            _ => unreachable!("step called on a node that is not a character"),
+        };
+        matched.then_some(next)
+    }
+
+    /// Reads the character after `pos`, or before it when matching
+    /// backwards. In unicode mode a surrogate pair is one character.
+    fn read_char(&self, pos: usize, forward: bool) -> Option<(u32, usize)> {
+        let input = self.input;
+        let unicode = self.regex.flags.unicode;
+        if forward {
+            let unit = u32::from(*input.get(pos)?);
+            if unicode && is_high_surrogate(input.get(pos)) && is_low_surrogate(input.get(pos + 1))
+            {
+                let low = u32::from(input[pos + 1]);
+                return Some((combine_surrogates(unit, low), pos + 2));
+            }
+            Some((unit, pos + 1))
+        } else {
+            let unit = u32::from(*input.get(pos.checked_sub(1)?)?);
+            if unicode
+                && pos >= 2
+                && is_low_surrogate(input.get(pos - 1))
+                && is_high_surrogate(input.get(pos - 2))
+            {
+                let high = u32::from(input[pos - 2]);
+                return Some((combine_surrogates(high, unit), pos - 2));
+            }
+            Some((unit, pos - 1))
+        }
+    }
+}
+
+struct Repeat<'a> {
+    node: &'a Node,
+    min: usize,
+    max: Option<usize>,
+    greedy: bool,
+    groups: std::ops::Range<usize>,
+}
+
+/// Whether every way `node` can match takes exactly one character, as in
+/// `.`, `[a-z]` or `(a|b)`.
+fn is_single_char(node: &Node) -> bool {
+    match node {
+        Node::Char(_) | Node::Any | Node::Class(_) => true,
+        Node::Group { node, .. } => is_single_char(node),
+        Node::Alternation(alternatives) => alternatives.iter().all(is_single_char),
+        Node::Sequence(nodes) => matches!(nodes.as_slice(), [node] if is_single_char(node)),
+        _ => false,
+    }
+}
+
+fn combine_surrogates(high: u32, low: u32) -> u32 {
+    0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+}
+
+fn chars_equal(a: u32, b: u32, ignore_case: bool, unicode: bool) -> bool {
+    a == b || (ignore_case && canonicalize(a, unicode) == canonicalize(b, unicode))
+}
+
+fn class_matches(class: &CharClass, c: u32, ignore_case: bool, unicode: bool) -> bool {
+    let included = class.includes(c)
+        || (ignore_case
+            && case_variants(c, unicode)
+                .into_iter()
+                .any(|variant| class.includes(variant)));
+    included != class.negate
+}
+
+/// The character `c` is compared as when ignoring case. With the `u` flag
+/// this is simple case folding, approximated by lowercasing; without it
+/// characters are uppercased, but never from outside ASCII into it.
+fn canonicalize(c: u32, unicode: bool) -> u32 {
+    let Some(ch) = char::from_u32(c) else {
+        return c; // A lone surrogate
+    };
+    let mapped = if unicode {
+        single_char(ch.to_lowercase())
+    } else {
+        single_char(ch.to_uppercase())
+    };
+    match mapped {
+        Some(mapped) if unicode || c < 128 || mapped >= 128 => mapped,
+        _ => c,
+    }
+}
+
+/// The other characters that canonicalize the same as `c`.
+fn case_variants(c: u32, unicode: bool) -> Vec<u32> {
+    let Some(ch) = char::from_u32(c) else {
+        return vec![];
+    };
+    let canonical = canonicalize(c, unicode);
+    [
+        single_char(ch.to_lowercase()),
+        single_char(ch.to_uppercase()),
+    ]
+    .into_iter()
+    .flatten()
+    .filter(|&variant| variant != c && canonicalize(variant, unicode) == canonical)
+    .collect()
+}
+
+/// The result of a case mapping, unless it maps to several characters.
+fn single_char(mut chars: impl Iterator<Item = char>) -> Option<u32> {
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c as u32),
+        _ => None,
+    }
+}
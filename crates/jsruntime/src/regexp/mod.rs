@@ -0,0 +1,337 @@
+//! `RegExp` objects, backed by a backtracking matcher. There are no
+//! prototypes yet, so each RegExp object carries its own methods.
+
+mod matcher;
+mod parser;
+
+pub use matcher::{advance_index, Captures};
+pub use parser::Regex;
+
+use matcher::find_at;
+use parser::Flags;
+use shadowjs_gc::Gc;
+use shadowjs_value::{to_number, to_string, Value};
+use shadowjs_vm::create_error;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+pub type Object = Gc<shadowjs_value::Object>;
+
+thread_local! {
+    // Compiled patterns by source and flags. RegExp objects only hold their
+    // source, so this saves parsing it again on every call.
+    static CACHE: RefCell<HashMap<(String, String), Rc<Regex>>> = RefCell::new(HashMap::new());
+}
+
+/// `RegExp(pattern, flags)`, which regular expression literals are also
+/// created with. Calling it with or without `new` is the same.
+pub fn regexp_constructor(_this: Value, args: Vec<Value>) -> Result<Value, Value> {
+    let pattern = args.first().unwrap_or(&Value::Undefined);
+    let flags = args.get(1).unwrap_or(&Value::Undefined);
+    let (source, flags) = match (as_regexp(pattern), flags) {
+        (Some(regexp), Value::Undefined) => {
+            (get_string(&regexp, "source"), get_string(&regexp, "flags"))
+        }
+        (Some(regexp), flags) => (get_string(&regexp, "source"), to_string(flags)),
+        (None, flags) => (optional_string(pattern), optional_string(flags)),
+    };
+    create_regexp(&source, &flags)
+}
+
+/// Creates a RegExp object, throwing a SyntaxError for an invalid pattern.
+pub fn create_regexp(pattern: &str, flags: &str) -> Result<Value, Value> {
+    let source = escape_source(pattern);
+    let regex = compile(&source, flags)?;
+    let flags = regex.flags;
+
+    let mut map = shadowjs_value::Object::new();
+    map.insert("source".to_string(), Value::String(Rc::new(source)));
+    map.insert(
+        "flags".to_string(),
+        Value::String(Rc::new(flags.to_string())),
+    );
+    map.insert("hasIndices".to_string(), Value::Boolean(flags.has_indices));
+    map.insert("global".to_string(), Value::Boolean(flags.global));
+    map.insert("ignoreCase".to_string(), Value::Boolean(flags.ignore_case));
+    map.insert("multiline".to_string(), Value::Boolean(flags.multiline));
+    map.insert("dotAll".to_string(), Value::Boolean(flags.dot_all));
+    map.insert("unicode".to_string(), Value::Boolean(flags.unicode));
+    map.insert("sticky".to_string(), Value::Boolean(flags.sticky));
+    map.insert("lastIndex".to_string(), Value::Number(0.0));
+    map.insert("exec".to_string(), Value::NativeFunction(exec));
+    map.insert("test".to_string(), Value::NativeFunction(test));
+    map.insert(
+        "toString".to_string(),
+        Value::NativeFunction(regexp_to_string),
+    );
+    Ok(Value::Object(Gc::new(map)))
+}
+
+/// Returns the object if `value` is a RegExp object.
+pub fn as_regexp(value: &Value) -> Option<Object> {
+    let Value::Object(obj) = value else {
+        return None;
+    };
+    let map = obj.borrow();
+    let is_regexp = matches!(map.get("source"), Some(Value::String(_)))
+        && matches!(map.get("flags"), Some(Value::String(_)))
+        && matches!(map.get("exec"), Some(Value::NativeFunction(_)));
+    is_regexp.then_some(*obj)
+}
+
+/// The compiled pattern of a RegExp object.
+pub fn regex_of(regexp: &Object) -> Result<Rc<Regex>, Value> {
+    compile(&get_string(regexp, "source"), &get_string(regexp, "flags"))
+}
+
+fn compile(source: &str, flags: &str) -> Result<Rc<Regex>, Value> {
+    let key = (source.to_string(), flags.to_string());
+    if let Some(regex) = CACHE.with(|cache| cache.borrow().get(&key).cloned()) {
+        return Ok(regex);
+    }
+
+    let parsed_flags = Flags::parse(flags).ok_or_else(|| {
+        create_error(
+            "SyntaxError",
+            &format!("Invalid flags supplied to RegExp constructor '{}'", flags),
+        )
+    })?;
+    let regex = Regex::new(source, parsed_flags).map_err(|message| {
+        create_error(
+            "SyntaxError",
+            &format!(
+                "Invalid regular expression: /{}/{}: {}",
+                source, flags, message
+            ),
+        )
+    })?;
+    let regex = Rc::new(regex);
+    CACHE.with(|cache| cache.borrow_mut().insert(key, regex.clone()));
+    Ok(regex)
+}
+
+/// The `source` of a pattern: escaped so that `/${source}/` reads back as
+/// the same regular expression.
+fn escape_source(pattern: &str) -> String {
+    if pattern.is_empty() {
+        return "(?:)".to_string();
+    }
+
+    let mut source = String::with_capacity(pattern.len());
+    let mut in_class = false;
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                source.push(c);
+                if let Some(escaped) = chars.next() {
+                    source.push(escaped);
+                }
+            }
+            '/' if !in_class => source.push_str("\\/"),
+            '\n' => source.push_str("\\n"),
+            '\r' => source.push_str("\\r"),
+            '\u{2028}' => source.push_str("\\u2028"),
+            '\u{2029}' => source.push_str("\\u2029"),
+            c => {
+                in_class = match c {
+                    '[' => true,
+                    ']' => false,
+                    _ => in_class,
+                };
+                source.push(c);
+            }
+        }
+    }
+    source
+}
+
+/// Runs a RegExp object against `input` the way `exec` does: global and
+/// sticky expressions start at `lastIndex` and update it.
+pub fn exec_raw(regexp: &Object, input: &[u16]) -> Result<Option<Captures>, Value> {
+    let regex = regex_of(regexp)?;
+    let flags = regex.flags;
+    let uses_last_index = flags.global || flags.sticky;
+
+    let start = if uses_last_index {
+        to_length(
+            &regexp
+                .borrow()
+                .get("lastIndex")
+                .cloned()
+                .unwrap_or(Value::Undefined),
+        )
+    } else {
+        0
+    };
+    let captures = if start <= input.len() {
+        find(&regex, input, start, flags.sticky)?
+    } else {
+        None
+    };
+
+    if uses_last_index {
+        let last_index = captures
+            .as_ref()
+            .map_or(0, |captures| captures[0].unwrap().1);
+        set_last_index(regexp, last_index);
+    }
+    Ok(captures)
+}
+
+/// Matches `regex` from `start`, throwing a RangeError if the match needs
+/// more backtracking than the stack allows.
+pub fn find(
+    regex: &Regex,
+    input: &[u16],
+    start: usize,
+    sticky: bool,
+) -> Result<Option<Captures>, Value> {
+    find_at(regex, input, start, sticky)
+        .map_err(|_| create_error("RangeError", "Maximum call stack size exceeded"))
+}
+
+pub fn set_last_index(regexp: &Object, index: usize) {
+    regexp
+        .borrow_mut()
+        .insert("lastIndex".to_string(), Value::Number(index as f64));
+}
+
+/// ToLength: a non-negative integer index.
+fn to_length(value: &Value) -> usize {
+    let n = to_number(value);
+    if n.is_nan() || n <= 0.0 {
+        0
+    } else {
+        n.min(9007199254740991.0) as usize
+    }
+}
+
+/// Builds the array-like object `exec` returns, with the matched strings
+/// and `index`, `input` and `groups`, plus `indices` with the `d` flag.
+pub fn match_result(regex: &Regex, captures: &Captures, input: &str, units: &[u16]) -> Value {
+    let strings: Vec<Value> = captures
+        .iter()
+        .map(|capture| match capture {
+            Some((start, end)) => {
+                Value::String(Rc::new(String::from_utf16_lossy(&units[*start..*end])))
+            }
+            None => Value::Undefined,
+        })
+        .collect();
+
+    let mut result = array_like(strings.clone());
+    result.insert(
+        "index".to_string(),
+        Value::Number(captures[0].unwrap().0 as f64),
+    );
+    result.insert(
+        "input".to_string(),
+        Value::String(Rc::new(input.to_string())),
+    );
+    result.insert("groups".to_string(), named_groups(regex, &strings));
+
+    if regex.flags.has_indices {
+        let indices: Vec<Value> = captures
+            .iter()
+            .map(|capture| match capture {
+                Some((start, end)) => Value::Array(Gc::new(vec![
+                    Value::Number(*start as f64),
+                    Value::Number(*end as f64),
+                ])),
+                None => Value::Undefined,
+            })
+            .collect();
+        let mut indices_object = array_like(indices.clone());
+        indices_object.insert("groups".to_string(), named_groups(regex, &indices));
+        result.insert(
+            "indices".to_string(),
+            Value::Object(Gc::new(indices_object)),
+        );
+    }
+    Value::Object(Gc::new(result))
+}
+
+/// An object mapping each group name to its entry in `values`, or undefined
+/// if the pattern has no named groups.
+fn named_groups(regex: &Regex, values: &[Value]) -> Value {
+    if regex.group_names.is_empty() {
+        return Value::Undefined;
+    }
+    let groups = regex
+        .group_names
+        .iter()
+        .map(|(name, index)| (name.clone(), values[*index].clone()))
+        .collect();
+    Value::Object(Gc::new(groups))
+}
+
+/// An object with keys "0" to "n - 1" and a `length`, standing in for an
+/// array with extra properties.
+fn array_like(values: Vec<Value>) -> shadowjs_value::Object {
+    let length = values.len();
+    let mut map: shadowjs_value::Object = values
+        .into_iter()
+        .enumerate()
+        .map(|(i, value)| (i.to_string(), value))
+        .collect();
+    map.insert("length".to_string(), Value::Number(length as f64));
+    map
+}
+
+fn exec(this: Value, args: Vec<Value>) -> Result<Value, Value> {
+    let regexp = this_regexp(&this, "exec")?;
+    let input = to_string(args.first().unwrap_or(&Value::Undefined));
+    let units: Vec<u16> = input.encode_utf16().collect();
+    match exec_raw(&regexp, &units)? {
+        Some(captures) => Ok(match_result(
+            &*regex_of(&regexp)?,
+            &captures,
+            &input,
+            &units,
+        )),
+        None => Ok(Value::Null),
+    }
+}
+
+fn test(this: Value, args: Vec<Value>) -> Result<Value, Value> {
+    let regexp = this_regexp(&this, "test")?;
+    let input = to_string(args.first().unwrap_or(&Value::Undefined));
+    let units: Vec<u16> = input.encode_utf16().collect();
+    Ok(Value::Boolean(exec_raw(&regexp, &units)?.is_some()))
+}
+
+fn regexp_to_string(this: Value, _args: Vec<Value>) -> Result<Value, Value> {
+    let regexp = this_regexp(&this, "toString")?;
+    let string = format!(
+        "/{}/{}",
+        get_string(&regexp, "source"),
+        get_string(&regexp, "flags")
+    );
+    Ok(Value::String(Rc::new(string)))
+}
+
+fn this_regexp(this: &Value, method: &str) -> Result<Object, Value> {
+    as_regexp(this).ok_or_else(|| {
+        create_error(
+            "TypeError",
+            &format!(
+                "Method RegExp.prototype.{} called on incompatible receiver {}",
+                method, this
+            ),
+        )
+    })
+}
+
+fn get_string(obj: &Object, key: &str) -> String {
+    obj.borrow().get(key).map(to_string).unwrap_or_default()
+}
+
+/// ToString, except that undefined is the empty string.
+fn optional_string(value: &Value) -> String {
+    match value {
+        Value::Undefined => String::new(),
+        value => to_string(value),
+    }
+}
@@ -0,0 +1,804 @@
+use std::fmt;
+use std::ops::Range;
+
+/// The flags a regular expression was created with.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Flags {
+    pub has_indices: bool,
+    pub global: bool,
+    pub ignore_case: bool,
+    pub multiline: bool,
+    pub dot_all: bool,
+    pub unicode: bool,
+    pub sticky: bool,
+}
+
+impl Flags {
+    /// Parses a flags string such as "gi". Returns None if a flag is unknown
+    /// or repeated.
+    pub fn parse(flags: &str) -> Option<Flags> {
+        let mut result = Flags::default();
+        for c in flags.chars() {
+            let flag = match c {
+                'd' => &mut result.has_indices,
+                'g' => &mut result.global,
+                'i' => &mut result.ignore_case,
+                'm' => &mut result.multiline,
+                's' => &mut result.dot_all,
+                'u' => &mut result.unicode,
+                'y' => &mut result.sticky,
+                _ => return None,
+            };
+            if *flag {
+                return None;
+            }
+            *flag = true;
+        }
+        Some(result)
+    }
+}
+
+impl fmt::Display for Flags {
+    // Flags are listed in a fixed order, whatever order they were written in
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let flags = [
+            (self.has_indices, 'd'),
+            (self.global, 'g'),
+            (self.ignore_case, 'i'),
+            (self.multiline, 'm'),
+            (self.dot_all, 's'),
+            (self.unicode, 'u'),
+            (self.sticky, 'y'),
+        ];
+        for (set, flag) in flags {
+            if set {
+                write!(f, "{}", flag)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A node of a parsed pattern. Characters are UTF-16 code units, or code
+/// points with the `u` flag.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    Empty,
+    Char(u32),
+    Any, // `.`
+    Class(CharClass),
+    LineStart, // `^`
+    LineEnd,   // `$`
+    WordBoundary {
+        negate: bool,
+    },
+    Sequence(Vec<Node>),
+    Alternation(Vec<Node>),
+    Group {
+        node: Box<Node>,
+        index: Option<usize>, // None for `(?:...)`
+    },
+    Look {
+        node: Box<Node>,
+        behind: bool,
+        negate: bool,
+    },
+    Repeat {
+        node: Box<Node>,
+        min: usize,
+        max: Option<usize>,
+        greedy: bool,
+        // Capture groups inside the repeated node, which each iteration resets
+        groups: Range<usize>,
+    },
+    BackReference(usize),
+}
+
+/// A `[...]` class, or a class escape such as `\d` outside of one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CharClass {
+    pub items: Vec<ClassItem>,
+    pub negate: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClassItem {
+    Range(u32, u32),
+    // A class escape such as `\d` or `\P{Letter}`, as sorted ranges
+    Set(Vec<(u32, u32)>, bool),
+}
+
+impl CharClass {
+    /// Whether `c` is in one of the items, ignoring `negate`.
+    pub fn includes(&self, c: u32) -> bool {
+        self.items.iter().any(|item| match item {
+            ClassItem::Range(start, end) => (*start..=*end).contains(&c),
+            ClassItem::Set(ranges, negate) => in_ranges(ranges, c) != *negate,
+        })
+    }
+}
+
+fn in_ranges(ranges: &[(u32, u32)], c: u32) -> bool {
+    let i = ranges.partition_point(|&(_, end)| end < c);
+    ranges.get(i).is_some_and(|&(start, _)| start <= c)
+}
+
+const DIGITS: &[(u32, u32)] = &[(0x30, 0x39)];
+const WORD: &[(u32, u32)] = &[(0x30, 0x39), (0x41, 0x5A), (0x5F, 0x5F), (0x61, 0x7A)];
+// WhiteSpace and LineTerminator
+const SPACE: &[(u32, u32)] = &[
+    (0x09, 0x0D),
+    (0x20, 0x20),
+    (0xA0, 0xA0),
+    (0x1680, 0x1680),
+    (0x2000, 0x200A),
+    (0x2028, 0x2029),
+    (0x202F, 0x202F),
+    (0x205F, 0x205F),
+    (0x3000, 0x3000),
+    (0xFEFF, 0xFEFF),
+];
+
+pub fn is_word_char(c: u32) -> bool {
+    in_ranges(WORD, c)
+}
+
+pub fn is_line_terminator(c: u32) -> bool {
+    matches!(c, 0x0A | 0x0D | 0x2028 | 0x2029)
+}
+
+/// A compiled regular expression.
+#[derive(Debug)]
+pub struct Regex {
+    pub node: Node,
+    pub flags: Flags,
+    // Capture groups, not counting the whole match
+    pub group_count: usize,
+    // Named groups with their capture index, in pattern order
+    pub group_names: Vec<(String, usize)>,
+    // Whether the pattern refers back to a group, so later matching can
+    // depend on what a group captured and not just where it ended
+    pub has_backreferences: bool,
+}
+
+impl Regex {
+    /// Parses `pattern`, returning the message for a syntax error.
+    pub fn new(pattern: &str, flags: Flags) -> Result<Regex, String> {
+        // Without the `u` flag, patterns are sequences of code units
+        let chars: Vec<u32> = if flags.unicode {
+            pattern.chars().map(|c| c as u32).collect()
+        } else {
+            pattern.encode_utf16().map(u32::from).collect()
+        };
+
+        let mut parser = Parser {
+            chars,
+            pos: 0,
+            unicode: flags.unicode,
+            group_count: 0,
+            total_groups: 0,
+            group_names: vec![],
+            has_backreferences: false,
+        };
+        parser.scan_groups()?;
+        let node = parser.parse_disjunction()?;
+        if parser.pos < parser.chars.len() {
+            // Only an unmatched `)` stops a disjunction early
+            return Err("Unmatched ')'".to_string());
+        }
+
+        Ok(Regex {
+            node,
+            flags,
+            group_count: parser.total_groups,
+            group_names: parser.group_names,
+            has_backreferences: parser.has_backreferences,
+        })
+    }
+}
+
+struct Parser {
+    chars: Vec<u32>,
+    pos: usize,
+    unicode: bool,
+    // Capture groups opened so far
+    group_count: usize,
+    // Capture groups in the whole pattern, which a backreference may refer to
+    // before the group itself
+    total_groups: usize,
+    group_names: Vec<(String, usize)>,
+    has_backreferences: bool,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<u32> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn peek_is(&self, c: char) -> bool {
+        self.peek() == Some(c as u32)
+    }
+
+    fn peek_nth_is(&self, n: usize, c: char) -> bool {
+        self.chars.get(self.pos + n) == Some(&(c as u32))
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek_is(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Counts the capture groups and collects their names before parsing,
+    /// since `\2` and `\k<name>` may appear before the group they refer to.
+    fn scan_groups(&mut self) -> Result<(), String> {
+        let mut in_class = false;
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            match char::from_u32(c) {
+                Some('\\') => self.pos += 1,
+                Some('[') => in_class = true,
+                Some(']') => in_class = false,
+                Some('(') if !in_class => {
+                    if !self.peek_is('?') {
+                        self.total_groups += 1;
+                    } else if self.peek_nth_is(1, '<')
+                        && !self.peek_nth_is(2, '=')
+                        && !self.peek_nth_is(2, '!')
+                    {
+                        self.total_groups += 1;
+                        self.pos += 2;
+                        let name = self.parse_group_name()?;
+                        if self.group_names.iter().any(|(n, _)| *n == name) {
+                            return Err("Duplicate capture group name".to_string());
+                        }
+                        self.group_names.push((name, self.total_groups));
+                    }
+                }
+                _ => {}
+            }
+        }
+        self.pos = 0;
+        Ok(())
+    }
+
+    /// Parses a group name up to and including its closing `>`.
+    fn parse_group_name(&mut self) -> Result<String, String> {
+        let mut name = String::new();
+        while let Some(c) = self.peek().and_then(char::from_u32) {
+            self.pos += 1;
+            if c == '>' {
+                break;
+            }
+            let valid = if name.is_empty() {
+                c.is_alphabetic() || c == '_' || c == '$'
+            } else {
+                c.is_alphanumeric() || c == '_' || c == '$'
+            };
+            if !valid {
+                return Err("Invalid capture group name".to_string());
+            }
+            name.push(c);
+        }
+        if name.is_empty() || self.chars.get(self.pos - 1) != Some(&('>' as u32)) {
+            return Err("Invalid capture group name".to_string());
+        }
+        Ok(name)
+    }
+
+    fn parse_disjunction(&mut self) -> Result<Node, String> {
+        let mut alternatives = vec![self.parse_alternative()?];
+        while self.eat('|') {
+            alternatives.push(self.parse_alternative()?);
+        }
+        Ok(if alternatives.len() == 1 {
+            alternatives.pop().unwrap()
+        } else {
+            Node::Alternation(alternatives)
+        })
+    }
+
+    fn parse_alternative(&mut self) -> Result<Node, String> {
+        let mut terms = vec![];
+        while let Some(c) = self.peek() {
+            if c == '|' as u32 || c == ')' as u32 {
+                break;
+            }
+            terms.push(self.parse_term()?);
+        }
+        Ok(match terms.len() {
+            0 => Node::Empty,
+            1 => terms.pop().unwrap(),
+            _ => Node::Sequence(terms),
+        })
+    }
+
+    fn parse_term(&mut self) -> Result<Node, String> {
+        let groups_before = self.group_count;
+        let (atom, quantifiable) = self.parse_atom()?;
+
+        let Some((min, max)) = self.parse_quantifier()? else {
+            return Ok(atom);
+        };
+        if !quantifiable {
+            return Err("Nothing to repeat".to_string());
+        }
+        if max.is_some_and(|max| max < min) {
+            return Err("numbers out of order in {} quantifier".to_string());
+        }
+        let greedy = !self.eat('?');
+
+        Ok(Node::Repeat {
+            node: Box::new(atom),
+            min,
+            max,
+            greedy,
+            groups: groups_before + 1..self.group_count + 1,
+        })
+    }
+
+    /// Parses an atom or assertion, and whether a quantifier may follow it.
+    fn parse_atom(&mut self) -> Result<(Node, bool), String> {
+        let c = self.peek().unwrap();
+        self.pos += 1;
+        let node = match char::from_u32(c) {
+            Some('^') => return Ok((Node::LineStart, false)),
+            Some('$') => return Ok((Node::LineEnd, false)),
+            Some('.') => Node::Any,
+            Some('(') => return self.parse_group(),
+            Some('[') => Node::Class(self.parse_class()?),
+            Some('\\') => match self.peek().and_then(char::from_u32) {
+                Some('b') => {
+                    self.pos += 1;
+                    return Ok((Node::WordBoundary { negate: false }, false));
+                }
+                Some('B') => {
+                    self.pos += 1;
+                    return Ok((Node::WordBoundary { negate: true }, false));
+                }
+                _ => self.parse_atom_escape()?,
+            },
+            Some('*' | '+' | '?') => return Err("Nothing to repeat".to_string()),
+            Some('{') => {
+                if self.unicode {
+                    return Err("Lone quantifier brackets".to_string());
+                }
+                // A `{` that starts a valid quantifier has nothing to repeat
+                self.pos -= 1;
+                if self.parse_quantifier()?.is_some() {
+                    return Err("Nothing to repeat".to_string());
+                }
+                self.pos += 1;
+                Node::Char(c)
+            }
+            Some(']' | '}') if self.unicode => return Err("Lone quantifier brackets".to_string()),
+            _ => Node::Char(c),
+        };
+        Ok((node, true))
+    }
+
+    /// Parses a group or lookaround after its `(`.
+    fn parse_group(&mut self) -> Result<(Node, bool), String> {
+        if !self.eat('?') {
+            self.group_count += 1;
+            return self.finish_group(Some(self.group_count));
+        }
+
+        let (behind, negate) = if self.eat('=') {
+            (false, false)
+        } else if self.eat('!') {
+            (false, true)
+        } else if self.eat(':') {
+            return self.finish_group(None);
+        } else if self.eat('<') {
+            if self.eat('=') {
+                (true, false)
+            } else if self.eat('!') {
+                (true, true)
+            } else {
+                // The names were checked by `scan_groups`
+                self.parse_group_name()?;
+                self.group_count += 1;
+                return self.finish_group(Some(self.group_count));
+            }
+        } else {
+            return Err("Invalid group".to_string());
+        };
+
+        let node = Node::Look {
+            node: Box::new(self.parse_group_body()?),
+            behind,
+            negate,
+        };
+        // Lookaheads may be quantified only for web compatibility
+        Ok((node, !behind && !self.unicode))
+    }
+
+    fn finish_group(&mut self, index: Option<usize>) -> Result<(Node, bool), String> {
+        let node = Node::Group {
+            node: Box::new(self.parse_group_body()?),
+            index,
+        };
+        Ok((node, true))
+    }
+
+    fn parse_group_body(&mut self) -> Result<Node, String> {
+        let node = self.parse_disjunction()?;
+        if !self.eat(')') {
+            return Err("Unterminated group".to_string());
+        }
+        Ok(node)
+    }
+
+    /// Parses `*`, `+`, `?` or `{n,m}` if one follows, as a minimum and an
+    /// optional maximum count.
+    fn parse_quantifier(&mut self) -> Result<Option<(usize, Option<usize>)>, String> {
+        let quantifier = match self.peek().and_then(char::from_u32) {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => {
+                let start = self.pos;
+                self.pos += 1;
+                let bounds = self.parse_braced_quantifier();
+                if bounds.is_none() {
+                    self.pos = start;
+                    if self.unicode {
+                        return Err("Incomplete quantifier".to_string());
+                    }
+                }
+                return Ok(bounds);
+            }
+            _ => return Ok(None),
+        };
+        self.pos += 1;
+        Ok(Some(quantifier))
+    }
+
+    fn parse_braced_quantifier(&mut self) -> Option<(usize, Option<usize>)> {
+        let min = self.parse_decimal()?;
+        let max = if self.eat(',') {
+            if self.peek_is('}') {
+                None
+            } else {
+                Some(self.parse_decimal()?)
+            }
+        } else {
+            Some(min)
+        };
+        self.eat('}').then_some((min, max))
+    }
+
+    fn parse_decimal(&mut self) -> Option<usize> {
+        let start = self.pos;
+        let mut value: usize = 0;
+        while let Some(digit) = self
+            .peek()
+            .and_then(char::from_u32)
+            .and_then(|c| c.to_digit(10))
+        {
+            value = value.saturating_mul(10).saturating_add(digit as usize);
+            self.pos += 1;
+        }
+        (self.pos > start).then_some(value)
+    }
+
+    /// Parses the escape after a `\` outside of a class.
+    fn parse_atom_escape(&mut self) -> Result<Node, String> {
+        let Some(c) = self.peek() else {
+            return Err("\\ at end of pattern".to_string());
+        };
+
+        match char::from_u32(c) {
+            Some('1'..='9') => {
+                let start = self.pos;
+                let n = self.parse_decimal().unwrap();
+                if n <= self.total_groups {
+                    self.has_backreferences = true;
+                    return Ok(Node::BackReference(n));
+                }
+                if self.unicode {
+                    return Err("Invalid escape".to_string());
+                }
+                // Not a group, so a legacy octal escape or an identity escape
+                self.pos = start;
+                Ok(Node::Char(self.parse_legacy_octal()))
+            }
+            Some('k') if self.unicode || !self.group_names.is_empty() => {
+                self.pos += 1;
+                if !self.eat('<') {
+                    return Err("Invalid named reference".to_string());
+                }
+                let name = self.parse_group_name()?;
+                match self.group_names.iter().find(|(n, _)| *n == name) {
+                    Some((_, index)) => {
+                        self.has_backreferences = true;
+                        Ok(Node::BackReference(*index))
+                    }
+                    None => Err("Invalid named capture referenced".to_string()),
+                }
+            }
+            _ => Ok(match self.parse_character_escape(false)? {
+                Escape::Char(c) => Node::Char(c),
+                Escape::Set(ranges, negate) => Node::Class(CharClass {
+                    items: vec![ClassItem::Set(ranges, negate)],
+                    negate: false,
+                }),
+            }),
+        }
+    }
+
+    /// Parses an octal escape of up to three digits, with a value of at most
+    /// 0o377. `\8` and `\9` are the digits themselves.
+    fn parse_legacy_octal(&mut self) -> u32 {
+        let first = self.peek().unwrap();
+        self.pos += 1;
+        let Some(mut value) = char::from_u32(first).and_then(|c| c.to_digit(8)) else {
+            return first;
+        };
+        let max_len = if value <= 3 { 3 } else { 2 };
+        for _ in 1..max_len {
+            match self
+                .peek()
+                .and_then(char::from_u32)
+                .and_then(|c| c.to_digit(8))
+            {
+                Some(digit) => {
+                    value = value * 8 + digit;
+                    self.pos += 1;
+                }
+                None => break,
+            }
+        }
+        value
+    }
+
+    /// Parses the escapes allowed both in and out of classes.
+    fn parse_character_escape(&mut self, in_class: bool) -> Result<Escape, String> {
+        let c = self.peek().ok_or("\\ at end of pattern")?;
+        self.pos += 1;
+        let invalid = || Err("Invalid escape".to_string());
+
+        let escaped = match char::from_u32(c) {
+            Some('d') => return Ok(Escape::Set(DIGITS.to_vec(), false)),
+            Some('D') => return Ok(Escape::Set(DIGITS.to_vec(), true)),
+            Some('w') => return Ok(Escape::Set(WORD.to_vec(), false)),
+            Some('W') => return Ok(Escape::Set(WORD.to_vec(), true)),
+            Some('s') => return Ok(Escape::Set(SPACE.to_vec(), false)),
+            Some('S') => return Ok(Escape::Set(SPACE.to_vec(), true)),
+            Some(p @ ('p' | 'P')) if self.unicode => {
+                let ranges = self.parse_property()?;
+                return Ok(Escape::Set(ranges, p == 'P'));
+            }
+            Some('f') => 0x0C,
+            Some('n') => 0x0A,
+            Some('r') => 0x0D,
+            Some('t') => 0x09,
+            Some('v') => 0x0B,
+            Some('b') if in_class => 0x08,
+            Some('-') if in_class && self.unicode => c,
+            Some('c') => match self.peek().and_then(char::from_u32) {
+                Some(letter) if letter.is_ascii_alphabetic() => {
+                    self.pos += 1;
+                    letter as u32 % 32
+                }
+                _ if self.unicode => return invalid(),
+                // `\c` without a letter is a backslash and a `c`
+                _ => {
+                    self.pos -= 1;
+                    '\\' as u32
+                }
+            },
+            Some('0') if !self.peek().is_some_and(|c| (0x30..=0x39).contains(&c)) => 0,
+            Some('0'..='9') if !self.unicode => {
+                self.pos -= 1;
+                self.parse_legacy_octal()
+            }
+            Some('x') => match self.parse_hex(2) {
+                Some(value) => value,
+                None if self.unicode => return invalid(),
+                None => c,
+            },
+            Some('u') => match self.parse_unicode_escape() {
+                Some(value) => value,
+                None if self.unicode => return Err("Invalid Unicode escape".to_string()),
+                None => c,
+            },
+            // With the `u` flag only syntax characters may be escaped
+            Some(
+                '^' | '$' | '\\' | '.' | '*' | '+' | '?' | '(' | ')' | '[' | ']' | '{' | '}' | '|'
+                | '/',
+            ) => c,
+            _ if self.unicode => return invalid(),
+            _ => c,
+        };
+        Ok(Escape::Char(escaped))
+    }
+
+    fn parse_hex(&mut self, count: usize) -> Option<u32> {
+        let start = self.pos;
+        let mut value = 0;
+        for _ in 0..count {
+            match self
+                .peek()
+                .and_then(char::from_u32)
+                .and_then(|c| c.to_digit(16))
+            {
+                Some(digit) => {
+                    value = value * 16 + digit;
+                    self.pos += 1;
+                }
+                None => {
+                    self.pos = start;
+                    return None;
+                }
+            }
+        }
+        Some(value)
+    }
+
+    /// Parses the rest of a `\uXXXX` escape, or with the `u` flag also a
+    /// `\u{...}` escape or a surrogate pair written as two escapes.
+    fn parse_unicode_escape(&mut self) -> Option<u32> {
+        if self.unicode && self.eat('{') {
+            let start = self.pos;
+            let mut value: u32 = 0;
+            while let Some(digit) = self
+                .peek()
+                .and_then(char::from_u32)
+                .and_then(|c| c.to_digit(16))
+            {
+                value = value.saturating_mul(16).saturating_add(digit);
+                self.pos += 1;
+            }
+            if self.pos == start || value > 0x10FFFF || !self.eat('}') {
+                return None;
+            }
+            return Some(value);
+        }
+
+        let value = self.parse_hex(4)?;
+        if self.unicode
+            && (0xD800..0xDC00).contains(&value)
+            && self.peek_is('\\')
+            && self.peek_nth_is(1, 'u')
+        {
+            let start = self.pos;
+            self.pos += 2;
+            match self.parse_hex(4) {
+                Some(low @ 0xDC00..=0xDFFF) => {
+                    return Some(0x10000 + ((value - 0xD800) << 10) + (low - 0xDC00));
+                }
+                _ => self.pos = start,
+            }
+        }
+        Some(value)
+    }
+
+    /// Parses the `{Name}` or `{Name=Value}` of a `\p` escape into the ranges
+    /// of code points with that Unicode property.
+    fn parse_property(&mut self) -> Result<Vec<(u32, u32)>, String> {
+        let invalid = || "Invalid property name".to_string();
+        if !self.eat('{') {
+            return Err(invalid());
+        }
+        let mut query = String::new();
+        loop {
+            match self.peek().and_then(char::from_u32) {
+                Some('}') => {
+                    self.pos += 1;
+                    break;
+                }
+                Some(c) if c.is_ascii_alphanumeric() || c == '_' || c == '=' => {
+                    query.push(c);
+                    self.pos += 1;
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        property_ranges(&query).ok_or_else(invalid)
+    }
+
+    /// Parses a class after its `[`.
+    fn parse_class(&mut self) -> Result<CharClass, String> {
+        let negate = self.eat('^');
+        let mut items = vec![];
+        loop {
+            let Some(c) = self.peek() else {
+                return Err("Unterminated character class".to_string());
+            };
+            if c == ']' as u32 {
+                self.pos += 1;
+                break;
+            }
+
+            let start = self.parse_class_atom()?;
+            if !self.peek_is('-') || self.peek_nth_is(1, ']') || self.pos + 1 >= self.chars.len() {
+                items.push(start.into_item());
+                continue;
+            }
+            self.pos += 1; // eat '-'
+            let end = self.parse_class_atom()?;
+            match (start, end) {
+                (Escape::Char(start), Escape::Char(end)) => {
+                    if start > end {
+                        return Err("Range out of order in character class".to_string());
+                    }
+                    items.push(ClassItem::Range(start, end));
+                }
+                _ if self.unicode => return Err("Invalid character class".to_string()),
+                // A class escape cannot bound a range, so the `-` is literal
+                (start, end) => {
+                    items.push(start.into_item());
+                    items.push(ClassItem::Range('-' as u32, '-' as u32));
+                    items.push(end.into_item());
+                }
+            }
+        }
+        Ok(CharClass { items, negate })
+    }
+
+    fn parse_class_atom(&mut self) -> Result<Escape, String> {
+        let c = self.peek().ok_or("Unterminated character class")?;
+        self.pos += 1;
+        if c != '\\' as u32 {
+            return Ok(Escape::Char(c));
+        }
+        if self.unicode && self.peek().is_some_and(|c| (0x31..=0x39).contains(&c)) {
+            return Err("Invalid class escape".to_string());
+        }
+        self.parse_character_escape(true)
+    }
+}
+
+enum Escape {
+    Char(u32),
+    Set(Vec<(u32, u32)>, bool),
+}
+
+impl Escape {
+    fn into_item(self) -> ClassItem {
+        match self {
+            Escape::Char(c) => ClassItem::Range(c, c),
+            Escape::Set(ranges, negate) => ClassItem::Set(ranges, negate),
+        }
+    }
+}
+
+/// Looks up a Unicode property such as `Letter`, `Script=Greek` or
+/// `Emoji` in the tables of `regex-syntax`.
+fn property_ranges(query: &str) -> Option<Vec<(u32, u32)>> {
+    use regex_syntax::hir::{Class, HirKind};
+
+    let (name, value) = match query.split_once('=') {
+        Some((name, value)) => (name, Some(value)),
+        None => (query, None),
+    };
+    if name.is_empty() || value.is_some_and(|v| v.is_empty() || v.contains('=')) {
+        return None;
+    }
+    let query = match (name, value) {
+        ("General_Category" | "gc", Some(value)) => value.to_string(),
+        ("Script" | "sc", Some(value)) => format!("Script={}", value),
+        ("Script_Extensions" | "scx", Some(value)) => format!("Script_Extensions={}", value),
+        (_, Some(_)) => return None,
+        (name, None) => name.to_string(),
+    };
+
+    let hir = regex_syntax::Parser::new()
+        .parse(&format!("\\p{{{}}}", query))
+        .ok()?;
+    match hir.kind() {
+        HirKind::Class(Class::Unicode(class)) => Some(
+            class
+                .ranges()
+                .iter()
+                .map(|range| (range.start() as u32, range.end() as u32))
+                .collect(),
+        ),
+        _ => None,
+    }
+}
@@ -0,0 +1,327 @@
+//! Methods available on every string value.
+
+use crate::regexp::{
+    advance_index, as_regexp, create_regexp, exec_raw, find, match_result, regex_of,
+    set_last_index, Captures, Regex,
+};
+use shadowjs_gc::Gc;
+use shadowjs_value::{to_number, to_string, to_uint32, Value};
+use shadowjs_vm::{create_error, VM};
+use std::rc::Rc;
+
+pub fn init_string_methods(vm: &mut VM) {
+    vm.define_string_method("match", string_match);
+    vm.define_string_method("replace", string_replace);
+    vm.define_string_method("split", string_split);
+}
+
+/// `str.match(regexp)`: the `exec` result, or with the `g` flag an array of
+/// every matched string. A non-RegExp argument is used as a pattern.
+fn string_match(this: Value, args: Vec<Value>) -> Result<Value, Value> {
+    let input = this_string(&this, "match")?;
+    let regexp = match args.first() {
+        Some(value) if as_regexp(value).is_some() => value.clone(),
+        None | Some(Value::Undefined) => create_regexp("", "")?,
+        Some(value) => create_regexp(&to_string(value), "")?,
+    };
+    let regexp = as_regexp(&regexp).unwrap();
+    let regex = regex_of(&regexp)?;
+    let units: Vec<u16> = input.encode_utf16().collect();
+
+    if !regex.flags.global {
+        return Ok(match exec_raw(&regexp, &units)? {
+            Some(captures) => match_result(&regex, &captures, &input, &units),
+            None => Value::Null,
+        });
+    }
+
+    set_last_index(&regexp, 0);
+    let mut matches = vec![];
+    while let Some(captures) = exec_raw(&regexp, &units)? {
+        let (start, end) = captures[0].unwrap();
+        matches.push(substring(&units, start, end));
+        // An empty match would be found again at the same index
+        if start == end {
+            set_last_index(&regexp, advance_index(&units, end, regex.flags.unicode));
+        }
+    }
+    Ok(if matches.is_empty() {
+        Value::Null
+    } else {
+        Value::Array(Gc::new(matches))
+    })
+}
+
+/// `str.replace(pattern, replacement)`: replaces the first match of a
+/// string or RegExp, or every match with the `g` flag. The replacement may
+/// use `$&`, `$1`, `$<name>` and the other `$` patterns.
+fn string_replace(this: Value, args: Vec<Value>) -> Result<Value, Value> {
+    let input = this_string(&this, "replace")?;
+    let pattern = args.first().unwrap_or(&Value::Undefined);
+    let replacement = args.get(1).unwrap_or(&Value::Undefined);
+    // Natives cannot call back into the interpreter yet
+    if matches!(replacement, Value::Function(_) | Value::NativeFunction(_)) {
+        return Err(create_error(
+            "TypeError",
+            "Replacement functions are not supported yet",
+        ));
+    }
+    let replacement: Vec<u16> = to_string(replacement).encode_utf16().collect();
+    let units: Vec<u16> = input.encode_utf16().collect();
+
+    let mut matches = vec![];
+    let mut regex = None;
+    match as_regexp(pattern) {
+        Some(regexp) => {
+            let compiled = regex_of(&regexp)?;
+            if compiled.flags.global {
+                set_last_index(&regexp, 0);
+                while let Some(captures) = exec_raw(&regexp, &units)? {
+                    let (start, end) = captures[0].unwrap();
+                    if start == end {
+                        let next = advance_index(&units, end, compiled.flags.unicode);
+                        set_last_index(&regexp, next);
+                    }
+                    matches.push(captures);
+                }
+            } else {
+                matches.extend(exec_raw(&regexp, &units)?);
+            }
+            regex = Some(compiled);
+        }
+        None => {
+            let search: Vec<u16> = to_string(pattern).encode_utf16().collect();
+            if let Some(start) = find_units(&units, &search) {
+                matches.push(vec![Some((start, start + search.len()))]);
+            }
+        }
+    }
+
+    let mut result = Vec::with_capacity(units.len());
+    let mut last_end = 0;
+    for captures in &matches {
+        let (start, end) = captures[0].unwrap();
+        result.extend_from_slice(&units[last_end..start]);
+        substitute(
+            &mut result,
+            &replacement,
+            &units,
+            captures,
+            regex.as_deref(),
+        );
+        last_end = end;
+    }
+    result.extend_from_slice(&units[last_end..]);
+    Ok(Value::String(Rc::new(String::from_utf16_lossy(&result))))
+}
+
+/// Appends `replacement` for a match, expanding its `$` patterns
+/// (GetSubstitution in the spec).
+fn substitute(
+    result: &mut Vec<u16>,
+    replacement: &[u16],
+    input: &[u16],
+    captures: &Captures,
+    regex: Option<&Regex>,
+) {
+    let (start, end) = captures[0].unwrap();
+    let group_count = captures.len() - 1;
+    let digit = |i: usize| {
+        replacement
+            .get(i)
+            .and_then(|&unit| char::from_u32(u32::from(unit)))
+            .and_then(|c| c.to_digit(10))
+            .map(|d| d as usize)
+    };
+    let push_capture = |result: &mut Vec<u16>, index: usize| {
+        if let Some((start, end)) = captures[index] {
+            result.extend_from_slice(&input[start..end]);
+        }
+    };
+
+    let mut i = 0;
+    while i < replacement.len() {
+        let unit = replacement[i];
+        if unit != u16::from(b'$') || i + 1 == replacement.len() {
+            result.push(unit);
+            i += 1;
+            continue;
+        }
+
+        let next = replacement[i + 1];
+        let consumed = match u8::try_from(next).map(char::from) {
+            Ok('$') => {
+                result.push(unit);
+                2
+            }
+            Ok('&') => {
+                result.extend_from_slice(&input[start..end]);
+                2
+            }
+            Ok('`') => {
+                result.extend_from_slice(&input[..start]);
+                2
+            }
+            Ok('\'') => {
+                result.extend_from_slice(&input[end..]);
+                2
+            }
+            Ok('0'..='9') => {
+                // Two digits are a group number if there is such a group
+                let one = digit(i + 1).unwrap();
+                match digit(i + 2).map(|two| one * 10 + two) {
+                    Some(n) if (1..=group_count).contains(&n) => {
+                        push_capture(result, n);
+                        3
+                    }
+                    _ if (1..=group_count).contains(&one) => {
+                        push_capture(result, one);
+                        2
+                    }
+                    _ => {
+                        result.push(unit);
+                        1
+                    }
+                }
+            }
+            Ok('<') => {
+                let close = replacement[i + 2..]
+                    .iter()
+                    .position(|&unit| unit == u16::from(b'>'));
+                match (regex, close) {
+                    (Some(regex), Some(close)) if !regex.group_names.is_empty() => {
+                        let name = String::from_utf16_lossy(&replacement[i + 2..i + 2 + close]);
+                        if let Some((_, index)) = regex.group_names.iter().find(|(n, _)| *n == name)
+                        {
+                            push_capture(result, *index);
+                        }
+                        close + 3
+                    }
+                    _ => {
+                        result.push(unit);
+                        1
+                    }
+                }
+            }
+            _ => {
+                result.push(unit);
+                1
+            }
+        };
+        i += consumed;
+    }
+}
+
+/// `str.split(separator, limit)`: the pieces between each match of a string
+/// or RegExp separator. Capture groups of a RegExp are included too.
+fn string_split(this: Value, args: Vec<Value>) -> Result<Value, Value> {
+    let input = this_string(&this, "split")?;
+    let separator = args.first().unwrap_or(&Value::Undefined);
+    let limit = match args.get(1) {
+        None | Some(Value::Undefined) => u32::MAX,
+        Some(limit) => to_uint32(to_number(limit)),
+    } as usize;
+    let units: Vec<u16> = input.encode_utf16().collect();
+
+    if let Some(regexp) = as_regexp(separator) {
+        let regex = regex_of(&regexp)?;
+        return Ok(Value::Array(Gc::new(split_regex(&regex, &units, limit)?)));
+    }
+
+    let mut parts = vec![];
+    if limit == 0 {
+        return Ok(Value::Array(Gc::new(parts)));
+    }
+    if let Value::Undefined = separator {
+        parts.push(Value::String(input));
+        return Ok(Value::Array(Gc::new(parts)));
+    }
+
+    let separator: Vec<u16> = to_string(separator).encode_utf16().collect();
+    if separator.is_empty() {
+        parts.extend((0..units.len().min(limit)).map(|i| substring(&units, i, i + 1)));
+        return Ok(Value::Array(Gc::new(parts)));
+    }
+    let mut start = 0;
+    while let Some(offset) = find_units(&units[start..], &separator) {
+        parts.push(substring(&units, start, start + offset));
+        if parts.len() == limit {
+            return Ok(Value::Array(Gc::new(parts)));
+        }
+        start += offset + separator.len();
+    }
+    parts.push(substring(&units, start, units.len()));
+    Ok(Value::Array(Gc::new(parts)))
+}
+
+/// Splits at each match of `regex`, which is tried at every index as if it
+/// were sticky (the spec's @@split).
+fn split_regex(regex: &Regex, units: &[u16], limit: usize) -> Result<Vec<Value>, Value> {
+    let mut parts = vec![];
+    if limit == 0 {
+        return Ok(parts);
+    }
+    if units.is_empty() {
+        if find(regex, units, 0, true)?.is_none() {
+            parts.push(substring(units, 0, 0));
+        }
+        return Ok(parts);
+    }
+
+    let unicode = regex.flags.unicode;
+    let mut last_end = 0;
+    let mut pos = 0;
+    while pos < units.len() {
+        let Some(captures) = find(regex, units, pos, true)? else {
+            pos = advance_index(units, pos, unicode);
+            continue;
+        };
+        let end = captures[0].unwrap().1.min(units.len());
+        if end == last_end {
+            pos = advance_index(units, pos, unicode);
+            continue;
+        }
+
+        parts.push(substring(units, last_end, pos));
+        if parts.len() == limit {
+            return Ok(parts);
+        }
+        for capture in &captures[1..] {
+            parts.push(match capture {
+                Some((start, end)) => substring(units, *start, *end),
+                None => Value::Undefined,
+            });
+            if parts.len() == limit {
+                return Ok(parts);
+            }
+        }
+        last_end = end;
+        pos = end;
+    }
+    parts.push(substring(units, last_end, units.len()));
+    Ok(parts)
+}
+
+fn this_string(this: &Value, method: &str) -> Result<Rc<String>, Value> {
+    match this {
+        Value::String(s) => Ok(s.clone()),
+        Value::Null | Value::Undefined => Err(create_error(
+            "TypeError",
+            &format!("String.prototype.{} called on null or undefined", method),
+        )),
+        value => Ok(Rc::new(to_string(value))),
+    }
+}
+
+fn find_units(haystack: &[u16], needle: &[u16]) -> Option<usize> {
+    if needle.is_empty() {
+        return Some(0);
+    }
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn substring(units: &[u16], start: usize, end: usize) -> Value {
+    Value::String(Rc::new(String::from_utf16_lossy(&units[start..end])))
+}
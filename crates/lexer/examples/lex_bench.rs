@@ -0,0 +1,69 @@
+//! Measures lexer throughput on a multi-megabyte script.
+//!
+//! Run with `cargo run --release -p shadowjs-lexer --example lex_bench`,
+//! optionally followed by the path of a script to lex instead of the
+//! generated one.
+
+use shadowjs_lexer::{Lexer, TokenType};
+use std::time::{Duration, Instant};
+
+// A slice of typical code, repeated to build the input
+const SNIPPET: &str = r#"
+// Adds up the prices of the items in a cart
+function cartTotal(items, discount) {
+    let total = 0;
+    for (let i = 0; i < items.length; i++) {
+        const item = items[i];
+        total += item.price * item.quantity;
+    }
+    /* Discounts are stored as a percentage */
+    if (discount !== null && discount > 0) {
+        total = total - total * (discount / 100);
+    }
+    return total;
+}
+
+const config = { name: "shadow", version: 0x1F, ratio: 1.5e-3, tags: ['a', 'b\n'] };
+let message = `Total: ${cartTotal(config.tags, 10)} for ${config.name}`;
+const pattern = /^[a-z_$][\w$]*$/gi;
+while (pattern.test(message) || config.version >>> 2 >= 1_000) {
+    config.version--;
+}
+"#;
+
+const TARGET_SIZE: usize = 8 * 1024 * 1024;
+const RUNS: usize = 5;
+
+fn main() {
+    let input = match std::env::args().nth(1) {
+        Some(path) => std::fs::read_to_string(&path).expect("could not read the script"),
+        None => SNIPPET.repeat(TARGET_SIZE / SNIPPET.len() + 1),
+    };
+
+    let mut best = Duration::MAX;
+    let mut tokens = 0;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        tokens = lex_all(&input);
+        best = best.min(start.elapsed());
+    }
+
+    let megabytes = input.len() as f64 / (1024.0 * 1024.0);
+    println!(
+        "{:.1} MB, {} tokens in {:.1} ms: {:.1} MB/s, {:.1} M tokens/s",
+        megabytes,
+        tokens,
+        best.as_secs_f64() * 1000.0,
+        megabytes / best.as_secs_f64(),
+        tokens as f64 / best.as_secs_f64() / 1_000_000.0
+    );
+}
+
+fn lex_all(input: &str) -> usize {
+    let mut lexer = Lexer::new(input);
+    let mut tokens = 0;
+    while lexer.next_token().token_type != TokenType::EOF {
+        tokens += 1;
+    }
+    tokens
+}
@@ -0,0 +1,31 @@
+use rustc_hash::FxHashMap;
+use std::borrow::Cow;
+
+/// An interned identifier name. Two symbols from the same interner are
+/// equal exactly when their names are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Symbol(u32);
+
+/// Deduplicates identifier names. Names are borrowed from the source, except
+/// those written with escapes, which differ from their source text.
+#[derive(Debug, Default)]
+pub struct Interner<'a> {
+    symbols: FxHashMap<Cow<'a, str>, Symbol>,
+    names: Vec<Cow<'a, str>>,
+}
+
+impl<'a> Interner<'a> {
+    pub fn intern(&mut self, name: Cow<'a, str>) -> Symbol {
+        if let Some(&symbol) = self.symbols.get(name.as_ref()) {
+            return symbol;
+        }
+        let symbol = Symbol(self.names.len() as u32);
+        self.names.push(name.clone());
+        self.symbols.insert(name, symbol);
+        symbol
+    }
+
+    pub fn resolve(&self, symbol: Symbol) -> &str {
+        &self.names[symbol.0 as usize]
+    }
+}
@@ -1,59 +1,146 @@
-use crate::token::{Token, TokenType};
+use crate::interner::{Interner, Symbol};
+use crate::token::{TemplateChunk, Token, TokenType};
+use shadowjs_ast::{Diagnostic, Position, Span};
+use std::borrow::Cow;
 
-pub struct Lexer {
-    input: Vec<char>,
+/// What an open `(` begins, which decides what may follow its `)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Paren {
+    // The head of an `if`, `while` or `for`, followed by a statement
+    StatementHead,
+    // The parameters of a function, followed by its body
+    Parameters { expression: bool },
+    Other,
+}
+
+/// Scans UTF-8 source text in place. Positions are byte offsets, and tokens
+/// borrow their text from the source.
+pub struct Lexer<'a> {
+    input: &'a str,
+    // Byte offsets of the current character and of the one after it
     position: usize,
     read_position: usize,
     ch: char,
     line: usize,
     column: usize,
+    // Line the previous token ended on
+    last_line: usize,
+    // Braces opened and not yet closed
+    brace_depth: usize,
+    // The brace depth at each open template substitution, so the `}` ending
+    // one resumes the template instead of closing a block
+    template_depths: Vec<usize>,
+    // Whether a `/` here starts a regular expression rather than a division,
+    // which depends on the token before it
+    regex_allowed: bool,
+    // Whether the last token was `if`, `while` or `for`, whose `(…)` head is
+    // followed by a statement rather than an operator
+    after_statement_keyword: bool,
+    // What each open `(` begins
+    paren_heads: Vec<Paren>,
+    // Whether the last token ended a statement or began one, such as `;`,
+    // `else` or the `{` of a block
+    statement_start: bool,
+    // Set by `function` until the `(` of its parameters: whether it begins
+    // a function expression rather than a declaration
+    function_expression: Option<bool>,
+    // Whether a `{` after the last token would begin an expression: an
+    // object literal, or the body of a function expression
+    brace_opens_expression: bool,
+    // For each open `{`, whether it began an expression, so the `}` closing
+    // it ends an operand rather than a block
+    brace_expressions: Vec<bool>,
+    interner: Interner<'a>,
+    errors: Vec<Diagnostic>,
 }
 
-impl Lexer {
-    pub fn new(input: &str) -> Self {
+impl<'a> Lexer<'a> {
+    pub fn new(input: &'a str) -> Self {
         let mut l = Self {
-            input: input.chars().collect(),
+            input,
             position: 0,
             read_position: 0,
             ch: '\0',
             line: 1,
             column: 0,
+            last_line: 1,
+            brace_depth: 0,
+            template_depths: vec![],
+            regex_allowed: true,
+            after_statement_keyword: false,
+            paren_heads: vec![],
+            statement_start: true,
+            function_expression: None,
+            brace_opens_expression: false,
+            brace_expressions: vec![],
+            interner: Interner::default(),
+            errors: vec![],
         };
         l.read_char();
         l
     }
 
     fn read_char(&mut self) {
-        if self.read_position >= self.input.len() {
-            self.ch = '\0';
-        } else {
-            self.ch = self.input[self.read_position];
+        // A \r\n pair is one line terminator, counted at the \n
+        let crlf = self.ch == '\r' && self.input.as_bytes().get(self.read_position) == Some(&b'\n');
+        if is_line_terminator(self.ch) && !crlf {
+            self.line += 1;
+            self.column = 0;
         }
         self.position = self.read_position;
-        self.read_position += 1;
+        self.ch = match self.input.as_bytes().get(self.position) {
+            None => '\0',
+            Some(&byte) if byte.is_ascii() => byte as char,
+            Some(_) => self.input[self.position..].chars().next().unwrap(),
+        };
+        // Stay at the end of the input once it is reached
+        self.read_position = (self.position + self.ch.len_utf8()).min(self.input.len());
         self.column += 1;
     }
 
     fn peek_char(&self) -> char {
-        if self.read_position >= self.input.len() {
-            '\0'
-        } else {
-            self.input[self.read_position]
+        self.peek_nth_char(0)
+    }
+
+    fn peek_nth_char(&self, n: usize) -> char {
+        self.input[self.read_position..]
+            .chars()
+            .nth(n)
+            .unwrap_or('\0')
+    }
+
+    /// The source text from byte offset `start` up to the current character.
+    fn slice_from(&self, start: usize) -> &'a str {
+        &self.input[start..self.position]
+    }
+
+    /// The name of an identifier token.
+    pub fn resolve(&self, symbol: Symbol) -> &str {
+        self.interner.resolve(symbol)
+    }
+
+    /// Consumes the characters after the current one if they spell out `rest`.
+    fn match_chars(&mut self, rest: &str) -> bool {
+        let matches = rest
+            .chars()
+            .enumerate()
+            .all(|(i, c)| self.peek_nth_char(i) == c);
+        if matches {
+            for _ in rest.chars() {
+                self.read_char();
+            }
         }
+        matches
     }
 
     fn skip_whitespace(&mut self) {
-        while self.ch.is_whitespace() {
-            if self.ch == '\n' {
-                self.line += 1;
-                self.column = 0;
-            }
+        while is_whitespace(self.ch) || is_line_terminator(self.ch) {
             self.read_char();
         }
     }
 
     fn skip_comment(&mut self) {
-        while self.ch != '\n' && self.ch != '\0' {
+        while !is_line_terminator(self.ch) && self.ch != '\0' {
             self.read_char();
         }
         self.skip_whitespace();
@@ -73,103 +160,906 @@ impl Lexer {
         self.skip_whitespace();
     }
 
-    pub fn next_token(&mut self) -> Token {
+    /// Makes a `/` starting the next token begin a regular expression, for
+    /// the parser to call after a contextual keyword such as `of`, which is
+    /// lexed as an identifier.
+    pub fn expect_operand(&mut self) {
+        self.regex_allowed = true;
+    }
+
+    /// Takes the diagnostics for malformed tokens lexed since the last call.
+    pub fn take_errors(&mut self) -> Vec<Diagnostic> {
+        std::mem::take(&mut self.errors)
+    }
+
+    fn current_position(&self) -> Position {
+        Position::new(self.line, self.column, self.position)
+    }
+
+    /// The span from `start` to just past the character before the current one.
+    fn span_from(&self, start: Position) -> Span {
+        Span::new(start, self.current_position())
+    }
+
+    pub fn next_token(&mut self) -> Token<'a> {
+        let mut token = self.read_token();
+        token.newline_before = token.span.start.line > self.last_line;
+        self.last_line = token.span.end.line;
+        // After an operand, `/` divides it; anywhere else it starts a regular
+        // expression. A `)` closing the head of an `if`, `while` or `for` is
+        // followed by a statement, and so is a `}` closing a block, unlike
+        // one closing an object literal or a function expression.
+        let in_expression = self.regex_allowed && !self.statement_start;
+        let mut closes_operand = false;
+        let mut closed_paren = Paren::Other;
+        self.statement_start = false;
+        match token.token_type {
+            TokenType::Function => self.function_expression = Some(in_expression),
+            TokenType::LParen => {
+                let paren = match self.function_expression.take() {
+                    Some(expression) => Paren::Parameters { expression },
+                    None if self.after_statement_keyword => Paren::StatementHead,
+                    None => Paren::Other,
+                };
+                self.paren_heads.push(paren);
+            }
+            TokenType::RParen => {
+                closed_paren = self.paren_heads.pop().unwrap_or(Paren::Other);
+                self.statement_start = closed_paren == Paren::StatementHead;
+            }
+            TokenType::LBrace => {
+                self.brace_expressions.push(self.brace_opens_expression);
+                self.statement_start = !self.brace_opens_expression;
+            }
+            TokenType::RBrace => {
+                closes_operand = self.brace_expressions.pop().unwrap_or(false);
+                self.statement_start = !closes_operand;
+            }
+            TokenType::SemiColon
+            | TokenType::Else
+            | TokenType::Do
+            | TokenType::Try
+            | TokenType::Finally => self.statement_start = true,
+            _ => {}
+        }
+        self.after_statement_keyword = matches!(
+            token.token_type,
+            TokenType::If | TokenType::While | TokenType::For
+        );
+        self.regex_allowed = !closes_operand
+            && (self.statement_start
+                || !matches!(
+                    token.token_type,
+                    TokenType::Identifier(_)
+                        | TokenType::Number(_)
+                        | TokenType::BigInt(_)
+                        | TokenType::String(_)
+                        | TokenType::RegExp { .. }
+                        | TokenType::NoSubstitutionTemplate(_)
+                        | TokenType::TemplateTail(_)
+                        | TokenType::True
+                        | TokenType::False
+                        | TokenType::Null
+                        | TokenType::This
+                        | TokenType::RParen
+                        | TokenType::RBracket
+                        | TokenType::PlusPlus
+                        | TokenType::MinusMinus
+                ));
+        self.brace_opens_expression = match token.token_type {
+            TokenType::RParen => closed_paren == Paren::Parameters { expression: true },
+            // An arrow function's body is a block
+            TokenType::Arrow => false,
+            _ => self.regex_allowed && !self.statement_start,
+        };
+        token
+    }
+
+    fn read_token(&mut self) -> Token<'a> {
         self.skip_whitespace();
 
+        let start = self.position;
+        let start_position = self.current_position();
         let token_type = match self.ch {
             '=' => {
-                if self.peek_char() == '=' {
-                    self.read_char();
+                if self.match_chars("==") {
+                    TokenType::StrictEqual
+                } else if self.match_chars("=") {
                     TokenType::Equal
+                } else if self.match_chars(">") {
+                    TokenType::Arrow
                 } else {
                     TokenType::Assign
                 }
             }
-            '+' => TokenType::Plus,
-            '-' => TokenType::Minus,
-            '*' => TokenType::Star,
+            '!' => {
+                if self.match_chars("==") {
+                    TokenType::StrictNotEqual
+                } else if self.match_chars("=") {
+                    TokenType::NotEqual
+                } else {
+                    TokenType::Bang
+                }
+            }
+            '+' => {
+                if self.match_chars("+") {
+                    TokenType::PlusPlus
+                } else if self.match_chars("=") {
+                    TokenType::PlusAssign
+                } else {
+                    TokenType::Plus
+                }
+            }
+            '-' => {
+                if self.match_chars("-") {
+                    TokenType::MinusMinus
+                } else if self.match_chars("=") {
+                    TokenType::MinusAssign
+                } else {
+                    TokenType::Minus
+                }
+            }
+            '*' => {
+                if self.match_chars("*=") {
+                    TokenType::StarStarAssign
+                } else if self.match_chars("*") {
+                    TokenType::StarStar
+                } else if self.match_chars("=") {
+                    TokenType::StarAssign
+                } else {
+                    TokenType::Star
+                }
+            }
+            '%' => {
+                if self.match_chars("=") {
+                    TokenType::PercentAssign
+                } else {
+                    TokenType::Percent
+                }
+            }
+            '&' => {
+                if self.match_chars("&=") {
+                    TokenType::AndAndAssign
+                } else if self.match_chars("&") {
+                    TokenType::AndAnd
+                } else if self.match_chars("=") {
+                    TokenType::AmpersandAssign
+                } else {
+                    TokenType::Ampersand
+                }
+            }
+            '|' => {
+                if self.match_chars("|=") {
+                    TokenType::OrOrAssign
+                } else if self.match_chars("|") {
+                    TokenType::OrOr
+                } else if self.match_chars("=") {
+                    TokenType::PipeAssign
+                } else {
+                    TokenType::Pipe
+                }
+            }
+            '^' => {
+                if self.match_chars("=") {
+                    TokenType::CaretAssign
+                } else {
+                    TokenType::Caret
+                }
+            }
+            '?' => {
+                if self.match_chars("?=") {
+                    TokenType::NullishAssign
+                } else if self.match_chars("?") {
+                    TokenType::Nullish
+                } else {
+                    TokenType::Question
+                }
+            }
+            '~' => TokenType::Tilde,
             '/' => {
                 if self.peek_char() == '/' {
                     self.skip_comment();
-                    return self.next_token();
+                    return self.read_token();
                 } else if self.peek_char() == '*' {
                     self.skip_multiline_comment();
-                    return self.next_token();
+                    return self.read_token();
+                } else if self.regex_allowed {
+                    return self.read_regexp();
+                } else if self.match_chars("=") {
+                    TokenType::SlashAssign
                 } else {
                     TokenType::Slash
                 }
             }
-            '<' => TokenType::LessThan,
-            '>' => TokenType::GreaterThan,
+            '<' => {
+                if self.match_chars("<=") {
+                    TokenType::ShiftLeftAssign
+                } else if self.match_chars("<") {
+                    TokenType::ShiftLeft
+                } else if self.match_chars("=") {
+                    TokenType::LessEqual
+                } else {
+                    TokenType::LessThan
+                }
+            }
+            '>' => {
+                if self.match_chars(">>=") {
+                    TokenType::UnsignedShiftRightAssign
+                } else if self.match_chars(">>") {
+                    TokenType::UnsignedShiftRight
+                } else if self.match_chars(">=") {
+                    TokenType::ShiftRightAssign
+                } else if self.match_chars(">") {
+                    TokenType::ShiftRight
+                } else if self.match_chars("=") {
+                    TokenType::GreaterEqual
+                } else {
+                    TokenType::GreaterThan
+                }
+            }
             ';' => TokenType::SemiColon,
             '(' => TokenType::LParen,
             ')' => TokenType::RParen,
-            '{' => TokenType::LBrace,
-            '}' => TokenType::RBrace,
+            '{' => {
+                self.brace_depth += 1;
+                TokenType::LBrace
+            }
+            '}' if self.template_depths.last() == Some(&self.brace_depth) => {
+                self.template_depths.pop();
+                return self.read_template();
+            }
+            '}' => {
+                self.brace_depth = self.brace_depth.saturating_sub(1);
+                TokenType::RBrace
+            }
             '[' => TokenType::LBracket,
             ']' => TokenType::RBracket,
             ':' => TokenType::Colon,
             ',' => TokenType::Comma,
-            '.' => TokenType::Dot,
+            '.' if self.peek_char().is_ascii_digit() => return self.read_number(),
+            '.' => {
+                if self.match_chars("..") {
+                    TokenType::Ellipsis
+                } else {
+                    TokenType::Dot
+                }
+            }
             '\0' => TokenType::EOF,
             '"' | '\'' => return self.read_string(self.ch),
-            c if c.is_alphabetic() || c == '_' => return self.read_identifier(),
-            c if c.is_digit(10) => return self.read_number(),
-            _ => TokenType::Illegal,
+            '`' => return self.read_template(),
+            c if is_identifier_start(c) || c == '\\' => return self.read_identifier(),
+            c if c.is_ascii_digit() => return self.read_number(),
+            _ => {
+                let end = Position::new(
+                    self.line,
+                    self.column + 1,
+                    self.position + self.ch.len_utf8(),
+                );
+                let span = Span::new(start_position, end);
+                self.errors
+                    .push(Diagnostic::error(span, "Invalid or unexpected token"));
+                TokenType::Illegal
+            }
         };
 
-        let literal = if token_type == TokenType::EOF {
-            "".to_string()
-        } else {
-            self.ch.to_string()
+        self.read_char();
+        Token::new(
+            token_type,
+            self.slice_from(start),
+            self.span_from(start_position),
+        )
+    }
+
+    /// Reads an identifier or keyword. Identifiers may contain `\u` escapes
+    /// for characters that could be written directly; keywords may not.
+    fn read_identifier(&mut self) -> Token<'a> {
+        let position = self.position;
+        let start = self.current_position();
+
+        // The name is borrowed unless an escape makes it differ from the source
+        let mut copy: Option<String> = None;
+        let mut error = None;
+        loop {
+            if self.ch == '\\' {
+                let first = self.position == position;
+                let copy = copy.get_or_insert_with(|| self.slice_from(position).to_string());
+                self.read_char(); // eat '\'
+                let decoded = if self.ch == 'u' {
+                    self.read_char(); // eat 'u'
+                    self.read_unicode_escape().ok()
+                } else {
+                    None
+                };
+                match decoded {
+                    Some(c) if first && is_identifier_start(c) => copy.push(c),
+                    Some(c) if !first && is_identifier_part(c) => copy.push(c),
+                    _ => {
+                        error.get_or_insert("Invalid Unicode escape sequence");
+                    }
+                }
+            } else if is_identifier_part(self.ch) {
+                if let Some(copy) = &mut copy {
+                    copy.push(self.ch);
+                }
+                self.read_char();
+            } else {
+                break;
+            }
+        }
+
+        let literal = self.slice_from(position);
+        let span = self.span_from(start);
+        let escaped = copy.is_some();
+        let name = copy.map_or(Cow::Borrowed(literal), Cow::Owned);
+        let token_type = match (keyword(&name), error) {
+            (_, Some(message)) => {
+                self.errors.push(Diagnostic::error(span, message));
+                TokenType::Illegal
+            }
+            (Some(_), None) if escaped => {
+                self.errors.push(Diagnostic::error(
+                    span,
+                    "Keyword must not contain escaped characters",
+                ));
+                TokenType::Illegal
+            }
+            (Some(keyword), None) => keyword,
+            (None, None) => TokenType::Identifier(self.interner.intern(name)),
         };
+        Token::new(token_type, literal, span)
+    }
 
-        let token = Token::new(token_type, literal, self.line, self.column);
-        self.read_char();
-        token
+    fn read_number(&mut self) -> Token<'a> {
+        let position = self.position;
+        let start = self.current_position();
+
+        // The literal must not run straight into an identifier or another
+        // digit, as in `3in` or `0b12`
+        let token_type = self.scan_number().and_then(|token_type| {
+            if is_identifier_start(self.ch) || self.ch == '\\' || self.ch.is_ascii_digit() {
+                Err("Invalid or unexpected token".to_string())
+            } else {
+                Ok(token_type)
+            }
+        });
+        let token_type = token_type.unwrap_or_else(|message| {
+            // Skip the rest of the malformed literal so it is reported once
+            while is_identifier_part(self.ch) || self.ch == '.' {
+                self.read_char();
+            }
+            self.errors
+                .push(Diagnostic::error(self.span_from(start), message));
+            TokenType::Illegal
+        });
+
+        Token::new(token_type, self.slice_from(position), self.span_from(start))
+    }
+
+    /// Scans a NumericLiteral, starting at its first digit or its leading `.`.
+    fn scan_number(&mut self) -> Result<TokenType<'a>, String> {
+        if self.ch == '0' {
+            let radix = match self.peek_char() {
+                'x' | 'X' => Some((16, "hexadecimal")),
+                'o' | 'O' => Some((8, "octal")),
+                'b' | 'B' => Some((2, "binary")),
+                _ => None,
+            };
+            if let Some((radix, name)) = radix {
+                self.read_char(); // eat '0'
+                self.read_char(); // eat prefix
+                let digits = self.scan_digits(radix)?;
+                if digits.is_empty() {
+                    return Err(format!(
+                        "Invalid or unexpected token: missing {} digits",
+                        name
+                    ));
+                }
+                if self.ch == 'n' {
+                    self.read_char();
+                    let prefix = match radix {
+                        16 => "0x",
+                        8 => "0o",
+                        _ => "0b",
+                    };
+                    let digits = without_separators(digits);
+                    return Ok(TokenType::BigInt(Cow::Owned(format!(
+                        "{}{}",
+                        prefix, digits
+                    ))));
+                }
+                // Accumulate in floating point so long literals lose precision
+                // instead of overflowing
+                let value = digits.chars().filter(|&c| c != '_').fold(0.0, |acc, c| {
+                    acc * radix as f64 + c.to_digit(radix).unwrap() as f64
+                });
+                return Ok(TokenType::Number(value));
+            }
+
+            if self.peek_char() == '_' {
+                return Err("Numeric separator can not be used after leading 0".to_string());
+            }
+            if self.peek_char().is_ascii_digit() {
+                return self.scan_legacy_number();
+            }
+        }
+
+        let integer = self.scan_digits(10)?;
+        let mut is_integer = true;
+        let mut fraction = "";
+        if self.ch == '.' {
+            self.read_char();
+            if self.ch == '_' {
+                return Err("Numeric separators are only allowed between digits".to_string());
+            }
+            fraction = self.scan_digits(10)?;
+            is_integer = false;
+        }
+        let exponent = self.scan_exponent()?;
+        if exponent.is_some() {
+            is_integer = false;
+        }
+
+        if self.ch == 'n' {
+            if !is_integer {
+                return Err("Invalid BigInt literal: it must be an integer".to_string());
+            }
+            self.read_char();
+            return Ok(TokenType::BigInt(without_separators(integer)));
+        }
+
+        Ok(TokenType::Number(parse_decimal(
+            integer, fraction, exponent,
+        )))
     }
 
-    fn read_identifier(&mut self) -> Token {
+    /// Scans a number with a leading zero, such as `017` or `089`. It is an
+    /// octal integer when every digit is octal, otherwise a decimal. The
+    /// parser rejects both forms in strict mode code.
+    fn scan_legacy_number(&mut self) -> Result<TokenType<'a>, String> {
         let position = self.position;
-        let col = self.column;
-        while self.ch.is_alphanumeric() || self.ch == '_' {
+        while self.ch.is_ascii_digit() {
             self.read_char();
         }
-        let literal: String = self.input[position..self.position].iter().collect();
-        let token_type = match literal.as_str() {
-            "let" => TokenType::Let,
-            "const" => TokenType::Const,
-            "function" => TokenType::Function,
-            "return" => TokenType::Return,
-            "if" => TokenType::If,
-            "else" => TokenType::Else,
-            "while" => TokenType::While,
-            "for" => TokenType::For,
-            _ => TokenType::Identifier(literal.clone()),
-        };
-        Token::new(token_type, literal, self.line, col)
+        let digits = self.slice_from(position);
+        if self.ch == '_' {
+            return Err("Numeric separator can not be used after leading 0".to_string());
+        }
+        if self.ch == 'n' {
+            return Err("Invalid BigInt literal: it can not have a leading 0".to_string());
+        }
+
+        if digits.chars().all(|c| c.is_digit(8)) {
+            let value = digits
+                .chars()
+                .fold(0.0, |acc, c| acc * 8.0 + c.to_digit(8).unwrap() as f64);
+            return Ok(TokenType::Number(value));
+        }
+
+        let mut fraction = "";
+        if self.ch == '.' {
+            self.read_char();
+            fraction = self.scan_digits(10)?;
+        }
+        let exponent = self.scan_exponent()?;
+        Ok(TokenType::Number(parse_decimal(digits, fraction, exponent)))
     }
 
-    fn read_number(&mut self) -> Token {
+    /// Scans an optional `e[+-]<digits>` suffix, returning the signed digits.
+    fn scan_exponent(&mut self) -> Result<Option<&'a str>, String> {
+        if !matches!(self.ch, 'e' | 'E') {
+            return Ok(None);
+        }
+        self.read_char(); // eat 'e'
         let position = self.position;
-        let col = self.column;
-        while self.ch.is_digit(10) || self.ch == '.' {
+        if matches!(self.ch, '+' | '-') {
             self.read_char();
         }
-        let literal: String = self.input[position..self.position].iter().collect();
-        let value = literal.parse::<f64>().unwrap_or(0.0);
-        Token::new(TokenType::Number(value), literal, self.line, col)
+        if self.scan_digits(10)?.is_empty() {
+            return Err("Invalid or unexpected token: missing exponent digits".to_string());
+        }
+        Ok(Some(self.slice_from(position)))
     }
 
-    fn read_string(&mut self, quote: char) -> Token {
-        let col = self.column;
+    /// Scans digits in `radix`, allowing single `_` separators between them.
+    /// The digits are returned as written, separators included.
+    fn scan_digits(&mut self, radix: u32) -> Result<&'a str, String> {
+        let position = self.position;
+        loop {
+            if self.ch.is_digit(radix) {
+                self.read_char();
+            } else if self.ch == '_' {
+                if self.position == position || !self.peek_char().is_digit(radix) {
+                    return Err(if self.peek_char() == '_' {
+                        "Only one underscore is allowed as numeric separator".to_string()
+                    } else {
+                        "Numeric separators are only allowed between digits".to_string()
+                    });
+                }
+                self.read_char();
+            } else {
+                return Ok(self.slice_from(position));
+            }
+        }
+    }
+
+    fn read_string(&mut self, quote: char) -> Token<'a> {
+        let position = self.position;
+        let start = self.current_position();
         self.read_char(); // skip opening quote
+
+        // The value is borrowed from the source unless an escape changes it,
+        // in which case it is copied from the first escape on
+        let value_start = self.position;
+        let mut copy: Option<String> = None;
+        let mut error = None;
+        let value_end = loop {
+            match self.ch {
+                c if c == quote => {
+                    let end = self.position;
+                    self.read_char(); // skip closing quote
+                    break end;
+                }
+                // Only a line continuation can span lines
+                '\0' | '\n' | '\r' => {
+                    error = Some("Unterminated string literal".to_string());
+                    break self.position;
+                }
+                '\\' => {
+                    let copy = copy.get_or_insert_with(|| self.slice_from(value_start).to_string());
+                    self.read_char(); // eat '\'
+                    match self.read_escape(false) {
+                        Ok(decoded) => copy.extend(decoded),
+                        Err(message) => {
+                            error.get_or_insert(message);
+                        }
+                    }
+                }
+                c => {
+                    if let Some(copy) = &mut copy {
+                        copy.push(c);
+                    }
+                    self.read_char();
+                }
+            }
+        };
+
+        let span = self.span_from(start);
+        let token_type = match error {
+            Some(message) => {
+                self.errors.push(Diagnostic::error(span, message));
+                TokenType::Illegal
+            }
+            None => TokenType::String(match copy {
+                Some(value) => Cow::Owned(value),
+                None => Cow::Borrowed(&self.input[value_start..value_end]),
+            }),
+        };
+        Token::new(token_type, self.slice_from(position), span)
+    }
+
+    /// Reads a regular expression literal. The pattern is only checked for
+    /// where it ends here; the RegExp engine parses it when it is created.
+    fn read_regexp(&mut self) -> Token<'a> {
+        let position = self.position;
+        let start = self.current_position();
+        self.read_char(); // eat '/'
+
+        let pattern_start = self.position;
+        let mut in_class = false;
+        loop {
+            match self.ch {
+                '/' if !in_class => break,
+                '\0' | '\n' | '\r' | '\u{2028}' | '\u{2029}' => {
+                    let span = self.span_from(start);
+                    self.errors.push(Diagnostic::error(
+                        span,
+                        "Invalid regular expression: missing /",
+                    ));
+                    return Token::new(TokenType::Illegal, self.slice_from(position), span);
+                }
+                '\\' => {
+                    self.read_char();
+                    if matches!(self.ch, '\0' | '\n' | '\r' | '\u{2028}' | '\u{2029}') {
+                        continue;
+                    }
+                }
+                '[' => in_class = true,
+                ']' => in_class = false,
+                _ => {}
+            }
+            self.read_char();
+        }
+        let pattern = self.slice_from(pattern_start);
+        self.read_char(); // eat '/'
+
+        let flags_start = self.position;
+        while is_identifier_part(self.ch) {
+            self.read_char();
+        }
+        let flags = self.slice_from(flags_start);
+
+        let literal = self.slice_from(position);
+        let span = self.span_from(start);
+        for (i, flag) in flags.char_indices() {
+            if !"dgimsuy".contains(flag) || flags[..i].contains(flag) {
+                self.errors
+                    .push(Diagnostic::error(span, "Invalid regular expression flags"));
+                return Token::new(TokenType::Illegal, literal, span);
+            }
+        }
+        Token::new(TokenType::RegExp { pattern, flags }, literal, span)
+    }
+
+    /// Reads a template literal part, starting at the '`' opening the literal
+    /// or the `}` ending a substitution, up to the '`' closing the literal
+    /// or the `${` opening the next substitution.
+    fn read_template(&mut self) -> Token<'a> {
         let position = self.position;
-        while self.ch != quote && self.ch != '\0' {
+        let start = self.current_position();
+        let head = self.ch == '`';
+        self.read_char(); // eat '`' or '}'
+
+        // As with strings, the cooked value is only copied once an escape or
+        // a carriage return makes it differ from the source
+        let text_start = self.position;
+        let mut copy: Option<String> = None;
+        let mut valid = true;
+        let (text_end, tail) = loop {
+            match self.ch {
+                '`' => {
+                    let end = self.position;
+                    self.read_char();
+                    break (end, true);
+                }
+                '$' if self.peek_char() == '{' => {
+                    let end = self.position;
+                    self.read_char(); // eat '$'
+                    self.read_char(); // eat '{'
+                    self.template_depths.push(self.brace_depth);
+                    break (end, false);
+                }
+                '\0' => {
+                    let span = self.span_from(start);
+                    self.errors
+                        .push(Diagnostic::error(span, "Unterminated template literal"));
+                    return Token::new(TokenType::Illegal, self.slice_from(position), span);
+                }
+                '\\' => {
+                    let copy = copy.get_or_insert_with(|| self.slice_from(text_start).to_string());
+                    self.read_char(); // eat '\'
+                                      // Invalid escapes are allowed in tagged templates, which see
+                                      // them as an undefined cooked string
+                    match self.read_escape(true) {
+                        Ok(decoded) => copy.extend(decoded),
+                        Err(_) => valid = false,
+                    }
+                }
+                // Line terminators in templates are normalized to \n
+                '\r' => {
+                    let copy = copy.get_or_insert_with(|| self.slice_from(text_start).to_string());
+                    self.read_char();
+                    if self.ch == '\n' {
+                        self.read_char();
+                    }
+                    copy.push('\n');
+                }
+                c => {
+                    if let Some(copy) = &mut copy {
+                        copy.push(c);
+                    }
+                    self.read_char();
+                }
+            }
+        };
+
+        let text = &self.input[text_start..text_end];
+        let raw = if text.contains('\r') {
+            Cow::Owned(normalize_line_terminators(text))
+        } else {
+            Cow::Borrowed(text)
+        };
+        let cooked = valid.then(|| copy.map_or(Cow::Borrowed(text), Cow::Owned));
+        let chunk = TemplateChunk { cooked, raw };
+        let token_type = match (head, tail) {
+            (true, true) => TokenType::NoSubstitutionTemplate(chunk),
+            (true, false) => TokenType::TemplateHead(chunk),
+            (false, false) => TokenType::TemplateMiddle(chunk),
+            (false, true) => TokenType::TemplateTail(chunk),
+        };
+        Token::new(token_type, self.slice_from(position), self.span_from(start))
+    }
+
+    /// Decodes the escape sequence after a `\`, leaving the character after
+    /// it as the current one. A line continuation decodes to nothing. Legacy
+    /// octal escapes are errors in templates; the parser rejects them in
+    /// strict mode strings.
+    fn read_escape(&mut self, in_template: bool) -> Result<Option<char>, String> {
+        let c = self.ch;
+        if c == '\0' {
+            return Err("Unterminated string literal".to_string());
+        }
+        self.read_char();
+
+        let decoded = match c {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            'b' => '\u{8}',
+            'f' => '\u{c}',
+            'v' => '\u{b}',
+            '0' if !self.ch.is_ascii_digit() => '\0',
+            '0'..='9' if in_template => {
+                return Err("Octal escape sequences are not allowed in template strings".into())
+            }
+            '0'..='7' => {
+                // Up to three octal digits, with a value of at most 0o377
+                let max_len = if c <= '3' { 3 } else { 2 };
+                let mut value = c.to_digit(8).unwrap();
+                for _ in 1..max_len {
+                    match self.ch.to_digit(8) {
+                        Some(digit) => {
+                            value = value * 8 + digit;
+                            self.read_char();
+                        }
+                        None => break,
+                    }
+                }
+                char::from_u32(value).unwrap()
+            }
+            'x' => {
+                let value = self
+                    .read_hex_digits(2)
+                    .ok_or("Invalid hexadecimal escape sequence")?;
+                char::from_u32(value).unwrap()
+            }
+            'u' => self.read_unicode_escape()?,
+            '\r' => {
+                if self.ch == '\n' {
+                    self.read_char();
+                }
+                return Ok(None);
+            }
+            '\n' | '\u{2028}' | '\u{2029}' => return Ok(None),
+            // Any other character, including '8' and '9', stands for itself
+            c => c,
+        };
+        Ok(Some(decoded))
+    }
+
+    /// Decodes the rest of a `\uXXXX` or `\u{X...}` escape after the `u`.
+    /// Strings hold UTF-8, so a surrogate pair written as two escapes is
+    /// combined and a lone surrogate becomes U+FFFD.
+    fn read_unicode_escape(&mut self) -> Result<char, String> {
+        let invalid = "Invalid Unicode escape sequence";
+        let value = if self.ch == '{' {
+            self.read_char(); // eat '{'
+            let mut value: u32 = 0;
+            let mut digits = 0;
+            while let Some(digit) = self.ch.to_digit(16) {
+                value = value.saturating_mul(16).saturating_add(digit);
+                digits += 1;
+                self.read_char();
+            }
+            if digits == 0 || self.ch != '}' {
+                return Err(invalid.to_string());
+            }
+            if value > 0x10FFFF {
+                return Err("Undefined Unicode code-point".to_string());
+            }
+            self.read_char(); // eat '}'
+            value
+        } else {
+            self.read_hex_digits(4).ok_or(invalid)?
+        };
+
+        if (0xD800..0xDC00).contains(&value) && self.ch == '\\' && self.peek_char() == 'u' {
+            let low = (1..=4)
+                .map(|n| self.peek_nth_char(n).to_digit(16))
+                .try_fold(0, |acc, digit| digit.map(|d| acc * 16 + d));
+            if let Some(low @ 0xDC00..=0xDFFF) = low {
+                for _ in 0..6 {
+                    self.read_char();
+                }
+                let combined = 0x10000 + ((value - 0xD800) << 10) + (low - 0xDC00);
+                return Ok(char::from_u32(combined).unwrap());
+            }
+        }
+        Ok(char::from_u32(value).unwrap_or(char::REPLACEMENT_CHARACTER))
+    }
+
+    fn read_hex_digits(&mut self, count: usize) -> Option<u32> {
+        let mut value = 0;
+        for _ in 0..count {
+            value = value * 16 + self.ch.to_digit(16)?;
             self.read_char();
         }
-        let literal: String = self.input[position..self.position].iter().collect();
-        self.read_char(); // skip closing quote
-        Token::new(TokenType::String(literal.clone()), literal, self.line, col)
+        Some(value)
+    }
+}
+
+fn keyword(name: &str) -> Option<TokenType<'static>> {
+    Some(match name {
+        "let" => TokenType::Let,
+        "const" => TokenType::Const,
+        "function" => TokenType::Function,
+        "return" => TokenType::Return,
+        "if" => TokenType::If,
+        "else" => TokenType::Else,
+        "while" => TokenType::While,
+        "for" => TokenType::For,
+        "do" => TokenType::Do,
+        "break" => TokenType::Break,
+        "continue" => TokenType::Continue,
+        "throw" => TokenType::Throw,
+        "try" => TokenType::Try,
+        "catch" => TokenType::Catch,
+        "finally" => TokenType::Finally,
+        "true" => TokenType::True,
+        "false" => TokenType::False,
+        "null" => TokenType::Null,
+        "this" => TokenType::This,
+        "typeof" => TokenType::Typeof,
+        "void" => TokenType::Void,
+        "delete" => TokenType::Delete,
+        "in" => TokenType::In,
+        "instanceof" => TokenType::Instanceof,
+        _ => return None,
+    })
+}
+
+/// IdentifierStart: ID_Start, `$` or `_`.
+fn is_identifier_start(c: char) -> bool {
+    if c.is_ascii() {
+        c.is_ascii_alphabetic() || c == '$' || c == '_'
+    } else {
+        unicode_ident::is_xid_start(c)
     }
 }
+
+/// IdentifierPart: ID_Continue, `$`, ZWNJ or ZWJ.
+fn is_identifier_part(c: char) -> bool {
+    if c.is_ascii() {
+        c.is_ascii_alphanumeric() || c == '$' || c == '_'
+    } else {
+        unicode_ident::is_xid_continue(c) || c == '\u{200c}' || c == '\u{200d}'
+    }
+}
+
+/// WhiteSpace: tab, vertical tab, form feed, the byte order mark and the
+/// space separators (category Zs).
+fn is_whitespace(c: char) -> bool {
+    matches!(
+        c,
+        '\t' | '\u{b}' | '\u{c}' | ' ' | '\u{a0}' | '\u{1680}' | '\u{2000}'
+            ..='\u{200a}' | '\u{202f}' | '\u{205f}' | '\u{3000}' | '\u{feff}'
+    )
+}
+
+/// LineTerminator: line feed, carriage return, and the line and paragraph
+/// separators.
+fn is_line_terminator(c: char) -> bool {
+    matches!(c, '\n' | '\r' | '\u{2028}' | '\u{2029}')
+}
+
+/// Converts the parts of a decimal literal, which are known to be well formed
+/// apart from any numeric separators.
+fn parse_decimal(integer: &str, fraction: &str, exponent: Option<&str>) -> f64 {
+    let integer = if integer.is_empty() { "0" } else { integer };
+    let fraction = if fraction.is_empty() { "0" } else { fraction };
+    let mut text = format!("{}.{}e{}", integer, fraction, exponent.unwrap_or("0"));
+    text.retain(|c| c != '_');
+    text.parse().unwrap()
+}
+
+/// Digits as scanned, without their numeric separators.
+fn without_separators(digits: &str) -> Cow<'_, str> {
+    if digits.contains('_') {
+        Cow::Owned(digits.replace('_', ""))
+    } else {
+        Cow::Borrowed(digits)
+    }
+}
+
+fn normalize_line_terminators(text: &str) -> String {
+    text.replace("\r\n", "\n").replace('\r', "\n")
+}
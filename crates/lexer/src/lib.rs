@@ -1,6 +1,12 @@
+pub mod interner;
 pub mod lexer;
 pub mod token;
 
+#[cfg(test)]
+mod tests;
+
+pub use interner::{Interner, Symbol};
 pub use lexer::Lexer;
+pub use token::TemplateChunk;
 pub use token::Token;
 pub use token::TokenType;
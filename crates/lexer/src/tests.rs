@@ -0,0 +1,364 @@
+use crate::{Lexer, TokenType};
+use std::borrow::Cow;
+
+/// The types of the tokens in `source`, without the final EOF.
+fn tokens(source: &str) -> Vec<TokenType<'_>> {
+    let mut lexer = Lexer::new(source);
+    let mut tokens = vec![];
+    loop {
+        let token = lexer.next_token();
+        if token.token_type == TokenType::EOF {
+            return tokens;
+        }
+        tokens.push(token.token_type);
+    }
+}
+
+/// The names of the identifiers in `source`.
+fn identifiers(source: &str) -> Vec<String> {
+    let mut lexer = Lexer::new(source);
+    let mut names = vec![];
+    loop {
+        match lexer.next_token().token_type {
+            TokenType::Identifier(symbol) => names.push(lexer.resolve(symbol).to_string()),
+            TokenType::EOF => return names,
+            _ => {}
+        }
+    }
+}
+
+/// The messages of the diagnostics for the malformed tokens in `source`.
+fn errors(source: &str) -> Vec<String> {
+    let mut lexer = Lexer::new(source);
+    while lexer.next_token().token_type != TokenType::EOF {}
+    lexer.take_errors().into_iter().map(|d| d.message).collect()
+}
+
+#[test]
+fn function_declaration() {
+    use TokenType::*;
+    let tokens = tokens("function add(a, b,) { return a + b; }");
+    assert!(matches!(
+        tokens.as_slice(),
+        [
+            Function,
+            Identifier(_),
+            LParen,
+            Identifier(_),
+            Comma,
+            Identifier(_),
+            Comma,
+            RParen,
+            LBrace,
+            Return,
+            Identifier(_),
+            Plus,
+            Identifier(_),
+            SemiColon,
+            RBrace,
+        ]
+    ));
+    assert_eq!(
+        identifiers("function add(a, b,) { return a + b; }"),
+        ["add", "a", "b", "a", "b"]
+    );
+}
+
+#[test]
+fn keywords_are_not_identifiers() {
+    use TokenType::*;
+    let tokens = tokens("function functions return returned");
+    assert!(matches!(
+        tokens.as_slice(),
+        [Function, Identifier(_), Return, Identifier(_)]
+    ));
+    assert_eq!(
+        identifiers("function functions return returned"),
+        ["functions", "returned"]
+    );
+}
+
+#[test]
+fn literals() {
+    assert_eq!(
+        tokens("1.5 'a' \"b\" true null"),
+        [
+            TokenType::Number(1.5),
+            TokenType::String("a".into()),
+            TokenType::String("b".into()),
+            TokenType::True,
+            TokenType::Null,
+        ]
+    );
+}
+
+#[test]
+fn malformed_tokens() {
+    assert_eq!(errors("let a = #;"), ["Invalid or unexpected token"]);
+    assert_eq!(errors("'abc"), ["Unterminated string literal"]);
+    assert_eq!(errors("3in x"), ["Invalid or unexpected token"]);
+    assert!(errors("function f() {}").is_empty());
+}
+
+#[test]
+fn identifiers_in_non_latin_scripts() {
+    assert_eq!(
+        identifiers("let π = число + 名前 * συνάρτηση(χ);"),
+        ["π", "число", "名前", "συνάρτηση", "χ"]
+    );
+    assert_eq!(
+        identifiers("مرحبا שלום नमस्ते 안녕 ひらがな"),
+        ["مرحبا", "שלום", "नमस्ते", "안녕", "ひらがな"]
+    );
+    assert_eq!(
+        identifiers("$ _ $_$ jQuery$1"),
+        ["$", "_", "$_$", "jQuery$1"]
+    );
+}
+
+#[test]
+fn identifiers_are_interned_and_tokens_borrow_the_source() {
+    let source = "let total = total + \\u0074otal + 'plain' + 'esc\\x61ped';";
+    let mut lexer = Lexer::new(source);
+    let mut symbols = vec![];
+    let mut strings = vec![];
+    loop {
+        let token = lexer.next_token();
+        // Every token's text is a slice of the source, not a copy
+        let offset = token.literal.as_ptr() as usize - source.as_ptr() as usize;
+        assert_eq!(&source[offset..offset + token.literal.len()], token.literal);
+        match token.token_type {
+            TokenType::Identifier(symbol) => symbols.push(symbol),
+            TokenType::String(value) => strings.push(value),
+            TokenType::EOF => break,
+            _ => {}
+        }
+    }
+
+    // The same name gives the same symbol, even when spelled with an escape
+    assert_eq!(symbols.len(), 3);
+    assert!(symbols.iter().all(|&symbol| symbol == symbols[0]));
+    assert_eq!(lexer.resolve(symbols[0]), "total");
+
+    // String values are only copied when an escape changes them
+    assert!(matches!(&strings[0], Cow::Borrowed("plain")));
+    assert!(matches!(&strings[1], Cow::Owned(value) if value == "escaped"));
+}
+
+#[test]
+fn joiners_continue_identifiers() {
+    // ZWNJ and ZWJ are part of the name, so these are three bindings
+    assert_eq!(
+        identifiers("a\u{200C}b a\u{200D}b ab"),
+        ["a\u{200C}b", "a\u{200D}b", "ab"]
+    );
+    assert_eq!(identifiers("a\u{203F}b"), ["a\u{203F}b"]);
+}
+
+#[test]
+fn escapes_spell_identifiers() {
+    assert_eq!(
+        identifiers(r"\u0061bc x\u{79}z \u03C0\u{3c1} caf\u00e9 $\u200D"),
+        ["abc", "xyz", "πρ", "café", "$\u{200D}"]
+    );
+}
+
+#[test]
+fn malformed_identifiers() {
+    // An escape must spell a character that can stand where it is
+    assert_eq!(
+        errors(r"let \u0030x = 1;"),
+        ["Invalid Unicode escape sequence"]
+    );
+    // U+203F can continue a name but not start one
+    assert_eq!(errors("let \u{203F} = 1;"), ["Invalid or unexpected token"]);
+    assert_eq!(
+        errors(r"i\u0066 (true) {}"),
+        ["Keyword must not contain escaped characters"]
+    );
+}
+
+#[test]
+fn unicode_whitespace_separates_tokens() {
+    use TokenType::*;
+    // NBSP, ideographic space, BOM, em space and narrow no-break space
+    let tokens = tokens("a\u{A0}+\u{3000}b\u{FEFF}+\u{2003}c\u{202F};");
+    assert!(matches!(
+        tokens.as_slice(),
+        [
+            Identifier(_),
+            Plus,
+            Identifier(_),
+            Plus,
+            Identifier(_),
+            SemiColon
+        ]
+    ));
+}
+
+#[test]
+fn unicode_line_terminators() {
+    for source in ["a\u{2028}b", "a\u{2029}b", "a\rb", "a // comment\u{2028}b"] {
+        let mut lexer = Lexer::new(source);
+        assert!(!lexer.next_token().newline_before);
+        assert!(lexer.next_token().newline_before, "{:?}", source);
+    }
+    // They can still be written in strings
+    assert_eq!(tokens("'\u{2028}'"), [TokenType::String("\u{2028}".into())]);
+}
+
+#[test]
+fn slash_after_a_statement_head_starts_a_regexp() {
+    let regexp = |source| {
+        tokens(source)
+            .iter()
+            .any(|token| matches!(token, TokenType::RegExp { .. }))
+    };
+    assert!(regexp("if (a) /b/.test(c);"));
+    assert!(regexp("while (f(x)) /b/g.exec(c);"));
+    assert!(regexp("for (;;) /b/;"));
+    assert!(regexp("if ((a)) /b/;"));
+    // Other parentheses close an operand, so the `/` divides it
+    assert!(!regexp("(a) / b / c;"));
+    assert!(!regexp("if (a) (b) / c / d;"));
+    assert!(!regexp("f(a) / b / c;"));
+}
+
+#[test]
+fn slash_after_a_brace_depends_on_what_it_closes() {
+    let regexp = |source| {
+        tokens(source)
+            .iter()
+            .any(|token| matches!(token, TokenType::RegExp { .. }))
+    };
+    // Blocks are followed by a statement
+    assert!(regexp("{} /foo/g.test(s);"));
+    assert!(regexp("if (a) {} /b/.exec(c);"));
+    assert!(regexp("function f() {} /b/.test(c);"));
+    assert!(regexp("try {} finally {} /b/;"));
+    assert!(regexp("const f = () => {}\n/b/.test(c);"));
+    assert!(regexp("x = { a: 1 }; /b/;"));
+    // Object literals and function expressions are operands
+    assert!(!regexp("x = function() {} / 2;"));
+    assert!(!regexp("x = function f(a = function() {}) {} / 2 / 1;"));
+    assert!(!regexp("let q = {} / foo / g;"));
+    assert!(!regexp("f({ a: {} } / 2, [{}] / 3);"));
+    assert!(!regexp("x = a ? {} / 2 : { b() {} } / 3;"));
+    assert!(!regexp("return { a: `${{}}` } / 2;"));
+}
+
+#[test]
+fn numeric_literals() {
+    use TokenType::Number;
+    assert_eq!(
+        tokens("0xff 0XA_b 0o17 0O7 0b101 0B1_0"),
+        [
+            Number(255.0),
+            Number(171.0),
+            Number(15.0),
+            Number(7.0),
+            Number(5.0),
+            Number(2.0)
+        ]
+    );
+    assert_eq!(
+        tokens("1e3 1E+3 2.5e-3 .5 5. .5e1 0.0"),
+        [
+            Number(1000.0),
+            Number(1000.0),
+            Number(0.0025),
+            Number(0.5),
+            Number(5.0),
+            Number(5.0),
+            Number(0.0)
+        ]
+    );
+    assert_eq!(
+        tokens("1_000 1_0.2_5 1e1_0 0x1_F"),
+        [Number(1000.0), Number(10.25), Number(1e10), Number(31.0)]
+    );
+    // With a leading zero, octal when every digit is octal and decimal
+    // otherwise
+    assert_eq!(
+        tokens("010 019 08.5 00"),
+        [Number(8.0), Number(19.0), Number(8.5), Number(0.0)]
+    );
+    // `1.` ends the number, so `1..a` reads a member of 1
+    assert!(matches!(
+        tokens("1..a").as_slice(),
+        [Number(n), TokenType::Dot, TokenType::Identifier(_)] if *n == 1.0
+    ));
+}
+
+#[test]
+fn bigint_literals_keep_their_digits() {
+    assert_eq!(
+        tokens("10n 0n 1_000n 0xFfn 0o7n 0b1_1n"),
+        [
+            TokenType::BigInt("10".into()),
+            TokenType::BigInt("0".into()),
+            TokenType::BigInt("1000".into()),
+            TokenType::BigInt("0xFf".into()),
+            TokenType::BigInt("0o7".into()),
+            TokenType::BigInt("0b11".into())
+        ]
+    );
+}
+
+#[test]
+fn malformed_numbers() {
+    assert_eq!(
+        errors("1__0"),
+        ["Only one underscore is allowed as numeric separator"]
+    );
+    assert_eq!(
+        errors("0x_1"),
+        ["Numeric separators are only allowed between digits"]
+    );
+    assert_eq!(
+        errors("1_"),
+        ["Numeric separators are only allowed between digits"]
+    );
+    assert_eq!(
+        errors("1._5"),
+        ["Numeric separators are only allowed between digits"]
+    );
+    assert_eq!(
+        errors("0_1"),
+        ["Numeric separator can not be used after leading 0"]
+    );
+    assert_eq!(
+        errors("07_1"),
+        ["Numeric separator can not be used after leading 0"]
+    );
+    assert_eq!(
+        errors("0x"),
+        ["Invalid or unexpected token: missing hexadecimal digits"]
+    );
+    assert_eq!(
+        errors("0b2"),
+        ["Invalid or unexpected token: missing binary digits"]
+    );
+    assert_eq!(
+        errors("1e"),
+        ["Invalid or unexpected token: missing exponent digits"]
+    );
+    assert_eq!(
+        errors("1.5n"),
+        ["Invalid BigInt literal: it must be an integer"]
+    );
+    assert_eq!(
+        errors("1e3n"),
+        ["Invalid BigInt literal: it must be an integer"]
+    );
+    assert_eq!(
+        errors("01n"),
+        ["Invalid BigInt literal: it can not have a leading 0"]
+    );
+    // Two numbers, which the parser rejects next to each other
+    assert_eq!(
+        tokens("1.2.3"),
+        [TokenType::Number(1.2), TokenType::Number(0.3)]
+    );
+    assert_eq!(errors("0xffz"), ["Invalid or unexpected token"]);
+}
@@ -1,5 +1,9 @@
+use crate::interner::Symbol;
+use shadowjs_ast::Span;
+use std::borrow::Cow;
+
 #[derive(Debug, Clone, PartialEq)]
-pub enum TokenType {
+pub enum TokenType<'a> {
     // Keywords
     Let,
     Const,
@@ -9,22 +13,83 @@ pub enum TokenType {
     Else,
     While,
     For,
+    Do,
+    Break,
+    Continue,
+    Throw,
+    Try,
+    Catch,
+    Finally,
+    True,
+    False,
+    Null,
+    This,
+    Typeof,
+    Void,
+    Delete,
+    In,
+    Instanceof,
 
     // Identifiers and Literals
-    Identifier(String),
+    Identifier(Symbol),
     Number(f64),
-    String(String),
+    // The digits of a BigInt literal, without the `n` suffix or separators
+    BigInt(Cow<'a, str>),
+    // Template literal parts, named as in the spec: `a`, `a${, }a${ and }a`
+    NoSubstitutionTemplate(TemplateChunk<'a>),
+    TemplateHead(TemplateChunk<'a>),
+    TemplateMiddle(TemplateChunk<'a>),
+    TemplateTail(TemplateChunk<'a>),
+    RegExp { pattern: &'a str, flags: &'a str },
+    // The value of a string literal, borrowed unless it contains escapes
+    String(Cow<'a, str>),
 
     // Operators
     Plus,
     Minus,
     Star,
     Slash,
+    Percent,
+    StarStar,
+    Bang,
+    Tilde,
+    Ampersand,
+    Pipe,
+    Caret,
+    ShiftLeft,
+    ShiftRight,
+    UnsignedShiftRight,
+    AndAnd,
+    OrOr,
+    Nullish,
+    Question,
     Assign,
+    PlusAssign,
+    MinusAssign,
+    StarAssign,
+    SlashAssign,
+    PercentAssign,
+    StarStarAssign,
+    ShiftLeftAssign,
+    ShiftRightAssign,
+    UnsignedShiftRightAssign,
+    AmpersandAssign,
+    PipeAssign,
+    CaretAssign,
+    AndAndAssign,
+    OrOrAssign,
+    NullishAssign,
+    PlusPlus,
+    MinusMinus,
     Equal,
     NotEqual,
+    StrictEqual,
+    StrictNotEqual,
     LessThan,
     GreaterThan,
+    LessEqual,
+    GreaterEqual,
+    Arrow,
 
     // Punctuation
     LParen,
@@ -37,26 +102,38 @@ pub enum TokenType {
     SemiColon,
     Comma,
     Dot,
+    Ellipsis,
 
     EOF,
     Illegal,
 }
 
+/// The text of a template literal part between its delimiters. `cooked` is
+/// None when the part contains an invalid escape sequence.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateChunk<'a> {
+    pub cooked: Option<Cow<'a, str>>,
+    pub raw: Cow<'a, str>,
+}
+
 #[derive(Debug, Clone, PartialEq)]
-pub struct Token {
-    pub token_type: TokenType,
-    pub literal: String,
-    pub line: usize,
-    pub column: usize,
+pub struct Token<'a> {
+    pub token_type: TokenType<'a>,
+    // The token's text in the source
+    pub literal: &'a str,
+    pub span: Span,
+    // Whether a line terminator separates the token from the one before it,
+    // which automatic semicolon insertion depends on
+    pub newline_before: bool,
 }
 
-impl Token {
-    pub fn new(token_type: TokenType, literal: String, line: usize, column: usize) -> Self {
+impl<'a> Token<'a> {
+    pub fn new(token_type: TokenType<'a>, literal: &'a str, span: Span) -> Self {
         Self {
             token_type,
             literal,
-            line,
-            column,
+            span,
+            newline_before: false,
         }
     }
 }
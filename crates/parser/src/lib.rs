@@ -1,3 +1,6 @@
 pub mod parser;
 
+#[cfg(test)]
+mod tests;
+
 pub use parser::Parser;
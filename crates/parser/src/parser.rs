@@ -1,191 +1,1086 @@
-use shadowjs_ast::{Expression, Program, Statement};
-use shadowjs_lexer::{Lexer, Token, TokenType};
-
-pub struct Parser {
-    lexer: Lexer,
-    cur_token: Token,
-    peek_token: Token,
-    errors: Vec<String>,
+use shadowjs_ast::precedence::{
+    ASSIGN, BITWISE_AND, BITWISE_OR, BITWISE_XOR, CALL, CONDITIONAL, EQUALS, EXPONENT,
+    LESS_GREATER, LOGICAL_AND, LOGICAL_OR, MEMBER, NULLISH, POSTFIX, PREFIX, PRODUCT, SHIFT, SUM,
+};
+use shadowjs_ast::{
+    ArrowBody, DeclarationKind, Diagnostic, ElementPattern, Expression, ExpressionKind, Identifier,
+    MethodKind, Parameter, Pattern, PatternKind, Position, Program, Property, PropertyKey,
+    PropertyKeyKind, PropertyKind, PropertyPattern, RestElement, Span, Statement, StatementKind,
+    TemplateElement,
+};
+use shadowjs_lexer::{Lexer, Symbol, TemplateChunk, Token, TokenType};
+
+pub struct Parser<'a> {
+    lexer: Lexer<'a>,
+    cur_token: Token<'a>,
+    peek_token: Token<'a>,
+    errors: Vec<Diagnostic>,
+    // Braces opened before the current token and not yet closed
+    brace_depth: usize,
+    // Whether the code being parsed is in strict mode
+    strict: bool,
+    // Where `{a = 1}` was written in an object literal, which is only valid
+    // once the literal turns out to be a pattern, as in `({a = 1} = b)`
+    shorthand_defaults: Vec<Span>,
+    // Where an array or object literal was wrapped in parentheses, which
+    // keeps it from becoming a pattern: `([a]) = b` is not a destructuring
+    parenthesized_literals: Vec<Span>,
 }
 
-impl Parser {
-    pub fn new(input: &str) -> Self {
+impl<'a> Parser<'a> {
+    pub fn new(input: &'a str) -> Self {
         let mut lexer = Lexer::new(input);
         let cur_token = lexer.next_token();
         let peek_token = lexer.next_token();
+        let errors = lexer.take_errors();
 
         Self {
             lexer,
             cur_token,
             peek_token,
-            errors: vec![],
+            errors,
+            brace_depth: 0,
+            strict: false,
+            shorthand_defaults: vec![],
+            parenthesized_literals: vec![],
         }
     }
 
+    /// The name of an identifier token.
+    fn name(&self, symbol: Symbol) -> String {
+        self.lexer.resolve(symbol).to_string()
+    }
+
     fn next_token(&mut self) {
-        self.cur_token = self.peek_token.clone();
-        self.peek_token = self.lexer.next_token();
+        match self.cur_token.token_type {
+            TokenType::LBrace => self.brace_depth += 1,
+            TokenType::RBrace => self.brace_depth = self.brace_depth.saturating_sub(1),
+            _ => {}
+        }
+        self.cur_token = std::mem::replace(&mut self.peek_token, self.lexer.next_token());
+        for diagnostic in self.lexer.take_errors() {
+            self.report(diagnostic);
+        }
     }
 
-    pub fn parse(&mut self) -> Result<Program, String> {
+    /// Parses the whole input. Statements that fail to parse are skipped, so
+    /// every error in the input is reported in one pass.
+    pub fn parse(&mut self) -> Result<Program, Vec<Diagnostic>> {
+        let statements = self.parse_statement_list(TokenType::EOF, true);
+
+        if !self.errors.is_empty() {
+            return Err(std::mem::take(&mut self.errors));
+        }
+
+        Ok(Program { statements })
+    }
+
+    /// Parses statements up to `end` or the end of input, leaving the
+    /// terminator as the current token. Scripts and function bodies start
+    /// with a directive prologue, where `"use strict"` turns on strict mode.
+    fn parse_statement_list(&mut self, end: TokenType, directives: bool) -> Vec<Statement> {
         let mut statements = vec![];
+        let mut in_prologue = directives;
+
+        while self.cur_token.token_type != end && self.cur_token.token_type != TokenType::EOF {
+            let start = self.cur_token.span;
+            let depth = self.brace_depth;
+            // Directives are matched as written, so `"use\x20strict"` is not one
+            let use_strict = matches!(self.cur_token.literal, "\"use strict\"" | "'use strict'");
+            let stmt = self.parse_statement();
+            self.check_shorthand_defaults(start, stmt.is_some());
+            self.parenthesized_literals
+                .retain(|span| span.start < start.start);
+            match stmt {
+                Some(stmt) => {
+                    if in_prologue {
+                        match &stmt.kind {
+                            StatementKind::Expression(
+                                expr @ Expression {
+                                    kind: ExpressionKind::String(_),
+                                    ..
+                                },
+                            ) => {
+                                if use_strict && expr.span == start {
+                                    self.strict = true;
+                                }
+                            }
+                            _ => in_prologue = false,
+                        }
+                    }
+                    statements.push(stmt);
+                    self.next_token();
+                }
+                None => self.synchronize(start, depth),
+            }
+        }
+
+        statements
+    }
 
-        while self.cur_token.token_type != TokenType::EOF {
-            if let Some(stmt) = self.parse_statement() {
-                statements.push(stmt);
+    /// Reports the `{a = 1}` object literals in the statement that started at
+    /// `start` which never became patterns. Once a statement fails to parse,
+    /// its literals are dropped without a report.
+    fn check_shorthand_defaults(&mut self, start: Span, report: bool) {
+        let mut i = 0;
+        while i < self.shorthand_defaults.len() {
+            let span = self.shorthand_defaults[i];
+            if span.start < start.start {
+                i += 1;
+                continue;
             }
+            self.shorthand_defaults.remove(i);
+            if report {
+                let diagnostic = Diagnostic::error(span, "Invalid shorthand property initializer")
+                    .with_suggestion("use ':' instead of '='");
+                self.report(diagnostic);
+            }
+        }
+    }
+
+    /// Skips the rest of a statement that failed to parse, leaving the first
+    /// token of the next statement, or the `}` closing the enclosing block,
+    /// as the current token. `depth` is the brace depth the statement started
+    /// at, so blocks opened inside it are skipped whole.
+    fn synchronize(&mut self, start: Span, depth: usize) {
+        if self.cur_token.span == start {
             self.next_token();
         }
 
-        Ok(Program { statements })
+        loop {
+            if self.cur_token.token_type == TokenType::EOF || self.brace_depth < depth {
+                return;
+            }
+            if self.brace_depth == depth {
+                match &self.cur_token.token_type {
+                    TokenType::RBrace => return,
+                    TokenType::SemiColon => {
+                        self.next_token();
+                        return;
+                    }
+                    token_type if Self::starts_statement(token_type) => return,
+                    _ => {}
+                }
+            }
+            self.next_token();
+        }
+    }
+
+    fn starts_statement(token_type: &TokenType) -> bool {
+        matches!(
+            token_type,
+            TokenType::Let
+                | TokenType::Const
+                | TokenType::Return
+                | TokenType::If
+                | TokenType::Function
+                | TokenType::While
+                | TokenType::Do
+                | TokenType::For
+                | TokenType::Break
+                | TokenType::Continue
+                | TokenType::Throw
+                | TokenType::Try
+        )
+    }
+
+    fn expect_peek(&mut self, token_type: TokenType, expected: &str) -> bool {
+        if self.peek_token.token_type == token_type {
+            self.next_token();
+            true
+        } else {
+            self.peek_error(expected);
+            false
+        }
+    }
+
+    fn peek_error(&mut self, expected: &str) {
+        let diagnostic = Self::expected_error(expected, &self.peek_token);
+        self.report(diagnostic);
+    }
+
+    fn cur_error(&mut self, expected: &str) {
+        let diagnostic = Self::expected_error(expected, &self.cur_token);
+        self.report(diagnostic);
+    }
+
+    fn expected_error(expected: &str, found: &Token) -> Diagnostic {
+        let diagnostic = match found.token_type {
+            TokenType::EOF => Diagnostic::error(
+                found.span,
+                format!("Expected {}, got end of input", expected),
+            ),
+            _ => Diagnostic::error(
+                found.span,
+                format!("Expected {}, got '{}'", expected, found.literal),
+            ),
+        };
+        // A missing delimiter can simply be inserted
+        match expected {
+            "')'" | "']'" | "'}'" | "':'" | "';'" | "'('" | "'{'" => {
+                diagnostic.with_suggestion(format!("insert {}", expected))
+            }
+            _ => diagnostic,
+        }
+    }
+
+    fn error_at_cur(&mut self, message: &str) {
+        let diagnostic = Diagnostic::error(self.cur_token.span, message);
+        self.report(diagnostic);
+    }
+
+    /// Records a diagnostic, unless one was already reported at the same
+    /// place: a single mistake often fails several enclosing parse functions.
+    fn report(&mut self, diagnostic: Diagnostic) {
+        let repeated = self
+            .errors
+            .iter()
+            .any(|error| error.span.start == diagnostic.span.start);
+        if !repeated {
+            self.errors.push(diagnostic);
+        }
+    }
+
+    /// Ends a statement whose last token is current, at an explicit `;` or
+    /// where automatic semicolon insertion puts one: before a token on a new
+    /// line, a `}` or the end of input.
+    fn consume_semicolon(&mut self) -> bool {
+        match self.peek_token.token_type {
+            TokenType::SemiColon => {
+                self.next_token();
+                true
+            }
+            _ if self.can_insert_semicolon() => true,
+            _ => {
+                self.peek_error("';'");
+                false
+            }
+        }
+    }
+
+    fn can_insert_semicolon(&self) -> bool {
+        matches!(
+            self.peek_token.token_type,
+            TokenType::RBrace | TokenType::EOF
+        ) || self.peek_token.newline_before
+    }
+
+    /// The span from `start` to the end of the current token, which is the
+    /// last token of the node being parsed.
+    fn span_from(&self, start: Span) -> Span {
+        start.to(self.cur_token.span)
     }
 
     fn parse_statement(&mut self) -> Option<Statement> {
         match self.cur_token.token_type {
+            TokenType::SemiColon => Some(Statement::new(StatementKind::Empty, self.cur_token.span)),
             TokenType::Let => self.parse_let_statement(),
             TokenType::Const => self.parse_const_statement(),
             TokenType::Return => self.parse_return_statement(),
             TokenType::If => self.parse_if_statement(),
             TokenType::LBrace => self.parse_block_statement(),
+            TokenType::Function => self.parse_function_statement(),
+            TokenType::While => self.parse_while_statement(),
+            TokenType::Do => self.parse_do_while_statement(),
+            TokenType::For => self.parse_for_statement(),
+            TokenType::Break => self.parse_break_statement(),
+            TokenType::Continue => self.parse_continue_statement(),
+            TokenType::Throw => self.parse_throw_statement(),
+            TokenType::Try => self.parse_try_statement(),
+            TokenType::Identifier(_) if self.peek_token.token_type == TokenType::Colon => {
+                self.parse_labeled_statement()
+            }
             _ => self.parse_expression_statement(),
         }
     }
 
     fn parse_block_statement(&mut self) -> Option<Statement> {
+        self.parse_block(false)
+    }
+
+    fn parse_block(&mut self, directives: bool) -> Option<Statement> {
+        let start = self.cur_token.span;
         self.next_token(); // eat '{'
-        let mut statements = vec![];
+        let statements = self.parse_statement_list(TokenType::RBrace, directives);
 
-        while self.cur_token.token_type != TokenType::RBrace
-            && self.cur_token.token_type != TokenType::EOF
-        {
-            if let Some(stmt) = self.parse_statement() {
-                statements.push(stmt);
-            }
-            self.next_token();
+        if self.cur_token.token_type != TokenType::RBrace {
+            self.cur_error("'}'");
+            return None;
         }
 
-        if self.cur_token.token_type == TokenType::RBrace {
-            // Idk what to do here
+        Some(Statement::new(
+            StatementKind::Block(statements),
+            self.span_from(start),
+        ))
+    }
+
+    fn parse_if_statement(&mut self) -> Option<Statement> {
+        let start = self.cur_token.span;
+        let condition = self.parse_parenthesized_condition()?;
+        self.next_token(); // eat ')'
+
+        let consequence = Box::new(self.parse_statement()?);
+
+        let mut alternative = None;
+        if self.peek_token.token_type == TokenType::Else {
+            self.next_token(); // eat '}' or ';'
+            self.next_token(); // eat 'else'
+            alternative = Some(Box::new(self.parse_statement()?));
         }
 
-        Some(Statement::Block(statements))
+        Some(Statement::new(
+            StatementKind::If {
+                condition,
+                consequence,
+                alternative,
+            },
+            self.span_from(start),
+        ))
     }
 
-    fn parse_if_statement(&mut self) -> Option<Statement> {
-        self.next_token(); // eat 'if'
-        if self.cur_token.token_type != TokenType::LParen {
+    fn parse_while_statement(&mut self) -> Option<Statement> {
+        // while (<expr>) <stmt>
+        let start = self.cur_token.span;
+        let condition = self.parse_parenthesized_condition()?;
+        self.next_token(); // eat ')'
+        let body = Box::new(self.parse_statement()?);
+
+        Some(Statement::new(
+            StatementKind::While { condition, body },
+            self.span_from(start),
+        ))
+    }
+
+    fn parse_do_while_statement(&mut self) -> Option<Statement> {
+        // do <stmt> while (<expr>);
+        let start = self.cur_token.span;
+        self.next_token(); // eat 'do'
+        let body = Box::new(self.parse_statement()?);
+
+        if !self.expect_peek(TokenType::While, "'while'") {
+            return None;
+        }
+        let condition = self.parse_parenthesized_condition()?;
+
+        // A semicolon is always inserted after a do-while, even on one line
+        if self.peek_token.token_type == TokenType::SemiColon {
+            self.next_token();
+        }
+
+        Some(Statement::new(
+            StatementKind::DoWhile { body, condition },
+            self.span_from(start),
+        ))
+    }
+
+    fn parse_for_statement(&mut self) -> Option<Statement> {
+        // for ([<init>]; [<expr>]; [<expr>]) <stmt>
+        let start = self.cur_token.span;
+        if !self.expect_peek(TokenType::LParen, "'('") {
             return None;
         }
         self.next_token(); // eat '('
-        let condition = self.parse_expression(0)?;
 
-        if self.peek_token.token_type != TokenType::RParen {
-            // Expect RParen
+        let init = match self.cur_token.token_type {
+            TokenType::SemiColon => None,
+            TokenType::Let | TokenType::Const => {
+                let declaration_start = self.cur_token.span;
+                let is_const = self.cur_token.token_type == TokenType::Const;
+                self.next_token(); // eat keyword
+                let pattern = self.parse_binding_pattern("variable name")?;
+                if self.peek_of() {
+                    let declaration = if is_const {
+                        DeclarationKind::Const
+                    } else {
+                        DeclarationKind::Let
+                    };
+                    return self.parse_for_of(start, Some(declaration), pattern);
+                }
+                Some(Box::new(self.parse_declaration_rest(
+                    declaration_start,
+                    is_const,
+                    pattern,
+                )?))
+            }
+            _ => {
+                let expr = self.parse_expression(0)?;
+                if self.peek_of() {
+                    let target = match self.reinterpret_as_pattern(expr, false) {
+                        Ok(target) => target,
+                        Err(span) => {
+                            let diagnostic =
+                                Diagnostic::error(span, "Invalid left-hand side in for-of loop");
+                            self.report(diagnostic);
+                            return None;
+                        }
+                    };
+                    return self.parse_for_of(start, None, target);
+                }
+                let span = expr.span;
+                Some(Box::new(Statement::new(
+                    StatementKind::Expression(expr),
+                    span,
+                )))
+            }
+        };
+        // Semicolons are never inserted in the header, so a declaration
+        // ended by a line break still needs its `;`
+        if self.cur_token.token_type != TokenType::SemiColon
+            && !self.expect_peek(TokenType::SemiColon, "';'")
+        {
+            return None;
         }
-        self.next_token(); // eat last token of expr
-        if self.cur_token.token_type != TokenType::RParen {
+
+        let condition = if self.peek_token.token_type == TokenType::SemiColon {
+            None
+        } else {
+            self.next_token(); // eat ';'
+            Some(self.parse_expression(0)?)
+        };
+        if !self.expect_peek(TokenType::SemiColon, "';'") {
+            return None;
+        }
+
+        let update = if self.peek_token.token_type == TokenType::RParen {
+            None
+        } else {
+            self.next_token(); // eat ';'
+            Some(self.parse_expression(0)?)
+        };
+        if !self.expect_peek(TokenType::RParen, "')'") {
             return None;
         }
         self.next_token(); // eat ')'
 
-        let consequence = Box::new(self.parse_statement()?);
+        let body = Box::new(self.parse_statement()?);
 
-        let mut alternative = None;
-        if self.peek_token.token_type == TokenType::Else {
-            self.next_token(); // eat '}' or ';'
-            self.next_token(); // eat 'else'
-            alternative = Some(Box::new(self.parse_statement()?));
+        Some(Statement::new(
+            StatementKind::For {
+                init,
+                condition,
+                update,
+                body,
+            },
+            self.span_from(start),
+        ))
+    }
+
+    /// Parses the rest of a `for…of` loop after its target, which is the
+    /// current token.
+    fn parse_for_of(
+        &mut self,
+        start: Span,
+        declaration: Option<DeclarationKind>,
+        target: Pattern,
+    ) -> Option<Statement> {
+        // for ([let|const] <pattern> of <expr>) <stmt>
+        // `of` is already the peek token, so the lexer is about to read the
+        // token after it
+        self.lexer.expect_operand();
+        self.next_token(); // eat target
+        self.next_token(); // eat 'of'
+        let iterable = self.parse_expression(0)?;
+        if !self.expect_peek(TokenType::RParen, "')'") {
+            return None;
         }
+        self.next_token(); // eat ')'
 
-        Some(Statement::If {
-            condition,
-            consequence,
-            alternative,
-        })
+        let body = Box::new(self.parse_statement()?);
+
+        Some(Statement::new(
+            StatementKind::ForOf {
+                declaration,
+                target,
+                iterable,
+                body,
+            },
+            self.span_from(start),
+        ))
     }
 
-    fn parse_let_statement(&mut self) -> Option<Statement> {
-        // let <ident> = <expr>;
-        match &self.peek_token.token_type {
-            TokenType::Identifier(name) => {
-                let name = name.clone();
-                self.next_token(); // eat 'let'
+    /// Whether the next token is the `of` of a `for…of` loop, which is not
+    /// a reserved word anywhere else.
+    fn peek_of(&self) -> bool {
+        matches!(self.peek_token.token_type,
+            TokenType::Identifier(name) if self.lexer.resolve(name) == "of")
+    }
+
+    /// Parses `(<expr>)` following the current keyword, leaving `)` as the current token.
+    fn parse_parenthesized_condition(&mut self) -> Option<Expression> {
+        if !self.expect_peek(TokenType::LParen, "'('") {
+            return None;
+        }
+        self.next_token(); // eat '('
+        let condition = self.parse_expression(0)?;
+        if !self.expect_peek(TokenType::RParen, "')'") {
+            return None;
+        }
+        Some(condition)
+    }
+
+    fn parse_break_statement(&mut self) -> Option<Statement> {
+        // break [<label>];
+        let start = self.cur_token.span;
+        let label = self.parse_jump_label();
+        Some(Statement::new(
+            StatementKind::Break(label),
+            self.span_from(start),
+        ))
+    }
 
-                if self.peek_token.token_type != TokenType::Assign {
+    fn parse_continue_statement(&mut self) -> Option<Statement> {
+        // continue [<label>];
+        let start = self.cur_token.span;
+        let label = self.parse_jump_label();
+        Some(Statement::new(
+            StatementKind::Continue(label),
+            self.span_from(start),
+        ))
+    }
+
+    fn parse_jump_label(&mut self) -> Option<Identifier> {
+        // The label must be on the same line as the keyword
+        let label = match &self.peek_token.token_type {
+            TokenType::Identifier(label) if !self.peek_token.newline_before => {
+                let label = Identifier::new(self.name(*label), self.peek_token.span);
+                self.next_token(); // eat 'break' or 'continue'
+                Some(label)
+            }
+            _ => None,
+        };
+
+        if !self.consume_semicolon() {
+            return None;
+        }
+
+        label
+    }
+
+    fn parse_throw_statement(&mut self) -> Option<Statement> {
+        // throw [no LineTerminator here] <expr>;
+        let start = self.cur_token.span;
+        if self.peek_token.newline_before {
+            let diagnostic = Diagnostic::error(self.cur_token.span, "Illegal newline after throw")
+                .with_suggestion("start the thrown expression on the same line as `throw`");
+            self.report(diagnostic);
+            return None;
+        }
+        self.next_token(); // eat 'throw'
+        let value = self.parse_expression(0)?;
+        if !self.consume_semicolon() {
+            return None;
+        }
+
+        Some(Statement::new(
+            StatementKind::Throw(value),
+            self.span_from(start),
+        ))
+    }
+
+    fn parse_try_statement(&mut self) -> Option<Statement> {
+        // try { <stmts> } catch (<ident>) { <stmts> } finally { <stmts> }
+        let start = self.cur_token.span;
+        if !self.expect_peek(TokenType::LBrace, "'{'") {
+            return None;
+        }
+        let block = Box::new(self.parse_block_statement()?);
+
+        let mut param = None;
+        let mut handler = None;
+        if self.peek_token.token_type == TokenType::Catch {
+            // The binding is optional: `catch { ... }`
+            self.next_token(); // eat '}'
+            if self.peek_token.token_type == TokenType::LParen {
+                self.next_token(); // eat 'catch'
+                param = match &self.peek_token.token_type {
+                    TokenType::Identifier(name) => {
+                        Some(Identifier::new(self.name(*name), self.peek_token.span))
+                    }
+                    _ => {
+                        self.peek_error("catch parameter");
+                        return None;
+                    }
+                };
+                self.next_token(); // eat '('
+                if !self.expect_peek(TokenType::RParen, "')'") {
                     return None;
                 }
-                self.next_token(); // eat ident
-                self.next_token(); // eat '='
+            }
+            if !self.expect_peek(TokenType::LBrace, "'{'") {
+                return None;
+            }
+            handler = Some(Box::new(self.parse_block_statement()?));
+        }
 
-                let value = self.parse_expression(0)?;
+        let mut finalizer = None;
+        if self.peek_token.token_type == TokenType::Finally {
+            self.next_token(); // eat '}'
+            if !self.expect_peek(TokenType::LBrace, "'{'") {
+                return None;
+            }
+            finalizer = Some(Box::new(self.parse_block_statement()?));
+        }
 
-                if self.peek_token.token_type == TokenType::SemiColon {
-                    self.next_token();
+        if handler.is_none() && finalizer.is_none() {
+            let diagnostic =
+                Self::expected_error("'catch' or 'finally' after try", &self.peek_token)
+                    .with_suggestion("add a `catch { }` or `finally { }` block");
+            self.report(diagnostic);
+            return None;
+        }
+
+        Some(Statement::new(
+            StatementKind::Try {
+                block,
+                param,
+                handler,
+                finalizer,
+            },
+            self.span_from(start),
+        ))
+    }
+
+    fn parse_labeled_statement(&mut self) -> Option<Statement> {
+        // <label>: <stmt>
+        let start = self.cur_token.span;
+        let TokenType::Identifier(label) = self.cur_token.token_type else {
+            unreachable!("a labeled statement starts with an identifier");
+        };
+        let label = Identifier::new(self.name(label), start);
+        self.next_token(); // eat label
+        self.next_token(); // eat ':'
+        let body = Box::new(self.parse_statement()?);
+
+        Some(Statement::new(
+            StatementKind::Labeled { label, body },
+            self.span_from(start),
+        ))
+    }
+
+    fn parse_let_statement(&mut self) -> Option<Statement> {
+        // let <pattern> [= <expr>];
+        let start = self.cur_token.span;
+        self.next_token(); // eat 'let'
+        let pattern = self.parse_binding_pattern("variable name")?;
+        self.parse_declaration_rest(start, false, pattern)
+    }
+
+    fn parse_const_statement(&mut self) -> Option<Statement> {
+        // const <pattern> = <expr>;
+        let start = self.cur_token.span;
+        self.next_token(); // eat 'const'
+        let pattern = self.parse_binding_pattern("variable name")?;
+        self.parse_declaration_rest(start, true, pattern)
+    }
+
+    /// Parses the `= <expr>;` following the pattern of a `let` or `const`
+    /// declaration. Only a `const` or a destructuring `let` requires the
+    /// initializer.
+    fn parse_declaration_rest(
+        &mut self,
+        start: Span,
+        is_const: bool,
+        pattern: Pattern,
+    ) -> Option<Statement> {
+        let value = if self.peek_token.token_type == TokenType::Assign {
+            self.next_token(); // eat pattern
+            self.next_token(); // eat '='
+            Some(self.parse_expression(0)?)
+        } else {
+            None
+        };
+
+        let missing = match (&pattern.kind, &value) {
+            (_, Some(_)) => None,
+            (PatternKind::Identifier(name), None) if is_const => Some(
+                Diagnostic::error(
+                    self.cur_token.span,
+                    "Missing initializer in const declaration",
+                )
+                .with_suggestion(format!("const {} = undefined", name)),
+            ),
+            (PatternKind::Identifier(_), None) => None,
+            (_, None) => Some(Diagnostic::error(
+                self.span_from(start),
+                "Missing initializer in destructuring declaration",
+            )),
+        };
+        if let Some(diagnostic) = missing {
+            self.report(diagnostic);
+            return None;
+        }
+        if !self.consume_semicolon() {
+            return None;
+        }
+
+        let kind = match value {
+            Some(value) if is_const => StatementKind::Const { pattern, value },
+            value => StatementKind::Let { pattern, value },
+        };
+        Some(Statement::new(kind, self.span_from(start)))
+    }
+
+    /// Parses the name or `[…]` or `{…}` pattern that a declaration,
+    /// parameter or `for…of` loop binds, starting at the current token.
+    /// `expected` names what was expected if there is neither.
+    fn parse_binding_pattern(&mut self, expected: &str) -> Option<Pattern> {
+        match &self.cur_token.token_type {
+            TokenType::Identifier(name) => Some(Pattern::new(
+                PatternKind::Identifier(self.name(*name)),
+                self.cur_token.span,
+            )),
+            TokenType::LBracket => self.parse_array_pattern(expected),
+            TokenType::LBrace => self.parse_object_pattern(expected),
+            _ => {
+                self.cur_error(expected);
+                None
+            }
+        }
+    }
+
+    fn parse_array_pattern(&mut self, expected: &str) -> Option<Pattern> {
+        // [<pattern> [= <expr>], , ...<pattern>]
+        let start = self.cur_token.span;
+        let mut elements = vec![];
+        let mut rest = None;
+
+        while self.peek_token.token_type != TokenType::RBracket {
+            self.next_token(); // eat '[' or ','
+            let element_start = self.cur_token.span;
+            match self.cur_token.token_type {
+                // A hole, whose `,` also ends it
+                TokenType::Comma => {
+                    elements.push(None);
+                    continue;
+                }
+                TokenType::Ellipsis => {
+                    self.next_token(); // eat '...'
+                    let target = self.parse_binding_pattern(expected)?;
+                    rest = Some(Box::new(RestElement {
+                        target,
+                        span: self.span_from(element_start),
+                    }));
+                    self.check_rest_element_end(TokenType::RBracket)?;
+                    break;
                 }
+                _ => {
+                    let target = self.parse_binding_pattern(expected)?;
+                    let default = self.parse_default_value()?;
+                    elements.push(Some(ElementPattern {
+                        target,
+                        default,
+                        span: self.span_from(element_start),
+                    }));
+                }
+            }
 
-                Some(Statement::Let { name, value })
+            if self.peek_token.token_type == TokenType::Comma {
+                self.next_token(); // eat element
+            } else if self.peek_token.token_type != TokenType::RBracket {
+                self.peek_error("',' or ']'");
+                return None;
             }
-            _ => None,
         }
+        self.next_token(); // eat last element or '['
+
+        Some(Pattern::new(
+            PatternKind::Array { elements, rest },
+            self.span_from(start),
+        ))
     }
 
-    fn parse_const_statement(&mut self) -> Option<Statement> {
-        // const <ident> = <expr>;
-        match &self.peek_token.token_type {
-            TokenType::Identifier(name) => {
-                let name = name.clone();
-                self.next_token(); // eat 'const'
+    fn parse_object_pattern(&mut self, expected: &str) -> Option<Pattern> {
+        // {<key>: <pattern> [= <expr>], <name> [= <expr>], ...<name>}
+        let start = self.cur_token.span;
+        let mut properties = vec![];
+        let mut rest = None;
+
+        while self.peek_token.token_type != TokenType::RBrace {
+            self.next_token(); // eat '{' or ','
+            let property_start = self.cur_token.span;
+            if self.cur_token.token_type == TokenType::Ellipsis {
+                let name = match &self.peek_token.token_type {
+                    TokenType::Identifier(name) => self.name(*name),
+                    _ => {
+                        self.peek_error(expected);
+                        return None;
+                    }
+                };
+                self.next_token(); // eat '...'
+                let target = Pattern::new(PatternKind::Identifier(name), self.cur_token.span);
+                rest = Some(Box::new(RestElement {
+                    target,
+                    span: self.span_from(property_start),
+                }));
+                self.check_rest_element_end(TokenType::RBrace)?;
+                break;
+            }
 
-                if self.peek_token.token_type != TokenType::Assign {
+            let name = match self.cur_token.token_type {
+                TokenType::Identifier(name) => Some(self.name(name)),
+                _ => None,
+            };
+            let key = self.parse_property_key()?;
+            let value = match name {
+                _ if self.peek_token.token_type == TokenType::Colon => {
+                    self.next_token(); // eat key
+                    self.next_token(); // eat ':'
+                    self.parse_binding_pattern(expected)?
+                }
+                Some(name) => Pattern::new(PatternKind::Identifier(name), key.span),
+                None => {
+                    self.peek_error("':'");
                     return None;
                 }
-                self.next_token(); // eat ident
-                self.next_token(); // eat '='
+            };
+            let default = self.parse_default_value()?;
+            properties.push(PropertyPattern {
+                key,
+                value,
+                default,
+                span: self.span_from(property_start),
+            });
 
-                let value = self.parse_expression(0)?;
+            if self.peek_token.token_type == TokenType::Comma {
+                self.next_token(); // eat property
+            } else if self.peek_token.token_type != TokenType::RBrace {
+                self.peek_error("',' or '}'");
+                return None;
+            }
+        }
+        self.next_token(); // eat last property or '{'
 
-                if self.peek_token.token_type == TokenType::SemiColon {
-                    self.next_token();
-                }
+        Some(Pattern::new(
+            PatternKind::Object { properties, rest },
+            self.span_from(start),
+        ))
+    }
+
+    /// Parses the `= <expr>` giving a pattern element a default, if there
+    /// is one.
+    fn parse_default_value(&mut self) -> Option<Option<Expression>> {
+        if self.peek_token.token_type != TokenType::Assign {
+            return Some(None);
+        }
+        self.next_token(); // eat target
+        self.next_token(); // eat '='
+        Some(Some(self.parse_expression(0)?))
+    }
+
+    /// Checks that the rest element just parsed is followed by `end`, since
+    /// it must be last and cannot have a default.
+    fn check_rest_element_end(&mut self, end: TokenType) -> Option<()> {
+        if self.peek_token.token_type == end {
+            return Some(());
+        }
+        let message = match self.peek_token.token_type {
+            TokenType::Assign => "Rest element may not have a default initializer",
+            _ => "Rest element must be last element",
+        };
+        self.report(Diagnostic::error(self.peek_token.span, message));
+        None
+    }
+
+    fn parse_return_statement(&mut self) -> Option<Statement> {
+        // return [no LineTerminator here] [<expr>];
+        let start = self.cur_token.span;
+        let value =
+            if self.peek_token.token_type == TokenType::SemiColon || self.can_insert_semicolon() {
+                None
+            } else {
+                self.next_token(); // eat 'return'
+                Some(self.parse_expression(0)?)
+            };
+        if !self.consume_semicolon() {
+            return None;
+        }
+
+        Some(Statement::new(
+            StatementKind::Return(value),
+            self.span_from(start),
+        ))
+    }
 
-                Some(Statement::Const { name, value })
+    fn parse_function_statement(&mut self) -> Option<Statement> {
+        // function <ident>(<params>) { <body> }
+        let start = self.cur_token.span;
+        let name = match &self.peek_token.token_type {
+            TokenType::Identifier(name) => Identifier::new(self.name(*name), self.peek_token.span),
+            _ => {
+                self.peek_error("function name");
+                return None;
+            }
+        };
+        self.next_token(); // eat 'function'
+
+        let (params, body) = self.parse_function_rest()?;
+        Some(Statement::new(
+            StatementKind::Function { name, params, body },
+            self.span_from(start),
+        ))
+    }
+
+    fn parse_function_literal(&mut self) -> Option<Expression> {
+        // function [<ident>](<params>) { <body> }
+        let start = self.cur_token.span;
+        let name = match &self.peek_token.token_type {
+            TokenType::Identifier(name) => {
+                let name = Identifier::new(self.name(*name), self.peek_token.span);
+                self.next_token(); // eat 'function'
+                Some(name)
             }
             _ => None,
+        };
+
+        let (params, body) = self.parse_function_rest()?;
+        Some(Expression::new(
+            ExpressionKind::Function { name, params, body },
+            self.span_from(start),
+        ))
+    }
+
+    fn parse_function_rest(&mut self) -> Option<(Vec<Parameter>, Box<Statement>)> {
+        if !self.expect_peek(TokenType::LParen, "'('") {
+            return None;
+        }
+        let params = self.parse_function_parameters()?;
+
+        if !self.expect_peek(TokenType::LBrace, "'{'") {
+            return None;
+        }
+        // A "use strict" directive only applies to the function it is in
+        let strict = self.strict;
+        let body = self.parse_block(true);
+        self.strict = strict;
+
+        Some((params, Box::new(body?)))
+    }
+
+    fn parse_function_parameters(&mut self) -> Option<Vec<Parameter>> {
+        let mut params = vec![];
+
+        while self.peek_token.token_type != TokenType::RParen {
+            self.next_token(); // eat '(' or ','
+            let start = self.cur_token.span;
+            if self.cur_token.token_type == TokenType::Ellipsis {
+                let target = self.parse_rest_parameter()?;
+                params.push(Parameter {
+                    target,
+                    default: None,
+                    rest: true,
+                    span: self.span_from(start),
+                });
+                break;
+            }
+            let target = self.parse_binding_pattern("parameter name")?;
+            let default = self.parse_default_value()?;
+            params.push(Parameter {
+                target,
+                default,
+                rest: false,
+                span: self.span_from(start),
+            });
+
+            if self.peek_token.token_type == TokenType::Comma {
+                self.next_token(); // eat param, a trailing comma is allowed
+            } else if self.peek_token.token_type != TokenType::RParen {
+                self.peek_error("',' or ')'");
+                return None;
+            }
         }
+        self.next_token(); // eat last param or '(' if empty
+
+        Some(params)
     }
 
-    fn parse_return_statement(&mut self) -> Option<Statement> {
-        self.next_token(); // eat 'return'
+    /// Parses the pattern after a `...` in a parameter list, which must be
+    /// the last parameter and cannot have a default.
+    fn parse_rest_parameter(&mut self) -> Option<Pattern> {
+        self.next_token(); // eat '...'
+        let target = self.parse_binding_pattern("parameter name")?;
 
-        let value = if self.cur_token.token_type == TokenType::SemiColon {
-            None
+        match self.peek_token.token_type {
+            TokenType::RParen => Some(target),
+            TokenType::Assign => {
+                let diagnostic = Diagnostic::error(
+                    self.peek_token.span,
+                    "Rest parameter may not have a default initializer",
+                );
+                self.report(diagnostic);
+                None
+            }
+            _ => {
+                let diagnostic = Diagnostic::error(
+                    self.peek_token.span,
+                    "Rest parameter must be last formal parameter",
+                );
+                self.report(diagnostic);
+                None
+            }
+        }
+    }
+
+    /// Parses the `=>` and body of an arrow function whose parameters end
+    /// at the current token.
+    fn parse_arrow_function(&mut self, params: Vec<Parameter>, start: Span) -> Option<Expression> {
+        self.next_token(); // eat the parameters
+        let body = if self.peek_token.token_type == TokenType::LBrace {
+            self.next_token(); // eat '=>'
+            let strict = self.strict;
+            let body = self.parse_block(true);
+            self.strict = strict;
+            ArrowBody::Block(Box::new(body?))
         } else {
-            Some(self.parse_expression(0)?)
+            self.next_token(); // eat '=>'
+            ArrowBody::Expression(Box::new(self.parse_expression(0)?))
         };
 
-        if self.peek_token.token_type == TokenType::SemiColon {
-            self.next_token();
-        }
+        Some(Expression::new(
+            ExpressionKind::Arrow { params, body },
+            self.span_from(start),
+        ))
+    }
 
-        Some(Statement::Return(value))
+    /// Whether the next token is a `=>` on the same line, making what was
+    /// just parsed the parameters of an arrow function.
+    fn peek_arrow(&self) -> bool {
+        self.peek_token.token_type == TokenType::Arrow && !self.peek_token.newline_before
     }
 
     fn parse_expression_statement(&mut self) -> Option<Statement> {
         let expr = self.parse_expression(0)?;
-
-        if self.peek_token.token_type == TokenType::SemiColon {
-            self.next_token();
+        if !self.consume_semicolon() {
+            return None;
         }
 
-        Some(Statement::Expression(expr))
+        let span = self.span_from(expr.span);
+        Some(Statement::new(StatementKind::Expression(expr), span))
     }
 
     fn parse_expression(&mut self, precedence: u8) -> Option<Expression> {
         let mut left = self.parse_prefix()?;
 
+        // An arrow function is an assignment expression, and its body takes
+        // everything an operator after it could apply to
+        if matches!(left.kind, ExpressionKind::Arrow { .. })
+            && left.span.end == self.cur_token.span.end
+        {
+            if precedence > 0 {
+                let diagnostic =
+                    Diagnostic::error(left.span, "Malformed arrow function parameter list");
+                self.report(diagnostic);
+                return None;
+            }
+            return Some(left);
+        }
+
         while self.peek_token.token_type != TokenType::SemiColon
             && precedence < self.peek_precedence()
+            && !self.is_restricted_postfix()
         {
+            if self.peek_token.token_type == TokenType::Nullish {
+                self.check_nullish_operand(&left)?;
+            }
             self.next_token();
             left = self.parse_infix(left)?;
         }
@@ -193,104 +1088,757 @@ impl Parser {
         Some(left)
     }
 
+    /// Rejects numbers with a leading zero, such as `017` and `089`, in
+    /// strict mode code.
+    fn check_legacy_number(&mut self) {
+        let mut digits = self.cur_token.literal.chars();
+        let is_legacy =
+            digits.next() == Some('0') && digits.next().is_some_and(|c| c.is_ascii_digit());
+        if !self.strict || !is_legacy {
+            return;
+        }
+
+        let literal = &self.cur_token.literal;
+        let digits = match literal.trim_start_matches('0') {
+            "" => "0",
+            digits => digits,
+        };
+        let diagnostic = if literal.chars().all(|c| c.is_digit(8)) {
+            Diagnostic::error(
+                self.cur_token.span,
+                "Octal literals are not allowed in strict mode",
+            )
+            .with_suggestion(format!("0o{}", digits))
+        } else {
+            Diagnostic::error(
+                self.cur_token.span,
+                "Decimals with leading zeros are not allowed in strict mode",
+            )
+            .with_suggestion(digits.to_string())
+        };
+        self.report(diagnostic);
+    }
+
+    /// Rejects legacy octal escapes such as `"\101"` and `"\08"` in strict
+    /// mode code. `\0` alone is still allowed.
+    fn check_octal_escape(&mut self) {
+        if !self.strict {
+            return;
+        }
+
+        let mut chars = self.cur_token.literal.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                continue;
+            }
+            let is_octal = match chars.next() {
+                Some('0') => chars.peek().is_some_and(|c| c.is_ascii_digit()),
+                Some(c) => ('1'..='9').contains(&c),
+                None => false,
+            };
+            if is_octal {
+                self.error_at_cur("Octal escape sequences are not allowed in strict mode");
+                return;
+            }
+        }
+    }
+
+    /// Whether the next token is a `++` or `--` on a new line, which starts
+    /// a prefix update in the next statement instead: `a\n++b` is `a; ++b;`.
+    fn is_restricted_postfix(&self) -> bool {
+        matches!(
+            self.peek_token.token_type,
+            TokenType::PlusPlus | TokenType::MinusMinus
+        ) && self.peek_token.newline_before
+    }
+
     fn parse_array_literal(&mut self) -> Option<Expression> {
+        let start = self.cur_token.span;
         let mut elements = vec![];
         if self.peek_token.token_type == TokenType::RBracket {
             self.next_token();
-            return Some(Expression::Array(elements));
+            return Some(Expression::new(
+                ExpressionKind::Array(elements),
+                self.span_from(start),
+            ));
         }
 
         self.next_token();
-        elements.push(self.parse_expression(0)?);
+        elements.push(self.parse_element()?);
 
         while self.peek_token.token_type == TokenType::Comma {
             self.next_token();
             self.next_token();
-            elements.push(self.parse_expression(0)?);
+            elements.push(self.parse_element()?);
         }
 
-        if self.peek_token.token_type != TokenType::RBracket {
+        if !self.expect_peek(TokenType::RBracket, "']'") {
             return None;
         }
-        self.next_token();
 
-        Some(Expression::Array(elements))
+        Some(Expression::new(
+            ExpressionKind::Array(elements),
+            self.span_from(start),
+        ))
     }
 
-    fn parse_object_literal(&mut self) -> Option<Expression> {
-        let mut pairs = vec![];
-        if self.peek_token.token_type == TokenType::RBrace {
-            self.next_token();
-            return Some(Expression::Object(pairs));
+    /// Parses an array element or call argument, which can be spread with
+    /// `...`.
+    fn parse_element(&mut self) -> Option<Expression> {
+        if self.cur_token.token_type != TokenType::Ellipsis {
+            return self.parse_expression(0);
         }
+        let start = self.cur_token.span;
+        self.next_token(); // eat '...'
+        let argument = self.parse_expression(0)?;
+        Some(Expression::new(
+            ExpressionKind::Spread(Box::new(argument)),
+            self.span_from(start),
+        ))
+    }
 
-        self.next_token();
-        pairs.push(self.parse_object_pair()?);
+    fn parse_object_literal(&mut self) -> Option<Expression> {
+        // {<key>: <expr>, <name>, <key>(<params>) {…}, get <key>() {…},
+        //  set <key>(<param>) {…}, ...<expr>}
+        let start = self.cur_token.span;
+        let mut properties = vec![];
+        while self.peek_token.token_type != TokenType::RBrace {
+            self.next_token(); // eat '{' or ','
+            properties.push(self.parse_object_property()?);
 
-        while self.peek_token.token_type == TokenType::Comma {
-            self.next_token();
-            self.next_token();
-            pairs.push(self.parse_object_pair()?);
+            if self.peek_token.token_type == TokenType::Comma {
+                self.next_token(); // eat property, a trailing comma is allowed
+            } else if self.peek_token.token_type != TokenType::RBrace {
+                self.peek_error("',' or '}'");
+                return None;
+            }
         }
+        self.next_token(); // eat last property or '{'
 
-        if self.peek_token.token_type != TokenType::RBrace {
-            return None;
+        Some(Expression::new(
+            ExpressionKind::Object(properties),
+            self.span_from(start),
+        ))
+    }
+
+    fn parse_object_property(&mut self) -> Option<Property> {
+        let start = self.cur_token.span;
+        if self.cur_token.token_type == TokenType::Ellipsis {
+            self.next_token(); // eat '...'
+            let argument = self.parse_expression(0)?;
+            return Some(Property::new(
+                PropertyKind::Spread(argument),
+                self.span_from(start),
+            ));
+        }
+        if let Some(kind) = self.accessor_kind() {
+            self.next_token(); // eat 'get' or 'set'
+            let key = self.parse_property_key()?;
+            return self.parse_method(start, kind, key);
         }
-        self.next_token();
 
-        Some(Expression::Object(pairs))
+        let name = match self.cur_token.token_type {
+            TokenType::Identifier(name) => Some(self.name(name)),
+            _ => None,
+        };
+        let key = self.parse_property_key()?;
+        let kind = match (&self.peek_token.token_type, name) {
+            (TokenType::LParen, _) => return self.parse_method(start, MethodKind::Method, key),
+            (TokenType::Colon, _) => {
+                self.next_token(); // eat key
+                self.next_token(); // eat ':'
+                let value = self.parse_expression(0)?;
+                PropertyKind::Value { key, value }
+            }
+            // `{a}` is short for `{a: a}`
+            (TokenType::Comma | TokenType::RBrace, Some(name)) => {
+                let value = Expression::new(ExpressionKind::Identifier(name), start);
+                PropertyKind::Value { key, value }
+            }
+            // `{a = 1}` only makes sense as a pattern
+            (TokenType::Assign, Some(name)) => {
+                self.next_token(); // eat key
+                self.next_token(); // eat '='
+                let default = self.parse_expression(0)?;
+                let span = self.span_from(start);
+                self.shorthand_defaults.push(span);
+                let value = Expression::new(
+                    ExpressionKind::Assign {
+                        target: Pattern::new(PatternKind::Identifier(name), start),
+                        operator: "=".to_string(),
+                        value: Box::new(default),
+                    },
+                    span,
+                );
+                PropertyKind::Value { key, value }
+            }
+            _ => {
+                self.peek_error("':'");
+                return None;
+            }
+        };
+        Some(Property::new(kind, self.span_from(start)))
     }
 
-    fn parse_object_pair(&mut self) -> Option<(String, Expression)> {
-        let key = match &self.cur_token.token_type {
-            TokenType::Identifier(s) | TokenType::String(s) => s.clone(),
+    /// Whether the current token is the `get` or `set` of an accessor
+    /// rather than a property named "get" or "set".
+    fn accessor_kind(&self) -> Option<MethodKind> {
+        let TokenType::Identifier(name) = self.cur_token.token_type else {
+            return None;
+        };
+        let kind = match self.lexer.resolve(name) {
+            "get" => MethodKind::Get,
+            "set" => MethodKind::Set,
             _ => return None,
         };
-        self.next_token();
-        if self.cur_token.token_type != TokenType::Colon {
-            return None;
+        matches!(
+            self.peek_token.token_type,
+            TokenType::Identifier(_)
+                | TokenType::String(_)
+                | TokenType::Number(_)
+                | TokenType::LBracket
+        )
+        .then_some(kind)
+    }
+
+    /// Parses the key of a property in an object literal or pattern: a
+    /// name, string, number or `[<expr>]`. Leaves its last token current.
+    fn parse_property_key(&mut self) -> Option<PropertyKey> {
+        let kind = match &self.cur_token.token_type {
+            TokenType::Identifier(name) => PropertyKeyKind::String(self.name(*name)),
+            TokenType::String(s) => PropertyKeyKind::String(s.to_string()),
+            TokenType::Number(value) => PropertyKeyKind::Number(*value),
+            TokenType::LBracket => {
+                self.next_token(); // eat '['
+                let key = self.parse_expression(0)?;
+                if !self.expect_peek(TokenType::RBracket, "']'") {
+                    return None;
+                }
+                let span = key.span;
+                return Some(PropertyKey::new(
+                    PropertyKeyKind::Computed(Box::new(key)),
+                    span,
+                ));
+            }
+            _ => {
+                self.cur_error("property name");
+                return None;
+            }
+        };
+        Some(PropertyKey::new(kind, self.cur_token.span))
+    }
+
+    /// Parses the parameters and body of a method, getter or setter after
+    /// its key. The property started at `start`.
+    fn parse_method(
+        &mut self,
+        start: Span,
+        kind: MethodKind,
+        key: PropertyKey,
+    ) -> Option<Property> {
+        let params_span = self.peek_token.span;
+        let (params, body) = self.parse_function_rest()?;
+        let message = match kind {
+            MethodKind::Get if !params.is_empty() => {
+                Some("Getter must not have any formal parameters")
+            }
+            MethodKind::Set if params.len() != 1 => {
+                Some("Setter must have exactly one formal parameter")
+            }
+            MethodKind::Set if params[0].rest => {
+                Some("Setter function argument must not be a rest parameter")
+            }
+            _ => None,
+        };
+        if let Some(message) = message {
+            self.report(Diagnostic::error(params_span, message));
         }
-        self.next_token();
-        let value = self.parse_expression(0)?;
-        Some((key, value))
+        let kind = PropertyKind::Method {
+            kind,
+            key,
+            params,
+            body,
+        };
+        Some(Property::new(kind, self.span_from(start)))
     }
 
     fn parse_index_expression(&mut self, left: Expression) -> Option<Expression> {
         self.next_token();
         let index = self.parse_expression(0)?;
-        if self.peek_token.token_type != TokenType::RBracket {
+        if !self.expect_peek(TokenType::RBracket, "']'") {
             return None;
         }
-        self.next_token();
-        Some(Expression::Index {
-            left: Box::new(left),
-            index: Box::new(index),
-        })
+        let span = self.span_from(left.span);
+        Some(Expression::new(
+            ExpressionKind::Index {
+                left: Box::new(left),
+                index: Box::new(index),
+            },
+            span,
+        ))
     }
 
     fn parse_member_expression(&mut self, left: Expression) -> Option<Expression> {
         self.next_token();
         let property = match &self.cur_token.token_type {
-            TokenType::Identifier(s) => s.clone(),
-            _ => return None,
+            TokenType::Identifier(name) => self.name(*name),
+            _ => {
+                self.cur_error("property name");
+                return None;
+            }
         };
-        self.next_token();
 
-        Some(Expression::Index {
-            left: Box::new(left),
-            index: Box::new(Expression::String(property)),
-        })
+        let property = Expression::new(ExpressionKind::String(property), self.cur_token.span);
+        let span = self.span_from(left.span);
+        Some(Expression::new(
+            ExpressionKind::Index {
+                left: Box::new(left),
+                index: Box::new(property),
+            },
+            span,
+        ))
     }
 
     fn parse_prefix(&mut self) -> Option<Expression> {
+        let literal = match &self.cur_token.token_type {
+            TokenType::Identifier(name) if self.peek_arrow() => {
+                let params = vec![Parameter::new(self.name(*name), self.cur_token.span)];
+                return self.parse_arrow_function(params, self.cur_token.span);
+            }
+            TokenType::Identifier(name) => ExpressionKind::Identifier(self.name(*name)),
+            TokenType::Number(val) => {
+                let val = *val;
+                self.check_legacy_number();
+                ExpressionKind::Number(val)
+            }
+            TokenType::BigInt(digits) => ExpressionKind::BigInt(digits.to_string()),
+            TokenType::String(val) => {
+                let val = val.to_string();
+                self.check_octal_escape();
+                ExpressionKind::String(val)
+            }
+            TokenType::RegExp { pattern, flags } => ExpressionKind::RegExp {
+                pattern: pattern.to_string(),
+                flags: flags.to_string(),
+            },
+            TokenType::True => ExpressionKind::Boolean(true),
+            TokenType::False => ExpressionKind::Boolean(false),
+            TokenType::Null => ExpressionKind::Null,
+            TokenType::This => ExpressionKind::This,
+            _ => return self.parse_compound_prefix(),
+        };
+        Some(Expression::new(literal, self.cur_token.span))
+    }
+
+    fn parse_compound_prefix(&mut self) -> Option<Expression> {
         match &self.cur_token.token_type {
-            TokenType::Identifier(name) => Some(Expression::Identifier(name.clone())),
-            TokenType::Number(val) => Some(Expression::Number(*val)),
-            TokenType::String(val) => Some(Expression::String(val.clone())),
+            TokenType::LParen => self.parse_parenthesized(),
+            TokenType::Bang
+            | TokenType::Minus
+            | TokenType::Plus
+            | TokenType::Tilde
+            | TokenType::Typeof
+            | TokenType::Void
+            | TokenType::Delete => self.parse_prefix_expression(),
             TokenType::LBracket => self.parse_array_literal(),
             TokenType::LBrace => self.parse_object_literal(),
-            _ => None,
+            TokenType::Function => self.parse_function_literal(),
+            TokenType::PlusPlus | TokenType::MinusMinus => self.parse_prefix_update(),
+            TokenType::NoSubstitutionTemplate(_) | TokenType::TemplateHead(_) => {
+                self.parse_template(false)
+            }
+            TokenType::EOF => {
+                self.error_at_cur("Unexpected end of input");
+                None
+            }
+            // The lexer has already reported it
+            TokenType::Illegal => None,
+            _ => {
+                let message = format!("Unexpected token '{}'", self.cur_token.literal);
+                self.error_at_cur(&message);
+                None
+            }
+        }
+    }
+
+    /// Parses a template literal starting at its first part. Each `${`
+    /// substitution is followed by the `}` part continuing the template.
+    fn parse_template(&mut self, tagged: bool) -> Option<Expression> {
+        let start = self.cur_token.span;
+        let mut quasis = vec![];
+        let mut expressions = vec![];
+
+        loop {
+            let (chunk, tail) = match &self.cur_token.token_type {
+                TokenType::NoSubstitutionTemplate(chunk) | TokenType::TemplateTail(chunk) => {
+                    (chunk.clone(), true)
+                }
+                TokenType::TemplateHead(chunk) | TokenType::TemplateMiddle(chunk) => {
+                    (chunk.clone(), false)
+                }
+                _ => unreachable!("parse_template called on a non-template token"),
+            };
+            if chunk.cooked.is_none() && !tagged {
+                self.error_at_cur("Invalid escape sequence in template");
+                return None;
+            }
+            quasis.push(self.template_element(chunk, tail));
+            if tail {
+                break;
+            }
+
+            self.next_token(); // eat the part ending in '${'
+            if let TokenType::TemplateMiddle(_) | TokenType::TemplateTail(_) =
+                self.cur_token.token_type
+            {
+                self.error_at_cur("Expected an expression in template substitution");
+                return None;
+            }
+            expressions.push(self.parse_expression(0)?);
+            if !matches!(
+                self.peek_token.token_type,
+                TokenType::TemplateMiddle(_) | TokenType::TemplateTail(_)
+            ) {
+                self.peek_error("'}'");
+                return None;
+            }
+            self.next_token();
+        }
+
+        Some(Expression::new(
+            ExpressionKind::Template {
+                quasis,
+                expressions,
+            },
+            self.span_from(start),
+        ))
+    }
+
+    /// Builds the element for the current template part, with a span that
+    /// leaves out its '`', '}' and '${' delimiters.
+    fn template_element(&self, chunk: TemplateChunk, tail: bool) -> TemplateElement {
+        let span = self.cur_token.span;
+        let end_delimiter = if tail { 1 } else { 2 };
+        let span = Span::new(
+            Position::new(
+                span.start.line,
+                span.start.column + 1,
+                span.start.offset + 1,
+            ),
+            Position::new(
+                span.end.line,
+                span.end.column - end_delimiter,
+                span.end.offset - end_delimiter,
+            ),
+        );
+        TemplateElement {
+            cooked: chunk.cooked.map(String::from),
+            raw: chunk.raw.into_owned(),
+            span,
+        }
+    }
+
+    fn parse_tagged_template(&mut self, tag: Expression) -> Option<Expression> {
+        let quasi = self.parse_template(true)?;
+        let span = self.span_from(tag.span);
+        Some(Expression::new(
+            ExpressionKind::TaggedTemplate {
+                tag: Box::new(tag),
+                quasi: Box::new(quasi),
+            },
+            span,
+        ))
+    }
+
+    /// Parses a parenthesized expression, or the parameters of an arrow
+    /// function if a `=>` follows the `)`. The parameters are parsed as
+    /// expressions until then: `a` and `a = 1` read the same either way.
+    fn parse_parenthesized(&mut self) -> Option<Expression> {
+        let start = self.cur_token.span;
+        let mut items = vec![];
+        let mut rest = None;
+        // The first `,` or `...`, which only a parameter list can contain
+        let mut parameter_token = None;
+
+        while self.peek_token.token_type != TokenType::RParen {
+            self.next_token(); // eat '(' or ','
+            if self.cur_token.token_type == TokenType::Ellipsis {
+                let rest_start = self.cur_token.span;
+                parameter_token.get_or_insert_with(|| self.cur_token.clone());
+                let target = self.parse_rest_parameter()?;
+                rest = Some((target, self.span_from(rest_start)));
+                break;
+            }
+            items.push(self.parse_expression(0)?);
+
+            if self.peek_token.token_type == TokenType::Comma {
+                parameter_token.get_or_insert_with(|| self.peek_token.clone());
+                self.next_token(); // eat item
+            } else if self.peek_token.token_type != TokenType::RParen {
+                self.peek_error("')'");
+                return None;
+            }
+        }
+        self.next_token(); // eat last item or '(' if empty
+
+        if self.peek_arrow() {
+            let mut params = Vec::with_capacity(items.len() + 1);
+            for item in items {
+                params.push(self.arrow_parameter(item)?);
+            }
+            if let Some((target, span)) = rest {
+                params.push(Parameter {
+                    target,
+                    default: None,
+                    rest: true,
+                    span,
+                });
+            }
+            return self.parse_arrow_function(params, start);
+        }
+
+        match (parameter_token, items.pop()) {
+            (None, Some(expr)) => {
+                if matches!(
+                    expr.kind,
+                    ExpressionKind::Array(_) | ExpressionKind::Object(_)
+                ) {
+                    self.parenthesized_literals.push(expr.span);
+                }
+                Some(expr)
+            }
+            (None, None) => {
+                self.error_at_cur("Unexpected token ')'");
+                None
+            }
+            (Some(token), _) => {
+                let diagnostic = match token.token_type {
+                    TokenType::Comma => Self::expected_error("')'", &token),
+                    _ => Diagnostic::error(
+                        token.span,
+                        format!("Unexpected token '{}'", token.literal),
+                    ),
+                };
+                self.report(diagnostic);
+                None
+            }
+        }
+    }
+
+    /// Converts an expression parsed inside parentheses to the arrow
+    /// function parameter it spells out.
+    fn arrow_parameter(&mut self, item: Expression) -> Option<Parameter> {
+        let span = item.span;
+        match self.reinterpret_as_element(item, true) {
+            Ok((target, default)) => Some(Parameter {
+                target,
+                default,
+                rest: false,
+                span,
+            }),
+            Err(span) => {
+                self.report(Diagnostic::error(span, "Invalid arrow function parameter"));
+                None
+            }
+        }
+    }
+
+    /// Converts an expression that turned out to be a pattern, such as the
+    /// array literal in `[a, b] = [b, a]`, to that pattern. Members such as
+    /// `a.b` are only allowed when `binding` is false. Fails with the span
+    /// of the part that is not a pattern.
+    fn reinterpret_as_pattern(&mut self, expr: Expression, binding: bool) -> Result<Pattern, Span> {
+        let span = expr.span;
+        if self.parenthesized_literals.contains(&span) {
+            return Err(span);
+        }
+        let kind = match expr.kind {
+            ExpressionKind::Identifier(name) => PatternKind::Identifier(name),
+            ExpressionKind::Index { .. } if !binding => PatternKind::Member(Box::new(expr)),
+            ExpressionKind::Array(items) => {
+                let mut elements = Vec::with_capacity(items.len());
+                let mut rest = None;
+                let count = items.len();
+                for (i, item) in items.into_iter().enumerate() {
+                    let item_span = item.span;
+                    match item.kind {
+                        ExpressionKind::Spread(argument) if i == count - 1 => {
+                            rest = Some(Box::new(RestElement {
+                                target: self.reinterpret_as_pattern(*argument, binding)?,
+                                span: item_span,
+                            }));
+                        }
+                        ExpressionKind::Spread(_) => return Err(item_span),
+                        _ => {
+                            let (target, default) = self.reinterpret_as_element(item, binding)?;
+                            elements.push(Some(ElementPattern {
+                                target,
+                                default,
+                                span: item_span,
+                            }));
+                        }
+                    }
+                }
+                PatternKind::Array { elements, rest }
+            }
+            ExpressionKind::Object(members) => {
+                self.shorthand_defaults
+                    .retain(|default| default.start < span.start || default.end > span.end);
+                let mut properties = Vec::with_capacity(members.len());
+                let mut rest = None;
+                let count = members.len();
+                for (i, member) in members.into_iter().enumerate() {
+                    match member.kind {
+                        PropertyKind::Value { key, value } => {
+                            let (value, default) = self.reinterpret_as_element(value, binding)?;
+                            properties.push(PropertyPattern {
+                                key,
+                                value,
+                                default,
+                                span: member.span,
+                            });
+                        }
+                        // `...rest` takes the remaining properties as a
+                        // whole, so it cannot be taken apart further
+                        PropertyKind::Spread(argument) if i == count - 1 => {
+                            let argument_span = argument.span;
+                            let target = self.reinterpret_as_pattern(argument, binding)?;
+                            match target.kind {
+                                PatternKind::Identifier(_) | PatternKind::Member(_) => {
+                                    rest = Some(Box::new(RestElement {
+                                        target,
+                                        span: member.span,
+                                    }));
+                                }
+                                _ => return Err(argument_span),
+                            }
+                        }
+                        PropertyKind::Spread(argument) => return Err(argument.span),
+                        PropertyKind::Method { .. } => return Err(span),
+                    }
+                }
+                PatternKind::Object { properties, rest }
+            }
+            _ => return Err(span),
+        };
+        Ok(Pattern::new(kind, span))
+    }
+
+    /// Converts an element of an array or object literal that turned out
+    /// to be a pattern, where `a = 1` gives `a` a default.
+    fn reinterpret_as_element(
+        &mut self,
+        expr: Expression,
+        binding: bool,
+    ) -> Result<(Pattern, Option<Expression>), Span> {
+        match expr.kind {
+            ExpressionKind::Assign {
+                target,
+                operator,
+                value,
+            } if operator == "=" => {
+                // The target was already made a pattern for assignment
+                if binding && has_member(&target) {
+                    return Err(expr.span);
+                }
+                Ok((target, Some(*value)))
+            }
+            _ => Ok((self.reinterpret_as_pattern(expr, binding)?, None)),
+        }
+    }
+
+    /// Reports an `&&` or `||` expression used as an operand of `??`, which
+    /// the grammar only allows in parentheses. The operand must end at the
+    /// current token, so a parenthesized one is told apart by its span
+    /// ending before the `)`.
+    fn check_nullish_operand(&mut self, operand: &Expression) -> Option<()> {
+        let ExpressionKind::Infix { operator, .. } = &operand.kind else {
+            return Some(());
+        };
+        if !matches!(operator.as_str(), "&&" | "||") || operand.span.end != self.cur_token.span.end
+        {
+            return Some(());
         }
+        let diagnostic = Diagnostic::error(
+            operand.span,
+            format!("Cannot mix '{}' with '??' without parentheses", operator),
+        )
+        .with_suggestion(format!("wrap the '{}' expression in parentheses", operator));
+        self.report(diagnostic);
+        None
+    }
+
+    fn parse_prefix_expression(&mut self) -> Option<Expression> {
+        let start = self.cur_token.span;
+        let operator = self.cur_token.literal.to_string();
+        self.next_token(); // eat operator
+        let right = self.parse_expression(PREFIX)?;
+
+        if operator == "delete" && self.strict {
+            if let ExpressionKind::Identifier(_) = right.kind {
+                self.report(Diagnostic::error(
+                    right.span,
+                    "Delete of an unqualified identifier in strict mode",
+                ));
+                return None;
+            }
+        }
+
+        // `-a ** b` is ambiguous, so the grammar requires parentheses
+        if self.peek_token.token_type == TokenType::StarStar {
+            // Keyword operators such as `typeof` need a space before the operand
+            let operator = if operator.chars().all(char::is_alphabetic) {
+                format!("{} ", operator)
+            } else {
+                operator
+            };
+            let diagnostic = Diagnostic::error(
+                self.span_from(start),
+                "Unary operator used immediately before exponentiation expression; \
+                 parentheses must be used to disambiguate operator precedence",
+            )
+            .with_suggestion(format!(
+                "write `({}x) ** y` or `{}(x ** y)`",
+                operator, operator
+            ));
+            self.report(diagnostic);
+            return None;
+        }
+
+        Some(Expression::new(
+            ExpressionKind::Prefix {
+                operator,
+                right: Box::new(right),
+            },
+            self.span_from(start),
+        ))
+    }
+
+    fn parse_prefix_update(&mut self) -> Option<Expression> {
+        let start = self.cur_token.span;
+        let operator = self.cur_token.literal.to_string();
+        self.next_token(); // eat '++' or '--'
+        let target = self.parse_expression(PREFIX)?;
+        if !Self::is_assignment_target(&target) {
+            self.error_at_cur("Invalid left-hand side expression in prefix operation");
+            return None;
+        }
+
+        Some(Expression::new(
+            ExpressionKind::Update {
+                operator,
+                prefix: true,
+                target: Box::new(target),
+            },
+            self.span_from(start),
+        ))
+    }
+
+    fn is_assignment_target(expr: &Expression) -> bool {
+        matches!(
+            expr.kind,
+            ExpressionKind::Identifier(_) | ExpressionKind::Index { .. }
+        )
     }
 
     fn parse_infix(&mut self, left: Expression) -> Option<Expression> {
@@ -303,76 +1851,266 @@ impl Parser {
         if self.cur_token.token_type == TokenType::Dot {
             return self.parse_member_expression(left);
         }
+        if matches!(
+            self.cur_token.token_type,
+            TokenType::NoSubstitutionTemplate(_) | TokenType::TemplateHead(_)
+        ) {
+            return self.parse_tagged_template(left);
+        }
+        if matches!(
+            self.cur_token.token_type,
+            TokenType::PlusPlus | TokenType::MinusMinus
+        ) {
+            return self.parse_postfix_update(left);
+        }
+        if self.cur_token.token_type == TokenType::Question {
+            return self.parse_conditional_expression(left);
+        }
+        if Self::precedence_of(&self.cur_token.token_type) == ASSIGN {
+            return self.parse_assignment(left);
+        }
 
         let operator = match self.cur_token.token_type {
             TokenType::Plus => "+",
             TokenType::Minus => "-",
             TokenType::Star => "*",
             TokenType::Slash => "/",
+            TokenType::Percent => "%",
+            TokenType::StarStar => "**",
             TokenType::Equal => "==",
             TokenType::NotEqual => "!=",
+            TokenType::StrictEqual => "===",
+            TokenType::StrictNotEqual => "!==",
             TokenType::LessThan => "<",
             TokenType::GreaterThan => ">",
-            _ => return None,
+            TokenType::LessEqual => "<=",
+            TokenType::GreaterEqual => ">=",
+            TokenType::Ampersand => "&",
+            TokenType::Pipe => "|",
+            TokenType::Caret => "^",
+            TokenType::ShiftLeft => "<<",
+            TokenType::ShiftRight => ">>",
+            TokenType::UnsignedShiftRight => ">>>",
+            TokenType::AndAnd => "&&",
+            TokenType::OrOr => "||",
+            TokenType::Nullish => "??",
+            TokenType::In => "in",
+            TokenType::Instanceof => "instanceof",
+            _ => {
+                let message = format!("Unexpected token '{}'", self.cur_token.literal);
+                self.error_at_cur(&message);
+                return None;
+            }
         }
         .to_string();
 
         let precedence = self.cur_precedence();
         self.next_token();
-        let right = self.parse_expression(precedence)?;
+        // Exponentiation is right-associative
+        let right = if operator == "**" {
+            self.parse_expression(precedence - 1)?
+        } else {
+            self.parse_expression(precedence)?
+        };
+        if operator == "??" {
+            self.check_nullish_operand(&right)?;
+        }
+
+        let span = self.span_from(left.span);
+        Some(Expression::new(
+            ExpressionKind::Infix {
+                left: Box::new(left),
+                operator,
+                right: Box::new(right),
+            },
+            span,
+        ))
+    }
+
+    fn parse_conditional_expression(&mut self, condition: Expression) -> Option<Expression> {
+        self.next_token(); // eat '?'
+        let consequence = self.parse_expression(0)?;
+        if !self.expect_peek(TokenType::Colon, ":") {
+            return None;
+        }
+        // Both branches are assignment expressions, so `a ? b : c ? d : e`
+        // nests to the right
+        self.next_token(); // eat ':'
+        let alternative = self.parse_expression(0)?;
 
-        Some(Expression::Infix {
-            left: Box::new(left),
-            operator,
-            right: Box::new(right),
-        })
+        let span = self.span_from(condition.span);
+        Some(Expression::new(
+            ExpressionKind::Conditional {
+                condition: Box::new(condition),
+                consequence: Box::new(consequence),
+                alternative: Box::new(alternative),
+            },
+            span,
+        ))
+    }
+
+    fn parse_postfix_update(&mut self, target: Expression) -> Option<Expression> {
+        if !Self::is_assignment_target(&target) {
+            self.error_at_cur("Invalid left-hand side expression in postfix operation");
+            return None;
+        }
+
+        let span = self.span_from(target.span);
+        Some(Expression::new(
+            ExpressionKind::Update {
+                operator: self.cur_token.literal.to_string(),
+                prefix: false,
+                target: Box::new(target),
+            },
+            span,
+        ))
+    }
+
+    fn parse_assignment(&mut self, target: Expression) -> Option<Expression> {
+        let operator = self.cur_token.literal.to_string();
+        let target_span = target.span;
+        // Only `=` can take a value apart
+        let target = match target.kind {
+            ExpressionKind::Array(_) | ExpressionKind::Object(_) if operator == "=" => {
+                self.reinterpret_as_pattern(target, false)
+            }
+            ExpressionKind::Identifier(_) | ExpressionKind::Index { .. } => {
+                self.reinterpret_as_pattern(target, false)
+            }
+            _ => Err(target_span),
+        };
+        let target = match target {
+            Ok(target) => target,
+            Err(span) if span == target_span => {
+                self.error_at_cur("Invalid left-hand side in assignment");
+                return None;
+            }
+            Err(span) => {
+                let diagnostic = Diagnostic::error(span, "Invalid destructuring assignment target");
+                self.report(diagnostic);
+                return None;
+            }
+        };
+        self.next_token(); // eat operator
+
+        // Assignment is right-associative
+        let value = self.parse_expression(ASSIGN - 1)?;
+
+        let span = self.span_from(target_span);
+        Some(Expression::new(
+            ExpressionKind::Assign {
+                target,
+                operator,
+                value: Box::new(value),
+            },
+            span,
+        ))
     }
 
     fn parse_call_expression(&mut self, function: Expression) -> Option<Expression> {
-        self.next_token(); // eat '('
         let mut arguments = vec![];
 
-        if self.cur_token.token_type != TokenType::RParen {
-            arguments.push(self.parse_expression(0)?);
+        if self.peek_token.token_type != TokenType::RParen {
+            self.next_token(); // eat '('
+            arguments.push(self.parse_element()?);
             while self.peek_token.token_type == TokenType::Comma {
                 self.next_token(); // eat current arg
                 self.next_token(); // eat comma
-                arguments.push(self.parse_expression(0)?);
+                arguments.push(self.parse_element()?);
             }
         }
 
-        if self.peek_token.token_type != TokenType::RParen {
+        // eat last arg or '(' if empty
+        if !self.expect_peek(TokenType::RParen, "')'") {
             return None;
         }
-        self.next_token(); // eat last arg or '(' if empty
 
-        Some(Expression::Call {
-            function: Box::new(function),
-            arguments,
-        })
+        let span = self.span_from(function.span);
+        Some(Expression::new(
+            ExpressionKind::Call {
+                function: Box::new(function),
+                arguments,
+            },
+            span,
+        ))
     }
 
     fn peek_precedence(&self) -> u8 {
-        match self.peek_token.token_type {
-            TokenType::Dot => 6,
-            TokenType::LParen | TokenType::LBracket => 5,
-            TokenType::Star | TokenType::Slash => 4,
-            TokenType::Plus | TokenType::Minus => 3,
-            TokenType::LessThan | TokenType::GreaterThan => 2,
-            TokenType::Equal | TokenType::NotEqual => 1,
-            _ => 0,
-        }
+        Self::precedence_of(&self.peek_token.token_type)
     }
 
     fn cur_precedence(&self) -> u8 {
-        match self.cur_token.token_type {
-            TokenType::Dot => 6,
-            TokenType::LParen | TokenType::LBracket => 5,
-            TokenType::Star | TokenType::Slash => 4,
-            TokenType::Plus | TokenType::Minus => 3,
-            TokenType::LessThan | TokenType::GreaterThan => 2,
-            TokenType::Equal | TokenType::NotEqual => 1,
+        Self::precedence_of(&self.cur_token.token_type)
+    }
+
+    fn precedence_of(token_type: &TokenType) -> u8 {
+        match token_type {
+            TokenType::Dot => MEMBER,
+            TokenType::LParen
+            | TokenType::LBracket
+            | TokenType::NoSubstitutionTemplate(_)
+            | TokenType::TemplateHead(_) => CALL,
+            TokenType::PlusPlus | TokenType::MinusMinus => POSTFIX,
+            TokenType::StarStar => EXPONENT,
+            TokenType::Star | TokenType::Slash | TokenType::Percent => PRODUCT,
+            TokenType::Plus | TokenType::Minus => SUM,
+            TokenType::ShiftLeft | TokenType::ShiftRight | TokenType::UnsignedShiftRight => SHIFT,
+            TokenType::LessThan
+            | TokenType::GreaterThan
+            | TokenType::LessEqual
+            | TokenType::GreaterEqual
+            | TokenType::In
+            | TokenType::Instanceof => LESS_GREATER,
+            TokenType::Equal
+            | TokenType::NotEqual
+            | TokenType::StrictEqual
+            | TokenType::StrictNotEqual => EQUALS,
+            TokenType::Ampersand => BITWISE_AND,
+            TokenType::Caret => BITWISE_XOR,
+            TokenType::Pipe => BITWISE_OR,
+            TokenType::AndAnd => LOGICAL_AND,
+            TokenType::OrOr => LOGICAL_OR,
+            TokenType::Nullish => NULLISH,
+            TokenType::Question => CONDITIONAL,
+            TokenType::Assign
+            | TokenType::PlusAssign
+            | TokenType::MinusAssign
+            | TokenType::StarAssign
+            | TokenType::SlashAssign
+            | TokenType::PercentAssign
+            | TokenType::StarStarAssign
+            | TokenType::ShiftLeftAssign
+            | TokenType::ShiftRightAssign
+            | TokenType::UnsignedShiftRightAssign
+            | TokenType::AmpersandAssign
+            | TokenType::PipeAssign
+            | TokenType::CaretAssign
+            | TokenType::AndAndAssign
+            | TokenType::OrOrAssign
+            | TokenType::NullishAssign => ASSIGN,
             _ => 0,
         }
     }
 }
+
+/// Whether `pattern` assigns to a member such as `a.b` anywhere, which a
+/// binding cannot.
+fn has_member(pattern: &Pattern) -> bool {
+    match &pattern.kind {
+        PatternKind::Identifier(_) => false,
+        PatternKind::Member(_) => true,
+        PatternKind::Object { properties, rest } => {
+            properties
+                .iter()
+                .any(|property| has_member(&property.value))
+                || rest.as_deref().is_some_and(|rest| has_member(&rest.target))
+        }
+        PatternKind::Array { elements, rest } => {
+            elements
+                .iter()
+                .flatten()
+                .any(|element| has_member(&element.target))
+                || rest.as_deref().is_some_and(|rest| has_member(&rest.target))
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use super::{errors, print};
+
+#[test]
+fn return_takes_no_value_from_the_next_line() {
+    assert_eq!(
+        print("function f() { return\n42 }"),
+        "function f(){return;42;}"
+    );
+    assert_eq!(
+        print("function f() { return /*\n*/ 42 }"),
+        "function f(){return;42;}"
+    );
+    assert_eq!(
+        print("function f() { return /* same line */ 42 }"),
+        "function f(){return 42;}"
+    );
+}
+
+#[test]
+fn closing_brace_and_end_of_input_end_a_statement() {
+    assert_eq!(
+        print("function f() { return 1 }"),
+        "function f(){return 1;}"
+    );
+    assert_eq!(print("let a = 1"), "let a=1;");
+}
+
+#[test]
+fn declarations_end_at_a_line_break() {
+    assert_eq!(print("let a = 1\nlet b = a + 1"), "let a=1;let b=a+1;");
+}
+
+#[test]
+fn brackets_on_the_next_line_continue_the_expression() {
+    assert_eq!(print("let a = b\n(21)"), "let a=b(21);");
+    assert_eq!(print("let a = b\n[1]"), "let a=b[1];");
+}
+
+#[test]
+fn operators_on_the_next_line_continue_the_expression() {
+    assert_eq!(print("let a = 1\n- 1"), "let a=1-1;");
+    assert_eq!(print("let a = b\n? 1\n: 2"), "let a=b?1:2;");
+}
+
+#[test]
+fn increment_on_the_next_line_is_a_prefix() {
+    assert_eq!(print("a\n++b"), "a;++b;");
+    assert_eq!(print("a\n--\nb"), "a;--b;");
+    assert_eq!(print("a++"), "a++;");
+}
+
+#[test]
+fn break_and_continue_take_no_label_from_the_next_line() {
+    assert_eq!(
+        print("outer: for (;;) { continue\nouter }"),
+        "outer:for(;;){continue;outer;}"
+    );
+    assert_eq!(
+        print("outer: for (;;) { break\nouter }"),
+        "outer:for(;;){break;outer;}"
+    );
+}
+
+#[test]
+fn do_while_ends_at_its_closing_parenthesis() {
+    assert_eq!(print("do k++; while (k < 3) f()"), "do k++;while(k<3);f();");
+}
+
+#[test]
+fn empty_statements() {
+    assert_eq!(
+        print("let a = 0;;\nif (a);\nfor (;;);"),
+        "let a=0;;if(a);for(;;);"
+    );
+}
+
+#[test]
+fn rejected_without_a_line_break() {
+    assert_eq!(errors("let a = 1 let b = 2"), ["Expected ';', got 'let'"]);
+    assert_eq!(errors("if (a) b else c"), ["Expected ';', got 'else'"]);
+    assert_eq!(errors("a b"), ["Expected ';', got 'b'"]);
+}
+
+#[test]
+fn never_inserted_where_forbidden() {
+    assert_eq!(errors("throw\nError()"), ["Illegal newline after throw"]);
+    assert_eq!(
+        errors("for (let i = 0\ni < 3; i++) {}"),
+        ["Expected ';', got 'i'", "Expected ';', got ')'"]
+    );
+    assert_eq!(errors("for (;\n) {}"), ["Unexpected token ')'"]);
+    assert_eq!(errors("let a = 1\n++\n"), ["Unexpected end of input"]);
+}
@@ -0,0 +1,80 @@
+use super::{errors, parse, print};
+use shadowjs_ast::{ExpressionKind, StatementKind};
+
+#[test]
+fn function_declaration() {
+    let program = parse("function add(a, b) { return a + b; }");
+    match &program.statements[0].kind {
+        StatementKind::Function { name, params, .. } => {
+            assert_eq!(name.name, "add");
+            assert_eq!(params.len(), 2);
+        }
+        kind => panic!("expected a function declaration, got {:?}", kind),
+    }
+    assert_eq!(
+        print("function add(a, b) { return a + b; }"),
+        "function add(a,b){return a+b;}"
+    );
+}
+
+#[test]
+fn function_expression() {
+    assert_eq!(
+        print("let f = function (x) { return x; };"),
+        "let f=function(x){return x;};"
+    );
+    assert_eq!(
+        print("let f = function fact(n) { return n; };"),
+        "let f=function fact(n){return n;};"
+    );
+    let program = parse("(function () {});");
+    match &program.statements[0].kind {
+        StatementKind::Expression(expr) => match &expr.kind {
+            ExpressionKind::Function { name, params, .. } => {
+                assert_eq!(name, &None);
+                assert!(params.is_empty());
+            }
+            kind => panic!("expected a function expression, got {:?}", kind),
+        },
+        kind => panic!("expected an expression statement, got {:?}", kind),
+    }
+}
+
+#[test]
+fn trailing_comma_in_parameters() {
+    assert_eq!(print("function f(a, b,) {}"), "function f(a,b){}");
+    assert_eq!(print("function f() {}"), "function f(){}");
+}
+
+#[test]
+fn nested_functions() {
+    assert_eq!(
+        print("function outer() { function inner() { return 1; } return inner(); }"),
+        "function outer(){function inner(){return 1;}return inner();}"
+    );
+    assert_eq!(
+        print("function f() { return function () { return function g() {}; }; }"),
+        "function f(){return function(){return function g(){};};}"
+    );
+}
+
+#[test]
+fn function_errors() {
+    assert_eq!(
+        errors("function () {}"),
+        ["Expected function name, got '('"]
+    );
+    assert_eq!(errors("function f {}"), ["Expected '(', got '{'"]);
+    assert_eq!(
+        errors("function f(a b) {}"),
+        ["Expected ',' or ')', got 'b'"]
+    );
+    assert_eq!(
+        errors("function f(a, , b) {}"),
+        ["Expected parameter name, got ','"]
+    );
+    assert_eq!(
+        errors("function f() { return 1;"),
+        ["Expected '}', got end of input"]
+    );
+}
@@ -0,0 +1,35 @@
+use super::{errors, print};
+
+#[test]
+fn numbers_print_as_their_values() {
+    assert_eq!(
+        print("x = [0xff, 0o17, 0b11, 1_000, 1e3, .5];"),
+        "x=[255,15,3,1000,1000,0.5];"
+    );
+    assert_eq!(print("x = 017 + 019;"), "x=15+19;");
+}
+
+#[test]
+fn bigints_keep_their_digits() {
+    assert_eq!(print("x = 1_000n + 0xFfn;"), "x=1000n+0xFfn;");
+}
+
+#[test]
+fn legacy_numbers_are_rejected_in_strict_mode() {
+    assert_eq!(
+        errors("'use strict'; x = 017;"),
+        ["Octal literals are not allowed in strict mode"]
+    );
+    assert_eq!(
+        errors("function f() { 'use strict'; return 019; }"),
+        ["Decimals with leading zeros are not allowed in strict mode"]
+    );
+    let diagnostics = crate::Parser::new("'use strict'; 010;")
+        .parse()
+        .unwrap_err();
+    assert_eq!(diagnostics[0].suggestion.as_deref(), Some("0o10"));
+    assert_eq!(
+        print("'use strict'; x = 0o17 + 0;"),
+        "\"use strict\";x=15+0;"
+    );
+}
@@ -0,0 +1,36 @@
+//! Parser tests. Programs are compared through their compact printed form,
+//! which keeps the expectations short and readable.
+
+mod asi;
+mod functions;
+mod literals;
+mod operators;
+mod spans;
+
+use crate::Parser;
+use shadowjs_ast::codegen::Style;
+use shadowjs_ast::{Diagnostic, Program};
+
+fn parse(source: &str) -> Program {
+    match Parser::new(source).parse() {
+        Ok(program) => program,
+        Err(diagnostics) => panic!("{:?} failed to parse: {:?}", source, messages(&diagnostics)),
+    }
+}
+
+/// The program parsed from `source`, printed back in the compact style.
+fn print(source: &str) -> String {
+    parse(source).to_source(&Style::Compact)
+}
+
+/// The messages of the diagnostics reported for `source`.
+fn errors(source: &str) -> Vec<String> {
+    match Parser::new(source).parse() {
+        Ok(program) => panic!("{:?} parsed as {:?}", source, program),
+        Err(diagnostics) => messages(&diagnostics),
+    }
+}
+
+fn messages(diagnostics: &[Diagnostic]) -> Vec<String> {
+    diagnostics.iter().map(|d| d.message.clone()).collect()
+}
@@ -0,0 +1,47 @@
+use super::{errors, print};
+
+#[test]
+fn logical_operator_precedence() {
+    assert_eq!(print("a || b && c;"), "a||b&&c;");
+    assert_eq!(print("(a || b) && c;"), "(a||b)&&c;");
+    assert_eq!(print("a ?? b ?? c;"), "a??b??c;");
+}
+
+#[test]
+fn nullish_mixed_with_logical_operators_needs_parentheses() {
+    assert_eq!(
+        errors("a ?? b || c;"),
+        ["Cannot mix '||' with '??' without parentheses"]
+    );
+    assert_eq!(
+        errors("a && b ?? c;"),
+        ["Cannot mix '&&' with '??' without parentheses"]
+    );
+    assert_eq!(
+        errors("a || b ?? c;"),
+        ["Cannot mix '||' with '??' without parentheses"]
+    );
+    assert_eq!(
+        errors("x = a ?? b && c;"),
+        ["Cannot mix '&&' with '??' without parentheses"]
+    );
+}
+
+#[test]
+fn nullish_accepts_parenthesized_logical_operands() {
+    assert_eq!(print("(a || b) ?? c;"), "(a||b)??c;");
+    assert_eq!(print("a ?? (b && c);"), "a??(b&&c);");
+    assert_eq!(print("(a ?? b) || c;"), "(a??b)||c;");
+    assert_eq!(print("a || (b ?? c);"), "a||(b??c);");
+    assert_eq!(print("f(a || b) ?? c;"), "f(a||b)??c;");
+}
+
+#[test]
+fn mixing_suggests_parentheses() {
+    let diagnostics = crate::Parser::new("a ?? b || c;").parse().unwrap_err();
+    assert_eq!(
+        diagnostics[0].suggestion.as_deref(),
+        Some("wrap the '||' expression in parentheses")
+    );
+    assert_eq!(diagnostics[0].span.start.column, 6);
+}
@@ -0,0 +1,145 @@
+use super::parse;
+use shadowjs_ast::{ExpressionKind, PatternKind, PropertyKind, Span, StatementKind};
+
+/// The source text `span` covers.
+fn text(source: &str, span: Span) -> &str {
+    &source[span.start.offset..span.end.offset]
+}
+
+#[test]
+fn names() {
+    let source = "function add(a, b = 1, ...rest) {}";
+    let program = parse(source);
+    let StatementKind::Function { name, params, .. } = &program.statements[0].kind else {
+        panic!("expected a function declaration");
+    };
+    assert_eq!(text(source, name.span), "add");
+    let params: Vec<&str> = params.iter().map(|p| text(source, p.span)).collect();
+    assert_eq!(params, ["a", "b = 1", "...rest"]);
+
+    let source = "outer: for (;;) { try {} catch (err) { break outer; } }";
+    let program = parse(source);
+    let StatementKind::Labeled { label, body } = &program.statements[0].kind else {
+        panic!("expected a labeled statement");
+    };
+    assert_eq!(label.span.start.column, 1);
+    assert_eq!(text(source, label.span), "outer");
+    let StatementKind::For { body, .. } = &body.kind else {
+        panic!("expected a for loop");
+    };
+    let StatementKind::Block(body) = &body.kind else {
+        panic!("expected a block");
+    };
+    let StatementKind::Try {
+        param: Some(param),
+        handler: Some(handler),
+        ..
+    } = &body[0].kind
+    else {
+        panic!("expected a try statement with a catch binding");
+    };
+    assert_eq!(text(source, param.span), "err");
+    let StatementKind::Block(handler) = &handler.kind else {
+        panic!("expected a block");
+    };
+    let StatementKind::Break(Some(label)) = &handler[0].kind else {
+        panic!("expected a labeled break");
+    };
+    assert_eq!(text(source, label.span), "outer");
+}
+
+#[test]
+fn patterns() {
+    let source = "let {a, b: [c, d = 2, ...e], [k]: f, ...g} = x;";
+    let program = parse(source);
+    let StatementKind::Let { pattern, .. } = &program.statements[0].kind else {
+        panic!("expected a let declaration");
+    };
+    assert_eq!(
+        text(source, pattern.span),
+        "{a, b: [c, d = 2, ...e], [k]: f, ...g}"
+    );
+    let PatternKind::Object { properties, rest } = &pattern.kind else {
+        panic!("expected an object pattern");
+    };
+    let spans: Vec<&str> = properties.iter().map(|p| text(source, p.span)).collect();
+    assert_eq!(spans, ["a", "b: [c, d = 2, ...e]", "[k]: f"]);
+    let keys: Vec<&str> = properties
+        .iter()
+        .map(|p| text(source, p.key.span))
+        .collect();
+    assert_eq!(keys, ["a", "b", "k"]);
+    assert_eq!(text(source, rest.as_ref().unwrap().span), "...g");
+    assert_eq!(text(source, rest.as_ref().unwrap().target.span), "g");
+
+    let inner = &properties[1].value;
+    assert_eq!(text(source, inner.span), "[c, d = 2, ...e]");
+    let PatternKind::Array { elements, rest } = &inner.kind else {
+        panic!("expected an array pattern");
+    };
+    let spans: Vec<&str> = elements
+        .iter()
+        .flatten()
+        .map(|e| text(source, e.span))
+        .collect();
+    assert_eq!(spans, ["c", "d = 2"]);
+    assert_eq!(text(source, rest.as_ref().unwrap().span), "...e");
+
+    let names: Vec<(&str, &str)> = pattern
+        .bindings()
+        .into_iter()
+        .map(|(name, span)| (name, text(source, span)))
+        .collect();
+    assert_eq!(
+        names,
+        [
+            ("a", "a"),
+            ("c", "c"),
+            ("d", "d"),
+            ("e", "e"),
+            ("f", "f"),
+            ("g", "g")
+        ]
+    );
+}
+
+#[test]
+fn assignment_patterns() {
+    // These are parsed as literals first, then turned into patterns
+    let source = "[a.b, {c = 1}] = x;";
+    let program = parse(source);
+    let StatementKind::Expression(expr) = &program.statements[0].kind else {
+        panic!("expected an expression statement");
+    };
+    let ExpressionKind::Assign { target, .. } = &expr.kind else {
+        panic!("expected an assignment");
+    };
+    assert_eq!(text(source, target.span), "[a.b, {c = 1}]");
+    let PatternKind::Array { elements, .. } = &target.kind else {
+        panic!("expected an array pattern");
+    };
+    let spans: Vec<&str> = elements
+        .iter()
+        .flatten()
+        .map(|e| text(source, e.target.span))
+        .collect();
+    assert_eq!(spans, ["a.b", "{c = 1}"]);
+}
+
+#[test]
+fn properties() {
+    let source = "({a, b: 1, [c]: 2, get d() {}, ...e});";
+    let program = parse(source);
+    let StatementKind::Expression(expr) = &program.statements[0].kind else {
+        panic!("expected an expression statement");
+    };
+    let ExpressionKind::Object(properties) = &expr.kind else {
+        panic!("expected an object literal");
+    };
+    let spans: Vec<&str> = properties.iter().map(|p| text(source, p.span)).collect();
+    assert_eq!(spans, ["a", "b: 1", "[c]: 2", "get d() {}", "...e"]);
+    let PropertyKind::Method { key, .. } = &properties[3].kind else {
+        panic!("expected an accessor");
+    };
+    assert_eq!(text(source, key.span), "d");
+}
@@ -0,0 +1,241 @@
+//! The ECMAScript abstract operations for type conversion and comparison.
+
+use crate::Value;
+use shadowjs_gc::Gc;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::rc::Rc;
+
+thread_local! {
+    // Arrays being converted to strings, innermost last
+    static JOINING: RefCell<Vec<Gc<Vec<Value>>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Runs `join` for `arr`, unless `arr` is being joined already further up,
+/// as when an array contains itself. Like Array.prototype.join, the
+/// repeated array then converts to "", which None stands for.
+pub(crate) fn join_once<R>(arr: Gc<Vec<Value>>, join: impl FnOnce() -> R) -> Option<R> {
+    if JOINING.with(|joining| joining.borrow().contains(&arr)) {
+        return None;
+    }
+    JOINING.with(|joining| joining.borrow_mut().push(arr));
+    let result = join();
+    JOINING.with(|joining| joining.borrow_mut().pop());
+    Some(result)
+}
+
+/// ECMAScript ToPrimitive. Objects have no user-visible `valueOf` or
+/// `toString` yet, so they always convert through their default string form.
+pub fn to_primitive(value: &Value) -> Value {
+    match value {
+        Value::Object(_)
+        | Value::Array(_)
+        | Value::Function(_)
+        | Value::NativeFunction(_)
+        | Value::Iterator(_) => Value::String(Rc::new(to_string(value))),
+        _ => value.clone(),
+    }
+}
+
+/// ECMAScript ToBoolean.
+pub fn to_boolean(value: &Value) -> bool {
+    match value {
+        Value::Boolean(b) => *b,
+        Value::Null | Value::Undefined | Value::Uninitialized => false,
+        Value::Number(n) => !(*n == 0.0 || n.is_nan()),
+        Value::String(s) => !s.is_empty(),
+        _ => true,
+    }
+}
+
+/// ECMAScript ToNumber.
+pub fn to_number(value: &Value) -> f64 {
+    match value {
+        Value::Number(n) => *n,
+        Value::Boolean(b) => *b as u8 as f64,
+        Value::String(s) => string_to_number(s),
+        Value::Null => 0.0,
+        Value::Undefined | Value::Uninitialized => f64::NAN,
+        _ => to_number(&to_primitive(value)),
+    }
+}
+
+/// ECMAScript StringToNumber: the StringNumericLiteral grammar, which allows
+/// surrounding whitespace and prefixed integers but not signed prefixes.
+pub fn string_to_number(s: &str) -> f64 {
+    let s = s.trim_matches(is_js_whitespace);
+    if s.is_empty() {
+        return 0.0;
+    }
+
+    let radix = match s.get(..2) {
+        Some("0x" | "0X") => 16,
+        Some("0o" | "0O") => 8,
+        Some("0b" | "0B") => 2,
+        _ => 10,
+    };
+    if radix != 10 {
+        let digits = &s[2..];
+        if digits.is_empty() {
+            return f64::NAN;
+        }
+        // Accumulate in floating point so long literals lose precision
+        // instead of overflowing
+        return digits
+            .chars()
+            .try_fold(0.0, |acc, c| {
+                c.to_digit(radix).map(|d| acc * radix as f64 + d as f64)
+            })
+            .unwrap_or(f64::NAN);
+    }
+
+    let unsigned = s.strip_prefix(['+', '-']).unwrap_or(s);
+    if unsigned == "Infinity" {
+        return if s.starts_with('-') {
+            f64::NEG_INFINITY
+        } else {
+            f64::INFINITY
+        };
+    }
+    // Rust also accepts spellings such as "inf" and "NaN", which JS does not
+    if !unsigned
+        .chars()
+        .all(|c| c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '+' | '-'))
+    {
+        return f64::NAN;
+    }
+    s.parse::<f64>().unwrap_or(f64::NAN)
+}
+
+/// ECMAScript ToString.
+pub fn to_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.to_string(),
+        Value::Number(n) => number_to_string(*n),
+        Value::Boolean(b) => b.to_string(),
+        Value::Null => "null".to_string(),
+        Value::Undefined | Value::Uninitialized => "undefined".to_string(),
+        Value::Object(_) => "[object Object]".to_string(),
+        Value::Array(arr) => join_once(*arr, || {
+            arr.borrow()
+                .iter()
+                .map(|elem| match elem {
+                    Value::Null | Value::Undefined => String::new(),
+                    _ => to_string(elem),
+                })
+                .collect::<Vec<_>>()
+                .join(",")
+        })
+        .unwrap_or_default(),
+        Value::Function(func) => format!("function {}() {{ [code] }}", func.borrow().name),
+        Value::NativeFunction(_) => "function () { [native code] }".to_string(),
+        Value::Iterator(_) => "[object Iterator]".to_string(),
+    }
+}
+
+/// ECMAScript Number::toString with radix 10: the shortest digit string that
+/// round-trips, laid out in fixed or exponential notation.
+pub fn number_to_string(n: f64) -> String {
+    if n.is_nan() {
+        return "NaN".to_string();
+    }
+    if n == 0.0 {
+        return "0".to_string();
+    }
+    if n.is_infinite() {
+        return if n > 0.0 { "Infinity" } else { "-Infinity" }.to_string();
+    }
+    if n < 0.0 {
+        return format!("-{}", number_to_string(-n));
+    }
+
+    // `{:e}` yields the shortest round-tripping digits, e.g. "1.2345e-7"
+    let formatted = format!("{:e}", n);
+    let (mantissa, exponent) = formatted.split_once('e').unwrap();
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let k = digits.len() as i32;
+    let point = exponent.parse::<i32>().unwrap() + 1;
+
+    if k <= point && point <= 21 {
+        format!("{}{}", digits, "0".repeat((point - k) as usize))
+    } else if 0 < point && point <= 21 {
+        let (int, frac) = digits.split_at(point as usize);
+        format!("{}.{}", int, frac)
+    } else if -6 < point && point <= 0 {
+        format!("0.{}{}", "0".repeat(-point as usize), digits)
+    } else {
+        let sign = if point - 1 < 0 { '-' } else { '+' };
+        let (first, rest) = digits.split_at(1);
+        let fraction = if rest.is_empty() {
+            String::new()
+        } else {
+            format!(".{}", rest)
+        };
+        format!("{}{}e{}{}", first, fraction, sign, (point - 1).abs())
+    }
+}
+
+/// ECMAScript IsStrictlyEqual (`===`).
+pub fn is_strictly_equal(x: &Value, y: &Value) -> bool {
+    match (x, y) {
+        (Value::Number(a), Value::Number(b)) => a == b,
+        _ => x == y,
+    }
+}
+
+/// ECMAScript IsLooselyEqual (`==`).
+pub fn is_loosely_equal(x: &Value, y: &Value) -> bool {
+    match (x, y) {
+        (Value::Null | Value::Undefined, Value::Null | Value::Undefined) => true,
+        (Value::Null | Value::Undefined, _) | (_, Value::Null | Value::Undefined) => false,
+        (Value::Number(a), Value::String(_)) => *a == to_number(y),
+        (Value::String(_), Value::Number(b)) => to_number(x) == *b,
+        (Value::Boolean(_), _) => is_loosely_equal(&Value::Number(to_number(x)), y),
+        (_, Value::Boolean(_)) => is_loosely_equal(x, &Value::Number(to_number(y))),
+        _ if is_object(x) && !is_object(y) => is_loosely_equal(&to_primitive(x), y),
+        _ if !is_object(x) && is_object(y) => is_loosely_equal(x, &to_primitive(y)),
+        _ => is_strictly_equal(x, y),
+    }
+}
+
+/// ECMAScript SameValueZero: strict equality, except that NaN equals itself.
+pub fn same_value_zero(x: &Value, y: &Value) -> bool {
+    match (x, y) {
+        (Value::Number(a), Value::Number(b)) if a.is_nan() && b.is_nan() => true,
+        _ => is_strictly_equal(x, y),
+    }
+}
+
+/// ECMAScript IsLessThan. Returns `None` when either side converts to NaN, in
+/// which case every relational operator evaluates to false.
+pub fn is_less_than(x: &Value, y: &Value) -> Option<bool> {
+    let px = to_primitive(x);
+    let py = to_primitive(y);
+    if let (Value::String(a), Value::String(b)) = (&px, &py) {
+        // Strings compare by UTF-16 code units
+        return Some(a.encode_utf16().cmp(b.encode_utf16()) == Ordering::Less);
+    }
+    let a = to_number(&px);
+    let b = to_number(&py);
+    if a.is_nan() || b.is_nan() {
+        return None;
+    }
+    Some(a < b)
+}
+
+fn is_object(value: &Value) -> bool {
+    matches!(
+        value,
+        Value::Object(_)
+            | Value::Array(_)
+            | Value::Function(_)
+            | Value::NativeFunction(_)
+            | Value::Iterator(_)
+    )
+}
+
+/// The WhiteSpace and LineTerminator code points trimmed by StringToNumber.
+/// Rust's White_Space set differs from them only in U+0085 and U+FEFF.
+fn is_js_whitespace(c: char) -> bool {
+    c == '\u{FEFF}' || (c.is_whitespace() && c != '\u{85}')
+}
@@ -1,47 +1,172 @@
-use rustc_hash::FxHashMap;
 use shadowjs_bytecode::Chunk;
-use shadowjs_gc::trace::Trace;
+use shadowjs_gc::trace::{Trace, Tracer};
 use shadowjs_gc::Gc;
 use std::fmt;
 use std::rc::Rc;
 
+mod conversions;
+mod object;
+
+pub use conversions::*;
+pub use object::*;
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Function {
     pub name: Rc<String>,
-    pub chunk: Chunk,
+    pub chunk: Rc<Chunk>,
     pub arity: usize,
+    pub has_rest: bool,
+    pub upvalues: Vec<Gc<Upvalue>>,
+    // The `this` of an arrow function, taken from where it was created.
+    // Other functions get theirs from the call.
+    pub this: Option<Value>,
 }
 
 impl Trace for Function {
-    fn trace(&self, visited: &mut std::collections::HashSet<usize>) {
-        self.chunk.trace(visited);
+    fn trace(&self, tracer: &mut Tracer) {
+        self.chunk.trace(tracer);
+        self.upvalues.trace(tracer);
+        if let Some(this) = &self.this {
+            this.trace(tracer);
+        }
+    }
+
+    fn heap_size(&self) -> usize {
+        self.upvalues.heap_size()
+    }
+}
+
+/// A variable captured by a closure. It points at a stack slot while the
+/// variable is still live, and owns the value once its scope has exited.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Upvalue {
+    Open(usize),
+    Closed(Value),
+}
+
+impl Trace for Upvalue {
+    fn trace(&self, tracer: &mut Tracer) {
+        if let Upvalue::Closed(value) = self {
+            value.trace(tracer);
+        }
     }
 }
 
+/// How far a `for…of` loop or array pattern has got through an array or
+/// string. Code never sees one: the compiler keeps it in a hidden slot.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValueIterator {
+    source: Value,
+    // An index into an array or array-like object, or a byte offset into a
+    // string
+    position: usize,
+}
+
+impl ValueIterator {
+    /// An iterator over the elements of an array or the characters of a
+    /// string, or None if `source` is not iterable. Objects of class "Array"
+    /// are read by index up to their `length`.
+    pub fn new(source: Value) -> Option<Self> {
+        match &source {
+            Value::Array(_) | Value::String(_) => {}
+            Value::Object(obj) if obj.borrow().class() == Some("Array") => {}
+            _ => return None,
+        }
+        Some(Self {
+            source,
+            position: 0,
+        })
+    }
+
+    /// The next value, or None once the iterator is done. An array is read
+    /// as it is now, so elements pushed during a loop are visited too.
+    pub fn next_value(&mut self) -> Option<Value> {
+        match &self.source {
+            Value::Array(arr) => {
+                let value = arr.borrow().get(self.position).cloned()?;
+                self.position += 1;
+                Some(value)
+            }
+            Value::String(s) => {
+                let c = s[self.position..].chars().next()?;
+                self.position += c.len_utf8();
+                Some(Value::String(Rc::new(c.to_string())))
+            }
+            Value::Object(obj) => {
+                let obj = obj.borrow();
+                let length = match obj.get("length") {
+                    Some(Value::Number(n)) => *n as usize,
+                    _ => 0,
+                };
+                if self.position >= length {
+                    return None;
+                }
+                let value = obj
+                    .get(&self.position.to_string())
+                    .cloned()
+                    .unwrap_or(Value::Undefined);
+                self.position += 1;
+                Some(value)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Trace for ValueIterator {
+    fn trace(&self, tracer: &mut Tracer) {
+        self.source.trace(tracer);
+    }
+}
+
+/// A function implemented in Rust. It receives the `this` value, which is
+/// undefined unless it was called as a method, and the arguments. An `Err`
+/// is thrown as an exception.
+pub type NativeFn = fn(Value, Vec<Value>) -> Result<Value, Value>;
+
 #[derive(Debug, Clone)]
 pub enum Value {
     Number(f64),
     Boolean(bool),
     String(Rc<String>),
-    Object(Gc<FxHashMap<String, Value>>),
+    Object(Gc<Object>),
     Array(Gc<Vec<Value>>),
-    NativeFunction(fn(Vec<Value>) -> Value),
+    NativeFunction(NativeFn),
     Function(Gc<Function>),
+    Iterator(Gc<ValueIterator>),
     Null,
     Undefined,
+    // The value of a `let` or `const` binding before its declaration has
+    // run. Code never sees one: reading the binding throws.
+    Uninitialized,
 }
 
 impl Trace for Value {
-    fn trace(&self, visited: &mut std::collections::HashSet<usize>) {
+    fn trace(&self, tracer: &mut Tracer) {
         match self {
-            Value::Object(obj) => obj.trace(visited),
-            Value::Array(arr) => arr.trace(visited),
-            Value::Function(func) => func.trace(visited),
+            Value::Object(obj) => obj.trace(tracer),
+            Value::Array(arr) => arr.trace(tracer),
+            Value::Function(func) => func.trace(tracer),
+            Value::Iterator(iter) => iter.trace(tracer),
             _ => {}
         }
     }
 }
 
+impl Value {
+    /// The result of the `typeof` operator.
+    pub fn type_of(&self) -> &'static str {
+        match self {
+            Value::Number(_) => "number",
+            Value::Boolean(_) => "boolean",
+            Value::String(_) => "string",
+            Value::Object(_) | Value::Array(_) | Value::Iterator(_) | Value::Null => "object",
+            Value::NativeFunction(_) | Value::Function(_) => "function",
+            Value::Undefined | Value::Uninitialized => "undefined",
+        }
+    }
+}
+
 impl PartialEq for Value {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
@@ -55,8 +180,10 @@ impl PartialEq for Value {
                 (*a as usize) == (*b as usize)
             }
             (Value::Function(a), Value::Function(b)) => a == b,
+            (Value::Iterator(a), Value::Iterator(b)) => a == b,
             (Value::Null, Value::Null) => true,
             (Value::Undefined, Value::Undefined) => true,
+            (Value::Uninitialized, Value::Uninitialized) => true,
             _ => false,
         }
     }
@@ -65,11 +192,12 @@ impl PartialEq for Value {
 impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Value::Number(n) => write!(f, "{}", n),
+            Value::Number(n) => write!(f, "{}", number_to_string(*n)),
             Value::Boolean(b) => write!(f, "{}", b),
             Value::String(s) => write!(f, "{}", s),
             Value::Object(_) => write!(f, "[object Object]"),
-            Value::Array(arr) => {
+            // An array inside itself is left empty, as when joining it
+            Value::Array(arr) => join_once(*arr, || {
                 write!(f, "[")?;
                 let arr = arr.borrow();
                 for (i, val) in arr.iter().enumerate() {
@@ -79,11 +207,26 @@ impl fmt::Display for Value {
                     write!(f, "{}", val)?;
                 }
                 write!(f, "]")
-            }
+            })
+            .unwrap_or(Ok(())),
             Value::NativeFunction(_) => write!(f, "[native function]"),
             Value::Function(func) => write!(f, "[function {}]", func.borrow().name),
+            Value::Iterator(_) => write!(f, "[object Iterator]"),
             Value::Null => write!(f, "null"),
-            Value::Undefined => write!(f, "undefined"),
+            Value::Undefined | Value::Uninitialized => write!(f, "undefined"),
         }
     }
 }
+
+/// ECMAScript ToUint32 for a value that is already a number.
+pub fn to_uint32(n: f64) -> u32 {
+    if !n.is_finite() {
+        return 0;
+    }
+    n.trunc().rem_euclid(4294967296.0) as u32
+}
+
+/// ECMAScript ToInt32 for a value that is already a number.
+pub fn to_int32(n: f64) -> i32 {
+    to_uint32(n) as i32
+}
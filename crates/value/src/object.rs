@@ -0,0 +1,420 @@
+use crate::Value;
+use indexmap::IndexMap;
+use rustc_hash::{FxBuildHasher, FxHashMap};
+use shadowjs_gc::trace::{Trace, Tracer};
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+/// An own property of an object: a plain value, or the functions reading
+/// and writing it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Property {
+    Data(Value),
+    // Either half may be missing, as with only `get x() {…}`
+    Accessor {
+        get: Option<Value>,
+        set: Option<Value>,
+    },
+}
+
+impl Trace for Property {
+    fn trace(&self, tracer: &mut Tracer) {
+        match self {
+            Property::Data(value) => value.trace(tracer),
+            Property::Accessor { get, set } => {
+                for function in [get, set].into_iter().flatten() {
+                    function.trace(tracer);
+                }
+            }
+        }
+    }
+}
+
+/// The most properties an object keeps in shaped form. Past this, every
+/// shape on the way would be copying a large table for one object.
+const MAX_SHAPED_PROPERTIES: usize = 64;
+
+/// The most transitions kept from one shape. Once this many are, those to
+/// shapes no object has anymore are dropped; objects adding yet another key
+/// past that are churning through layouts no other object shares.
+const MAX_TRANSITIONS: usize = 64;
+
+thread_local! {
+    static EMPTY_SHAPE: Rc<Shape> = Rc::new(Shape::new(vec![], FxHashMap::default()));
+    static NEXT_SHAPE_ID: Cell<u64> = const { Cell::new(0) };
+}
+
+/// The layout of an object: which keys it has, in the order they were
+/// added, and the slot holding each. Objects given the same keys in the
+/// same order share a shape, so a shape can stand in for a property lookup.
+///
+/// Shapes form a tree rooted at the empty shape. Adding a key follows the
+/// transition for it, which is made the first time and reused after that.
+#[derive(Debug)]
+pub struct Shape {
+    // Never reused, so caches can tell shapes apart by it
+    id: u64,
+    keys: Vec<Rc<str>>,
+    slots: FxHashMap<Rc<str>, usize>,
+    // Kept while no object has the shape for now, as objects made in a loop
+    // are often freed before the next one is made, until room is needed
+    transitions: RefCell<FxHashMap<Rc<str>, Rc<Shape>>>,
+}
+
+impl Shape {
+    fn new(keys: Vec<Rc<str>>, slots: FxHashMap<Rc<str>, usize>) -> Shape {
+        let id = NEXT_SHAPE_ID.with(|next| next.replace(next.get() + 1));
+        Shape {
+            id,
+            keys,
+            slots,
+            transitions: RefCell::default(),
+        }
+    }
+
+    pub fn empty() -> Rc<Shape> {
+        EMPTY_SHAPE.with(Rc::clone)
+    }
+
+    /// A number no other shape has, for inline caches to compare.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// The slot holding `key` in objects of this shape.
+    pub fn lookup(&self, key: &str) -> Option<usize> {
+        self.slots.get(key).copied()
+    }
+
+    /// The keys in slot order, which is the order they were added.
+    pub fn keys(&self) -> &[Rc<str>] {
+        &self.keys
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// The shape after adding `key`, which must not be in this one. `None`
+    /// if the object should leave shapes behind instead.
+    fn transition(self: &Rc<Shape>, key: &str) -> Option<Rc<Shape>> {
+        if self.keys.len() >= MAX_SHAPED_PROPERTIES {
+            return None;
+        }
+        let mut transitions = self.transitions.borrow_mut();
+        if let Some(shape) = transitions.get(key) {
+            return Some(Rc::clone(shape));
+        }
+        if transitions.len() >= MAX_TRANSITIONS {
+            // A shape only the transition holds is used by no object. Its
+            // own transitions go with it, except to shapes objects still have.
+            transitions.retain(|_, shape| Rc::strong_count(shape) > 1);
+            if transitions.len() >= MAX_TRANSITIONS {
+                return None;
+            }
+        }
+
+        let key: Rc<str> = Rc::from(key);
+        let mut keys = self.keys.clone();
+        keys.push(Rc::clone(&key));
+        let mut slots = self.slots.clone();
+        slots.insert(Rc::clone(&key), self.keys.len());
+        let shape = Rc::new(Shape::new(keys, slots));
+        transitions.insert(key, Rc::clone(&shape));
+        Some(shape)
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Storage {
+    // The value of each key in the shape, by slot
+    Shaped {
+        shape: Rc<Shape>,
+        slots: Vec<Property>,
+    },
+    // For objects that have had properties deleted or grown too large
+    Dictionary(IndexMap<String, Property, FxBuildHasher>),
+}
+
+/// The properties of an object, in the order they were first defined.
+/// Redefining a property keeps its place, and removing one keeps the order
+/// of the rest.
+///
+/// Objects start out with a [`Shape`] and their values in slots. Deleting a
+/// property, or adding too many, moves an object to a hash map of its own
+/// for good.
+#[derive(Debug, Clone)]
+pub struct Object {
+    storage: Storage,
+    // The name of the built-in constructor that made the object, such as
+    // "TypeError". There are no prototypes yet, so `instanceof` checks this
+    // instead. "Array" marks the array-like objects the VM makes where an
+    // array needs properties of its own, such as a tagged template's
+    // strings.
+    class: Option<Rc<str>>,
+    // Set by `freeze`. The VM ignores assignments to and deletions of the
+    // properties of a frozen object.
+    frozen: bool,
+}
+
+impl Default for Object {
+    fn default() -> Self {
+        Object {
+            storage: Storage::Shaped {
+                shape: Shape::empty(),
+                slots: vec![],
+            },
+            class: None,
+            frozen: false,
+        }
+    }
+}
+
+impl Object {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// An empty object made by the built-in constructor named `class`.
+    pub fn with_class(class: &str) -> Self {
+        Object {
+            class: Some(Rc::from(class)),
+            ..Self::default()
+        }
+    }
+
+    pub fn class(&self) -> Option<&str> {
+        self.class.as_deref()
+    }
+
+    /// Stops the object's properties from being changed, added or removed
+    /// from JS code. Rust code can still change them.
+    pub fn freeze(&mut self) {
+        self.frozen = true;
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    /// The object's shape, unless it is in dictionary mode.
+    pub fn shape(&self) -> Option<&Rc<Shape>> {
+        match &self.storage {
+            Storage::Shaped { shape, .. } => Some(shape),
+            Storage::Dictionary(_) => None,
+        }
+    }
+
+    /// The property in `slot` of the object's shape.
+    ///
+    /// # Panics
+    ///
+    /// If the object is in dictionary mode or the slot is out of range.
+    pub fn slot(&self, slot: usize) -> &Property {
+        match &self.storage {
+            Storage::Shaped { slots, .. } => &slots[slot],
+            Storage::Dictionary(_) => panic!("dictionary objects have no slots"),
+        }
+    }
+
+    /// The property in `slot`, for changing in place.
+    ///
+    /// # Panics
+    ///
+    /// As for [`Object::slot`].
+    pub fn slot_mut(&mut self, slot: usize) -> &mut Property {
+        match &mut self.storage {
+            Storage::Shaped { slots, .. } => &mut slots[slot],
+            Storage::Dictionary(_) => panic!("dictionary objects have no slots"),
+        }
+    }
+
+    pub fn property(&self, key: &str) -> Option<&Property> {
+        match &self.storage {
+            Storage::Shaped { shape, slots } => shape.lookup(key).map(|slot| &slots[slot]),
+            Storage::Dictionary(properties) => properties.get(key),
+        }
+    }
+
+    fn property_mut(&mut self, key: &str) -> Option<&mut Property> {
+        match &mut self.storage {
+            Storage::Shaped { shape, slots } => shape.lookup(key).map(|slot| &mut slots[slot]),
+            Storage::Dictionary(properties) => properties.get_mut(key),
+        }
+    }
+
+    /// The value of `key` if it is a data property. Accessors are left to
+    /// the VM, which can call them.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self.property(key)? {
+            Property::Data(value) => Some(value),
+            Property::Accessor { .. } => None,
+        }
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.property(key).is_some()
+    }
+
+    /// Makes `key` a data property holding `value`, replacing an accessor.
+    pub fn insert(&mut self, key: String, value: Value) {
+        self.define(key, Property::Data(value));
+    }
+
+    /// Gives `key` a getter, keeping its setter if it is already an accessor.
+    pub fn define_getter(&mut self, key: String, getter: Value) {
+        match self.property_mut(&key) {
+            Some(Property::Accessor { get, .. }) => *get = Some(getter),
+            _ => {
+                let accessor = Property::Accessor {
+                    get: Some(getter),
+                    set: None,
+                };
+                self.define(key, accessor);
+            }
+        }
+    }
+
+    /// Gives `key` a setter, keeping its getter if it is already an accessor.
+    pub fn define_setter(&mut self, key: String, setter: Value) {
+        match self.property_mut(&key) {
+            Some(Property::Accessor { set, .. }) => *set = Some(setter),
+            _ => {
+                let accessor = Property::Accessor {
+                    get: None,
+                    set: Some(setter),
+                };
+                self.define(key, accessor);
+            }
+        }
+    }
+
+    fn define(&mut self, key: String, property: Property) {
+        if let Some(existing) = self.property_mut(&key) {
+            *existing = property;
+            return;
+        }
+        match &mut self.storage {
+            Storage::Shaped { shape, slots } => match shape.transition(&key) {
+                Some(next) => {
+                    *shape = next;
+                    slots.push(property);
+                }
+                None => {
+                    self.make_dictionary().insert(key, property);
+                }
+            },
+            Storage::Dictionary(properties) => {
+                properties.insert(key, property);
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<Property> {
+        if !self.contains_key(key) {
+            return None;
+        }
+        self.make_dictionary().shift_remove(key)
+    }
+
+    /// Moves the properties out of slots into a map of their own.
+    fn make_dictionary(&mut self) -> &mut IndexMap<String, Property, FxBuildHasher> {
+        if let Storage::Shaped { shape, slots } = &mut self.storage {
+            let properties = shape
+                .keys()
+                .iter()
+                .map(|key| key.to_string())
+                .zip(std::mem::take(slots))
+                .collect();
+            self.storage = Storage::Dictionary(properties);
+        }
+        match &mut self.storage {
+            Storage::Dictionary(properties) => properties,
+            Storage::Shaped { .. } => unreachable!(),
+        }
+    }
+
+    /// The keys in the order JS code sees them: array indices in ascending
+    /// order, then the other keys in the order they were defined.
+    pub fn keys(&self) -> Vec<&str> {
+        let keys: Vec<&str> = match &self.storage {
+            Storage::Shaped { shape, .. } => shape.keys().iter().map(|key| &**key).collect(),
+            Storage::Dictionary(properties) => properties.keys().map(String::as_str).collect(),
+        };
+        let mut indices: Vec<(u32, &str)> = keys
+            .iter()
+            .filter_map(|key| Some((array_index(key)?, *key)))
+            .collect();
+        if indices.is_empty() {
+            return keys;
+        }
+        indices.sort_unstable();
+        let mut ordered: Vec<&str> = indices.into_iter().map(|(_, key)| key).collect();
+        ordered.extend(keys.into_iter().filter(|key| array_index(key).is_none()));
+        ordered
+    }
+
+    pub fn len(&self) -> usize {
+        match &self.storage {
+            Storage::Shaped { slots, .. } => slots.len(),
+            Storage::Dictionary(properties) => properties.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl FromIterator<(String, Value)> for Object {
+    fn from_iter<I: IntoIterator<Item = (String, Value)>>(iter: I) -> Self {
+        let mut object = Object::new();
+        for (key, value) in iter {
+            object.insert(key, value);
+        }
+        object
+    }
+}
+
+impl Trace for Object {
+    fn trace(&self, tracer: &mut Tracer) {
+        match &self.storage {
+            Storage::Shaped { slots, .. } => {
+                for property in slots {
+                    property.trace(tracer);
+                }
+            }
+            Storage::Dictionary(properties) => {
+                for property in properties.values() {
+                    property.trace(tracer);
+                }
+            }
+        }
+    }
+
+    fn heap_size(&self) -> usize {
+        match &self.storage {
+            Storage::Shaped { slots, .. } => slots.heap_size(),
+            // The entries, leaving out the index into them and the keys' text
+            Storage::Dictionary(properties) => {
+                properties.capacity() * std::mem::size_of::<(String, Property)>()
+            }
+        }
+    }
+}
+
+/// The array index `key` names, if it is one: an integer below 2³² - 1
+/// written the way ToString writes it, so "01" and "1.0" are not indices.
+pub fn array_index(key: &str) -> Option<u32> {
+    if key.len() > 1 && key.starts_with('0') {
+        return None;
+    }
+    if !key.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    key.parse::<u32>().ok().filter(|index| *index != u32::MAX)
+}
@@ -1,31 +0,0 @@
-use shadowjs_value::Value;
-use std::collections::HashMap;
-
-#[derive(Debug, Clone)]
-pub struct Environment {
-    values: HashMap<String, Value>,
-    outer: Option<Box<Environment>>,
-}
-
-impl Environment {
-    pub fn new() -> Self {
-        Self {
-            values: HashMap::new(),
-            outer: None,
-        }
-    }
-
-    pub fn define(&mut self, name: String, value: Value) {
-        self.values.insert(name, value);
-    }
-
-    pub fn get(&self, name: &str) -> Option<Value> {
-        match self.values.get(name) {
-            Some(val) => Some(val.clone()),
-            None => match &self.outer {
-                Some(outer) => outer.get(name),
-                None => None,
-            },
-        }
-    }
-}
@@ -1,24 +1,120 @@
+use shadowjs_gc::Gc;
+use shadowjs_value::{to_string, Object, Value};
 use std::fmt;
+use std::rc::Rc;
 
+/// Why an instruction could not complete. Every variant is catchable from JS:
+/// errors raised by the VM itself surface as the matching Error object.
 #[derive(Debug)]
-pub enum RuntimeError {
+pub enum ErrorKind {
     StackUnderflow,
     UndefinedVariable(String),
+    /// A `let` or `const` binding read before its declaration ran
+    UninitializedVariable(String),
     TypeError(String),
+    RangeError(String),
     UnknownOperator(String),
     Custom(String),
+    /// A value raised by `throw`
+    Thrown(Value),
 }
 
-impl fmt::Display for RuntimeError {
+impl ErrorKind {
+    /// The value JS code sees when it catches the error.
+    pub fn to_value(&self) -> Value {
+        match self {
+            ErrorKind::Thrown(value) => value.clone(),
+            ErrorKind::UndefinedVariable(name) => {
+                create_error("ReferenceError", &format!("{} is not defined", name))
+            }
+            ErrorKind::UninitializedVariable(name) => create_error(
+                "ReferenceError",
+                &format!("Cannot access '{}' before initialization", name),
+            ),
+            ErrorKind::TypeError(msg) => create_error("TypeError", msg),
+            ErrorKind::RangeError(msg) => create_error("RangeError", msg),
+            ErrorKind::StackUnderflow | ErrorKind::UnknownOperator(_) | ErrorKind::Custom(_) => {
+                create_error("Error", &self.to_string())
+            }
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            RuntimeError::StackUnderflow => write!(f, "Stack underflow"),
-            RuntimeError::UndefinedVariable(name) => write!(f, "Undefined variable '{}'", name),
-            RuntimeError::TypeError(msg) => write!(f, "Type error: {}", msg),
-            RuntimeError::UnknownOperator(op) => write!(f, "Unknown operator: {}", op),
-            RuntimeError::Custom(msg) => write!(f, "Error: {}", msg),
+            ErrorKind::StackUnderflow => write!(f, "Stack underflow"),
+            ErrorKind::UndefinedVariable(name) => write!(f, "Undefined variable '{}'", name),
+            ErrorKind::UninitializedVariable(name) => {
+                write!(f, "Cannot access '{}' before initialization", name)
+            }
+            ErrorKind::TypeError(msg) => write!(f, "Type error: {}", msg),
+            ErrorKind::RangeError(msg) => write!(f, "Range error: {}", msg),
+            ErrorKind::UnknownOperator(op) => write!(f, "Unknown operator: {}", op),
+            ErrorKind::Custom(msg) => write!(f, "Error: {}", msg),
+            ErrorKind::Thrown(value) => write!(f, "{}", describe_exception(value)),
         }
     }
 }
 
+/// Where in a script an error was raised. Lines and columns start at 1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceLocation {
+    pub file: Rc<String>,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+/// An exception that escaped the script, with the location of the
+/// instruction that raised it.
+#[derive(Debug)]
+pub struct RuntimeError {
+    pub kind: ErrorKind,
+    pub location: Option<SourceLocation>,
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Uncaught {}", self.kind)?;
+        if let Some(location) = &self.location {
+            write!(f, " at {}", location)?;
+        }
+        Ok(())
+    }
+}
+
 impl std::error::Error for RuntimeError {}
+
+/// Creates an Error object such as the ones the `TypeError` constructor makes.
+pub fn create_error(name: &str, message: &str) -> Value {
+    let mut map = Object::with_class(name);
+    map.insert("name".to_string(), Value::String(Rc::new(name.to_string())));
+    map.insert(
+        "message".to_string(),
+        Value::String(Rc::new(message.to_string())),
+    );
+    Value::Object(Gc::new(map))
+}
+
+/// Formats an exception the way `Error.prototype.toString` would, falling back
+/// to ToString for values that are not Error objects.
+pub fn describe_exception(value: &Value) -> String {
+    if let Value::Object(obj) = value {
+        let obj = obj.borrow();
+        if let (Some(name), Some(message)) = (obj.get("name"), obj.get("message")) {
+            let (name, message) = (to_string(name), to_string(message));
+            return match (name.is_empty(), message.is_empty()) {
+                (_, true) => name,
+                (true, false) => message,
+                (false, false) => format!("{}: {}", name, message),
+            };
+        }
+    }
+    to_string(value)
+}
@@ -1,7 +1,7 @@
-pub mod environment;
 pub mod error;
 pub mod vm;
 
-pub use error::RuntimeError;
+pub use error::{create_error, ErrorKind, RuntimeError, SourceLocation};
+pub use shadowjs_gc::HeapStats;
 pub use shadowjs_value::Value;
-pub use vm::VM;
+pub use vm::{CacheStats, VM};
@@ -1,47 +1,179 @@
+use crate::error::{ErrorKind, RuntimeError, SourceLocation};
 use rustc_hash::FxHashMap;
-use shadowjs_bytecode::{Chunk, Constant, OpCode};
+use shadowjs_bytecode::{Chunk, Constant, OpCode, PropertyCache};
 use shadowjs_gc::trace::Trace;
-use shadowjs_gc::{Gc, GC};
+use shadowjs_gc::{Gc, HeapScope, HeapStats, GC};
 use shadowjs_jit::JitCompiler;
-use shadowjs_value::Value;
+use shadowjs_value::{
+    array_index, is_less_than, is_loosely_equal, is_strictly_equal, to_boolean, to_int32,
+    to_number, to_primitive, to_string, to_uint32, Function, NativeFn, Object, Property, Upvalue,
+    Value, ValueIterator,
+};
+use std::borrow::Cow;
+use std::cell::Cell;
 use std::rc::Rc;
 
+const FRAMES_MAX: usize = 4096;
+
+struct CallFrame {
+    closure: Option<Gc<Function>>, // None for the top-level script
+    chunk: Rc<Chunk>,
+    ip: usize,
+    base: usize, // Stack index of the callee, arguments and locals follow it
+    this: Value,
+    returns_to: ReturnTo,
+}
+
+/// Where a frame's result goes when it returns.
+enum ReturnTo {
+    // Pushed for the caller, as for an ordinary call
+    Stack,
+    // Dropped, for a setter: the assignment's value is already on the stack
+    // below it
+    Discard,
+    // Defined as this property of the object CopyProperties is filling in,
+    // for a getter. The object stays on the caller's stack meanwhile.
+    Property(Gc<Object>, String),
+}
+
 pub struct VM {
     stack: Vec<Value>,
-    globals: FxHashMap<String, Value>,
+    frames: Vec<CallFrame>,
+    // Upvalues still pointing into the stack, sorted by slot
+    open_upvalues: Vec<Gc<Upvalue>>,
+    // The cell holding each global. Cells are never removed, so the
+    // chunks' global caches can keep their indices.
+    globals: FxHashMap<String, usize>,
+    global_cells: Vec<Value>,
+    // The strings object of each tagged template that has run, found
+    // through the chunks' template caches
+    template_objects: Vec<Value>,
+    // Methods found on every string, such as `"a".match`
+    string_methods: FxHashMap<String, Value>,
+    // Creates the object for a regular expression literal
+    regexp_constructor: Option<NativeFn>,
+    // The built-in Error constructors and the class of the objects they make
+    error_constructors: Vec<(NativeFn, &'static str)>,
     debug: bool,
+    cache_stats: CacheStats,
     jit_compiler: JitCompiler,
     gc: GC,
 }
 
+/// How often the inline caches had the answer, counted since the VM was
+/// created. Property accesses are only counted on objects, the one kind of
+/// value their caches apply to.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CacheStats {
+    pub property_hits: u64,
+    pub property_misses: u64,
+    pub global_hits: u64,
+    pub global_misses: u64,
+}
+
+impl Default for VM {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl VM {
     pub fn new() -> Self {
-        let mut globals = FxHashMap::default();
-        globals.insert(
-            "print".to_string(),
-            Value::NativeFunction(|args| {
+        let mut vm = Self {
+            stack: Vec::with_capacity(256),
+            frames: Vec::with_capacity(64),
+            open_upvalues: Vec::new(),
+            globals: FxHashMap::default(),
+            global_cells: Vec::new(),
+            template_objects: Vec::new(),
+            string_methods: FxHashMap::default(),
+            regexp_constructor: None,
+            error_constructors: Vec::new(),
+            debug: false,
+            cache_stats: CacheStats::default(),
+            jit_compiler: JitCompiler::new(),
+            gc: GC::new(),
+        };
+        vm.define_global(
+            "print",
+            Value::NativeFunction(|_, args| {
                 for arg in args {
                     print!("{} ", arg);
                 }
                 println!();
-                Value::Undefined
+                Ok(Value::Undefined)
             }),
         );
 
-        Self {
-            stack: Vec::with_capacity(256),
-            globals,
-            debug: false,
-            jit_compiler: JitCompiler::new(),
-            gc: GC::new(),
-        }
+        vm.define_global("NaN", Value::Number(f64::NAN));
+        vm.define_global("Infinity", Value::Number(f64::INFINITY));
+        vm
     }
 
     pub fn set_debug(&mut self, debug: bool) {
         self.debug = debug;
     }
 
-    pub fn execute(&mut self, chunk: Chunk) -> Result<(), String> {
+    pub fn define_global(&mut self, name: &str, value: Value) {
+        let cell = self.global_cell(name);
+        self.global_cells[cell] = value;
+    }
+
+    /// The value of the global `name`, if it has been defined.
+    pub fn global(&self, name: &str) -> Option<Value> {
+        let cell = *self.globals.get(name)?;
+        Some(self.global_cells[cell].clone())
+    }
+
+    pub fn cache_stats(&self) -> CacheStats {
+        self.cache_stats
+    }
+
+    pub fn heap_stats(&self) -> HeapStats {
+        self.gc.stats()
+    }
+
+    /// Makes values allocated until the scope is dropped belong to this VM,
+    /// for embedders creating values to hand to it. Scripts run in it
+    /// already.
+    pub fn enter_heap(&self) -> HeapScope {
+        self.gc.enter()
+    }
+
+    /// The cell of the global `name`, made for it if it has none yet.
+    fn global_cell(&mut self, name: &str) -> usize {
+        if let Some(&cell) = self.globals.get(name) {
+            return cell;
+        }
+        self.global_cells.push(Value::Undefined);
+        let cell = self.global_cells.len() - 1;
+        self.globals.insert(name.to_string(), cell);
+        cell
+    }
+
+    /// Makes `method` available on every string value as `name`. It is called
+    /// with the string as `this`.
+    pub fn define_string_method(&mut self, name: &str, method: NativeFn) {
+        self.string_methods
+            .insert(name.to_string(), Value::NativeFunction(method));
+    }
+
+    /// Sets the function regular expression literals are created with. It is
+    /// called with the pattern and flags strings.
+    pub fn set_regexp_constructor(&mut self, constructor: NativeFn) {
+        self.regexp_constructor = Some(constructor);
+    }
+
+    /// Defines the global `name` as a constructor of Error objects, which
+    /// `instanceof` recognizes the objects of. `Error` itself matches every
+    /// error object.
+    pub fn define_error_constructor(&mut self, name: &'static str, constructor: NativeFn) {
+        self.define_global(name, Value::NativeFunction(constructor));
+        self.error_constructors.push((constructor, name));
+    }
+
+    pub fn execute(&mut self, chunk: Chunk) -> Result<(), RuntimeError> {
+        let _heap = self.gc.enter();
         // Try JIT first
         if let Ok(func) = self.jit_compiler.compile(&chunk) {
             if self.debug {
@@ -50,261 +182,1192 @@ impl VM {
             let result = func();
             self.push(Value::Number(result));
             return Ok(());
-        } else {
-            if self.debug {
-                println!("JIT compilation failed, falling back to interpreter");
+        } else if self.debug {
+            println!("JIT compilation failed, falling back to interpreter");
+        }
+
+        // The script occupies the callee slot of its own frame
+        self.frames.push(CallFrame {
+            closure: None,
+            chunk: Rc::new(chunk),
+            ip: 0,
+            base: self.stack.len(),
+            this: Value::Undefined,
+            returns_to: ReturnTo::Stack,
+        });
+        self.push(Value::Undefined);
+
+        let result = self.run();
+        self.stack.clear();
+        self.frames.clear();
+        self.open_upvalues.clear();
+        result
+    }
+
+    fn run(&mut self) -> Result<(), RuntimeError> {
+        let (mut chunk, mut ip, mut base) = self.load_frame();
+
+        loop {
+            if ip >= chunk.code.len() {
+                // Falling off the end of the script ends the program
+                return Ok(());
+            }
+
+            match self.step(&mut chunk, &mut ip, &mut base) {
+                Ok(false) => {}
+                Ok(true) => return Ok(()),
+                Err(error) => {
+                    self.frames.last_mut().unwrap().ip = ip;
+                    if let Err(exception) = self.throw(error.to_value(), 0) {
+                        let location =
+                            chunk.location(ip - 1).map(|(line, column)| SourceLocation {
+                                file: Rc::clone(&chunk.source_name),
+                                line,
+                                column,
+                            });
+                        return Err(RuntimeError {
+                            kind: ErrorKind::Thrown(exception),
+                            location,
+                        });
+                    }
+                    (chunk, ip, base) = self.load_frame();
+                }
             }
         }
+    }
 
-        let mut ip = 0;
-        while ip < chunk.code.len() {
-            if ip % 1000 == 0 {
-                let mut roots: Vec<&dyn Trace> = Vec::new();
-                for val in &self.stack {
-                    roots.push(val);
+    /// Executes the instruction at `ip`, returning whether the script has
+    /// finished. `chunk`, `ip` and `base` cache the current frame.
+    #[inline(always)]
+    fn step(
+        &mut self,
+        chunk: &mut Rc<Chunk>,
+        ip: &mut usize,
+        base: &mut usize,
+    ) -> Result<bool, ErrorKind> {
+        let op = &chunk.code[*ip];
+        if self.debug {
+            println!("Op: {:?}", op);
+        }
+        *ip += 1;
+
+        match op {
+            OpCode::Constant(idx) => match &chunk.constants[*idx] {
+                Constant::Number(n) => self.push(Value::Number(*n)),
+                Constant::String(s) => self.push(Value::String(s.clone())),
+                Constant::Function(_) => {
+                    return Err(ErrorKind::Custom(
+                        "Functions must be loaded with Closure".into(),
+                    ))
                 }
-                for val in self.globals.values() {
-                    roots.push(val);
+            },
+            OpCode::Add => {
+                let b = to_primitive(&self.pop()?);
+                let a = to_primitive(&self.pop()?);
+                match (&a, &b) {
+                    (Value::String(_), _) | (_, Value::String(_)) => {
+                        self.push(Value::String(Rc::new(to_string(&a) + &to_string(&b))))
+                    }
+                    _ => self.push(Value::Number(to_number(&a) + to_number(&b))),
                 }
-                roots.push(&chunk);
-
-                self.gc.collect(&roots);
             }
-
-            let op = &chunk.code[ip];
-            if self.debug {
-                println!("Op: {:?}", op);
-            }
-            ip += 1;
-
-            match op {
-                OpCode::Constant(idx) => match &chunk.constants[*idx] {
-                    Constant::Number(n) => self.push(Value::Number(*n)),
-                    Constant::String(s) => self.push(Value::String(s.clone())),
-                    Constant::Function(f) => {
-                        let func = shadowjs_value::Function {
-                            name: f.name.clone(),
-                            chunk: f.chunk.clone(),
-                            arity: f.arity,
-                        };
-                        self.push(Value::Function(Gc::new(func)));
+            OpCode::Sub => {
+                let (a, b) = self.pop_numbers()?;
+                self.push(Value::Number(a - b));
+            }
+            OpCode::Mul => {
+                let (a, b) = self.pop_numbers()?;
+                self.push(Value::Number(a * b));
+            }
+            OpCode::Div => {
+                let (a, b) = self.pop_numbers()?;
+                self.push(Value::Number(a / b));
+            }
+            OpCode::Mod => {
+                let (a, b) = self.pop_numbers()?;
+                self.push(Value::Number(a % b));
+            }
+            OpCode::Exp => {
+                let (a, b) = self.pop_numbers()?;
+                self.push(Value::Number(exponentiate(a, b)));
+            }
+            OpCode::ShiftLeft => {
+                let (a, b) = self.pop_numbers()?;
+                let result = to_int32(a).wrapping_shl(to_uint32(b) & 31);
+                self.push(Value::Number(result as f64));
+            }
+            OpCode::ShiftRight => {
+                let (a, b) = self.pop_numbers()?;
+                let result = to_int32(a) >> (to_uint32(b) & 31);
+                self.push(Value::Number(result as f64));
+            }
+            OpCode::UnsignedShiftRight => {
+                let (a, b) = self.pop_numbers()?;
+                let result = to_uint32(a) >> (to_uint32(b) & 31);
+                self.push(Value::Number(result as f64));
+            }
+            OpCode::BitAnd => {
+                let (a, b) = self.pop_numbers()?;
+                self.push(Value::Number((to_int32(a) & to_int32(b)) as f64));
+            }
+            OpCode::BitOr => {
+                let (a, b) = self.pop_numbers()?;
+                self.push(Value::Number((to_int32(a) | to_int32(b)) as f64));
+            }
+            OpCode::BitXor => {
+                let (a, b) = self.pop_numbers()?;
+                self.push(Value::Number((to_int32(a) ^ to_int32(b)) as f64));
+            }
+            OpCode::BitNot => {
+                let n = to_number(&self.pop()?);
+                self.push(Value::Number(!to_int32(n) as f64));
+            }
+            OpCode::Negate => {
+                let n = to_number(&self.pop()?);
+                self.push(Value::Number(-n));
+            }
+            OpCode::Not => {
+                let value = self.pop()?;
+                self.push(Value::Boolean(self.is_falsey(&value)));
+            }
+            OpCode::ToNumber => {
+                let n = to_number(&self.pop()?);
+                self.push(Value::Number(n));
+            }
+            OpCode::TypeOf => {
+                let value = self.pop()?;
+                self.push(Value::String(Rc::new(value.type_of().to_string())));
+            }
+            OpCode::TypeOfGlobal(idx) => {
+                let name = match &chunk.constants[*idx] {
+                    Constant::String(s) => s,
+                    _ => return Err(ErrorKind::Custom("Global name must be a string".into())),
+                };
+                let type_name = match self.globals.get(name.as_str()) {
+                    Some(&cell) => self.global_cells[cell].type_of(),
+                    None => "undefined",
+                };
+                self.push(Value::String(Rc::new(type_name.to_string())));
+            }
+            OpCode::DeleteGlobal(idx) => {
+                let name = match &chunk.constants[*idx] {
+                    Constant::String(s) => s,
+                    _ => return Err(ErrorKind::Custom("Global name must be a string".into())),
+                };
+                let undeclared = !self.globals.contains_key(name.as_str());
+                self.push(Value::Boolean(undeclared));
+            }
+            OpCode::Delete => {
+                let index = self.pop()?;
+                let target = self.pop()?;
+                match (target, index) {
+                    (Value::Object(obj), key) => {
+                        let key = property_key(&key);
+                        let mut obj = obj.borrow_mut();
+                        if obj.is_frozen() {
+                            // A frozen object keeps its properties
+                            let deleted = !obj.contains_key(&key);
+                            drop(obj);
+                            self.push(Value::Boolean(deleted));
+                            return Ok(false);
+                        }
+                        obj.remove(&key);
                     }
-                },
-                OpCode::Add => {
-                    let b = self.pop()?;
-                    let a = self.pop()?;
-                    match (a, b) {
-                        (Value::Number(a), Value::Number(b)) => self.push(Value::Number(a + b)),
-                        (Value::String(a), Value::String(b)) => {
-                            self.push(Value::String(Rc::new(format!("{}{}", a, b))))
+                    (Value::Array(arr), key) => {
+                        // Without holes, the closest we can get is clearing the element
+                        if let Some(idx) = element_index(&key) {
+                            if let Some(elem) = arr.borrow_mut().get_mut(idx) {
+                                *elem = Value::Undefined;
+                            }
                         }
-                        _ => return Err("Operands must be numbers or strings".to_string()),
                     }
-                }
-                OpCode::Sub => {
-                    let b = self.pop()?;
-                    let a = self.pop()?;
-                    match (a, b) {
-                        (Value::Number(a), Value::Number(b)) => self.push(Value::Number(a - b)),
-                        _ => return Err("Operands must be numbers".to_string()),
+                    (Value::Null | Value::Undefined, _) => {
+                        return Err(ErrorKind::TypeError(
+                            "Cannot convert undefined or null to object".into(),
+                        ))
                     }
+                    _ => {}
                 }
-                OpCode::Mul => {
-                    let b = self.pop()?;
-                    let a = self.pop()?;
-                    match (a, b) {
-                        (Value::Number(a), Value::Number(b)) => self.push(Value::Number(a * b)),
-                        _ => return Err("Operands must be numbers".to_string()),
+                self.push(Value::Boolean(true));
+            }
+            OpCode::Equal => {
+                let b = self.pop()?;
+                let a = self.pop()?;
+                self.push(Value::Boolean(is_loosely_equal(&a, &b)));
+            }
+            OpCode::NotEqual => {
+                let b = self.pop()?;
+                let a = self.pop()?;
+                self.push(Value::Boolean(!is_loosely_equal(&a, &b)));
+            }
+            OpCode::StrictEqual => {
+                let b = self.pop()?;
+                let a = self.pop()?;
+                self.push(Value::Boolean(is_strictly_equal(&a, &b)));
+            }
+            OpCode::StrictNotEqual => {
+                let b = self.pop()?;
+                let a = self.pop()?;
+                self.push(Value::Boolean(!is_strictly_equal(&a, &b)));
+            }
+            // `a <= b` is `!(b < a)`, except that NaN makes both false
+            OpCode::LessThan => {
+                let b = self.pop()?;
+                let a = self.pop()?;
+                self.push(Value::Boolean(is_less_than(&a, &b) == Some(true)));
+            }
+            OpCode::GreaterThan => {
+                let b = self.pop()?;
+                let a = self.pop()?;
+                self.push(Value::Boolean(is_less_than(&b, &a) == Some(true)));
+            }
+            OpCode::LessEqual => {
+                let b = self.pop()?;
+                let a = self.pop()?;
+                self.push(Value::Boolean(is_less_than(&b, &a) == Some(false)));
+            }
+            OpCode::GreaterEqual => {
+                let b = self.pop()?;
+                let a = self.pop()?;
+                self.push(Value::Boolean(is_less_than(&a, &b) == Some(false)));
+            }
+            OpCode::In => {
+                let target = self.pop()?;
+                let key = self.pop()?;
+                let found = match (&target, &key) {
+                    (Value::Object(obj), key) => obj.borrow().contains_key(&property_key(key)),
+                    (Value::Array(arr), key) => {
+                        property_key(key) == "length"
+                            || element_index(key).is_some_and(|i| i < arr.borrow().len())
                     }
-                }
-                OpCode::Div => {
-                    let b = self.pop()?;
-                    let a = self.pop()?;
-                    match (a, b) {
-                        (Value::Number(a), Value::Number(b)) => self.push(Value::Number(a / b)),
-                        _ => return Err("Operands must be numbers".to_string()),
+                    _ => {
+                        return Err(ErrorKind::TypeError(format!(
+                            "Cannot use 'in' operator to search for '{}' in {}",
+                            key, target
+                        )))
                     }
-                }
-                OpCode::Equal => {
-                    let b = self.pop()?;
-                    let a = self.pop()?;
-                    self.push(Value::Boolean(a == b));
-                }
-                OpCode::NotEqual => {
-                    let b = self.pop()?;
-                    let a = self.pop()?;
-                    self.push(Value::Boolean(a != b));
-                }
-                OpCode::LessThan => {
-                    let b = self.pop()?;
-                    let a = self.pop()?;
-                    match (a, b) {
-                        (Value::Number(a), Value::Number(b)) => self.push(Value::Boolean(a < b)),
-                        _ => return Err("Operands must be numbers".to_string()),
+                };
+                self.push(Value::Boolean(found));
+            }
+            OpCode::InstanceOf => {
+                let constructor = self.pop()?;
+                let value = self.pop()?;
+                match constructor {
+                    // There are no prototype chains yet. Objects made by the
+                    // built-in Error constructors record which one made them,
+                    // and nothing is an instance of any other function.
+                    Value::NativeFunction(constructor) => {
+                        let found = self.is_error_instance(&value, constructor);
+                        self.push(Value::Boolean(found))
                     }
-                }
-                OpCode::GreaterThan => {
-                    let b = self.pop()?;
-                    let a = self.pop()?;
-                    match (a, b) {
-                        (Value::Number(a), Value::Number(b)) => self.push(Value::Boolean(a > b)),
-                        _ => return Err("Operands must be numbers".to_string()),
+                    Value::Function(_) => self.push(Value::Boolean(false)),
+                    _ => {
+                        return Err(ErrorKind::TypeError(
+                            "Right-hand side of 'instanceof' is not callable".into(),
+                        ))
                     }
                 }
-                OpCode::Pop => {
-                    self.pop()?;
+            }
+            OpCode::Pop => {
+                self.pop()?;
+            }
+            OpCode::Dup => {
+                let val = self.peek(0)?.clone();
+                self.push(val);
+            }
+            OpCode::Dup2 => {
+                let a = self.peek(1)?.clone();
+                let b = self.peek(0)?.clone();
+                self.push(a);
+                self.push(b);
+            }
+            OpCode::Bury(depth) => {
+                let val = self.pop()?;
+                if self.stack.len() < *depth {
+                    return Err(ErrorKind::StackUnderflow);
                 }
-                OpCode::GetGlobal(idx) => {
-                    let name = match &chunk.constants[*idx] {
-                        Constant::String(s) => s,
-                        _ => return Err("Global name must be a string".to_string()),
-                    };
-                    let val = self
-                        .globals
-                        .get(name.as_str())
-                        .ok_or(format!("Undefined variable '{}'", name))?;
-                    self.push(val.clone());
+                let at = self.stack.len() - depth;
+                self.stack.insert(at, val);
+            }
+            OpCode::GetGlobal(idx, cache) => {
+                let cache = &chunk.global_caches[*cache];
+                let cell = match cache.get() {
+                    Some(cell) => {
+                        self.cache_stats.global_hits += 1;
+                        cell
+                    }
+                    None => {
+                        self.cache_stats.global_misses += 1;
+                        let name = match &chunk.constants[*idx] {
+                            Constant::String(s) => s,
+                            _ => {
+                                return Err(ErrorKind::Custom(
+                                    "Global name must be a string".into(),
+                                ))
+                            }
+                        };
+                        let cell = *self
+                            .globals
+                            .get(name.as_str())
+                            .ok_or_else(|| ErrorKind::UndefinedVariable(name.to_string()))?;
+                        cache.set(Some(cell));
+                        cell
+                    }
+                };
+                self.push(self.global_cells[cell].clone());
+            }
+            OpCode::SetGlobal(idx, cache) => {
+                let cache = &chunk.global_caches[*cache];
+                let cell = match cache.get() {
+                    Some(cell) => {
+                        self.cache_stats.global_hits += 1;
+                        cell
+                    }
+                    None => {
+                        self.cache_stats.global_misses += 1;
+                        let name = match &chunk.constants[*idx] {
+                            Constant::String(s) => s,
+                            _ => {
+                                return Err(ErrorKind::Custom(
+                                    "Global name must be a string".into(),
+                                ))
+                            }
+                        };
+                        let cell = self.global_cell(name);
+                        cache.set(Some(cell));
+                        cell
+                    }
+                };
+                self.global_cells[cell] = self.peek(0)?.clone();
+            }
+            OpCode::GetLocal(slot) => {
+                let val = self.stack[*base + *slot].clone();
+                self.push(val);
+            }
+            OpCode::SetLocal(slot) => {
+                let val = self.peek(0)?.clone();
+                self.stack[*base + *slot] = val;
+            }
+            OpCode::GetUpvalue(idx) => {
+                let upvalue = self.current_upvalue(*idx);
+                let val = match &*upvalue.borrow() {
+                    Upvalue::Open(slot) => self.stack[*slot].clone(),
+                    Upvalue::Closed(val) => val.clone(),
+                };
+                self.push(val);
+            }
+            OpCode::SetUpvalue(idx) => {
+                let upvalue = self.current_upvalue(*idx);
+                let val = self.peek(0)?.clone();
+                let mut upvalue = upvalue.borrow_mut();
+                match &mut *upvalue {
+                    Upvalue::Open(slot) => self.stack[*slot] = val,
+                    Upvalue::Closed(closed) => *closed = val,
                 }
-                OpCode::SetGlobal(idx) => {
+            }
+            OpCode::CheckInitialized(idx) => {
+                if let Value::Uninitialized = self.peek(0)? {
                     let name = match &chunk.constants[*idx] {
-                        Constant::String(s) => s,
-                        _ => return Err("Global name must be a string".to_string()),
+                        Constant::String(s) => s.to_string(),
+                        _ => return Err(ErrorKind::Custom("Binding name must be a string".into())),
                     };
-                    let val = self.peek(0)?.clone();
-                    self.globals.insert(name.to_string(), val);
+                    return Err(ErrorKind::UninitializedVariable(name));
                 }
-                OpCode::Call(arg_count) => {
-                    let mut args = vec![];
-                    for _ in 0..*arg_count {
-                        args.push(self.pop()?);
+            }
+            OpCode::AssignConst => {
+                return Err(ErrorKind::TypeError(
+                    "Assignment to constant variable.".into(),
+                ))
+            }
+            OpCode::CloseUpvalue => {
+                self.close_upvalues(self.stack.len() - 1);
+                self.pop()?;
+            }
+            OpCode::Closure(idx) => {
+                let template = match &chunk.constants[*idx] {
+                    Constant::Function(f) => Rc::clone(f),
+                    _ => {
+                        return Err(ErrorKind::Custom(
+                            "Closure operand must be a function".into(),
+                        ))
                     }
-                    args.reverse();
-                    let callee = self.pop()?;
-                    match callee {
-                        Value::NativeFunction(f) => {
-                            let result = f(args);
-                            self.push(result);
+                };
+                let upvalues = template
+                    .upvalues
+                    .iter()
+                    .map(|desc| {
+                        if desc.is_local {
+                            self.capture_upvalue(*base + desc.index)
+                        } else {
+                            self.current_upvalue(desc.index)
                         }
-                        _ => return Err("Can only call functions".to_string()),
-                    }
+                    })
+                    .collect();
+                let this = template
+                    .is_arrow
+                    .then(|| self.frames.last().unwrap().this.clone());
+                let func = Function {
+                    name: template.name.clone(),
+                    chunk: Rc::clone(&template.chunk),
+                    arity: template.arity,
+                    has_rest: template.has_rest,
+                    upvalues,
+                    this,
+                };
+                self.push(Value::Function(Gc::new(func)));
+            }
+            OpCode::Call(arg_count) => {
+                let callee = self.peek(*arg_count)?.clone();
+                self.call_value(callee, *arg_count, Value::Undefined, chunk, ip, base)?;
+            }
+            OpCode::CallMethod(arg_count) => {
+                // The receiver sits below the callee and becomes `this`
+                let receiver_slot = self
+                    .stack
+                    .len()
+                    .checked_sub(arg_count + 2)
+                    .ok_or(ErrorKind::StackUnderflow)?;
+                let receiver = self.stack.remove(receiver_slot);
+                let callee = self.peek(*arg_count)?.clone();
+                self.call_value(callee, *arg_count, receiver, chunk, ip, base)?;
+            }
+            OpCode::CallSpread => {
+                let arg_count = self.spread_arguments()?;
+                let callee = self.peek(arg_count)?.clone();
+                self.call_value(callee, arg_count, Value::Undefined, chunk, ip, base)?;
+            }
+            OpCode::CallMethodSpread => {
+                let arg_count = self.spread_arguments()?;
+                let receiver_slot = self
+                    .stack
+                    .len()
+                    .checked_sub(arg_count + 2)
+                    .ok_or(ErrorKind::StackUnderflow)?;
+                let receiver = self.stack.remove(receiver_slot);
+                let callee = self.peek(arg_count)?.clone();
+                self.call_value(callee, arg_count, receiver, chunk, ip, base)?;
+            }
+            OpCode::This => {
+                let this = self.frames.last().unwrap().this.clone();
+                self.push(this);
+            }
+            OpCode::RegExp(pattern, flags) => {
+                let constructor = self.regexp_constructor.ok_or_else(|| {
+                    ErrorKind::TypeError("Regular expressions are not available".into())
+                })?;
+                let args = [pattern, flags]
+                    .map(|idx| match &chunk.constants[*idx] {
+                        Constant::String(s) => Value::String(Rc::clone(s)),
+                        _ => Value::Undefined,
+                    })
+                    .to_vec();
+                let regexp = constructor(Value::Undefined, args).map_err(ErrorKind::Thrown)?;
+                self.push(regexp);
+            }
+            OpCode::Array(count) => {
+                let mut elements = Vec::with_capacity(*count);
+                for _ in 0..*count {
+                    elements.push(self.pop()?);
                 }
-                OpCode::Array(count) => {
-                    let mut elements = Vec::with_capacity(*count);
-                    for _ in 0..*count {
-                        elements.push(self.pop()?);
-                    }
-                    elements.reverse();
-                    self.push(Value::Array(Gc::new(elements)));
-                }
-                OpCode::Object(count) => {
-                    let mut map = FxHashMap::default();
-                    for _ in 0..*count {
-                        let value = self.pop()?;
-                        let key = self.pop()?;
-                        let key_str = match key {
-                            Value::String(s) => s,
-                            _ => return Err("Object key must be a string".to_string()),
+                elements.reverse();
+                self.push(Value::Array(Gc::new(elements)));
+            }
+            OpCode::ArrayPush => {
+                let value = self.pop()?;
+                match self.peek(0)? {
+                    Value::Array(arr) => arr.borrow_mut().push(value),
+                    _ => return Err(ErrorKind::Custom("ArrayPush needs an array".into())),
+                }
+            }
+            OpCode::ArraySpread => {
+                let values = spread(&self.pop()?)?;
+                match self.peek(0)? {
+                    Value::Array(arr) => arr.borrow_mut().extend(values),
+                    _ => return Err(ErrorKind::Custom("ArraySpread needs an array".into())),
+                }
+            }
+            OpCode::GetIterator => {
+                let iter = iterator(self.pop()?)?;
+                self.push(Value::Iterator(Gc::new(iter)));
+            }
+            OpCode::IteratorNext(target) => match self.iterator_next()? {
+                Some(value) => self.push(value),
+                None => *ip = *target,
+            },
+            OpCode::IteratorValue => {
+                let value = self.iterator_next()?.unwrap_or(Value::Undefined);
+                self.push(value);
+            }
+            OpCode::IteratorRest => {
+                let mut rest = vec![];
+                while let Some(value) = self.iterator_next()? {
+                    rest.push(value);
+                }
+                self.push(Value::Array(Gc::new(rest)));
+            }
+            OpCode::ObjectRest(count) => {
+                let count = *count;
+                let source = self.peek(0)?.clone();
+                if matches!(source, Value::Null | Value::Undefined) {
+                    return Err(ErrorKind::TypeError(format!(
+                        "Cannot destructure '{}' as it is {}",
+                        source, source
+                    )));
+                }
+                let keys_start = self
+                    .stack
+                    .len()
+                    .checked_sub(count + 1)
+                    .ok_or(ErrorKind::StackUnderflow)?;
+                let excluded: Vec<String> = self.stack[keys_start..keys_start + count]
+                    .iter()
+                    .map(to_string)
+                    .collect();
+                let rest = Gc::new(Object::new());
+                let keys = self.start_copy(&source, rest, &excluded);
+                self.stack.truncate(keys_start);
+                self.push(Value::Object(rest));
+                self.push(source);
+                self.push(Value::Array(Gc::new(keys)));
+            }
+            OpCode::Object => self.push(Value::Object(Gc::new(Object::new()))),
+            OpCode::DefineProperty | OpCode::DefineGetter | OpCode::DefineSetter => {
+                let value = self.pop()?;
+                let key = to_string(&self.pop()?);
+                let obj = match self.peek(0)? {
+                    Value::Object(obj) => *obj,
+                    _ => return Err(ErrorKind::Custom("Properties need an object".into())),
+                };
+                let mut obj = obj.borrow_mut();
+                match op {
+                    OpCode::DefineGetter => obj.define_getter(key, value),
+                    OpCode::DefineSetter => obj.define_setter(key, value),
+                    _ => obj.insert(key, value),
+                }
+            }
+            OpCode::ObjectSpread => {
+                let source = self.peek(0)?.clone();
+                let obj = match self.peek(1)? {
+                    Value::Object(obj) => *obj,
+                    _ => return Err(ErrorKind::Custom("ObjectSpread needs an object".into())),
+                };
+                let keys = self.start_copy(&source, obj, &[]);
+                self.push(Value::Array(Gc::new(keys)));
+            }
+            OpCode::CopyProperties => {
+                let keys = match self.peek(0)? {
+                    Value::Array(keys) => *keys,
+                    _ => return Err(ErrorKind::Custom("CopyProperties needs keys".into())),
+                };
+                let source = self.peek(1)?.clone();
+                let target = match self.peek(2)? {
+                    Value::Object(obj) => *obj,
+                    _ => return Err(ErrorKind::Custom("CopyProperties needs an object".into())),
+                };
+                // Only objects leave keys to copy
+                if let Value::Object(obj) = &source {
+                    loop {
+                        let Some(key) = keys.borrow_mut().pop() else {
+                            break;
                         };
-                        map.insert(key_str.to_string(), value);
-                    }
-                    self.push(Value::Object(Gc::new(map)));
-                }
-                OpCode::GetIndex => {
-                    let index = self.pop()?;
-                    let target = self.pop()?;
-                    match target {
-                        Value::Array(arr) => {
-                            let idx = match index {
-                                Value::Number(n) => n as usize,
-                                _ => return Err("Array index must be a number".to_string()),
-                            };
-                            let arr = arr.borrow();
-                            if idx < arr.len() {
-                                self.push(arr[idx].clone());
-                            } else {
-                                self.push(Value::Undefined);
-                            }
-                        }
-                        Value::Object(obj) => {
-                            let key = match index {
-                                Value::String(s) => s,
-                                _ => return Err("Object key must be a string".to_string()),
-                            };
-                            let obj = obj.borrow();
-                            if let Some(val) = obj.get(key.as_str()) {
-                                self.push(val.clone());
-                            } else {
-                                self.push(Value::Undefined);
+                        let key = to_string(&key);
+                        let property = obj.borrow().property(&key).cloned();
+                        let value = match property {
+                            Some(Property::Data(value)) => value,
+                            Some(Property::Accessor {
+                                get: Some(getter), ..
+                            }) => {
+                                // The getter runs in a frame of its own, which
+                                // defines the property when it returns. This
+                                // instruction then runs again for the rest.
+                                self.push(getter.clone());
+                                let depth = self.frames.len();
+                                self.call_value(getter, 0, source.clone(), chunk, ip, base)?;
+                                if self.frames.len() > depth {
+                                    self.frames[depth - 1].ip -= 1;
+                                    self.frames.last_mut().unwrap().returns_to =
+                                        ReturnTo::Property(target, key);
+                                    return Ok(false);
+                                }
+                                self.pop()?
                             }
-                        }
-                        _ => return Err("Cannot index non-array/object".to_string()),
+                            Some(Property::Accessor { get: None, .. }) => Value::Undefined,
+                            // Deleted by a getter
+                            None => continue,
+                        };
+                        target.borrow_mut().insert(key, value);
                     }
                 }
-                OpCode::SetIndex => {
-                    let value = self.pop()?;
-                    let index = self.pop()?;
-                    let target = self.pop()?;
-                    match target {
-                        Value::Array(arr) => {
-                            let idx = match index {
-                                Value::Number(n) => n as usize,
-                                _ => return Err("Array index must be a number".into()),
-                            };
-                            let mut arr = arr.borrow_mut();
-                            if idx < arr.len() {
-                                arr[idx] = value.clone();
-                            } else if idx == arr.len() {
-                                arr.push(value.clone());
-                            } else {
-                                return Err(
-                                    "Array index out of bounds (sparse arrays not supported)"
-                                        .into(),
-                                );
-                            }
+                self.pop()?;
+                self.pop()?;
+            }
+            OpCode::Concat(count) => {
+                let start = self
+                    .stack
+                    .len()
+                    .checked_sub(*count)
+                    .ok_or(ErrorKind::StackUnderflow)?;
+                let joined: String = self.stack.drain(start..).map(|v| to_string(&v)).collect();
+                self.push(Value::String(Rc::new(joined)));
+            }
+            OpCode::TemplateObject(count, cache) => {
+                let cell = &chunk.template_caches[*cache];
+                if let Some(strings) = cell.get().and_then(|i| self.template_objects.get(i)) {
+                    let strings = strings.clone();
+                    let start = self
+                        .stack
+                        .len()
+                        .checked_sub(2 * count)
+                        .ok_or(ErrorKind::StackUnderflow)?;
+                    self.stack.truncate(start);
+                    self.push(strings);
+                    return Ok(false);
+                }
+                // Arrays cannot carry a `raw` property, so the strings are an
+                // array-like object
+                let mut raw = self.pop_array_like(*count)?;
+                raw.freeze();
+                let mut strings = self.pop_array_like(*count)?;
+                strings.insert("raw".to_string(), Value::Object(Gc::new(raw)));
+                strings.freeze();
+                let strings = Value::Object(Gc::new(strings));
+                self.template_objects.push(strings.clone());
+                cell.set(Some(self.template_objects.len() - 1));
+                self.push(strings);
+            }
+            OpCode::GetIndex => {
+                let index = self.pop()?;
+                let target = self.pop()?;
+                self.get_property(target, index, chunk, ip, base)?;
+            }
+            OpCode::SetIndex => {
+                let value = self.pop()?;
+                let index = self.pop()?;
+                let target = self.pop()?;
+                self.set_property(target, index, value, chunk, ip, base)?;
+            }
+            OpCode::GetNamed(idx, cache) => {
+                let target = self.pop()?;
+                let name = match &chunk.constants[*idx] {
+                    Constant::String(s) => Rc::clone(s),
+                    _ => return Err(ErrorKind::Custom("Property name must be a string".into())),
+                };
+                if let Value::Object(obj) = &target {
+                    let obj = obj.borrow();
+                    let cache = &chunk.property_caches[*cache];
+                    if let Some(slot) = self.cached_slot(cache, &obj, &name) {
+                        if let Property::Data(value) = obj.slot(slot) {
+                            let value = value.clone();
+                            drop(obj);
+                            self.push(value);
+                            return Ok(false);
                         }
-                        Value::Object(obj) => {
-                            let key = match index {
-                                Value::String(s) => s,
-                                _ => return Err("Object key must be a string".into()),
-                            };
-                            let mut obj = obj.borrow_mut();
-                            obj.insert(key.to_string(), value.clone());
+                    }
+                }
+                self.get_property(target, Value::String(name), chunk, ip, base)?;
+            }
+            OpCode::SetNamed(idx, cache) => {
+                let value = self.pop()?;
+                let target = self.pop()?;
+                let name = match &chunk.constants[*idx] {
+                    Constant::String(s) => Rc::clone(s),
+                    _ => return Err(ErrorKind::Custom("Property name must be a string".into())),
+                };
+                if let Value::Object(obj) = &target {
+                    let mut obj = obj.borrow_mut();
+                    let cache = &chunk.property_caches[*cache];
+                    // A frozen object's setters still run, so it takes the
+                    // slow path
+                    let slot = if obj.is_frozen() {
+                        None
+                    } else {
+                        self.cached_slot(cache, &obj, &name)
+                    };
+                    if let Some(slot) = slot {
+                        if let Property::Data(old) = obj.slot_mut(slot) {
+                            *old = value.clone();
+                            drop(obj);
+                            self.push(value);
+                            return Ok(false);
                         }
-                        _ => return Err("Cannot set property on non-object".into()),
                     }
-                    self.push(value);
                 }
-                OpCode::Jump(target) => {
-                    ip = *target;
+                self.set_property(target, Value::String(name), value, chunk, ip, base)?;
+            }
+            OpCode::Jump(target) => {
+                *ip = *target;
+            }
+            OpCode::Loop(target) => {
+                *ip = *target;
+                if self.gc.should_collect() {
+                    self.collect_garbage();
+                }
+            }
+            OpCode::JumpIfFalse(target) => {
+                let condition = self.peek(0)?;
+                if self.is_falsey(condition) {
+                    *ip = *target;
+                } else {
+                    // Condition is true, fall through.
                 }
-                OpCode::JumpIfFalse(target) => {
-                    let condition = self.peek(0)?;
-                    if self.is_falsey(condition) {
-                        ip = *target;
-                    } else {
-                        // Condition is true, fall through.
+                self.pop()?; // Pop the condition
+            }
+            OpCode::JumpIfTrue(target) => {
+                let condition = self.pop()?;
+                if !self.is_falsey(&condition) {
+                    *ip = *target;
+                }
+            }
+            OpCode::JumpIfNotNullish(target) => {
+                let value = self.pop()?;
+                if !matches!(value, Value::Null | Value::Undefined) {
+                    *ip = *target;
+                }
+            }
+            OpCode::Return => {
+                let result = self.pop()?;
+                let frame = self.frames.pop().unwrap();
+                if self.frames.is_empty() {
+                    return Ok(true);
+                }
+                // Discard the callee, its arguments and locals
+                self.close_upvalues(frame.base);
+                self.stack.truncate(frame.base);
+                match frame.returns_to {
+                    ReturnTo::Stack => self.push(result),
+                    ReturnTo::Discard => {}
+                    ReturnTo::Property(target, key) => target.borrow_mut().insert(key, result),
+                }
+                (*chunk, *ip, *base) = self.load_frame();
+            }
+            OpCode::Undefined => self.push(Value::Undefined),
+            OpCode::Uninitialized => self.push(Value::Uninitialized),
+            OpCode::Null => self.push(Value::Null),
+            OpCode::True => self.push(Value::Boolean(true)),
+            OpCode::False => self.push(Value::Boolean(false)),
+            OpCode::Throw => return Err(ErrorKind::Thrown(self.pop()?)),
+        }
+        Ok(false)
+    }
+
+    /// Whether `value` was made by the Error constructor `constructor`, or
+    /// by any of them when it is `Error`.
+    fn is_error_instance(&self, value: &Value, constructor: NativeFn) -> bool {
+        let Value::Object(obj) = value else {
+            return false;
+        };
+        let Some((_, name)) = self
+            .error_constructors
+            .iter()
+            .find(|(known, _)| std::ptr::fn_addr_eq(*known, constructor))
+        else {
+            return false;
+        };
+        match obj.borrow().class() {
+            Some(class) => *name == "Error" || class == *name,
+            None => false,
+        }
+    }
+
+    /// Pushes `target[index]`. A getter is called in a new frame, which
+    /// leaves its result in its place when it returns.
+    fn get_property(
+        &mut self,
+        target: Value,
+        index: Value,
+        chunk: &mut Rc<Chunk>,
+        ip: &mut usize,
+        base: &mut usize,
+    ) -> Result<(), ErrorKind> {
+        match target {
+            Value::Array(arr) => {
+                let arr = arr.borrow();
+                let value = match element_index(&index) {
+                    Some(idx) => arr.get(idx).cloned().unwrap_or(Value::Undefined),
+                    None if property_key(&index) == "length" => Value::Number(arr.len() as f64),
+                    // Arrays have no other properties
+                    None => Value::Undefined,
+                };
+                drop(arr);
+                self.push(value);
+            }
+            Value::String(s) => {
+                // Strings are indexed by UTF-16 code unit, as in JS
+                let value = match &index {
+                    Value::Number(n) if *n >= 0.0 && n.fract() == 0.0 => s
+                        .encode_utf16()
+                        .nth(*n as usize)
+                        .map(|unit| Value::String(Rc::new(String::from_utf16_lossy(&[unit]))))
+                        .unwrap_or(Value::Undefined),
+                    Value::String(key) if key.as_str() == "length" => {
+                        Value::Number(s.encode_utf16().count() as f64)
+                    }
+                    key => self
+                        .string_methods
+                        .get(&*property_key(key))
+                        .cloned()
+                        .unwrap_or(Value::Undefined),
+                };
+                self.push(value);
+            }
+            Value::Object(obj) => {
+                let property = obj.borrow().property(&property_key(&index)).cloned();
+                match property {
+                    Some(Property::Data(value)) => self.push(value),
+                    Some(Property::Accessor {
+                        get: Some(getter), ..
+                    }) => {
+                        // The getter's frame leaves its result here
+                        self.push(getter.clone());
+                        self.call_value(getter, 0, Value::Object(obj), chunk, ip, base)?;
                     }
-                    self.pop()?; // Pop the condition
+                    _ => self.push(Value::Undefined),
                 }
-                OpCode::Return => {
+            }
+            Value::Null | Value::Undefined => {
+                return Err(ErrorKind::TypeError(format!(
+                    "Cannot read properties of {} (reading '{}')",
+                    target,
+                    to_string(&index)
+                )))
+            }
+            _ => return Err(ErrorKind::TypeError("Cannot index non-array/object".into())),
+        }
+        Ok(())
+    }
+
+    /// Sets `target[index]` to `value` and pushes `value`. A setter is
+    /// called in a new frame, which drops its own result.
+    fn set_property(
+        &mut self,
+        target: Value,
+        index: Value,
+        value: Value,
+        chunk: &mut Rc<Chunk>,
+        ip: &mut usize,
+        base: &mut usize,
+    ) -> Result<(), ErrorKind> {
+        match target {
+            Value::Array(arr) => {
+                // Arrays have no other properties to set
+                let Some(idx) = element_index(&index) else {
+                    self.push(value);
                     return Ok(());
+                };
+                let mut arr = arr.borrow_mut();
+                if idx < arr.len() {
+                    arr[idx] = value.clone();
+                } else if idx == arr.len() {
+                    arr.push(value.clone());
+                } else {
+                    return Err(ErrorKind::RangeError(
+                        "Array index out of bounds (sparse arrays not supported)".into(),
+                    ));
+                }
+            }
+            Value::Object(obj) => {
+                let key = property_key(&index);
+                let property = obj.borrow().property(&key).cloned();
+                match property {
+                    Some(Property::Accessor {
+                        set: Some(setter), ..
+                    }) => {
+                        // The assignment's value is its result, so the
+                        // setter's own result is dropped
+                        self.push(value.clone());
+                        self.push(setter.clone());
+                        self.push(value);
+                        let depth = self.frames.len();
+                        self.call_value(setter, 1, Value::Object(obj), chunk, ip, base)?;
+                        if self.frames.len() > depth {
+                            self.frames.last_mut().unwrap().returns_to = ReturnTo::Discard;
+                        } else {
+                            self.pop()?;
+                        }
+                        return Ok(());
+                    }
+                    // Without a setter the assignment does nothing
+                    Some(Property::Accessor { set: None, .. }) => {}
+                    // Nor does assigning to a frozen object
+                    _ if obj.borrow().is_frozen() => {}
+                    _ => obj.borrow_mut().insert(key.into_owned(), value.clone()),
                 }
-                OpCode::Undefined => self.push(Value::Undefined),
-                OpCode::Null => self.push(Value::Null),
+            }
+            _ => {
+                return Err(ErrorKind::TypeError(
+                    "Cannot set property on non-object".into(),
+                ))
             }
         }
+        self.push(value);
         Ok(())
     }
 
+    /// The slot of `name` in `obj`, from `cache` if it has seen the object's
+    /// shape before. Misses are looked up in the shape and cached, unless
+    /// the object is in dictionary mode.
+    fn cached_slot(
+        &mut self,
+        cache: &Cell<PropertyCache>,
+        obj: &Object,
+        name: &str,
+    ) -> Option<usize> {
+        let Some(shape) = obj.shape() else {
+            self.cache_stats.property_misses += 1;
+            return None;
+        };
+        let mut entries = cache.get();
+        if let Some(slot) = entries.lookup(shape.id()) {
+            self.cache_stats.property_hits += 1;
+            return Some(slot);
+        }
+        self.cache_stats.property_misses += 1;
+        let slot = shape.lookup(name)?;
+        entries.insert(shape.id(), slot);
+        cache.set(entries);
+        Some(slot)
+    }
+
+    /// Unwinds to the innermost handler for an exception raised by the
+    /// instruction before the current frame's `ip`, popping frames that have
+    /// none. An exception no handler above the first `floor` frames catches
+    /// is returned once they are all that is left; with a floor of 0 it ends
+    /// the script.
+    fn throw(&mut self, exception: Value, floor: usize) -> Result<(), Value> {
+        loop {
+            let frame = self.frames.last_mut().unwrap();
+            if let Some(handler) = frame.chunk.find_handler(frame.ip - 1) {
+                let depth = frame.base + handler.stack_depth;
+                frame.ip = handler.target;
+                self.close_upvalues(depth);
+                self.stack.truncate(depth);
+                self.push(exception);
+                return Ok(());
+            }
+
+            let frame = self.frames.pop().unwrap();
+            self.close_upvalues(frame.base);
+            self.stack.truncate(frame.base);
+            if self.frames.len() == floor {
+                return Err(exception);
+            }
+        }
+    }
+
+    fn collect_garbage(&mut self) {
+        let mut roots: Vec<&dyn Trace> = Vec::new();
+        for val in &self.stack {
+            roots.push(val);
+        }
+        for val in &self.global_cells {
+            roots.push(val);
+        }
+        for val in &self.template_objects {
+            roots.push(val);
+        }
+        for frame in &self.frames {
+            roots.push(&*frame.chunk);
+            roots.push(&frame.this);
+            if let Some(closure) = &frame.closure {
+                roots.push(closure);
+            }
+        }
+        for upvalue in &self.open_upvalues {
+            roots.push(upvalue);
+        }
+
+        self.gc.collect(&roots);
+    }
+
+    fn load_frame(&self) -> (Rc<Chunk>, usize, usize) {
+        let frame = self.frames.last().unwrap();
+        (Rc::clone(&frame.chunk), frame.ip, frame.base)
+    }
+
+    fn current_upvalue(&self, idx: usize) -> Gc<Upvalue> {
+        let closure = self.frames.last().unwrap().closure.unwrap();
+        let upvalue = closure.borrow().upvalues[idx];
+        upvalue
+    }
+
+    fn capture_upvalue(&mut self, slot: usize) -> Gc<Upvalue> {
+        let pos = self
+            .open_upvalues
+            .partition_point(|upvalue| matches!(*upvalue.borrow(), Upvalue::Open(s) if s < slot));
+        if let Some(upvalue) = self.open_upvalues.get(pos) {
+            if *upvalue.borrow() == Upvalue::Open(slot) {
+                return *upvalue;
+            }
+        }
+        let upvalue = Gc::new(Upvalue::Open(slot));
+        self.open_upvalues.insert(pos, upvalue);
+        upvalue
+    }
+
+    /// Moves every captured variable at or above `slot` off the stack.
+    fn close_upvalues(&mut self, slot: usize) {
+        let pos = self
+            .open_upvalues
+            .partition_point(|upvalue| matches!(*upvalue.borrow(), Upvalue::Open(s) if s < slot));
+        for upvalue in self.open_upvalues.drain(pos..) {
+            let mut upvalue = upvalue.borrow_mut();
+            if let Upvalue::Open(s) = *upvalue {
+                *upvalue = Upvalue::Closed(self.stack[s].clone());
+            }
+        }
+    }
+
     #[inline(always)]
     fn is_falsey(&self, value: &Value) -> bool {
-        match value {
-            Value::Boolean(b) => !*b,
-            Value::Null | Value::Undefined => true,
-            Value::Number(n) => *n == 0.0,
-            Value::String(s) => s.is_empty(),
-            _ => false,
+        !to_boolean(value)
+    }
+
+    /// Calls `callee`, which sits below its `arg_count` arguments on the
+    /// stack. Natives run to completion and leave their result in place of
+    /// the callee; closures get a new frame, which `chunk`, `ip` and `base`
+    /// are switched to.
+    fn call_value(
+        &mut self,
+        callee: Value,
+        arg_count: usize,
+        this: Value,
+        chunk: &mut Rc<Chunk>,
+        ip: &mut usize,
+        base: &mut usize,
+    ) -> Result<(), ErrorKind> {
+        match callee {
+            Value::NativeFunction(f) => {
+                let args = self.stack.split_off(self.stack.len() - arg_count);
+                self.pop()?; // callee
+                let result = f(this, args).map_err(ErrorKind::Thrown)?;
+                self.push(result);
+            }
+            Value::Function(closure) => {
+                if self.frames.len() >= FRAMES_MAX {
+                    return Err(ErrorKind::RangeError(
+                        "Maximum call stack size exceeded".into(),
+                    ));
+                }
+                let func = closure.borrow();
+
+                // Missing arguments are undefined. Extra ones go into the rest
+                // array, or are dropped if there is none.
+                let new_base = self.stack.len() - arg_count - 1;
+                let params_end = new_base + 1 + func.arity;
+                let rest = if func.has_rest && self.stack.len() > params_end {
+                    self.stack.split_off(params_end)
+                } else {
+                    vec![]
+                };
+                self.stack.resize(params_end, Value::Undefined);
+                if func.has_rest {
+                    self.push(Value::Array(Gc::new(rest)));
+                }
+
+                self.frames.last_mut().unwrap().ip = *ip;
+                self.frames.push(CallFrame {
+                    closure: Some(closure),
+                    chunk: Rc::clone(&func.chunk),
+                    ip: 0,
+                    base: new_base,
+                    this: func.this.clone().unwrap_or(this),
+                    returns_to: ReturnTo::Stack,
+                });
+                drop(func);
+                // Only now is `this` reachable, from the new frame
+                if self.gc.should_collect() {
+                    self.collect_garbage();
+                }
+                (*chunk, *ip, *base) = self.load_frame();
+            }
+            _ => {
+                return Err(ErrorKind::TypeError(format!(
+                    "{} is not a function",
+                    callee
+                )))
+            }
+        }
+        Ok(())
+    }
+
+    /// Starts copying the own properties of `source` other than `excluded`
+    /// to `target`, as object spread and rest do. The elements of an array
+    /// or string are copied here. An object's keys are returned in reverse
+    /// for CopyProperties, which can call getters.
+    fn start_copy(&self, source: &Value, target: Gc<Object>, excluded: &[String]) -> Vec<Value> {
+        match source {
+            Value::Object(obj) => obj
+                .borrow()
+                .keys()
+                .into_iter()
+                .rev()
+                .filter(|key| !excluded.iter().any(|excluded| excluded == key))
+                .map(|key| Value::String(Rc::new(key.to_string())))
+                .collect(),
+            Value::Array(arr) => {
+                let arr = arr.borrow();
+                let mut target = target.borrow_mut();
+                for (i, value) in arr.iter().enumerate() {
+                    let key = i.to_string();
+                    if !excluded.contains(&key) {
+                        target.insert(key, value.clone());
+                    }
+                }
+                vec![]
+            }
+            Value::String(s) => {
+                let mut target = target.borrow_mut();
+                for (i, unit) in s.encode_utf16().enumerate() {
+                    let key = i.to_string();
+                    if !excluded.contains(&key) {
+                        let unit = String::from_utf16_lossy(&[unit]);
+                        target.insert(key, Value::String(Rc::new(unit)));
+                    }
+                }
+                vec![]
+            }
+            _ => vec![],
+        }
+    }
+
+    /// Replaces the array of arguments on top of the stack with its
+    /// elements, returning how many there are.
+    fn spread_arguments(&mut self) -> Result<usize, ErrorKind> {
+        let args = match self.pop()? {
+            Value::Array(arr) => arr.borrow().clone(),
+            _ => {
+                return Err(ErrorKind::Custom(
+                    "Spread arguments must be an array".into(),
+                ))
+            }
+        };
+        let arg_count = args.len();
+        self.stack.extend(args);
+        Ok(arg_count)
+    }
+
+    /// Pops the two operands of a numeric binary operator, converting each
+    /// with ToNumber.
+    fn pop_numbers(&mut self) -> Result<(f64, f64), ErrorKind> {
+        let b = self.pop()?;
+        let a = self.pop()?;
+        Ok((to_number(&a), to_number(&b)))
+    }
+
+    /// Pops `count` values into an object with keys "0" to "count - 1" and a
+    /// `length`, in the order they were pushed.
+    fn pop_array_like(&mut self, count: usize) -> Result<Object, ErrorKind> {
+        let start = self
+            .stack
+            .len()
+            .checked_sub(count)
+            .ok_or(ErrorKind::StackUnderflow)?;
+        let mut map = Object::with_class("Array");
+        for (i, value) in self.stack.drain(start..).enumerate() {
+            map.insert(i.to_string(), value);
         }
+        map.insert("length".to_string(), Value::Number(count as f64));
+        Ok(map)
     }
 
     #[inline(always)]
@@ -313,15 +1376,64 @@ impl VM {
     }
 
     #[inline(always)]
-    fn pop(&mut self) -> Result<Value, &'static str> {
-        self.stack.pop().ok_or("Stack underflow")
+    fn pop(&mut self) -> Result<Value, ErrorKind> {
+        self.stack.pop().ok_or(ErrorKind::StackUnderflow)
     }
 
     #[inline(always)]
-    fn peek(&self, distance: usize) -> Result<&Value, &'static str> {
+    /// Advances the iterator on top of the stack, leaving it there.
+    fn iterator_next(&self) -> Result<Option<Value>, ErrorKind> {
+        match self.peek(0)? {
+            Value::Iterator(iter) => Ok(iter.borrow_mut().next_value()),
+            _ => Err(ErrorKind::Custom("Expected an iterator".into())),
+        }
+    }
+
+    fn peek(&self, distance: usize) -> Result<&Value, ErrorKind> {
         if self.stack.len() <= distance {
-            return Err("Stack underflow");
+            return Err(ErrorKind::StackUnderflow);
         }
         Ok(&self.stack[self.stack.len() - 1 - distance])
     }
 }
+
+/// The values `...value` expands to in an array literal or call: the
+/// elements of an array, or the characters of a string.
+fn spread(value: &Value) -> Result<Vec<Value>, ErrorKind> {
+    let mut iter = iterator(value.clone())?;
+    Ok(std::iter::from_fn(|| iter.next_value()).collect())
+}
+
+/// `value` converted to a property key, borrowing it if it is a string.
+fn property_key(value: &Value) -> Cow<'_, str> {
+    match value {
+        Value::String(s) => Cow::Borrowed(s.as_str()),
+        value => Cow::Owned(to_string(value)),
+    }
+}
+
+/// The array element `key` names, if it is an array index: an integer
+/// below 2³² - 1, given as a number or as the string ToString gives for it.
+/// Other keys, such as -1 or 1.5, name ordinary properties.
+fn element_index(key: &Value) -> Option<usize> {
+    match key {
+        Value::Number(n) if *n >= 0.0 && n.fract() == 0.0 && *n < 4294967295.0 => Some(*n as usize),
+        Value::String(key) => array_index(key).map(|i| i as usize),
+        _ => None,
+    }
+}
+
+/// An iterator over `value` for `for…of`, spread and array patterns.
+fn iterator(value: Value) -> Result<ValueIterator, ErrorKind> {
+    ValueIterator::new(value.clone())
+        .ok_or_else(|| ErrorKind::TypeError(format!("{} is not iterable", value)))
+}
+
+/// The `**` operator. Unlike `f64::powf`, a NaN exponent always yields NaN and
+/// so does raising ±1 to an infinite power.
+fn exponentiate(base: f64, exponent: f64) -> f64 {
+    if exponent.is_nan() || (base.abs() == 1.0 && exponent.is_infinite()) {
+        return f64::NAN;
+    }
+    base.powf(exponent)
+}